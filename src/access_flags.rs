@@ -0,0 +1,128 @@
+// module access_flags
+// class (JVMS table 4.1-B), field (table 4.5-A), method (table 4.6-A), nested class (4.7.6-A) and parameter (4.7.24)
+// access_flags
+
+pub const ACC_PUBLIC: u16 = 0x0001;
+pub const ACC_PRIVATE: u16 = 0x0002;
+pub const ACC_PROTECTED: u16 = 0x0004;
+pub const ACC_STATIC: u16 = 0x0008;
+pub const ACC_FINAL: u16 = 0x0010;
+pub const ACC_SUPER: u16 = 0x0020;
+pub const ACC_SYNCHRONIZED: u16 = 0x0020;
+pub const ACC_VOLATILE: u16 = 0x0040;
+pub const ACC_BRIDGE: u16 = 0x0040;
+pub const ACC_TRANSIENT: u16 = 0x0080;
+pub const ACC_VARARGS: u16 = 0x0080;
+pub const ACC_NATIVE: u16 = 0x0100;
+pub const ACC_INTERFACE: u16 = 0x0200;
+pub const ACC_ABSTRACT: u16 = 0x0400;
+pub const ACC_STRICT: u16 = 0x0800;
+pub const ACC_SYNTHETIC: u16 = 0x1000;
+pub const ACC_ANNOTATION: u16 = 0x2000;
+pub const ACC_ENUM: u16 = 0x4000;
+pub const ACC_MODULE: u16 = 0x8000;
+pub const ACC_MANDATED: u16 = 0x8000;
+
+// the names of the flags of a table that are set
+pub(crate) fn flag_names(flags: u16, table: &[(u16, &'static str)]) -> Vec<&'static str> {
+    table.iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+const CLASS_FLAGS: [(u16, &str); 9] = [
+    (ACC_PUBLIC, "ACC_PUBLIC"),
+    (ACC_FINAL, "ACC_FINAL"),
+    (ACC_SUPER, "ACC_SUPER"),
+    (ACC_INTERFACE, "ACC_INTERFACE"),
+    (ACC_ABSTRACT, "ACC_ABSTRACT"),
+    (ACC_SYNTHETIC, "ACC_SYNTHETIC"),
+    (ACC_ANNOTATION, "ACC_ANNOTATION"),
+    (ACC_ENUM, "ACC_ENUM"),
+    (ACC_MODULE, "ACC_MODULE"),
+];
+
+pub fn class_flag_names(flags: u16) -> Vec<&'static str> {
+    flag_names(flags, &CLASS_FLAGS)
+}
+
+const FIELD_FLAGS: [(u16, &str); 9] = [
+    (ACC_PUBLIC, "ACC_PUBLIC"),
+    (ACC_PRIVATE, "ACC_PRIVATE"),
+    (ACC_PROTECTED, "ACC_PROTECTED"),
+    (ACC_STATIC, "ACC_STATIC"),
+    (ACC_FINAL, "ACC_FINAL"),
+    (ACC_VOLATILE, "ACC_VOLATILE"),
+    (ACC_TRANSIENT, "ACC_TRANSIENT"),
+    (ACC_SYNTHETIC, "ACC_SYNTHETIC"),
+    (ACC_ENUM, "ACC_ENUM"),
+];
+
+pub fn field_flag_names(flags: u16) -> Vec<&'static str> {
+    flag_names(flags, &FIELD_FLAGS)
+}
+
+const METHOD_FLAGS: [(u16, &str); 12] = [
+    (ACC_PUBLIC, "ACC_PUBLIC"),
+    (ACC_PRIVATE, "ACC_PRIVATE"),
+    (ACC_PROTECTED, "ACC_PROTECTED"),
+    (ACC_STATIC, "ACC_STATIC"),
+    (ACC_FINAL, "ACC_FINAL"),
+    (ACC_SYNCHRONIZED, "ACC_SYNCHRONIZED"),
+    (ACC_BRIDGE, "ACC_BRIDGE"),
+    (ACC_VARARGS, "ACC_VARARGS"),
+    (ACC_NATIVE, "ACC_NATIVE"),
+    (ACC_ABSTRACT, "ACC_ABSTRACT"),
+    (ACC_STRICT, "ACC_STRICT"),
+    (ACC_SYNTHETIC, "ACC_SYNTHETIC"),
+];
+
+pub fn method_flag_names(flags: u16) -> Vec<&'static str> {
+    flag_names(flags, &METHOD_FLAGS)
+}
+
+const NESTED_CLASS_FLAGS: [(u16, &str); 10] = [
+    (ACC_PUBLIC, "ACC_PUBLIC"),
+    (ACC_PRIVATE, "ACC_PRIVATE"),
+    (ACC_PROTECTED, "ACC_PROTECTED"),
+    (ACC_STATIC, "ACC_STATIC"),
+    (ACC_FINAL, "ACC_FINAL"),
+    (ACC_INTERFACE, "ACC_INTERFACE"),
+    (ACC_ABSTRACT, "ACC_ABSTRACT"),
+    (ACC_SYNTHETIC, "ACC_SYNTHETIC"),
+    (ACC_ANNOTATION, "ACC_ANNOTATION"),
+    (ACC_ENUM, "ACC_ENUM"),
+];
+
+pub fn nested_class_flag_names(flags: u16) -> Vec<&'static str> {
+    flag_names(flags, &NESTED_CLASS_FLAGS)
+}
+
+const PARAMETER_FLAGS: [(u16, &str); 3] = [
+    (ACC_FINAL, "ACC_FINAL"),
+    (ACC_SYNTHETIC, "ACC_SYNTHETIC"),
+    (ACC_MANDATED, "ACC_MANDATED"),
+];
+
+pub fn parameter_flag_names(flags: u16) -> Vec<&'static str> {
+    flag_names(flags, &PARAMETER_FLAGS)
+}
+
+// java source modifiers of a method
+pub fn method_modifiers(flags: u16) -> Vec<String> {
+    [
+        (ACC_PUBLIC, "public"),
+        (ACC_PRIVATE, "private"),
+        (ACC_PROTECTED, "protected"),
+        (ACC_ABSTRACT, "abstract"),
+        (ACC_STATIC, "static"),
+        (ACC_FINAL, "final"),
+        (ACC_SYNCHRONIZED, "synchronized"),
+        (ACC_NATIVE, "native"),
+        (ACC_STRICT, "strictfp"),
+    ].iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
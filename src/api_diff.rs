@@ -0,0 +1,284 @@
+// module api_diff
+// the public and protected API of a set of classes, and the changes between two versions of it classified by the
+// binary compatibility rules of JLS chapter 13
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use crate::access_flags::*;
+use crate::attribute::{self, AttributeKind};
+use crate::class_file_reader::LiteralInfo;
+use crate::hierarchy::{Hierarchy, Member};
+use crate::ClassFile;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Breaking,       // pre-existing binaries fail to link or see stale values
+    SourceOnly,     // pre-existing binaries link, recompiling their sources fails
+    Compatible,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Breaking => write!(f, "breaking"),
+            Severity::SourceOnly => write!(f, "source-only"),
+            Severity::Compatible => write!(f, "compatible"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Change {
+    pub severity: Severity,
+    pub name: String,   // class, or class.name:descriptor
+    pub description: String,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}: {}", self.severity, self.name, self.description)
+    }
+}
+
+#[derive(Debug)]
+pub struct ApiMember {
+    pub flags: u16,
+    pub constant: Option<String>,   // ConstantValue of a field, strings quoted
+    pub throws: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ApiClass {
+    pub flags: u16,
+    pub supertypes: BTreeSet<String>,   // superclasses and interfaces, direct and inherited
+    pub fields: Members,    // by name and descriptor
+    pub methods: Members,
+}
+
+// the public and protected classes, and their public and protected members
+#[derive(Debug, Default)]
+pub struct Api {
+    pub classes: BTreeMap<String, ApiClass>,
+}
+
+fn is_api(flags: u16) -> bool {
+    flags & (ACC_PUBLIC | ACC_PROTECTED) != 0
+}
+
+fn visibility(flags: u16) -> &'static str {
+    match flags {
+        f if f & ACC_PUBLIC != 0 => "public",
+        f if f & ACC_PROTECTED != 0 => "protected",
+        f if f & ACC_PRIVATE != 0 => "private",
+        _ => "package private",
+    }
+}
+
+fn rank(flags: u16) -> u8 {
+    match visibility(flags) {
+        "public" => 3,
+        "protected" => 2,
+        "package private" => 1,
+        _ => 0,
+    }
+}
+
+fn constant(class_file: &ClassFile, attributes: &[crate::Attribute]) -> Option<String> {
+    match attribute::find(class_file, attributes, "ConstantValue")? {
+        AttributeKind::ConstantValue(LiteralInfo::String(s)) => Some(format!("{:?}", s)),
+        AttributeKind::ConstantValue(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+// the flags of the InnerClasses row of a nested class have protected and private
+fn class_flags(class_file: &ClassFile) -> u16 {
+    let name = class_file.get_class_name();
+    class_file.get_class_attributes().inner_classes.unwrap_or_default().into_iter()
+        .find(|i| i.inner_class == name)
+        .map_or(class_file.get_access_flags(), |i| i.flags)
+}
+
+impl Api {
+    // the hierarchy, of the classes and what they use, gives the inherited supertypes
+    pub fn new(hierarchy: &Hierarchy, classes: &[ClassFile]) -> Self {
+        let mut api = Api::default();
+        for class_file in classes {
+            let name = class_file.get_class_name();
+            let flags = class_flags(class_file);
+            if !is_api(flags) || flags & ACC_MODULE != 0 {
+                continue;
+            }
+            let mut supertypes: BTreeSet<String> = class_file.get_super_class_name().into_iter()
+                .chain(class_file.get_interface_names())
+                .collect();
+            supertypes.extend(hierarchy.superclasses(&name));
+            supertypes.extend(hierarchy.all_interfaces(&name));
+            let fields = class_file.get_fields().into_iter()
+                .filter(|f| is_api(*f.get_flags()))
+                .map(|f| ((f.get_name().clone(), f.get_descriptor().clone()), ApiMember {
+                    flags: *f.get_flags(),
+                    constant: constant(class_file, f.get_attributes()),
+                    throws: Vec::new(),
+                }))
+                .collect();
+            let methods = class_file.get_methods().into_iter()
+                .filter(|m| is_api(*m.get_flags()))
+                .map(|m| ((m.get_name().clone(), m.get_descriptor().clone()), ApiMember {
+                    flags: *m.get_flags(),
+                    constant: None,
+                    throws: m.throws().clone(),
+                }))
+                .collect();
+            api.classes.insert(name, ApiClass { flags, supertypes, fields, methods });
+        }
+        api
+    }
+}
+
+struct Changes(Vec<Change>);
+
+impl Changes {
+    fn add(&mut self, severity: Severity, name: &str, description: String) {
+        self.0.push(Change { severity, name: name.to_string(), description });
+    }
+
+    fn flag(&mut self, name: &str, (old, new): (u16, u16), flag: u16, word: &str, (added, removed): (Severity, Severity)) {
+        match (old & flag != 0, new & flag != 0) {
+            (false, true) => self.add(added, name, format!("{} added", word)),
+            (true, false) => self.add(removed, name, format!("{} removed", word)),
+            _ => (),
+        }
+    }
+
+    fn visibility(&mut self, name: &str, old: u16, new: u16) {
+        if rank(new) < rank(old) {
+            self.add(Severity::Breaking, name, format!("visibility reduced from {} to {}", visibility(old), visibility(new)));
+        } else if rank(new) > rank(old) {
+            self.add(Severity::Compatible, name, format!("visibility increased from {} to {}", visibility(old), visibility(new)));
+        }
+    }
+}
+
+// the changes from old to new, breaking ones first, the hierarchy of the new classes finds members that moved to a
+// supertype
+pub fn diff(old: &Api, new: &Api, hierarchy: &Hierarchy) -> Vec<Change> {
+    let mut changes = Changes(Vec::new());
+    for (name, old_class) in &old.classes {
+        let Some(new_class) = new.classes.get(name) else {
+            let description = match hierarchy.get_class_path().get_class(name) {
+                Some(c) => format!("visibility reduced from {} to {}", visibility(old_class.flags), visibility(class_flags(&c))),
+                None => "removed".to_string(),
+            };
+            changes.add(Severity::Breaking, name, description);
+            continue;
+        };
+        diff_class(&mut changes, name, old_class, new_class, hierarchy);
+    }
+    for name in new.classes.keys().filter(|n| !old.classes.contains_key(*n)) {
+        changes.add(Severity::Compatible, name, "added".to_string());
+    }
+    let mut changes = changes.0;
+    changes.sort_by(|a, b| (a.severity, &a.name).cmp(&(b.severity, &b.name)));
+    changes
+}
+
+fn diff_class(changes: &mut Changes, name: &str, old: &ApiClass, new: &ApiClass, hierarchy: &Hierarchy) {
+    let (o, n) = (old.flags, new.flags);
+    let kind = |flags: u16| if flags & ACC_INTERFACE != 0 { "interface" } else { "class" };
+    if kind(o) != kind(n) {
+        changes.add(Severity::Breaking, name, format!("changed from {} to {}", kind(o), kind(n)));
+    }
+    changes.visibility(name, o, n);
+    // JLS 13.4.1 and 13.4.2
+    if n & ACC_INTERFACE == 0 {
+        changes.flag(name, (o, n), ACC_ABSTRACT, "abstract", (Severity::Breaking, Severity::Compatible));
+        changes.flag(name, (o, n), ACC_FINAL, "final", (Severity::Breaking, Severity::Compatible));
+    }
+    // JLS 13.4.4
+    for s in old.supertypes.difference(&new.supertypes) {
+        changes.add(Severity::Breaking, name, format!("supertype {} removed", s));
+    }
+    for s in new.supertypes.difference(&old.supertypes) {
+        changes.add(Severity::Compatible, name, format!("supertype {} added", s));
+    }
+
+    let member = |(m, d): &(String, String)| format!("{}.{}:{}", name, m, d);
+    let mut replaced = BTreeSet::<&(String, String)>::new();
+    for (key, old_field) in &old.fields {
+        let Some(new_field) = new.fields.get(key) else {
+            let inherited = hierarchy.find_field(name, &key.0, &key.1);
+            removed(changes, name, key, (&old.fields, &new.fields), &mut replaced, inherited);
+            continue;
+        };
+        let (o, n, field) = (old_field.flags, new_field.flags, member(key));
+        changes.visibility(&field, o, n);
+        // JLS 13.4.9
+        changes.flag(&field, (o, n), ACC_FINAL, "final", (Severity::Breaking, Severity::Compatible));
+        changes.flag(&field, (o, n), ACC_STATIC, "static", (Severity::Breaking, Severity::Breaking));
+        match (&old_field.constant, &new_field.constant) {
+            (Some(a), Some(b)) if a != b => changes.add(Severity::Breaking, &field, format!("constant value changed from {} to {}", a, b)),
+            (Some(a), None) => changes.add(Severity::Breaking, &field, format!("constant value {} removed", a)),
+            _ => (),
+        }
+    }
+    for key in new.fields.keys().filter(|k| !old.fields.contains_key(*k) && !replaced.contains(k)) {
+        changes.add(Severity::Compatible, &member(key), "added".to_string());
+    }
+
+    let mut replaced = BTreeSet::<&(String, String)>::new();
+    for (key, old_method) in &old.methods {
+        let Some(new_method) = new.methods.get(key) else {
+            // constructors are not inherited
+            let inherited = hierarchy.find_method(name, &key.0, &key.1).filter(|m| key.0 != "<init>" || m.class == name);
+            removed(changes, name, key, (&old.methods, &new.methods), &mut replaced, inherited);
+            continue;
+        };
+        let (o, n, method) = (old_method.flags, new_method.flags, member(key));
+        changes.visibility(&method, o, n);
+        // JLS 13.4.16, 13.4.17 and 13.4.19, a final class has no subclasses to override the method
+        changes.flag(&method, (o, n), ACC_ABSTRACT, "abstract", (Severity::Breaking, Severity::Compatible));
+        let final_added = if old.flags & ACC_FINAL == 0 { Severity::Breaking } else { Severity::Compatible };
+        changes.flag(&method, (o, n), ACC_FINAL, "final", (final_added, Severity::Compatible));
+        changes.flag(&method, (o, n), ACC_STATIC, "static", (Severity::Breaking, Severity::Breaking));
+        // JLS 13.4.21, catch clauses and throws clauses of callers and overriders stop compiling
+        for e in new_method.throws.iter().filter(|e| !old_method.throws.contains(e)) {
+            changes.add(Severity::SourceOnly, &method, format!("throws {} added", e));
+        }
+        for e in old_method.throws.iter().filter(|e| !new_method.throws.contains(e)) {
+            changes.add(Severity::SourceOnly, &method, format!("throws {} removed", e));
+        }
+    }
+    for key in new.methods.keys().filter(|k| !old.methods.contains_key(*k) && !replaced.contains(k)) {
+        // JLS 13.5.3 and 13.4.12, implementations compiled before link and fail only when the method is called
+        let (severity, description) = match new.methods[key].flags & ACC_ABSTRACT != 0 {
+            true => (Severity::SourceOnly, "abstract method added"),
+            false => (Severity::Compatible, "added"),
+        };
+        changes.add(severity, &member(key), description.to_string());
+    }
+}
+
+type Members = BTreeMap<(String, String), ApiMember>;
+
+// a member the new class does not declare as API: one it made less visible, the only one of its name whose descriptor
+// changed, one a supertype now declares, or one that is gone
+fn removed<'a>(changes: &mut Changes, class: &str, key: &(String, String), (old, new): (&Members, &'a Members),
+               replaced: &mut BTreeSet<&'a (String, String)>, inherited: Option<Member>) {
+    let name = format!("{}.{}:{}", class, key.0, key.1);
+    if let Some(m) = inherited.as_ref().filter(|m| m.class == class) {
+        changes.add(Severity::Breaking, &name, format!("visibility reduced from {} to {}", visibility(old[key].flags), visibility(m.access_flags)));
+        return;
+    }
+    let renamed: Vec<&'a (String, String)> = new.keys().filter(|k| k.0 == key.0 && !old.contains_key(*k)).collect();
+    if let [only] = renamed.as_slice() {
+        if old.keys().filter(|k| k.0 == key.0).count() == 1 {
+            changes.add(Severity::Breaking, &name, format!("descriptor changed to {}", only.1));
+            replaced.insert(only);
+            return;
+        }
+    }
+    match inherited {
+        Some(m) if is_api(m.access_flags) => changes.add(Severity::Compatible, &name, format!("moved to {}", m.class)),
+        _ => changes.add(Severity::Breaking, &name, "removed".to_string()),
+    }
+}
@@ -0,0 +1,301 @@
+// module attribute
+// every JVMS attribute as one typed enum, decoded on demand from its raw bytes
+use std::any::Any;
+use std::borrow::Cow;
+use std::fmt;
+use crate::class_file_reader::{ConstantInfo, Index, LiteralInfo, Tag};
+use crate::code::{self, Code, LineNumber, LocalVariable};
+use crate::code::stack_map::{self, StackMapFrame};
+use crate::module::{self, ModuleDescriptor};
+use crate::record::{self, RecordComponent};
+use crate::type_annotation::{self, TypeAnnotation};
+use crate::access_flags::ACC_SYNTHETIC;
+use crate::{class_name, utf8, Annotation, Attribute, AttributeReader, ClassAttributes, ClassFile, ElementValue, Field, Method};
+
+#[derive(Debug)]
+pub struct InnerClass {
+    pub inner_class: String,
+    pub outer_class: Option<String>,
+    pub inner_name: Option<String>,
+    pub flags: u16,
+}
+
+// constant pool indexes, the handle and its static arguments
+#[derive(Debug)]
+pub struct BootstrapMethod {
+    pub method_ref: u16,
+    pub arguments: Vec<u16>,
+}
+
+#[derive(Debug)]
+pub struct MethodParameter {
+    pub name: Option<String>,
+    pub flags: u16,
+}
+
+pub enum AttributeKind {
+    ConstantValue(LiteralInfo),
+    Code(Code),
+    StackMapTable(Vec<StackMapFrame>),
+    Exceptions(Vec<String>),
+    InnerClasses(Vec<InnerClass>),
+    EnclosingMethod(String, Option<String>),        // class, name:descriptor
+    Synthetic,
+    Signature(String),
+    SourceFile(String),
+    SourceDebugExtension(String),
+    LineNumberTable(Vec<LineNumber>),
+    LocalVariableTable(Vec<LocalVariable>),
+    LocalVariableTypeTable(Vec<LocalVariable>),      // descriptor holds the signature
+    Deprecated,
+    RuntimeVisibleAnnotations(Vec<Annotation>),
+    RuntimeInvisibleAnnotations(Vec<Annotation>),
+    RuntimeVisibleParameterAnnotations(Vec<Vec<Annotation>>),
+    RuntimeInvisibleParameterAnnotations(Vec<Vec<Annotation>>),
+    RuntimeVisibleTypeAnnotations(Vec<TypeAnnotation>),
+    RuntimeInvisibleTypeAnnotations(Vec<TypeAnnotation>),
+    AnnotationDefault(ElementValue),
+    BootstrapMethods(Vec<BootstrapMethod>),
+    MethodParameters(Vec<MethodParameter>),
+    Module(ModuleDescriptor),
+    ModulePackages(Vec<String>),
+    ModuleMainClass(String),
+    NestHost(String),
+    NestMembers(Vec<String>),
+    Record(Vec<RecordComponent>),
+    PermittedSubclasses(Vec<String>),
+    Custom(String, Box<dyn Any>),   // from a registered AttributeDecoder
+    Unknown(String, Vec<u8>),
+}
+
+// decoder for an attribute the JVMS does not define, registered with ClassFile::register_decoder
+pub trait AttributeDecoder {
+    fn name(&self) -> &str;
+    fn decode(&self, class_file: &ClassFile, info: &[u8]) -> Box<dyn Any>;
+}
+
+// why an attribute's bytes do not decode
+#[derive(Debug)]
+pub enum DecodeError {
+    Truncated,                  // the entries run past the end of the attribute
+    Tag(&'static str, u8),      // what the tag is of, a value JVMS does not define
+    Constant(u16),              // not the kind of constant the entry needs
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::Truncated => write!(f, "the entries run past the end of the attribute"),
+            DecodeError::Tag(what, tag) => write!(f, "unknown {} {}", what, tag),
+            DecodeError::Constant(index) => write!(f, "constant #{} is not the kind the entry needs", index),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl Attribute {
+    // an attribute that does not decode is kept as its bytes, Unknown, as one with a name JVMS does not define is
+    pub fn decode(&self, class_file: &ClassFile) -> AttributeKind {
+        self.try_decode(class_file).unwrap_or_else(|_| AttributeKind::Unknown(self.name.clone(), self.info.clone()))
+    }
+
+    // the error for an attribute whose bytes do not decode
+    pub fn try_decode(&self, class_file: &ClassFile) -> Result<AttributeKind, DecodeError> {
+        if let Some(decoder) = class_file.decoders.iter().find(|d| d.name() == self.name) {
+            return Ok(AttributeKind::Custom(self.name.clone(), decoder.decode(class_file, &self.info)));
+        }
+        let cp = &class_file.constant_pool;
+        let mut reader = AttributeReader::new(&self.info);
+        Ok(match self.name.as_str() {
+            "ConstantValue" => {
+                let index = reader.try_u16()?;
+                AttributeKind::ConstantValue(cp.find_constant_value(index).ok_or(DecodeError::Constant(index))?)
+            }
+            "Code" => AttributeKind::Code(Code::try_new(&self.info)?),
+            "StackMapTable" => AttributeKind::StackMapTable(stack_map::get_stack_map_table(class_file, &self.info)?),
+            "Exceptions" => AttributeKind::Exceptions(read_class_names(class_file, &mut reader)?),
+            "InnerClasses" => {
+                let count = reader.try_u16()?;
+                AttributeKind::InnerClasses((0..count).map(|_| Ok(InnerClass {
+                    inner_class: class_name(class_file, reader.try_u16()?)?,
+                    outer_class: optional(reader.try_u16()?, |i| class_name(class_file, i))?,
+                    inner_name: optional(reader.try_u16()?, |i| utf8(class_file, i))?,
+                    flags: reader.try_u16()?,
+                })).collect::<Result<_, DecodeError>>()?)
+            }
+            "EnclosingMethod" => {
+                let class = class_name(class_file, reader.try_u16()?)?;
+                AttributeKind::EnclosingMethod(class, optional(reader.try_u16()?, |i| name_and_type(class_file, i))?)
+            }
+            "Synthetic" => AttributeKind::Synthetic,
+            "Signature" => AttributeKind::Signature(utf8(class_file, reader.try_u16()?)?),
+            "SourceFile" => AttributeKind::SourceFile(utf8(class_file, reader.try_u16()?)?),
+            "SourceDebugExtension" => AttributeKind::SourceDebugExtension(String::from_utf8_lossy(&self.info).to_string()),
+            "LineNumberTable" => AttributeKind::LineNumberTable(code::get_line_number_table(&self.info)?),
+            "LocalVariableTable" => AttributeKind::LocalVariableTable(code::get_local_variable_table(class_file, &self.info)?),
+            "LocalVariableTypeTable" => AttributeKind::LocalVariableTypeTable(code::get_local_variable_table(class_file, &self.info)?),
+            "Deprecated" => AttributeKind::Deprecated,
+            "RuntimeVisibleAnnotations" =>
+                AttributeKind::RuntimeVisibleAnnotations(ClassAttributes::get_annotations(class_file, &self.info)?),
+            "RuntimeInvisibleAnnotations" =>
+                AttributeKind::RuntimeInvisibleAnnotations(ClassAttributes::get_annotations(class_file, &self.info)?),
+            "RuntimeVisibleParameterAnnotations" =>
+                AttributeKind::RuntimeVisibleParameterAnnotations(read_parameter_annotations(class_file, &mut reader)?),
+            "RuntimeInvisibleParameterAnnotations" =>
+                AttributeKind::RuntimeInvisibleParameterAnnotations(read_parameter_annotations(class_file, &mut reader)?),
+            "RuntimeVisibleTypeAnnotations" =>
+                AttributeKind::RuntimeVisibleTypeAnnotations(type_annotation::get_type_annotations(class_file, &self.info)?),
+            "RuntimeInvisibleTypeAnnotations" =>
+                AttributeKind::RuntimeInvisibleTypeAnnotations(type_annotation::get_type_annotations(class_file, &self.info)?),
+            "AnnotationDefault" => AttributeKind::AnnotationDefault(ElementValue::new(&mut reader, class_file)?),
+            "BootstrapMethods" => {
+                let count = reader.try_u16()?;
+                AttributeKind::BootstrapMethods((0..count).map(|_| {
+                    let method_ref = constant_index(class_file, &mut reader, Some(Tag::MethodHandle))?;
+                    let num = reader.try_u16()?;
+                    let arguments = (0..num).map(|_| constant_index(class_file, &mut reader, None)).collect::<Result<_, _>>()?;
+                    Ok(BootstrapMethod { method_ref, arguments })
+                }).collect::<Result<_, DecodeError>>()?)
+            }
+            "MethodParameters" => {
+                let count = reader.try_u8()?;
+                AttributeKind::MethodParameters((0..count).map(|_| Ok(MethodParameter {
+                    name: optional(reader.try_u16()?, |i| utf8(class_file, i))?,
+                    flags: reader.try_u16()?,
+                })).collect::<Result<_, DecodeError>>()?)
+            }
+            "Module" => AttributeKind::Module(ModuleDescriptor::new(class_file, &self.info)?),
+            "ModulePackages" => AttributeKind::ModulePackages(module::get_module_packages(class_file, &self.info)?),
+            "ModuleMainClass" => AttributeKind::ModuleMainClass(module::get_module_main_class(class_file, &self.info)?),
+            "NestHost" => AttributeKind::NestHost(class_name(class_file, reader.try_u16()?)?),
+            "NestMembers" => AttributeKind::NestMembers(read_class_names(class_file, &mut reader)?),
+            "Record" => AttributeKind::Record(record::get_record_components(class_file, &self.info)?),
+            "PermittedSubclasses" => AttributeKind::PermittedSubclasses(record::get_permitted_subclasses(class_file, &self.info)?),
+            _ => AttributeKind::Unknown(self.name.clone(), self.info.clone()),
+        })
+    }
+}
+
+// index 0 for none
+pub(crate) fn optional<F: Fn(u16) -> Result<String, DecodeError>>(index: u16, f: F) -> Result<Option<String>, DecodeError> {
+    match index {
+        0 => Ok(None),
+        i => f(i).map(Some),
+    }
+}
+
+// name:descriptor of a NameAndType entry
+fn name_and_type(class_file: &ClassFile, index: u16) -> Result<String, DecodeError> {
+    match class_file.constant_pool.find_constant(index) {
+        Some(ConstantInfo(Tag::NameAndType, Index::Pair(n, d))) => Ok(format!("{}:{}", utf8(class_file, *n)?, utf8(class_file, *d)?)),
+        _ => Err(DecodeError::Constant(index)),
+    }
+}
+
+// an index kept as it is, checked to be in the pool and of the tag when there is one
+fn constant_index(class_file: &ClassFile, reader: &mut AttributeReader, tag: Option<Tag>) -> Result<u16, DecodeError> {
+    let index = reader.try_u16()?;
+    match class_file.constant_pool.find_constant(index) {
+        Some(ConstantInfo(t, _)) if tag.is_none_or(|tag| *t == tag) => Ok(index),
+        _ => Err(DecodeError::Constant(index)),
+    }
+}
+
+fn read_class_names(class_file: &ClassFile, reader: &mut AttributeReader) -> Result<Vec<String>, DecodeError> {
+    let count = reader.try_u16()?;
+    (0..count).map(|_| class_name(class_file, reader.try_u16()?)).collect()
+}
+
+fn read_parameter_annotations(class_file: &ClassFile, reader: &mut AttributeReader) -> Result<Vec<Vec<Annotation>>, DecodeError> {
+    let num_parameters = reader.try_u8()?;
+    (0..num_parameters).map(|_| {
+        let num = reader.try_u16()?;
+        (0..num).map(|_| Annotation::new(reader, class_file)).collect()
+    })
+    .collect()
+}
+
+// decode just the named attribute out of a list
+pub fn find(class_file: &ClassFile, attributes: &[Attribute], name: &str) -> Option<AttributeKind> {
+    attributes.iter().find(|a| a.name == name).map(|a| a.decode(class_file))
+}
+
+// the same attribute queries on classes, fields, methods, Code and record components
+pub trait HasAttributes {
+    fn attributes<'a>(&'a self, class_file: &ClassFile) -> Cow<'a, [Attribute]>;
+
+    // ACC_SYNTHETIC for the things that have access flags
+    fn synthetic_flag(&self) -> bool {
+        false
+    }
+
+    fn has_attribute(&self, class_file: &ClassFile, name: &str) -> bool {
+        self.attributes(class_file).iter().any(|a| a.name == name)
+    }
+
+    fn find_attribute(&self, class_file: &ClassFile, name: &str) -> Option<AttributeKind> {
+        find(class_file, &self.attributes(class_file), name)
+    }
+
+    fn decode_attributes(&self, class_file: &ClassFile) -> Vec<AttributeKind> {
+        self.attributes(class_file).iter().map(|a| a.decode(class_file)).collect()
+    }
+
+    fn is_deprecated(&self, class_file: &ClassFile) -> bool {
+        self.has_attribute(class_file, "Deprecated")
+    }
+
+    fn is_synthetic(&self, class_file: &ClassFile) -> bool {
+        self.synthetic_flag() || self.has_attribute(class_file, "Synthetic")
+    }
+
+    fn signature(&self, class_file: &ClassFile) -> Option<String> {
+        match self.find_attribute(class_file, "Signature") {
+            Some(AttributeKind::Signature(signature)) => Some(signature),
+            _ => None,
+        }
+    }
+}
+
+impl HasAttributes for ClassFile {
+    fn attributes<'a>(&'a self, class_file: &ClassFile) -> Cow<'a, [Attribute]> {
+        Cow::Owned(class_file.get_attributes_vec(&self.attributes))
+    }
+
+    fn synthetic_flag(&self) -> bool {
+        self.access_flags & ACC_SYNTHETIC != 0
+    }
+}
+
+impl HasAttributes for Field {
+    fn attributes<'a>(&'a self, _class_file: &ClassFile) -> Cow<'a, [Attribute]> {
+        Cow::Borrowed(self.get_attributes())
+    }
+
+    fn synthetic_flag(&self) -> bool {
+        self.get_flags() & ACC_SYNTHETIC != 0
+    }
+}
+
+impl HasAttributes for Method {
+    fn attributes<'a>(&'a self, _class_file: &ClassFile) -> Cow<'a, [Attribute]> {
+        Cow::Borrowed(self.get_attributes())
+    }
+
+    fn synthetic_flag(&self) -> bool {
+        self.get_flags() & ACC_SYNTHETIC != 0
+    }
+}
+
+impl HasAttributes for Code {
+    fn attributes<'a>(&'a self, class_file: &ClassFile) -> Cow<'a, [Attribute]> {
+        Cow::Owned(class_file.get_attributes_vec(self.get_attributes()))
+    }
+}
+
+impl HasAttributes for RecordComponent {
+    fn attributes<'a>(&'a self, _class_file: &ClassFile) -> Cow<'a, [Attribute]> {
+        Cow::Borrowed(self.get_attributes())
+    }
+}
@@ -0,0 +1,457 @@
+// module call_graph
+// method call graph of a set of classes on a class path, from the invoke instructions and the lambdas and method
+// references of LambdaMetafactory call sites. Virtual calls go to the overriding methods of the analyzed classes
+// below the resolved one, all of them with CHA, those of classes that reachable code instantiates with RTA
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::rc::Rc;
+use serde::Serialize;
+use crate::access_flags::*;
+use crate::attribute::{self, AttributeKind, BootstrapMethod};
+use crate::class_file_reader::{ConstantInfo, Index, MethodInfo, Tag};
+use crate::code::Code;
+use crate::code::cfg::escape;
+use crate::code::frames::ClassHierarchy;
+use crate::code::opcode::Opcode;
+use crate::hierarchy::{Hierarchy, Member};
+use crate::{Attribute, ClassFile};
+
+const LAMBDA_METAFACTORY: &str = "java/lang/invoke/LambdaMetafactory";
+
+// reference kinds of a MethodHandle, JVMS 5.4.3.5
+const REF_INVOKE_VIRTUAL: u8 = 5;
+const REF_INVOKE_STATIC: u8 = 6;
+const REF_INVOKE_SPECIAL: u8 = 7;
+const REF_NEW_INVOKE_SPECIAL: u8 = 8;
+const REF_INVOKE_INTERFACE: u8 = 9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Cha,    // class hierarchy analysis
+    Rta,    // rapid type analysis
+}
+
+// an invoke instruction, or the implementation of a lambda, with the class, name and descriptor it names
+enum Site {
+    Static(String, String, String),
+    Special(String, String, String),
+    Virtual(String, String, String),
+}
+
+pub struct CallGraph {
+    methods: BTreeSet<Member>,                  // declared by the analyzed classes
+    nodes: Vec<Member>,                         // the callers and callees, by id
+    ids: HashMap<Member, usize>,
+    calls: Vec<BTreeSet<usize>>,                // callees of each node
+}
+
+fn member(class_file: &ClassFile, method: &MethodInfo) -> Member {
+    Member {
+        class: class_file.get_class_name(),
+        name: class_file.constant_pool.get_item(&method.name_index),
+        descriptor: class_file.constant_pool.get_item(&method.descriptor_index),
+        access_flags: method.access_flags,
+    }
+}
+
+// the call sites of a method body and the classes it creates
+fn scan(class_file: &ClassFile, code: &Code, bootstrap_methods: &[BootstrapMethod]) -> (Vec<Site>, Vec<String>) {
+    let cp = &class_file.constant_pool;
+    let mut sites = Vec::new();
+    let mut created = Vec::new();
+    for (_, opcode) in code.get_instructions() {
+        let (class, name, descriptor) = match opcode {
+            Opcode::Invokestatic(i) | Opcode::Invokespecial(i) | Opcode::Invokevirtual(i) | Opcode::Invokeinterface(i, _) => {
+                match cp.get_member(*i) {
+                    Some(member) => member,
+                    None => continue,
+                }
+            }
+            Opcode::Invokedynamic(i) => {
+                if let Some((site, class)) = lambda(class_file, *i, bootstrap_methods) {
+                    sites.push(site);
+                    created.extend(class);
+                }
+                continue;
+            }
+            Opcode::New(i) => {
+                if let Some(ConstantInfo(Tag::Class, Index::Single(n))) = cp.find_constant(*i) {
+                    created.extend(cp.find_utf8(*n));
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        sites.push(match opcode {
+            Opcode::Invokestatic(_) => Site::Static(class, name, descriptor),
+            Opcode::Invokespecial(_) => Site::Special(class, name, descriptor),
+            _ => Site::Virtual(class, name, descriptor),
+        });
+    }
+    (sites, created)
+}
+
+// the implementation method handle of a LambdaMetafactory call site, the second static argument, and the class a
+// constructor reference creates
+fn lambda(class_file: &ClassFile, index: u16, bootstrap_methods: &[BootstrapMethod]) -> Option<(Site, Option<String>)> {
+    let cp = &class_file.constant_pool;
+    let ConstantInfo(Tag::InvokeDynamic, Index::Dynamic(bootstrap, _)) = cp.find_constant(index)? else { return None };
+    let bootstrap = bootstrap_methods.get(*bootstrap as usize)?;
+    let ConstantInfo(Tag::MethodHandle, Index::MethodHandle(_, factory)) = cp.find_constant(bootstrap.method_ref)? else { return None };
+    if cp.get_member(*factory)?.0 != LAMBDA_METAFACTORY {
+        return None;
+    }
+    let ConstantInfo(Tag::MethodHandle, Index::MethodHandle(kind, implementation)) = cp.find_constant(*bootstrap.arguments.get(1)?)?
+        else { return None };
+    let (class, name, descriptor) = cp.get_member(*implementation)?;
+    match *kind {
+        REF_INVOKE_STATIC => Some((Site::Static(class, name, descriptor), None)),
+        REF_INVOKE_SPECIAL => Some((Site::Special(class, name, descriptor), None)),
+        REF_NEW_INVOKE_SPECIAL => Some((Site::Special(class.clone(), name, descriptor), Some(class))),
+        REF_INVOKE_VIRTUAL | REF_INVOKE_INTERFACE => Some((Site::Virtual(class, name, descriptor), None)),
+        _ => None,
+    }
+}
+
+// the methods a call site runs, shared between the sites that dispatch the same way
+type Targets = Rc<Vec<Member>>;
+
+struct Builder<'a> {
+    hierarchy: &'a Hierarchy,
+    analyzed: HashSet<String>,
+    implementations: HashMap<String, Vec<String>>,  // the concrete analyzed classes below each class and interface
+    instantiated: Option<HashSet<String>>,          // RTA only
+    resolved: RefCell<HashMap<(String, String, String), Member>>,
+    selected: RefCell<HashMap<(String, Member), Option<Member>>>,
+    dispatched: RefCell<HashMap<(String, Member), (usize, Targets)>>,   // with the number of classes instantiated then
+}
+
+impl Builder<'_> {
+    // a method the class path does not have is its own resolution
+    fn resolve(&self, class: &str, name: &str, descriptor: &str) -> Member {
+        let key = (class.to_string(), name.to_string(), descriptor.to_string());
+        if let Some(member) = self.resolved.borrow().get(&key) {
+            return member.clone();
+        }
+        let member = self.hierarchy.find_method(class, name, descriptor).unwrap_or_else(|| Member {
+            class: class.to_string(),
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+            access_flags: 0,
+        });
+        self.resolved.borrow_mut().insert(key, member.clone());
+        member
+    }
+
+    fn select(&self, receiver: &str, resolved: &Member) -> Option<Member> {
+        let key = (receiver.to_string(), resolved.clone());
+        if let Some(member) = self.selected.borrow().get(&key) {
+            return member.clone();
+        }
+        let member = self.hierarchy.select_method(receiver, resolved);
+        self.selected.borrow_mut().insert(key, member.clone());
+        member
+    }
+
+    fn targets(&self, caller: &Member, site: &Site) -> Targets {
+        match site {
+            Site::Static(class, name, descriptor) => Rc::new(vec![self.resolve(class, name, descriptor)]),
+            Site::Special(class, name, descriptor) => {
+                let resolved = self.resolve(class, name, descriptor);
+                // a super call, invokespecial of a superclass method other than a constructor, selects from the
+                // caller's superclass
+                let superclass = self.hierarchy.superclasses(&caller.class).into_iter().next();
+                match superclass {
+                    Some(s) if name != "<init>" && resolved.access_flags & ACC_PRIVATE == 0 && *class != caller.class
+                        && !self.hierarchy.is_interface(class) =>
+                        Rc::new(vec![self.select(&s, &resolved).unwrap_or(resolved)]),
+                    _ => Rc::new(vec![resolved]),
+                }
+            }
+            Site::Virtual(class, name, descriptor) => {
+                let resolved = self.resolve(class, name, descriptor);
+                if resolved.access_flags & (ACC_PRIVATE | ACC_FINAL | ACC_STATIC) != 0 {
+                    return Rc::new(vec![resolved]);
+                }
+                let instantiated = self.instantiated.as_ref().map_or(0, |i| i.len());
+                if let Some((n, targets)) = self.dispatched.borrow().get(&(class.clone(), resolved.clone())) {
+                    if *n == instantiated {
+                        return targets.clone();
+                    }
+                }
+                let mut targets = BTreeSet::new();
+                for receiver in self.implementations.get(class).into_iter().flatten() {
+                    if self.instantiated.as_ref().is_some_and(|i| !i.contains(receiver)) {
+                        continue;
+                    }
+                    targets.extend(self.select(receiver, &resolved));
+                }
+                // library methods stand for the library implementations
+                if !self.analyzed.contains(&resolved.class) {
+                    targets.insert(resolved.clone());
+                }
+                let targets = Rc::new(targets.into_iter().collect::<Vec<_>>());
+                self.dispatched.borrow_mut().insert((class.clone(), resolved), (instantiated, targets.clone()));
+                targets
+            }
+        }
+    }
+}
+
+impl CallGraph {
+    // the calls of every method of the classes with CHA, of the methods reachable from the entry points with RTA
+    pub fn new(hierarchy: &Hierarchy, classes: &[String], entry_points: &[Member], algorithm: Algorithm) -> Self {
+        let class_path = hierarchy.get_class_path();
+        let mut builder = Builder {
+            hierarchy,
+            analyzed: classes.iter().cloned().collect(),
+            implementations: HashMap::new(),
+            instantiated: None,
+            resolved: RefCell::new(HashMap::new()),
+            selected: RefCell::new(HashMap::new()),
+            dispatched: RefCell::new(HashMap::new()),
+        };
+        let mut graph = CallGraph { methods: BTreeSet::new(), nodes: Vec::new(), ids: HashMap::new(), calls: Vec::new() };
+        let mut bodies = HashMap::<Member, (Vec<Site>, Vec<String>)>::new();
+        for class in classes {
+            let Some(class_file) = class_path.get_class(class) else { continue };
+            if class_file.get_access_flags() & (ACC_INTERFACE | ACC_ABSTRACT) == 0 {
+                let supertypes = [class.clone()].into_iter().chain(hierarchy.superclasses(class)).chain(hierarchy.all_interfaces(class));
+                for s in supertypes {
+                    builder.implementations.entry(s).or_default().push(class.clone());
+                }
+            }
+            let bootstrap_methods = match attribute::find(&class_file, &class_file.get_attributes_vec(&class_file.attributes), "BootstrapMethods") {
+                Some(AttributeKind::BootstrapMethods(b)) => b,
+                _ => Vec::new(),
+            };
+            for raw in &class_file.methods {
+                let method = member(&class_file, raw);
+                if let Some(code) = class_file.get_attributes_vec(&raw.attributes).into_iter().find(|a| a.name == "Code") {
+                    bodies.insert(method.clone(), scan(&class_file, &Code::new(&code.info), &bootstrap_methods));
+                }
+                graph.methods.insert(method);
+            }
+        }
+
+        let mut pending: VecDeque<Member> = match algorithm {
+            Algorithm::Cha => graph.methods.iter().cloned().collect(),
+            Algorithm::Rta => {
+                let instance = entry_points.iter().filter(|m| m.access_flags & ACC_STATIC == 0).map(|m| m.class.clone());
+                builder.instantiated = Some(instance.collect());
+                entry_points.iter().cloned().collect()
+            }
+        };
+        let initializers: HashMap<String, Member> = graph.methods.iter()
+            .filter(|m| m.name == "<clinit>")
+            .map(|m| (m.class.clone(), m.clone()))
+            .collect();
+        let mut reached = HashSet::<Member>::new();
+        let mut instantiated = 0;
+        loop {
+            while let Some(method) = pending.pop_front() {
+                if !reached.insert(method.clone()) {
+                    continue;
+                }
+                graph.id(&method);
+                pending.extend(initializers.get(&method.class).cloned());
+                let Some((sites, created)) = bodies.get(&method) else { continue };
+                if let Some(instantiated) = builder.instantiated.as_mut() {
+                    instantiated.extend(created.iter().cloned());
+                }
+                for site in sites {
+                    for target in builder.targets(&method, site).iter() {
+                        graph.add(&method, target);
+                        if !reached.contains(target) {
+                            pending.push_back(target.clone());
+                        }
+                    }
+                }
+            }
+            let now = builder.instantiated.as_ref().map_or(0, |i| i.len());
+            if now == instantiated {
+                break;
+            }
+            instantiated = now;
+            // a class instantiated later adds targets to the virtual calls already seen
+            for method in &reached {
+                for site in bodies.get(method).into_iter().flat_map(|(sites, _)| sites) {
+                    for target in builder.targets(method, site).iter() {
+                        graph.add(method, target);
+                        if !reached.contains(target) {
+                            pending.push_back(target.clone());
+                        }
+                    }
+                }
+            }
+            if pending.is_empty() {
+                break;
+            }
+        }
+        graph
+    }
+
+    fn id(&mut self, method: &Member) -> usize {
+        if let Some(id) = self.ids.get(method) {
+            return *id;
+        }
+        self.nodes.push(method.clone());
+        self.calls.push(BTreeSet::new());
+        self.ids.insert(method.clone(), self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    fn add(&mut self, caller: &Member, callee: &Member) {
+        let (caller, callee) = (self.id(caller), self.id(callee));
+        self.calls[caller].insert(callee);
+    }
+
+    fn sorted<I: Iterator<Item = usize>>(&self, ids: I) -> Vec<&Member> {
+        let mut methods: Vec<&Member> = ids.map(|id| &self.nodes[id]).collect();
+        methods.sort();
+        methods
+    }
+
+    // callers and their callees in order
+    fn edges(&self) -> Vec<(&Member, Vec<&Member>)> {
+        let mut edges: Vec<(&Member, Vec<&Member>)> = (0..self.nodes.len())
+            .filter(|id| !self.calls[*id].is_empty())
+            .map(|id| (&self.nodes[id], self.sorted(self.calls[id].iter().copied())))
+            .collect();
+        edges.sort();
+        edges
+    }
+
+    // methods declared by the analyzed classes
+    pub fn get_methods(&self) -> &BTreeSet<Member> {
+        &self.methods
+    }
+
+    pub fn callees(&self, method: &Member) -> Vec<&Member> {
+        match self.ids.get(method) {
+            Some(id) => self.sorted(self.calls[*id].iter().copied()),
+            None => Vec::new(),
+        }
+    }
+
+    // a search of all the calls, the graph keeps them one way
+    pub fn callers(&self, method: &Member) -> Vec<&Member> {
+        match self.ids.get(method) {
+            Some(id) => self.sorted((0..self.nodes.len()).filter(|caller| self.calls[*caller].contains(id))),
+            None => Vec::new(),
+        }
+    }
+
+    // the methods the graph has, analyzed or called, named as parse_method takes them
+    pub fn find_methods(&self, name: &str) -> Vec<&Member> {
+        let Some((class, method, descriptor)) = parse_method(name) else { return Vec::new() };
+        self.methods.iter().chain(&self.nodes)
+            .filter(|m| m.class == class && m.name == method && descriptor.is_none_or(|d| m.descriptor == d))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    // every method the entry points call directly or indirectly, the entry points included
+    pub fn reachable(&self, entry_points: &[Member]) -> BTreeSet<Member> {
+        let mut reached = BTreeSet::new();
+        let mut pending: Vec<&Member> = entry_points.iter().collect();
+        while let Some(method) = pending.pop() {
+            if reached.insert(method.clone()) {
+                pending.extend(self.callees(method));
+            }
+        }
+        reached
+    }
+
+    // a line for each call, caller -> callee
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (caller, callees) in self.edges() {
+            for callee in callees {
+                text.push_str(&format!("{} -> {}\n", caller, callee));
+            }
+        }
+        text
+    }
+
+    // Graphviz, one node per method
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph \"calls\" {\n  node [shape=box];\n");
+        for (caller, callees) in self.edges() {
+            for callee in callees {
+                dot.push_str(&format!("  \"{}\" -> \"{}\";\n", escape(&caller.to_string()), escape(&callee.to_string())));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_json(&self) -> String {
+        let calls = self.edges().into_iter()
+            .flat_map(|(caller, callees)| callees.into_iter().map(move |callee| Call { from: caller.to_string(), to: callee.to_string() }))
+            .collect();
+        let graph = JsonCallGraph { methods: self.methods.iter().map(|m| m.to_string()).collect(), calls };
+        serde_json::to_string(&graph).unwrap_or_default() + "\n"
+    }
+}
+
+#[derive(Serialize)]
+struct Call {
+    from: String,
+    to: String,
+}
+
+#[derive(Serialize)]
+struct JsonCallGraph {
+    methods: Vec<String>,
+    calls: Vec<Call>,
+}
+
+// class.name or class.name:descriptor, with the class as an internal or binary name
+fn parse_method(name: &str) -> Option<(String, &str, Option<&str>)> {
+    let (name, descriptor) = match name.split_once(':') {
+        Some((n, d)) => (n, Some(d)),
+        None => (name, None),
+    };
+    let (class, method) = name.rsplit_once('.')?;
+    Some((class.replace('.', "/"), method, descriptor))
+}
+
+// the methods a class on the class path declares with the name, as parse_method takes it
+pub fn find_declared_methods(hierarchy: &Hierarchy, name: &str) -> Vec<Member> {
+    let Some((class, method, descriptor)) = parse_method(name) else { return Vec::new() };
+    let Some(class_file) = hierarchy.get_class_path().get_class(&class) else { return Vec::new() };
+    class_file.methods.iter()
+        .map(|m| member(&class_file, m))
+        .filter(|m| m.name == method && descriptor.is_none_or(|d| m.descriptor == d))
+        .collect()
+}
+
+// the public static void main(String[]) methods of the classes, the methods with one of the annotations and the
+// public methods of the classes that have one, annotations as internal names
+pub fn find_entry_points(hierarchy: &Hierarchy, classes: &[String], annotations: &[String]) -> Vec<Member> {
+    let descriptors: Vec<String> = annotations.iter().map(|a| format!("L{};", a)).collect();
+    let annotated = |class_file: &ClassFile, attributes: &[Attribute]| {
+        ["RuntimeVisibleAnnotations", "RuntimeInvisibleAnnotations"].iter().any(|name| match attribute::find(class_file, attributes, name) {
+            Some(AttributeKind::RuntimeVisibleAnnotations(a) | AttributeKind::RuntimeInvisibleAnnotations(a)) =>
+                a.iter().any(|a| descriptors.contains(a.get_type())),
+            _ => false,
+        })
+    };
+    let mut entry_points = Vec::new();
+    for class in classes {
+        let Some(class_file) = hierarchy.get_class_path().get_class(class) else { continue };
+        let class_annotated = annotated(&class_file, &class_file.get_attributes_vec(&class_file.attributes));
+        for raw in &class_file.methods {
+            let method = member(&class_file, raw);
+            let main = method.name == "main" && method.descriptor == "([Ljava/lang/String;)V"
+                && method.access_flags & (ACC_PUBLIC | ACC_STATIC) == ACC_PUBLIC | ACC_STATIC;
+            let public = method.access_flags & ACC_PUBLIC != 0 && !method.name.starts_with('<');
+            if main || (class_annotated && public) || annotated(&class_file, &class_file.get_attributes_vec(&raw.attributes)) {
+                entry_points.push(method);
+            }
+        }
+    }
+    entry_points
+}
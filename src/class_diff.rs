@@ -0,0 +1,434 @@
+// module class_diff
+// two versions of a class compared by meaning rather than by bytes: the constant pool by value, fields and methods by
+// name and descriptor, attributes decoded, and the code of each method aligned instruction by instruction with its
+// constant pool references resolved, so renumbering the pool or moving code shows no change
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+use crate::access_flags::*;
+use crate::attribute::{AttributeKind, BootstrapMethod};
+use crate::class_file_reader::{ConstantInfo, ConstantPool, Index, Tag};
+use crate::code::{self, Code};
+use crate::code::opcode::Opcode;
+use crate::code::stack_map::StackMapFrame;
+use crate::jasm::{self, CLASS_KEYWORDS, FIELD_KEYWORDS, METHOD_KEYWORDS};
+use crate::{Attribute, ClassFile};
+
+// unchanged lines shown around a change
+const CONTEXT: usize = 3;
+
+// cells of the alignment table past which the lines between a common prefix and suffix are replaced as a whole
+const ALIGN_LIMIT: usize = 1 << 24;
+
+enum Edit<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+// longest common subsequence, after the common prefix and suffix
+fn align<'a>(old: &'a [String], new: &'a [String]) -> Vec<Edit<'a>> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+    let (a, b) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+    let mut edits: Vec<Edit> = old[..prefix].iter().map(|s| Edit::Same(s)).collect();
+    if (a.len() + 1) * (b.len() + 1) > ALIGN_LIMIT {
+        edits.extend(a.iter().map(|s| Edit::Removed(s)));
+        edits.extend(b.iter().map(|s| Edit::Added(s)));
+    } else {
+        // lengths of the common subsequences of the suffixes a[i..] and b[j..]
+        let width = b.len() + 1;
+        let mut table = vec![0u32; (a.len() + 1) * width];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                table[i * width + j] = match a[i] == b[j] {
+                    true => table[(i + 1) * width + j + 1] + 1,
+                    false => table[(i + 1) * width + j].max(table[i * width + j + 1]),
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                edits.push(Edit::Same(&a[i]));
+                i += 1;
+                j += 1;
+            } else if j == b.len() || (i < a.len() && table[(i + 1) * width + j] >= table[i * width + j + 1]) {
+                edits.push(Edit::Removed(&a[i]));
+                i += 1;
+            } else {
+                edits.push(Edit::Added(&b[j]));
+                j += 1;
+            }
+        }
+    }
+    edits.extend(old[old.len() - suffix..].iter().map(|s| Edit::Same(s)));
+    edits
+}
+
+// the changed lines with a little unchanged context, nothing when they are the same
+fn write_edits(out: &mut String, indent: &str, old: &[String], new: &[String]) {
+    let edits = align(old, new);
+    let changed: Vec<bool> = edits.iter().map(|e| !matches!(e, Edit::Same(_))).collect();
+    let near = |i: usize| changed[i.saturating_sub(CONTEXT)..(i + CONTEXT + 1).min(changed.len())].contains(&true);
+    let mut skipped = false;
+    for (i, edit) in edits.iter().enumerate() {
+        if !near(i) {
+            skipped = true;
+            continue;
+        }
+        if skipped {
+            let _ = writeln!(out, "{}  ...", indent);
+            skipped = false;
+        }
+        let _ = match edit {
+            Edit::Same(s) => writeln!(out, "{}  {}", indent, s),
+            Edit::Removed(s) => writeln!(out, "{}- {}", indent, s),
+            Edit::Added(s) => writeln!(out, "{}+ {}", indent, s),
+        };
+    }
+}
+
+// the flags of an InnerClasses row
+const INNER_CLASS_KEYWORDS: [(u16, &str); 10] = [
+    (ACC_PUBLIC, "public"),
+    (ACC_PRIVATE, "private"),
+    (ACC_PROTECTED, "protected"),
+    (ACC_STATIC, "static"),
+    (ACC_FINAL, "final"),
+    (ACC_INTERFACE, "interface"),
+    (ACC_ABSTRACT, "abstract"),
+    (ACC_SYNTHETIC, "synthetic"),
+    (ACC_ANNOTATION, "annotation"),
+    (ACC_ENUM, "enum"),
+];
+
+// - for none
+fn flags(flags: u16, keywords: &[(u16, &str)]) -> String {
+    match jasm::flags_text(flags, keywords).trim_end() {
+        "" => "-".to_string(),
+        text => text.to_string(),
+    }
+}
+
+fn hex(info: &[u8]) -> Vec<String> {
+    info.chunks(32).map(|chunk| chunk.iter().map(|b| format!("{:02x}", b)).collect()).collect()
+}
+
+// a constant by value, with the bootstrap method of a dynamic constant in place of its index
+fn constant(cp: &ConstantPool, bootstrap: &[BootstrapMethod], index: u16, depth: u8) -> String {
+    match cp.get_constant(index) {
+        ConstantInfo(tag @ (Tag::Dynamic | Tag::InvokeDynamic), Index::Dynamic(bsm, _)) if depth < 8 => {
+            let keyword = if *tag == Tag::Dynamic { "Dynamic" } else { "InvokeDynamic" };
+            let (name, descriptor) = cp.get_name_and_type(index).unwrap_or_default();
+            let method = match bootstrap.get(*bsm as usize) {
+                Some(b) => bootstrap_text(cp, bootstrap, b, depth + 1),
+                None => format!("bootstrap {}", bsm),
+            };
+            format!("{} {} {} {}", keyword, name, descriptor, method)
+        }
+        _ => jasm::constant_text(cp, index),
+    }
+}
+
+fn bootstrap_text(cp: &ConstantPool, bootstrap: &[BootstrapMethod], b: &BootstrapMethod, depth: u8) -> String {
+    let arguments: Vec<String> = b.arguments.iter().map(|a| constant(cp, bootstrap, *a, depth)).collect();
+    format!("{} [{}]", constant(cp, bootstrap, b.method_ref, depth), arguments.join(", "))
+}
+
+// what diff compares of one class, each part as lines
+struct ClassText<'a> {
+    class_file: &'a ClassFile,
+    bootstrap: Vec<BootstrapMethod>,
+}
+
+impl<'a> ClassText<'a> {
+    fn new(class_file: &'a ClassFile) -> Self {
+        let bootstrap = class_file.get_class_attributes().bootstrap_methods.unwrap_or_default();
+        ClassText { class_file, bootstrap }
+    }
+
+    fn constant(&self, index: u16) -> String {
+        constant(&self.class_file.constant_pool, &self.bootstrap, index, 0)
+    }
+
+    fn pool(&self) -> BTreeSet<String> {
+        let cp = &self.class_file.constant_pool;
+        (1..=cp.constant_info.len() as u16)
+            .filter(|i| !matches!(cp.get_constant(*i), ConstantInfo(Tag::Unusable, _)))
+            .map(|i| self.constant(i))
+            .collect()
+    }
+
+    // attributes by name, Code has its own comparison
+    fn attributes(&self, attributes: &[Attribute]) -> BTreeMap<String, Vec<String>> {
+        let mut found = BTreeMap::<String, Vec<String>>::new();
+        for a in attributes.iter().filter(|a| a.name != "Code") {
+            let lines = self.attribute(a);
+            found.entry(a.name.clone()).or_default().extend(lines);
+        }
+        found
+    }
+
+    fn attribute(&self, a: &Attribute) -> Vec<String> {
+        let cp = &self.class_file.constant_pool;
+        match a.decode(self.class_file) {
+            AttributeKind::ConstantValue(value) => vec![jasm::literal_text(&value)],
+            AttributeKind::Exceptions(v) | AttributeKind::NestMembers(v) | AttributeKind::PermittedSubclasses(v)
+                | AttributeKind::ModulePackages(v) => v,
+            AttributeKind::InnerClasses(v) => v.iter()
+                .map(|i| format!("{} {} {} {}", i.inner_class, i.outer_class.as_deref().unwrap_or("-"),
+                    i.inner_name.as_deref().unwrap_or("-"), flags(i.flags, &INNER_CLASS_KEYWORDS)))
+                .collect(),
+            AttributeKind::EnclosingMethod(class, method) => vec![format!("{} {}", class, method.unwrap_or_default())],
+            AttributeKind::Synthetic | AttributeKind::Deprecated => Vec::new(),
+            AttributeKind::Signature(s) | AttributeKind::SourceFile(s) | AttributeKind::SourceDebugExtension(s)
+                | AttributeKind::ModuleMainClass(s) | AttributeKind::NestHost(s) => s.lines().map(String::from).collect(),
+            AttributeKind::RuntimeVisibleAnnotations(v) | AttributeKind::RuntimeInvisibleAnnotations(v) =>
+                v.iter().map(|a| format!("{:?}", a)).collect(),
+            AttributeKind::RuntimeVisibleParameterAnnotations(v) | AttributeKind::RuntimeInvisibleParameterAnnotations(v) =>
+                v.iter().enumerate().map(|(i, a)| format!("{} {:?}", i, a)).collect(),
+            AttributeKind::RuntimeVisibleTypeAnnotations(v) | AttributeKind::RuntimeInvisibleTypeAnnotations(v) =>
+                v.iter().map(|a| format!("{:?}", a)).collect(),
+            AttributeKind::AnnotationDefault(value) => vec![format!("{:?}", value)],
+            AttributeKind::BootstrapMethods(v) => v.iter().map(|b| bootstrap_text(cp, &self.bootstrap, b, 0)).collect(),
+            AttributeKind::MethodParameters(v) => v.iter()
+                .map(|p| format!("{} {}", p.name.as_deref().unwrap_or("-"), flags(p.flags, &FIELD_KEYWORDS)))
+                .collect(),
+            AttributeKind::Module(m) => format!("{:#?}", m).lines().map(String::from).collect(),
+            AttributeKind::Record(v) => v.iter()
+                .flat_map(|c| {
+                    let attributes = self.attributes(c.get_attributes()).into_iter()
+                        .flat_map(|(name, lines)| std::iter::once(format!("    {}", name)).chain(lines.into_iter().map(|l| format!("        {}", l))));
+                    std::iter::once(format!("{} {}", c.get_name(), c.get_descriptor())).chain(attributes)
+                })
+                .collect(),
+            _ => hex(&a.info),
+        }
+    }
+
+    // the instructions, branch targets as labels numbered in order, and the lines of the tables the code has
+    fn code(&self, info: &[u8]) -> (Vec<String>, BTreeMap<String, Vec<String>>) {
+        let code = Code::new(info);
+        let instructions: Vec<(u32, &Opcode)> = code.get_instructions().map(|(pc, o)| (pc as u32, o)).collect();
+        let mut targets = BTreeSet::<u32>::new();
+        for (pc, o) in &instructions {
+            let target = |offset: i32| (*pc as i32 + offset) as u32;
+            match o {
+                Opcode::Tableswitch(default, _, _, offsets) => {
+                    targets.insert(target(*default));
+                    targets.extend(offsets.iter().map(|o| target(*o)));
+                }
+                Opcode::Lookupswitch(default, pairs) => {
+                    targets.insert(target(*default));
+                    targets.extend(pairs.iter().map(|(_, o)| target(*o)));
+                }
+                o => targets.extend(o.branch_offset().map(target)),
+            }
+        }
+        for e in code.get_exception_table() {
+            targets.extend([e.start_pc as u32, e.end_pc as u32, e.handler_pc as u32]);
+        }
+        let labels: HashMap<u32, String> = targets.iter().enumerate().map(|(i, pc)| (*pc, format!("L{}", i))).collect();
+        let label = |pc: u32| labels.get(&pc).cloned().unwrap_or_else(|| format!("@{}", pc));
+
+        let mut lines = vec![format!("stack {} locals {}", code.get_max_stack(), code.get_max_locals())];
+        for (pc, o) in &instructions {
+            if let Some(l) = labels.get(pc) {
+                lines.push(format!("{}:", l));
+            }
+            lines.push(format!("    {}", self.instruction(*pc, o, &label)));
+        }
+        if let Some((pc, o)) = instructions.last() {
+            if let Some(l) = labels.get(&(pc + o.length(*pc))) {
+                lines.push(format!("{}:", l));
+            }
+        }
+        for e in code.get_exception_table() {
+            let catch_type = match e.catch_type {
+                0 => "any".to_string(),
+                i => self.class_file.constant_pool.get_name(i),
+            };
+            lines.push(format!("    catch {} from {} to {} using {}", catch_type,
+                label(e.start_pc as u32), label(e.end_pc as u32), label(e.handler_pc as u32)));
+        }
+
+        // pcs become labels, or go, so moved code does not show as a change of every table
+        let mut tables = BTreeMap::<String, Vec<String>>::new();
+        for a in self.class_file.get_attributes_vec(code.get_attributes()) {
+            let text = match a.name.as_str() {
+                "LineNumberTable" => match code::get_line_number_table(&a.info) {
+                    Ok(mut table) => {
+                        table.sort_by_key(|l| l.start_pc);
+                        table.iter().map(|l| format!("line {}", l.line_number)).collect()
+                    }
+                    Err(_) => hex(&a.info),
+                },
+                "LocalVariableTable" | "LocalVariableTypeTable" => match code::get_local_variable_table(self.class_file, &a.info) {
+                    Ok(table) => table.iter()
+                        .map(|v| format!("{} {} {} from {} to {}", v.index, v.name, v.descriptor,
+                            label(v.start_pc as u32), label(v.start_pc as u32 + v.length as u32)))
+                        .collect(),
+                    Err(_) => hex(&a.info),
+                },
+                "StackMapTable" => match a.decode(self.class_file) {
+                    AttributeKind::StackMapTable(frames) => {
+                        let mut pc: Option<u32> = None;
+                        frames.iter().map(|f| {
+                            let (delta, text) = match f {
+                                StackMapFrame::Same(d) => (*d, "same".to_string()),
+                                StackMapFrame::SameLocals1StackItem(d, t) => (*d, format!("same_locals_1_stack_item {:?}", t)),
+                                StackMapFrame::Chop(d, k) => (*d, format!("chop {}", k)),
+                                StackMapFrame::Append(d, locals) => (*d, format!("append {:?}", locals)),
+                                StackMapFrame::Full(d, locals, stack) => (*d, format!("full {:?} {:?}", locals, stack)),
+                            };
+                            let at = pc.map_or(delta as u32, |p| p + delta as u32 + 1);
+                            pc = Some(at);
+                            format!("{} {}", label(at), text)
+                        })
+                        .collect()
+                    }
+                    _ => hex(&a.info),
+                },
+                _ => self.attribute(&a),
+            };
+            tables.entry(a.name.clone()).or_default().extend(text);
+        }
+        (lines, tables)
+    }
+
+    fn instruction(&self, pc: u32, o: &Opcode, label: &dyn Fn(u32) -> String) -> String {
+        let cp = &self.class_file.constant_pool;
+        let target = |offset: i32| label((pc as i32 + offset) as u32);
+        let mnemonic = o.mnemonic();
+        match o {
+            Opcode::Getfield(i) | Opcode::Getstatic(i) | Opcode::Putfield(i) | Opcode::Putstatic(i)
+                | Opcode::Invokevirtual(i) | Opcode::Invokespecial(i) | Opcode::Invokestatic(i)
+                | Opcode::Invokedynamic(i) | Opcode::Ldc2W(i) | Opcode::LdcW(i) => format!("{} {}", mnemonic, self.constant(*i)),
+            Opcode::Ldc(i) => format!("{} {}", mnemonic, self.constant(*i as u16)),
+            Opcode::Invokeinterface(i, count) => format!("{} {} {}", mnemonic, self.constant(*i), count),
+            Opcode::New(i) | Opcode::Anewarray(i) | Opcode::Checkcast(i) | Opcode::Instanceof(i) =>
+                format!("{} {}", mnemonic, cp.get_name(*i)),
+            Opcode::Multianewarray(i, dimensions) => format!("{} {} {}", mnemonic, cp.get_name(*i), dimensions),
+            Opcode::Tableswitch(default, low, _, offsets) => {
+                let offsets: Vec<String> = offsets.iter().map(|o| target(*o)).collect();
+                format!("{} {} {} default {}", mnemonic, low, offsets.join(" "), target(*default))
+            }
+            Opcode::Lookupswitch(default, pairs) => {
+                let pairs: Vec<String> = pairs.iter().map(|(m, o)| format!("{} {}", m, target(*o))).collect();
+                format!("{} {} default {}", mnemonic, pairs.join(" "), target(*default))
+            }
+            o => match o.branch_offset() {
+                Some(offset) => format!("{} {}", mnemonic, target(offset)),
+                None => o.to_string(),
+            },
+        }
+    }
+}
+
+fn write_attributes(out: &mut String, indent: &str, what: &str, old: &BTreeMap<String, Vec<String>>, new: &BTreeMap<String, Vec<String>>) {
+    let names: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    for name in names {
+        match (old.get(name), new.get(name)) {
+            (Some(_), None) => { let _ = writeln!(out, "{}- {} {}", indent, what, name); }
+            (None, Some(_)) => { let _ = writeln!(out, "{}+ {} {}", indent, what, name); }
+            (Some(a), Some(b)) if a != b => {
+                let _ = writeln!(out, "{}{} {}", indent, what, name);
+                write_edits(out, &format!("{}    ", indent), a, b);
+            }
+            _ => (),
+        }
+    }
+}
+
+// the part of a member that changed, under a heading naming it
+fn write_member(out: &mut String, heading: &str, body: &str) {
+    if !body.is_empty() {
+        let _ = writeln!(out, "{}", heading);
+        out.push_str(body);
+    }
+}
+
+// what changed from old to new, nothing when they mean the same
+pub fn diff(old: &ClassFile, new: &ClassFile) -> String {
+    let (a, b) = (ClassText::new(old), ClassText::new(new));
+    let mut out = String::new();
+    let mut changed = |what: &str, x: String, y: String| {
+        if x != y {
+            let _ = writeln!(out, "{} {} -> {}", what, x, y);
+        }
+    };
+    changed("version", old.get_version(), new.get_version());
+    changed("flags", flags(old.get_access_flags(), &CLASS_KEYWORDS), flags(new.get_access_flags(), &CLASS_KEYWORDS));
+    changed("class", old.get_class_name(), new.get_class_name());
+    changed("super", old.get_super_class_name().unwrap_or_default(), new.get_super_class_name().unwrap_or_default());
+    let (x, y) = (old.get_interface_names(), new.get_interface_names());
+    if x != y {
+        out.push_str("interfaces\n");
+        write_edits(&mut out, "    ", &x, &y);
+    }
+    let (x, y) = (a.pool(), b.pool());
+    if x != y {
+        out.push_str("constant pool\n");
+        for removed in x.difference(&y) {
+            let _ = writeln!(out, "    - {}", removed);
+        }
+        for added in y.difference(&x) {
+            let _ = writeln!(out, "    + {}", added);
+        }
+    }
+
+    let (old_fields, new_fields) = (old.get_fields(), new.get_fields());
+    let key = |name: &String, descriptor: &String| (name.clone(), descriptor.clone());
+    let new_by_key: HashMap<_, _> = new_fields.iter().map(|f| (key(f.get_name(), f.get_descriptor()), f)).collect();
+    for f in &old_fields {
+        let heading = format!("field {} {}", f.get_name(), f.get_descriptor());
+        let Some(g) = new_by_key.get(&key(f.get_name(), f.get_descriptor())) else {
+            let _ = writeln!(out, "- {}", heading);
+            continue;
+        };
+        let mut body = String::new();
+        let (x, y) = (flags(*f.get_flags(), &FIELD_KEYWORDS), flags(*g.get_flags(), &FIELD_KEYWORDS));
+        if x != y {
+            let _ = writeln!(body, "    flags {} -> {}", x, y);
+        }
+        write_attributes(&mut body, "    ", "attribute", &a.attributes(f.get_attributes()), &b.attributes(g.get_attributes()));
+        write_member(&mut out, &heading, &body);
+    }
+    for g in new_fields.iter().filter(|g| !old_fields.iter().any(|f| f.get_name() == g.get_name() && f.get_descriptor() == g.get_descriptor())) {
+        let _ = writeln!(out, "+ field {} {}", g.get_name(), g.get_descriptor());
+    }
+
+    let (old_methods, new_methods) = (old.get_methods(), new.get_methods());
+    let new_by_key: HashMap<_, _> = new_methods.iter().map(|m| (key(m.get_name(), m.get_descriptor()), m)).collect();
+    let code = |text: &ClassText, attributes: &[Attribute]| match attributes.iter().find(|a| a.name == "Code") {
+        Some(a) => text.code(&a.info),
+        None => (Vec::new(), BTreeMap::new()),
+    };
+    for m in &old_methods {
+        let heading = format!("method {} {}", m.get_name(), m.get_descriptor());
+        let Some(n) = new_by_key.get(&key(m.get_name(), m.get_descriptor())) else {
+            let _ = writeln!(out, "- {}", heading);
+            continue;
+        };
+        let mut body = String::new();
+        let (x, y) = (flags(*m.get_flags(), &METHOD_KEYWORDS), flags(*n.get_flags(), &METHOD_KEYWORDS));
+        if x != y {
+            let _ = writeln!(body, "    flags {} -> {}", x, y);
+        }
+        write_attributes(&mut body, "    ", "attribute", &a.attributes(m.get_attributes()), &b.attributes(n.get_attributes()));
+        let ((x, x_tables), (y, y_tables)) = (code(&a, m.get_attributes()), code(&b, n.get_attributes()));
+        if x != y {
+            body.push_str("    code\n");
+            write_edits(&mut body, "        ", &x, &y);
+        }
+        write_attributes(&mut body, "    ", "code attribute", &x_tables, &y_tables);
+        write_member(&mut out, &heading, &body);
+    }
+    for n in new_methods.iter().filter(|n| !old_methods.iter().any(|m| m.get_name() == n.get_name() && m.get_descriptor() == n.get_descriptor())) {
+        let _ = writeln!(out, "+ method {} {}", n.get_name(), n.get_descriptor());
+    }
+
+    let class_attributes = |c: &ClassFile, text: &ClassText| text.attributes(&c.get_attributes_vec(&c.attributes));
+    write_attributes(&mut out, "", "attribute", &class_attributes(old, &a), &class_attributes(new, &b));
+    out
+}
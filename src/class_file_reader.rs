@@ -1,6 +1,6 @@
 // module class_file_reader
-use std::fs::File;
-use std::io::Read;
+use std::fs;
+use std::io::{Cursor, Read};
 use std::collections::HashMap;
 use std::fmt;
 use std::io::Seek;
@@ -8,27 +8,28 @@ use crate::Dump;
 
 const JAVAP_FILE_NOT_FOUND: i32 = 1;
 
-    const TAG_UTF8: u8 = 1;
-    const TAG_INTEGER: u8 = 3;
-    const TAG_FLOAT: u8 = 4;
-    const TAG_LONG: u8 = 5;
-    const TAG_DOUBLE: u8 = 6;
-    const TAG_CLASS: u8 = 7;
-    const TAG_STRING: u8 = 8;
-    const TAG_FIELDREF: u8 = 9;
-    const TAG_METHODREF: u8 = 10;
-    const TAG_INTERFACEMETHODREF: u8 = 11;
-    const TAG_NAMEANDTYPE: u8 = 12;
-    const TAG_METHODHANDLE: u8 = 15;
-    const TAG_METHODTYPE: u8 = 16;
-    const TAG_DYNAMIC: u8 = 17;
-    const TAG_INVOKEDYNAMIC: u8 = 18;
-    const TAG_MODULE: u8 = 19;
-    const TAG_PACKAGE: u8 = 20;
-
-
-enum Tag {
-    Utf8,
+    pub(crate) const TAG_UTF8: u8 = 1;
+    pub(crate) const TAG_INTEGER: u8 = 3;
+    pub(crate) const TAG_FLOAT: u8 = 4;
+    pub(crate) const TAG_LONG: u8 = 5;
+    pub(crate) const TAG_DOUBLE: u8 = 6;
+    pub(crate) const TAG_CLASS: u8 = 7;
+    pub(crate) const TAG_STRING: u8 = 8;
+    pub(crate) const TAG_FIELDREF: u8 = 9;
+    pub(crate) const TAG_METHODREF: u8 = 10;
+    pub(crate) const TAG_INTERFACEMETHODREF: u8 = 11;
+    pub(crate) const TAG_NAMEANDTYPE: u8 = 12;
+    pub(crate) const TAG_METHODHANDLE: u8 = 15;
+    pub(crate) const TAG_METHODTYPE: u8 = 16;
+    pub(crate) const TAG_DYNAMIC: u8 = 17;
+    pub(crate) const TAG_INVOKEDYNAMIC: u8 = 18;
+    pub(crate) const TAG_MODULE: u8 = 19;
+    pub(crate) const TAG_PACKAGE: u8 = 20;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Tag {
+    Utf8 = 1,
     Integer = 3,
     Float,
     Long,
@@ -45,6 +46,7 @@ enum Tag {
     InvokeDynamic,
     Module,
     Package,
+    Unusable,   // second slot of a Long or Double
 }
 
 impl fmt::Display for Tag {
@@ -67,11 +69,12 @@ impl fmt::Display for Tag {
             Tag::InvokeDynamic =>     write!(f, "InvokeDynamic     "),
             Tag::Module =>            write!(f, "Module            "),
             Tag::Package =>           write!(f, "Package           "),
+            Tag::Unusable =>          write!(f, "Unusable          "),
         }
     }
 }
 
-#[derive(Debug)] // todo format numbers to say 17.0
+#[derive(Debug, Clone, PartialEq, Eq)] // todo format numbers to say 17.0
 pub enum Index {
     Single(u16),
     Pair(u16, u16),
@@ -91,7 +94,8 @@ impl fmt::Display for Index {
     }
 }
 
-pub struct ConstantInfo(Tag, pub Index);
+#[derive(Clone)]
+pub struct ConstantInfo(pub(crate) Tag, pub Index);
 
 impl fmt::Display for ConstantInfo {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -115,7 +119,7 @@ impl fmt::Display for LiteralInfo {
             LiteralInfo::Integer(i) => write!(f, "{}", i),
             LiteralInfo::Long(l) => write!(f, "{}", l),
             LiteralInfo::Double(d) => write!(f, "{}", d),
-            &LiteralInfo::Float(_) => todo!(),
+            LiteralInfo::Float(fl) => write!(f, "{}", fl),
         }
     }
 }
@@ -138,7 +142,7 @@ impl FieldInfo {
    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct AttributeInfo {
      pub attribute_name_index: Index,
      pub info: Vec<u8>,
@@ -160,26 +164,64 @@ impl fmt::Display for JavaVersion {
     }
 }
 
+#[derive(Debug)]
+pub enum ClassFileError {
+    NotClass,               // no 0xcafebabe magic
+    Truncated(String),      // what was being read when the bytes ran out
+    BadTag(u8),             // of a constant pool entry
+    BadClass,               // this_class, super_class or an interface is not a Class entry
+    BadName,                // a member name or descriptor, or an attribute name, is not a Utf8 entry
+}
+
+impl fmt::Display for ClassFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClassFileError::NotClass => write!(f, "not a java class file"),
+            ClassFileError::Truncated(what) => write!(f, "truncated reading {}", what),
+            ClassFileError::BadTag(tag) => write!(f, "invalid tag {} in constant pool", tag),
+            ClassFileError::BadClass => write!(f, "this_class, super_class or an interface is not a Class entry"),
+            ClassFileError::BadName => write!(f, "a member name or descriptor, or an attribute name, is not a Utf8 entry"),
+        }
+    }
+}
+
+impl std::error::Error for ClassFileError {}
+
+// the whole class is held in memory, from a file, a jar entry or a jimage resource
 pub struct ClassFileReader {
-    file: File,
+    file: Cursor<Vec<u8>>,
     mode: Dump,
     pub file_name: String,
     context: Option<String>,
+    // the first error, every read after it gives 0 or nothing so counts end the reading early
+    pub(crate) error: Option<ClassFileError>,
 }
 
 impl ClassFileReader {
     pub fn new(file_name: &String, dump: Dump) -> Self {
+        let bytes = match fs::read(file_name) {
+            Ok(r) => r,
+            Err(err) => {
+                eprintln!("jcfreader: Can't open file {} - {}", &file_name, err);
+                std::process::exit(JAVAP_FILE_NOT_FOUND);
+            }
+        };
+        ClassFileReader::from_bytes(file_name, bytes, dump)
+    }
+
+    pub fn from_bytes(file_name: &str, bytes: Vec<u8>, dump: Dump) -> Self {
         Self {
-            file : match File::open(file_name) {
-                Ok(r) => r,
-                Err(err) => {
-                    eprintln!("jcfreader: Can't open file {} - {}", &file_name, err);
-                    std::process::exit(JAVAP_FILE_NOT_FOUND);
-                }
-            },
+            file: Cursor::new(bytes),
             file_name: file_name.to_string(),
             mode: dump,
             context: None,
+            error: None,
+        }
+    }
+
+    pub(crate) fn fail(&mut self, error: ClassFileError) {
+        if self.error.is_none() {
+            self.error = Some(error);
         }
     }
 
@@ -191,7 +233,7 @@ impl ClassFileReader {
         s
     }
 
-    pub fn dump_bytes<'a>(&'a mut self, pos: u64, buf: &'a [u8]) -> &[u8] {
+    pub fn dump_bytes<'a>(&'a mut self, pos: u64, buf: &'a [u8]) -> &'a [u8] {
         let mut pos = pos;
         match self.mode {
             Dump::Hex => {
@@ -229,51 +271,55 @@ impl ClassFileReader {
         num
     }
 
-    pub fn read_f64(&mut self) -> f64 {
-        let mut buf = [0; 8];
+    // len bytes and where they start, None after an error or when fewer are left
+    fn take(&mut self, len: usize, what: &str) -> Option<(u64, Vec<u8>)> {
+        if self.error.is_some() {
+            return None;
+        }
         let pos = self.file.stream_position().unwrap();
-        match self.file.read_exact(&mut buf) {
-            Ok(_) => self.dump(pos, &buf, f64::from_be_bytes(buf)),
-            Err(err) => {
-                eprintln!("jcfreader: error reading f64 {} - {}", self.file_name, err);
-                std::process::exit(JAVAP_FILE_NOT_FOUND);
-            }
+        if (self.file.get_ref().len() as u64).saturating_sub(pos) < len as u64 {
+            let what = match self.context.take() {
+                Some(context) => format!("{} {}", what, context),
+                None => what.to_string(),
+            };
+            self.fail(ClassFileError::Truncated(format!("{} at {:#x}", what, pos)));
+            return None;
+        }
+        let mut buf = vec![0u8; len];
+        self.file.read_exact(&mut buf).unwrap();
+        Some((pos, buf))
+    }
+
+    fn take_array<const N: usize>(&mut self, what: &str) -> Option<(u64, [u8; N])> {
+        let (pos, buf) = self.take(N, what)?;
+        Some((pos, buf.try_into().unwrap()))
+    }
+
+    pub fn read_f64(&mut self) -> f64 {
+        match self.take_array("f64") {
+            Some((pos, buf)) => self.dump(pos, &buf, f64::from_be_bytes(buf)),
+            None => 0.0,
         }
     }
 
     pub fn read_f32(&mut self) -> f32 {
-        let mut buf = [0; 4];
-        let pos = self.file.stream_position().unwrap();
-        match self.file.read_exact(&mut buf) {
-            Ok(_) => self.dump(pos, &buf, f32::from_be_bytes(buf)),
-            Err(err) => {
-                eprintln!("jcfreader: error reading f32 {} - {}", self.file_name, err);
-                std::process::exit(JAVAP_FILE_NOT_FOUND);
-            }
+        match self.take_array("f32") {
+            Some((pos, buf)) => self.dump(pos, &buf, f32::from_be_bytes(buf)),
+            None => 0.0,
         }
     }
 
     pub fn read_u64(&mut self) -> u64 {
-        let mut buf = [0; 8];
-        let pos = self.file.stream_position().unwrap();
-        match self.file.read_exact(&mut buf) {
-            Ok(_) => self.dump(pos, &buf, u64::from_be_bytes(buf)),
-            Err(err) => {
-                eprintln!("jcfreader: error reading u64 {} - {}", self.file_name, err);
-                std::process::exit(JAVAP_FILE_NOT_FOUND);
-            }
+        match self.take_array("u64") {
+            Some((pos, buf)) => self.dump(pos, &buf, u64::from_be_bytes(buf)),
+            None => 0,
         }
     }
 
     pub fn read_u32(&mut self) -> u32 {
-        let mut buf = [0; 4];
-        let pos = self.file.stream_position().unwrap();
-        match self.file.read_exact(&mut buf) {
-            Ok(_) => self.dump(pos, &buf, u32::from_be_bytes(buf)),
-            Err(err) => {
-                eprintln!("jcfreader: error reading u32 {} - {}", self.file_name, err);
-                std::process::exit(JAVAP_FILE_NOT_FOUND);
-            }
+        match self.take_array("u32") {
+            Some((pos, buf)) => self.dump(pos, &buf, u32::from_be_bytes(buf)),
+            None => 0,
         }
     }
 
@@ -281,53 +327,33 @@ impl ClassFileReader {
         self.context = Some(context.to_string());
         self
     }
-     
 
     pub fn read_u16(&mut self) -> u16 {
-        let mut buf = [0; 2];
-        let pos = self.file.stream_position().unwrap();
-        match self.file.read_exact(&mut buf) {
-            Ok(_) => self.dump(pos, &buf, u16::from_be_bytes(buf)),
-            Err(err) => {
-                eprintln!("jcfreader: error reading u16 {} - {}", self.file_name, err); 
-                std::process::exit(JAVAP_FILE_NOT_FOUND);
-            }
+        match self.take_array("u16") {
+            Some((pos, buf)) => self.dump(pos, &buf, u16::from_be_bytes(buf)),
+            None => 0,
         }
     }
 
     fn read_u8(&mut self) -> u8 {
-        let mut buf = [0; 1];
-        let pos = self.file.stream_position().unwrap();
-        match self.file.read_exact(&mut buf) {
-            Ok(_) => self.dump(pos, &buf, u8::from_be_bytes(buf)),
-            Err(err) => {
-                eprintln!("jcfreader: error reading u8 {} - {}", self.file_name, err);
-                std::process::exit(JAVAP_FILE_NOT_FOUND);
-            }
+        match self.take_array("u8") {
+            Some((pos, buf)) => self.dump(pos, &buf, u8::from_be_bytes(buf)),
+            None => 0,
         }
     }
 
     fn read_bytes(&mut self, len: usize) -> Vec<u8> {
-        let mut buf = vec![0u8; len];
-        let pos = self.file.stream_position().unwrap();
-        match self.file.read_exact(&mut buf) {
-            Ok(_) => self.dump_bytes(pos, &buf).to_vec(),
-            Err(err) => {
-                eprintln!("jcfreader: error reading {} bytes {} - {}", len, self.file_name, err);
-                std::process::exit(JAVAP_FILE_NOT_FOUND);
-            }
+        match self.take(len, "bytes") {
+            Some((pos, buf)) => self.dump_bytes(pos, &buf).to_vec(),
+            None => Vec::new(),
         }
     }
- 
-    fn read_string(&mut self, len: usize) -> String {
-        let mut buf = vec![0u8; len];
-        let pos = self.file.stream_position().unwrap();
-        match self.file.read_exact(&mut buf) {
-            Ok(_) => self.dump_string(pos, String::from_utf8(buf).unwrap()),
-            Err(err) => {
-                eprintln!("jcfreader: error reading n bytes {} - {}", self.file_name, err);
-                std::process::exit(JAVAP_FILE_NOT_FOUND);
-            }
+
+    // modified UTF-8, the raw bytes are kept too
+    fn read_string(&mut self, len: usize) -> (String, Vec<u8>) {
+        match self.take(len, "utf8") {
+            Some((pos, buf)) => (self.dump_string(pos, decode_modified_utf8(&buf)), buf),
+            None => (String::new(), Vec::new()),
         }
     }
 
@@ -404,18 +430,72 @@ impl ClassFileReader {
 
 } //
 
+// JVMS 4.4.7, 0 and supplementary characters differ from standard UTF-8
+pub fn decode_modified_utf8(bytes: &[u8]) -> String {
+    let mut units = Vec::<u16>::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i] as u16;
+        if b & 0x80 == 0 {
+            units.push(b);
+            i += 1;
+        } else if b & 0xe0 == 0xc0 && i + 1 < bytes.len() {
+            units.push(((b & 0x1f) << 6) | (bytes[i + 1] as u16 & 0x3f));
+            i += 2;
+        } else if b & 0xf0 == 0xe0 && i + 2 < bytes.len() {
+            units.push(((b & 0x0f) << 12) | ((bytes[i + 1] as u16 & 0x3f) << 6) | (bytes[i + 2] as u16 & 0x3f));
+            i += 3;
+        } else {
+            units.push(0xfffd);
+            i += 1;
+        }
+    }
+    String::from_utf16_lossy(&units)
+}
+
+pub fn encode_modified_utf8(s: &str) -> Vec<u8> {
+    let mut bytes = Vec::<u8>::with_capacity(s.len());
+    for u in s.encode_utf16() {
+        match u {
+            0x0001..=0x007f => bytes.push(u as u8),
+            0x0000 | 0x0080..=0x07ff => {
+                bytes.push(0xc0 | (u >> 6) as u8);
+                bytes.push(0x80 | (u & 0x3f) as u8);
+            }
+            _ => {
+                bytes.push(0xe0 | (u >> 12) as u8);
+                bytes.push(0x80 | ((u >> 6) & 0x3f) as u8);
+                bytes.push(0x80 | (u & 0x3f) as u8);
+            }
+        }
+    }
+    bytes
+}
+
 // todo create iterator for this which combines the both :___)
+#[derive(Clone)]
 pub struct ConstantPool {
     pub constant_info: Vec<ConstantInfo>,
-    literal_pool: HashMap<u16, LiteralInfo>, 
+    pub(crate) literal_pool: HashMap<u16, LiteralInfo>, 
+    // Utf8 entries that would not be written back the same from their String
+    pub(crate) raw_utf8: HashMap<u16, Vec<u8>>,
 }
 
 impl ConstantPool {
+    pub(crate) fn empty() -> Self {
+        Self {
+            constant_info: Vec::new(),
+            literal_pool: HashMap::new(),
+            raw_utf8: HashMap::new(),
+        }
+    }
+
     pub fn new(reader: &mut ClassFileReader) -> Self {
         let count = reader.context("constant count").read_u16();
         let mut cp = Self {
             constant_info : Vec::<ConstantInfo>::with_capacity(count as usize),
             literal_pool : HashMap::new(),
+            raw_utf8 : HashMap::new(),
         };
         cp.read_constant_pool(count, reader);
         cp
@@ -449,12 +529,15 @@ impl ConstantPool {
                 TAG_INVOKEDYNAMIC => self.read_invoke_dynamic(reader),
                 TAG_MODULE => self.read_module(reader),
                 TAG_PACKAGE => self.read_package(reader),
-                _ => { 
-                    eprintln!("Invalid tag {} in constant pool", tag);
-                    std::process::exit(JAVAP_FILE_NOT_FOUND);
+                _ => {
+                    reader.fail(ClassFileError::BadTag(tag));
+                    return;
                 }
             };
             self.constant_info.push(info);
+            if tag == TAG_LONG || tag == TAG_DOUBLE {
+                self.constant_info.push(ConstantInfo(Tag::Unusable, Index::Single(0)));
+            }
             index += 1; 
         };
     }
@@ -473,7 +556,10 @@ impl ConstantPool {
 
     fn read_utf8(&mut self, index: u16, reader: &mut ClassFileReader) -> ConstantInfo {
         let len = reader.context("utf8 len").read_u16();  
-        let string = reader.read_string(len as usize);
+        let (string, bytes) = reader.read_string(len as usize);
+        if encode_modified_utf8(&string) != bytes {
+            self.raw_utf8.insert(index, bytes);
+        }
         self.literal_pool.insert(index, LiteralInfo::String(string));
         ConstantInfo(Tag::Utf8, Index::Single(index))  
     }
@@ -538,6 +624,22 @@ impl ConstantPool {
         ConstantInfo(Tag::Package, reader.context("package").read_constant_index())
     }
 
+    // ConstantValue, an Integer, Float, Long, Double or String entry
+    pub fn get_constant_value(&self, index: u16) -> LiteralInfo {
+        match self.get_constant(index) {
+            ConstantInfo(Tag::String, i) => LiteralInfo::String(self.get_item(i)),
+            _ => self.get_literal(index).clone(),
+        }
+    }
+
+    // bytes of a Utf8 entry as they are written to the class file
+    pub fn get_utf8_bytes(&self, index: u16) -> Vec<u8> {
+        match self.raw_utf8.get(&index) {
+            Some(bytes) => bytes.clone(),
+            None => encode_modified_utf8(&self.get_literal(index).to_string()),
+        }
+    }
+
     pub fn get_literal(&self, index: u16) -> &LiteralInfo {
         self.literal_pool.get(&index).unwrap()
     }
@@ -549,29 +651,99 @@ impl ConstantPool {
             Index::Pair(i1, i2) => 
                 format!("{}:{}", self.get_literal(*i1), self.get_literal(*i2)),
             Index::Ref(i1, i2) => {
-                let info = &self.constant_info[*i1 as usize];
-                let data1 = self.get_item(&info.1);
-                let info = &self.constant_info[*i2 as usize];
-                let data2 = self.get_item(&info.1);
-                format!("{}.{}", data2, data1)
+                let class = self.get_item(&self.get_constant(*i1).1);
+                let name_and_type = self.get_item(&self.get_constant(*i2).1);
+                format!("{}.{}", class, name_and_type)
             },
             Index::Dynamic(i1, i2) => {
-                let info = &self.constant_info[*i1 as usize];
-                let bootstrap = &info.1;
-                let info = &self.constant_info[*i2 as usize];
-                let name_and_type = self.get_item(&info.1);
-                format!("{}.{}", bootstrap, name_and_type)
+                let name_and_type = self.get_item(&self.get_constant(*i2).1);
+                format!("{}:{}", i1, name_and_type)
             }
             Index::MethodHandle(i1, i2) => {
-                let info = &self.constant_info[*i1 as usize];
-                let kind = &info.1;
-                let info = &self.constant_info[*i2 as usize];
-                let index = self.get_item(&info.1);
-                format!("{}.{}", kind, index)
+                let reference = self.get_item(&self.get_constant(*i2).1);
+                format!("{}:{}", i1, reference)
             }
         }
     }
 
+    // constant_info has a slot for every index, including the unusable ones after Long and Double
+    pub fn get_constant(&self, index: u16) -> &ConstantInfo {
+        &self.constant_info[index as usize - 1]
+    }
+
+    // None for index 0 or past the end
+    pub(crate) fn find_constant(&self, index: u16) -> Option<&ConstantInfo> {
+        match index {
+            0 => None,
+            i => self.constant_info.get(i as usize - 1),
+        }
+    }
+
+    pub(crate) fn find_class(&self, index: u16) -> Option<String> {
+        self.find_named(index, Tag::Class)
+    }
+
+    // name behind a Class, Module or Package entry, None for an entry with another tag
+    pub(crate) fn find_named(&self, index: u16, tag: Tag) -> Option<String> {
+        match self.find_constant(index)? {
+            ConstantInfo(t, Index::Single(name)) if *t == tag => self.find_utf8(*name),
+            _ => None,
+        }
+    }
+
+    // as get_constant_value, None for an entry a ConstantValue attribute cannot name
+    pub(crate) fn find_constant_value(&self, index: u16) -> Option<LiteralInfo> {
+        match self.find_constant(index)? {
+            ConstantInfo(Tag::String, Index::Single(i)) => self.find_utf8(*i).map(LiteralInfo::String),
+            ConstantInfo(Tag::Integer | Tag::Float | Tag::Long | Tag::Double, _) => self.literal_pool.get(&index).cloned(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn find_utf8(&self, index: u16) -> Option<String> {
+        match self.find_constant(index)? {
+            ConstantInfo(Tag::Utf8, _) => Some(self.get_item(&Index::Single(index))),
+            _ => None,
+        }
+    }
+
+    // whether an attribute name index is the Utf8 entry of this name
+    pub(crate) fn is_named(&self, index: &Index, name: &str) -> bool {
+        matches!(index, Index::Single(i) if self.find_utf8(*i).as_deref() == Some(name))
+    }
+
+    // name and descriptor behind a Fieldref, Methodref, InterfaceMethodref, Dynamic or InvokeDynamic entry
+    pub fn get_name_and_type(&self, index: u16) -> Option<(String, String)> {
+        let nt = match self.find_constant(index)? {
+            ConstantInfo(Tag::FieldRef | Tag::MethodRef | Tag::InterfaceMethodRef, Index::Ref(_, nt)) => *nt,
+            ConstantInfo(Tag::Dynamic | Tag::InvokeDynamic, Index::Dynamic(_, nt)) => *nt,
+            _ => return None,
+        };
+        match self.find_constant(nt)? {
+            ConstantInfo(Tag::NameAndType, Index::Pair(n, d)) => Some((self.find_utf8(*n)?, self.find_utf8(*d)?)),
+            _ => None,
+        }
+    }
+
+    // class, name and descriptor of a Fieldref, Methodref or InterfaceMethodref entry
+    pub fn get_member(&self, index: u16) -> Option<(String, String, String)> {
+        let class = match self.find_constant(index)? {
+            ConstantInfo(Tag::FieldRef | Tag::MethodRef | Tag::InterfaceMethodRef, Index::Ref(c, _)) => *c,
+            _ => return None,
+        };
+        let class = match self.find_constant(class)? {
+            ConstantInfo(Tag::Class, Index::Single(n)) => self.find_utf8(*n)?,
+            _ => return None,
+        };
+        let (name, descriptor) = self.get_name_and_type(index)?;
+        Some((class, name, descriptor))
+    }
+
+    // name behind a Class, Module, Package, String or MethodType entry
+    pub fn get_name(&self, index: u16) -> String {
+        self.get_item(&self.get_constant(index).1)
+    }
+
     // fn iter(&self) -> PoolIter {
         // let mut iter = self.constant_pool.iter();
         // iter
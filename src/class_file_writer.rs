@@ -0,0 +1,168 @@
+// module class_file_writer
+// ClassFile back to bytes, an unmodified class writes back byte for byte
+use std::fmt;
+use std::io::Write;
+use crate::class_file_reader::{AttributeInfo, ConstantPool, Index, LiteralInfo, Tag};
+use crate::ClassFile;
+
+const JAVA_MAGIC: u32 = 0xcafebabe;
+
+// why a class cannot be written
+#[derive(Debug)]
+pub enum WriteError {
+    Utf8Length(u16, usize),     // index and length of an entry longer than its u16 length can say
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WriteError::Utf8Length(index, len) => write!(f, "Utf8 constant #{} is {} bytes, more than 65535", index, len),
+        }
+    }
+}
+
+impl std::error::Error for WriteError {}
+
+#[derive(Default)]
+pub struct ClassFileWriter {
+    bytes: Vec<u8>,
+}
+
+impl ClassFileWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn write_u8(&mut self, v: u8) {
+        self.bytes.push(v);
+    }
+
+    pub fn write_u16(&mut self, v: u16) {
+        self.bytes.extend_from_slice(&v.to_be_bytes());
+    }
+
+    pub fn write_u32(&mut self, v: u32) {
+        self.bytes.extend_from_slice(&v.to_be_bytes());
+    }
+
+    pub fn write_u64(&mut self, v: u64) {
+        self.bytes.extend_from_slice(&v.to_be_bytes());
+    }
+
+    pub fn write_bytes(&mut self, v: &[u8]) {
+        self.bytes.extend_from_slice(v);
+    }
+
+    pub fn write_index(&mut self, index: &Index) {
+        match index {
+            Index::Single(i) => self.write_u16(*i),
+            Index::Pair(i1, i2) | Index::Ref(i1, i2) | Index::Dynamic(i1, i2) => {
+                self.write_u16(*i1);
+                self.write_u16(*i2);
+            }
+            Index::MethodHandle(kind, i) => {
+                self.write_u8(*kind);
+                self.write_u16(*i);
+            }
+        }
+    }
+
+    // the unusable slot after a Long or Double is counted but not written
+    pub fn write_constant_pool(&mut self, cp: &ConstantPool) -> Result<(), WriteError> {
+        self.write_u16(cp.constant_info.len() as u16 + 1);
+        for (i, info) in cp.constant_info.iter().enumerate() {
+            let index = i as u16 + 1;
+            match info.0 {
+                Tag::Unusable => continue,
+                Tag::Utf8 => {
+                    let bytes = cp.get_utf8_bytes(index);
+                    self.write_u8(Tag::Utf8 as u8);
+                    self.write_u16(u16::try_from(bytes.len()).map_err(|_| WriteError::Utf8Length(index, bytes.len()))?);
+                    self.write_bytes(&bytes);
+                }
+                Tag::Integer | Tag::Float | Tag::Long | Tag::Double => {
+                    self.write_u8(info.0 as u8);
+                    match cp.get_literal(index) {
+                        LiteralInfo::Integer(v) => self.write_u32(*v),
+                        LiteralInfo::Float(v) => self.write_u32(v.to_bits()),
+                        LiteralInfo::Long(v) => self.write_u64(*v),
+                        LiteralInfo::Double(v) => self.write_u64(v.to_bits()),
+                        LiteralInfo::String(_) => unreachable!(),
+                    }
+                }
+                _ => {
+                    self.write_u8(info.0 as u8);
+                    self.write_index(&info.1);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write_attributes(&mut self, attributes: &[AttributeInfo]) {
+        self.write_u16(attributes.len() as u16);
+        for a in attributes {
+            self.write_index(&a.attribute_name_index);
+            self.write_u32(a.info.len() as u32);
+            self.write_bytes(&a.info);
+        }
+    }
+
+    pub fn write_class_file(&mut self, class_file: &ClassFile) -> Result<(), WriteError> {
+        self.write_u32(JAVA_MAGIC);
+        self.write_u16(class_file.version.0);
+        self.write_u16(class_file.version.1);
+        self.write_constant_pool(&class_file.constant_pool)?;
+        self.write_u16(class_file.access_flags);
+        self.write_index(&class_file.this_class);
+        self.write_index(&class_file.super_class);
+        self.write_u16(class_file.interfaces.len() as u16);
+        for i in &class_file.interfaces {
+            self.write_index(i);
+        }
+        self.write_u16(class_file.fields.len() as u16);
+        for f in &class_file.fields {
+            self.write_u16(f.access_flags);
+            self.write_index(&f.name_index);
+            self.write_index(&f.descriptor_index);
+            self.write_attributes(&f.attributes);
+        }
+        self.write_u16(class_file.methods.len() as u16);
+        for m in &class_file.methods {
+            self.write_u16(m.access_flags);
+            self.write_index(&m.name_index);
+            self.write_index(&m.descriptor_index);
+            self.write_attributes(&m.attributes);
+        }
+        self.write_attributes(&class_file.attributes);
+        Ok(())
+    }
+}
+
+impl ClassFile {
+    // as try_to_bytes, ending the process for a class that cannot be written
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self.try_to_bytes() {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                eprintln!("jcfr: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    pub fn try_to_bytes(&self) -> Result<Vec<u8>, WriteError> {
+        let mut writer = ClassFileWriter::new();
+        writer.write_class_file(self)?;
+        Ok(writer.into_bytes())
+    }
+
+    pub fn write_to<W: Write>(&self, mut w: W) -> std::io::Result<()> {
+        let bytes = self.try_to_bytes().map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        w.write_all(&bytes)
+    }
+}
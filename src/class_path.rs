@@ -0,0 +1,320 @@
+// module class_path
+// finds classes by internal name, java/lang/String, through directories, jar and jmod files and the JDK's runtime image
+// in order, each parsed once
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use crate::access_flags::ACC_INTERFACE;
+use crate::code::frames::ClassHierarchy;
+use crate::jimage::{Image, ImageError};
+use crate::jmod::{Jmod, JmodError, Section};
+use crate::zip::{ZipError, ZipFile};
+use crate::class_file_reader::ClassFileError;
+use crate::ClassFile;
+
+#[derive(Debug)]
+pub enum ClassPathError {
+    Io(PathBuf, std::io::Error),
+    Zip(PathBuf, ZipError),
+    Image(PathBuf, ImageError),
+    Jmod(PathBuf, JmodError),
+    Class(PathBuf, String, ClassFileError),     // entry of the path, file in it
+}
+
+impl fmt::Display for ClassPathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClassPathError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            ClassPathError::Zip(path, e) => write!(f, "{}: {}", path.display(), e),
+            ClassPathError::Image(path, e) => write!(f, "{}: {}", path.display(), e),
+            ClassPathError::Jmod(path, e) => write!(f, "{}: {}", path.display(), e),
+            ClassPathError::Class(path, name, e) => write!(f, "{}: {}: {}", path.display(), name, e),
+        }
+    }
+}
+
+impl std::error::Error for ClassPathError {}
+
+enum Entry {
+    Directory(PathBuf),
+    Jar(PathBuf, ZipFile, Vec<u16>),    // versions under META-INF/versions of a multi-release jar, newest first
+    Image(PathBuf, Image, HashMap<String, String>),     // module of each package, java/lang to java.base
+    Jmod(PathBuf, Jmod),
+}
+
+pub enum Origin {
+    Directory(PathBuf),
+    Jar(PathBuf),
+    Module(String),
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Origin::Directory(path) | Origin::Jar(path) => write!(f, "{}", path.display()),
+            Origin::Module(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ClassPath {
+    entries: Vec<Entry>,
+    release: Option<u16>,   // multi-release jars are read as for this Java version, the newest they have when None
+    classes: RefCell<HashMap<String, Option<Rc<ClassFile>>>>,
+}
+
+fn is_jar(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("jar") || e.eq_ignore_ascii_case("zip"))
+}
+
+fn is_jmod(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("jmod"))
+}
+
+// java.lang.String or java/lang/String to java/lang/String
+pub fn internal_name(name: &str) -> String {
+    name.strip_suffix(".class").unwrap_or(name).replace('.', "/")
+}
+
+impl ClassPath {
+    pub fn new() -> Self {
+        ClassPath::default()
+    }
+
+    // a class path as given to java -cp, entries separated by : (; on Windows)
+    pub fn add_class_path(&mut self, class_path: &str) -> Result<(), ClassPathError> {
+        for path in std::env::split_paths(class_path).filter(|p| !p.as_os_str().is_empty()) {
+            self.add(&path)?;
+        }
+        Ok(())
+    }
+
+    // a module path, jars, jmods and exploded modules, or directories holding them
+    pub fn add_module_path(&mut self, module_path: &str) -> Result<(), ClassPathError> {
+        for path in std::env::split_paths(module_path).filter(|p| !p.as_os_str().is_empty()) {
+            if !path.is_dir() || path.join("module-info.class").is_file() {
+                self.add(&path)?;
+                continue;
+            }
+            let read = std::fs::read_dir(&path).map_err(|e| ClassPathError::Io(path.clone(), e))?;
+            let mut modules: Vec<PathBuf> = read.filter_map(|d| d.ok().map(|d| d.path()))
+                .filter(|p| p.is_dir() || is_jar(p) || is_jmod(p))
+                .collect();
+            modules.sort();
+            for module in modules {
+                self.add(&module)?;
+            }
+        }
+        Ok(())
+    }
+
+    // a directory of class files, a jar or a jmod
+    pub fn add(&mut self, path: &Path) -> Result<(), ClassPathError> {
+        if path.is_dir() {
+            self.entries.push(Entry::Directory(path.to_path_buf()));
+            return Ok(());
+        }
+        if is_jmod(path) {
+            let jmod = Jmod::open(path).map_err(|e| match e {
+                JmodError::Io(e) => ClassPathError::Io(path.to_path_buf(), e),
+                e => ClassPathError::Jmod(path.to_path_buf(), e),
+            })?;
+            self.entries.push(Entry::Jmod(path.to_path_buf(), jmod));
+            return Ok(());
+        }
+        let jar = ZipFile::open(path).map_err(|e| match e {
+            ZipError::Io(e) => ClassPathError::Io(path.to_path_buf(), e),
+            e => ClassPathError::Zip(path.to_path_buf(), e),
+        })?;
+        let versions = match jar.read_entry("META-INF/MANIFEST.MF") {
+            Some(Ok(manifest)) if String::from_utf8_lossy(&manifest).lines()
+                .any(|l| l.split_once(':').is_some_and(|(k, v)| k.trim().eq_ignore_ascii_case("Multi-Release") && v.trim().eq_ignore_ascii_case("true"))) => {
+                let versions: BTreeSet<u16> = jar.get_entries().iter()
+                    .filter_map(|e| e.name.strip_prefix("META-INF/versions/")?.split_once('/')?.0.parse().ok())
+                    .filter(|v| *v >= 9)
+                    .collect();
+                versions.into_iter().rev().collect()
+            }
+            _ => Vec::new(),
+        };
+        self.entries.push(Entry::Jar(path.to_path_buf(), jar, versions));
+        Ok(())
+    }
+
+    // the modules of a JDK, java_home/lib/modules
+    pub fn add_runtime_image(&mut self, java_home: &Path) -> Result<(), ClassPathError> {
+        let path = java_home.join("lib").join("modules");
+        let image = Image::open(&path).map_err(|e| match e {
+            ImageError::Io(e) => ClassPathError::Io(path.clone(), e),
+            e => ClassPathError::Image(path.clone(), e),
+        })?;
+        let mut packages = HashMap::new();
+        for name in image.get_names() {
+            if let Some((module, class)) = name.strip_prefix('/').and_then(|n| n.split_once('/')) {
+                if let (Some((package, _)), true) = (class.rsplit_once('/'), class.ends_with(".class")) {
+                    packages.entry(package.to_string()).or_insert(module.to_string());
+                }
+            }
+        }
+        self.entries.push(Entry::Image(path, image, packages));
+        Ok(())
+    }
+
+    pub fn set_release(&mut self, release: u16) {
+        self.release = Some(release);
+        self.classes.borrow_mut().clear();
+    }
+
+    fn versions<'a>(&self, versions: &'a [u16]) -> impl Iterator<Item = &'a u16> {
+        let release = self.release;
+        versions.iter().filter(move |v| release.is_none_or(|r| **v <= r))
+    }
+
+    // the entry that has the class and the class's name inside it
+    fn locate(&self, name: &str) -> Option<(&Entry, String)> {
+        let file_name = format!("{}.class", name);
+        for entry in &self.entries {
+            let found = match entry {
+                Entry::Directory(dir) => dir.join(&file_name).is_file().then(|| file_name.clone()),
+                Entry::Jar(_, jar, versions) => self.versions(versions)
+                    .map(|v| format!("META-INF/versions/{}/{}", v, file_name))
+                    .chain([file_name.clone()])
+                    .find(|n| jar.get_entry(n).is_some()),
+                Entry::Jmod(_, jmod) => jmod.contains(Section::Classes, &file_name).then(|| file_name.clone()),
+                Entry::Image(_, image, packages) => name.rsplit_once('/')
+                    .and_then(|(package, _)| packages.get(package))
+                    .map(|m| format!("/{}/{}", m, file_name))
+                    .filter(|n| image.contains(n)),
+            };
+            if let Some(found) = found {
+                return Some((entry, found));
+            }
+        }
+        None
+    }
+
+    // bytes of the class file, from the first entry that has it
+    pub fn get_bytes(&self, name: &str) -> Result<Option<Vec<u8>>, ClassPathError> {
+        let Some((entry, entry_name)) = self.locate(name) else { return Ok(None) };
+        let bytes = match entry {
+            Entry::Directory(dir) => {
+                let path = dir.join(&entry_name);
+                std::fs::read(&path).map_err(|e| ClassPathError::Io(path, e))
+            }
+            Entry::Jar(path, jar, _) => jar.read_entry(&entry_name).unwrap().map_err(|e| ClassPathError::Zip(path.clone(), e)),
+            Entry::Jmod(path, jmod) => jmod.read_entry(Section::Classes, &entry_name).unwrap()
+                .map_err(|e| ClassPathError::Zip(path.clone(), e)),
+            Entry::Image(path, image, _) => image.read_entry(&entry_name).unwrap().map_err(|e| ClassPathError::Image(path.clone(), e)),
+        };
+        bytes.map(Some)
+    }
+
+    // the parsed class, from the first entry that has it
+    pub fn read_class(&self, name: &str) -> Result<Option<ClassFile>, ClassPathError> {
+        let Some(bytes) = self.get_bytes(name)? else { return Ok(None) };
+        ClassFile::try_from_bytes(bytes).map(Some).map_err(|e| {
+            let (entry, entry_name) = self.locate(name).unwrap();
+            let path = match entry {
+                Entry::Directory(path) | Entry::Jar(path, _, _) | Entry::Image(path, _, _) | Entry::Jmod(path, _) => path,
+            };
+            ClassPathError::Class(path.clone(), entry_name, e)
+        })
+    }
+
+    // where the class is read from, the module for the runtime image and jmods
+    pub fn get_origin(&self, name: &str) -> Option<Origin> {
+        let (entry, entry_name) = self.locate(name)?;
+        Some(match entry {
+            Entry::Directory(dir) => Origin::Directory(dir.clone()),
+            Entry::Jar(path, _, _) => Origin::Jar(path.clone()),
+            Entry::Jmod(path, _) => Origin::Module(path.file_stem().unwrap_or_default().to_string_lossy().to_string()),
+            Entry::Image(_, _, _) => Origin::Module(entry_name[1..].split_once('/')?.0.to_string()),
+        })
+    }
+
+    // module-info of a module of the runtime image or of a jmod
+    pub fn get_module_info(&self, module: &str) -> Option<ClassFile> {
+        for entry in &self.entries {
+            let bytes = match entry {
+                Entry::Jmod(path, jmod) if path.file_stem().is_some_and(|s| s == module) => jmod.read_class("module-info")
+                    .and_then(Result::ok),
+                Entry::Image(_, image, _) => image.read_entry(&format!("/{}/module-info.class", module)).and_then(Result::ok),
+                _ => None,
+            };
+            if let Some(bytes) = bytes {
+                return ClassFile::try_from_bytes(bytes).ok();
+            }
+        }
+        None
+    }
+
+    // the parsed class, None when it is not found or its entry cannot be read, which is reported once
+    pub fn get_class(&self, name: &str) -> Option<Rc<ClassFile>> {
+        if let Some(class_file) = self.classes.borrow().get(name) {
+            return class_file.clone();
+        }
+        let class_file = match self.read_class(name) {
+            Ok(class_file) => class_file.map(Rc::new),
+            Err(e) => {
+                eprintln!("jcfr: {}", e);
+                None
+            }
+        };
+        self.classes.borrow_mut().insert(name.to_string(), class_file.clone());
+        class_file
+    }
+
+    // internal names of every class on the path, sorted, without module-info
+    pub fn get_class_names(&self) -> Vec<String> {
+        let mut names = BTreeSet::new();
+        for entry in &self.entries {
+            match entry {
+                Entry::Directory(dir) => {
+                    let mut pending = vec![dir.clone()];
+                    while let Some(d) = pending.pop() {
+                        for path in std::fs::read_dir(&d).into_iter().flatten().filter_map(|e| e.ok().map(|e| e.path())) {
+                            if path.is_dir() {
+                                pending.push(path);
+                            } else if let Some(name) = path.strip_prefix(dir).ok().and_then(|p| p.to_str())
+                                    .and_then(|p| p.strip_suffix(".class")) {
+                                names.insert(name.replace(std::path::MAIN_SEPARATOR, "/"));
+                            }
+                        }
+                    }
+                }
+                Entry::Jar(_, jar, versions) => {
+                    let prefixes: Vec<String> = self.versions(versions).map(|v| format!("META-INF/versions/{}/", v)).collect();
+                    for e in jar.get_entries() {
+                        let name = prefixes.iter().find_map(|p| e.name.strip_prefix(p.as_str())).unwrap_or(&e.name);
+                        if !name.starts_with("META-INF/") {
+                            if let Some(name) = name.strip_suffix(".class") {
+                                names.insert(name.to_string());
+                            }
+                        }
+                    }
+                }
+                Entry::Jmod(_, jmod) => names.extend(jmod.get_class_names()),
+                Entry::Image(_, image, _) => {
+                    let classes = image.get_names().into_iter()
+                        .filter_map(|n| Some(n.strip_prefix('/')?.split_once('/')?.1.strip_suffix(".class")?.to_string()));
+                    names.extend(classes);
+                }
+            }
+        }
+        names.retain(|n| !n.ends_with("module-info"));
+        names.into_iter().collect()
+    }
+}
+
+impl ClassHierarchy for ClassPath {
+    fn get_super_class(&self, name: &str) -> Option<Option<String>> {
+        self.get_class(name).map(|c| c.get_super_class_name())
+    }
+
+    fn is_interface(&self, name: &str) -> bool {
+        self.get_class(name).is_some_and(|c| c.access_flags & ACC_INTERFACE != 0)
+    }
+}
@@ -1,70 +1,245 @@
 // module code
 
-mod opcode;
-use std::io::Read;
-use jcfreader::class_file_reader::{AttributeInfo};
+pub mod opcode;
+pub mod stack_map;
+pub mod assembler;
+pub mod limits;
+pub mod frames;
+pub mod cfg;
+pub mod verifier;
+use crate::attribute::DecodeError;
+use crate::class_file_reader::{AttributeInfo, Index};
+use crate::{utf8, AttributeReader, ClassFile};
+use crate::class_file_writer::ClassFileWriter;
 use opcode::Opcode;
 
-struct Exception {
-    start_pc: u16,
-    end_pc: u16,
-    hander_pc: u16,
-    catch_type: u16,
+pub struct Exception {
+    pub start_pc: u16,
+    pub end_pc: u16,
+    pub handler_pc: u16,
+    pub catch_type: u16,
 }
 
+#[derive(Default)]
 pub struct Code {
     max_stack: u16,
     max_locals: u16,
     pub code: Vec<Opcode>,
+    pc: Vec<u16>,       // offset of each instruction in code
     exception_table: Vec<Exception>,
     attributes: Vec<AttributeInfo>,
 }
 
 impl Code {
-    pub fn new(info: &Vec<u8>) -> Code {
+    // an empty Code for bytes that do not decode, try_new says why
+    pub fn new(info: &[u8]) -> Code {
+        Code::try_new(info).unwrap_or_default()
+    }
+
+    pub fn try_new(info: &[u8]) -> Result<Code, DecodeError> {
         let mut reader = CodeReader::new(info);
-        Self {
-            max_stack : reader.read_u16(),
-            max_locals : reader.read_u16(),
-            code: {
-                let code_length = reader.read_u32();
-                let mut v = Vec::<Opcode>::with_capacity(code_length as usize);
-                reader.set_length(code_length);
-                while reader.has_bytes() {
-                    let o = opcode::get_opcode(reader.read_u8(), &mut reader);
-                    v.push(o);
-                }
-                v
-            },
+        let max_stack = reader.read_u16();
+        let max_locals = reader.read_u16();
+        let code_length = reader.read_u32();
+        // decode from the code bytes alone so the tables after it are always in step
+        let bytes = reader.read_bytes(code_length as usize);
+        let mut code_reader = CodeReader::new(&bytes);
+        let mut code = Vec::<Opcode>::with_capacity(code_length as usize);
+        let mut pc = Vec::<u16>::with_capacity(code_length as usize);
+        code_reader.set_length(code_length);
+        while code_reader.has_bytes() {
+            pc.push(code_reader.count as u16);
+            code.push(opcode::get_opcode(code_reader.read_u8(), &mut code_reader));
+        }
+        if let Some(error) = code_reader.error {
+            return Err(error);
+        }
+        let code = Self {
+            max_stack,
+            max_locals,
+            code,
+            pc,
             exception_table: {
                 let exception_table_length = reader.read_u16();
                 let mut v = Vec::<Exception>::with_capacity(exception_table_length as usize);
+                for _ in 0..exception_table_length {
+                    v.push(Exception {
+                        start_pc: reader.read_u16(),
+                        end_pc: reader.read_u16(),
+                        handler_pc: reader.read_u16(),
+                        catch_type: reader.read_u16(),
+                    });
+                }
                 v
             },
-            attributes: {
-                let attributes_count = reader.read_u16();
-                let mut v = Vec::<AttributeInfo>::with_capacity(attributes_count  as usize);
-                v
-            },
+            attributes: Code::read_attribute_list(&mut reader),
+        };
+        match reader.error {
+            Some(error) => Err(error),
+            None => Ok(code),
+        }
+    }
+
+    // just the attributes of a Code attribute, without decoding the instructions, none when it does not decode
+    pub fn read_attributes(info: &[u8]) -> Vec<AttributeInfo> {
+        let Some(start) = attributes_start(info) else { return Vec::new() };
+        let mut reader = CodeReader::new(&info[start..]);
+        let attributes = Code::read_attribute_list(&mut reader);
+        match reader.error {
+            Some(_) => Vec::new(),
+            None => attributes,
+        }
+    }
+
+    fn read_attribute_list(reader: &mut CodeReader) -> Vec<AttributeInfo> {
+        let attributes_count = reader.read_u16();
+        let mut v = Vec::<AttributeInfo>::with_capacity(attributes_count  as usize);
+        for _ in 0..attributes_count {
+            let attribute_name_index = Index::Single(reader.read_u16());
+            let length = reader.read_u32();
+            v.push(AttributeInfo {
+                attribute_name_index,
+                info: reader.read_bytes(length as usize),
+            });
         }
+        v
+    }
+
+    pub fn get_max_stack(&self) -> u16 {
+        self.max_stack
+    }
+
+    pub fn get_max_locals(&self) -> u16 {
+        self.max_locals
+    }
+
+    // each instruction with its offset in the code array
+    pub fn get_instructions(&self) -> impl Iterator<Item = (u16, &Opcode)> {
+        self.pc.iter().copied().zip(self.code.iter())
+    }
+
+    pub fn get_exception_table(&self) -> &Vec<Exception> {
+        &self.exception_table
+    }
+
+    pub fn get_attributes(&self) -> &Vec<AttributeInfo> {
+        &self.attributes
+    }
+}
+
+// a Code attribute's bytes with only the attributes keep accepts, everything before them is copied as is
+pub fn remove_attributes<F: Fn(&AttributeInfo) -> bool>(info: &[u8], keep: F) -> Vec<u8> {
+    let attributes: Vec<AttributeInfo> = Code::read_attributes(info).into_iter().filter(|a| keep(a)).collect();
+    set_attributes(info, &attributes)
+}
+
+// a Code attribute's bytes with its attributes replaced, or as they are when they do not decode that far
+pub fn set_attributes(info: &[u8], attributes: &[AttributeInfo]) -> Vec<u8> {
+    let Some(start) = attributes_start(info) else { return info.to_vec() };
+    let mut writer = ClassFileWriter::new();
+    writer.write_bytes(&info[..start]);
+    writer.write_attributes(attributes);
+    writer.into_bytes()
+}
+
+// offset of attributes_count, after the code and the exception table
+fn attributes_start(info: &[u8]) -> Option<usize> {
+    let code_length = u32::from_be_bytes(info.get(4..8)?.try_into().ok()?) as usize;
+    let exceptions = 8usize.checked_add(code_length)?;
+    let exception_table_length = u16::from_be_bytes(info.get(exceptions..exceptions + 2)?.try_into().ok()?) as usize;
+    let start = exceptions + 2 + exception_table_length * 8;
+    (start + 2 <= info.len()).then_some(start)
+}
+
+pub struct LocalVariable {
+    pub start_pc: u16,
+    pub length: u16,
+    pub name: String,
+    pub descriptor: String,
+    pub index: u16,
+}
+
+// LocalVariableTable, LocalVariableTypeTable has the same layout with a signature in place of the descriptor
+pub fn get_local_variable_table(class_file: &ClassFile, info: &[u8]) -> Result<Vec<LocalVariable>, DecodeError> {
+    let mut reader = AttributeReader::new(info);
+    let count = reader.try_u16()?;
+    let mut table = Vec::<LocalVariable>::with_capacity(count as usize);
+    for _ in 0..count {
+        table.push(LocalVariable {
+            start_pc: reader.try_u16()?,
+            length: reader.try_u16()?,
+            name: utf8(class_file, reader.try_u16()?)?,
+            descriptor: utf8(class_file, reader.try_u16()?)?,
+            index: reader.try_u16()?,
+        });
+    }
+    Ok(table)
+}
+
+pub struct LineNumber {
+    pub start_pc: u16,
+    pub line_number: u16,
+}
+
+pub fn get_line_number_table(info: &[u8]) -> Result<Vec<LineNumber>, DecodeError> {
+    let mut reader = AttributeReader::new(info);
+    let count = reader.try_u16()?;
+    let mut table = Vec::<LineNumber>::with_capacity(count as usize);
+    for _ in 0..count {
+        table.push(LineNumber {
+            start_pc: reader.try_u16()?,
+            line_number: reader.try_u16()?,
+        });
     }
+    Ok(table)
 }
 
+// the entry with the greatest start_pc not after pc
+pub fn get_line_number(table: &[LineNumber], pc: u16) -> Option<u16> {
+    table.iter()
+        .filter(|l| l.start_pc <= pc)
+        .max_by_key(|l| l.start_pc)
+        .map(|l| l.line_number)
+}
+
+// reads past the end give 0 and keep the error, for the caller to check once
 pub struct CodeReader<'a> {
     count: u32,
     length: u32,
     bytes: &'a [u8],
+    error: Option<DecodeError>,
 }
 
 impl <'a>CodeReader<'a> {
-    fn new(info: &'a Vec<u8>) -> Self {
+    fn new(info: &'a [u8]) -> Self {
        Self {
            count: 0,
            length: 0,
            bytes: info,
+           error: None,
        }
     }
 
+    fn fail(&mut self, error: DecodeError) {
+        self.error.get_or_insert(error);
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        self.count = self.count.saturating_add(len as u32);
+        if self.error.is_some() || len > self.bytes.len() {
+            self.fail(DecodeError::Truncated);
+            return None;
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Some(bytes)
+    }
+
+    // None once the bytes run out, for the switch tables that are as long as their operands say
+    fn try_u32(&mut self) -> Option<u32> {
+        self.take(4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
     fn has_bytes(&mut self) -> bool {
         self.count < self.length
     }
@@ -75,24 +250,26 @@ impl <'a>CodeReader<'a> {
     }
 
     fn read_u32(&mut self) -> u32 {
-        self.count += 2;
-        let mut buf = [0; 4];
-        self.bytes.read_exact(&mut buf).unwrap();
-        u32::from_be_bytes(buf)
+        self.try_u32().unwrap_or(0)
     }
 
     fn read_u16(&mut self) -> u16 {
-        self.count += 2;
-        let mut buf = [0; 2];
-        self.bytes.read_exact(&mut buf).unwrap();
-        u16::from_be_bytes(buf)
+        self.take(2).map_or(0, |b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Vec<u8> {
+        self.take(len).map_or(Vec::new(), |b| b.to_vec())
+    }
+
+    // skip the padding that puts switch operands on a 4 byte boundary
+    fn align(&mut self) {
+        while !self.count.is_multiple_of(4) {
+            self.read_u8();
+        }
     }
 
     fn read_u8(&mut self) -> u8 {
-        self.count += 1;
-        let mut buf = [0; 1];
-        self.bytes.read_exact(&mut buf).unwrap();
-        u8::from_be_bytes(buf)
+        self.take(1).map_or(0, |b| b[0])
     }
 }
 
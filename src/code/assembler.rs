@@ -0,0 +1,487 @@
+// module assembler
+// instructions with symbolic labels back to Code bytes
+use std::collections::HashMap;
+use std::fmt;
+use crate::class_file_reader::{AttributeInfo, Index};
+use crate::class_file_writer::ClassFileWriter;
+use crate::code::{Code, Exception, LineNumber};
+use crate::code::opcode::Opcode;
+use crate::{AttributeReader, ClassFile};
+use crate::attribute::{AttributeKind, DecodeError, HasAttributes};
+use crate::type_annotation;
+
+#[derive(Debug)]
+pub enum AssembleError {
+    BranchOutOfRange(u16, i32),     // pc of a conditional branch, offset it needs
+    UnplacedLabel(Label),
+    CodeTooLarge(u32),
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssembleError::BranchOutOfRange(pc, offset) =>
+                write!(f, "branch at {} to offset {} does not fit in 16 bits", pc, offset),
+            AssembleError::UnplacedLabel(label) => write!(f, "label {} is never placed", label.0),
+            AssembleError::CodeTooLarge(len) => write!(f, "code is {} bytes, more than 65535", len),
+        }
+    }
+}
+
+impl std::error::Error for AssembleError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Label(pub usize);
+
+#[derive(Debug, Clone)]
+pub enum Instruction {
+    Op(Opcode),                                 // written as is
+    Label(Label),                               // the offset of the next instruction
+    Branch(Opcode, Label),                      // goto and jsr take the _w form when the label is too far
+    Tableswitch(Label, i32, Vec<Label>),        // default, low, a target for low..=high
+    Lookupswitch(Label, Vec<(i32, Label)>),     // default, match and target
+    Ldc(u16),                                   // ldc or ldc_w
+    Iinc(u16, i16),                             // iinc or wide iinc
+    Line(u16),                                  // source line of the next instruction
+}
+
+#[derive(Debug, Clone)]
+pub struct Handler {
+    pub start: Label,
+    pub end: Label,
+    pub handler: Label,
+    pub catch_type: u16,    // 0 for any
+}
+
+// an entry of a LocalVariableTable or LocalVariableTypeTable, in scope from start up to end
+#[derive(Debug, Clone)]
+pub struct Local {
+    pub start: Label,
+    pub end: Label,
+    pub name_index: u16,
+    pub descriptor_index: u16,     // the signature in a LocalVariableTypeTable
+    pub index: u16,
+}
+
+#[derive(Default)]
+pub struct Assembler {
+    pub instructions: Vec<Instruction>,
+    pub handlers: Vec<Handler>,
+    pub local_variables: Vec<Local>,
+    pub local_variable_types: Vec<Local>,
+    type_annotations: Vec<CodeTypeAnnotations>,
+    labels: usize,
+}
+
+// a Runtime*TypeAnnotations attribute of a Code attribute, with labels for the offsets in it and for the end of each
+// local variable range
+#[derive(Clone)]
+struct CodeTypeAnnotations {
+    attribute_name_index: Index,
+    info: Vec<u8>,
+    offsets: Vec<(usize, Label, Option<Label>)>,
+}
+
+pub struct Assembled {
+    pub code: Vec<u8>,
+    pub exception_table: Vec<Exception>,
+    pub line_numbers: Vec<LineNumber>,
+    local_variables: Vec<Local>,
+    local_variable_types: Vec<Local>,
+    type_annotations: Vec<AttributeInfo>,
+    labels: Vec<Option<u16>>,
+}
+
+impl Assembled {
+    pub fn get_offset(&self, label: Label) -> Option<u16> {
+        self.labels.get(label.0).copied().flatten()
+    }
+
+    pub fn line_number_table(&self) -> Vec<u8> {
+        let mut writer = ClassFileWriter::new();
+        writer.write_u16(self.line_numbers.len() as u16);
+        for l in &self.line_numbers {
+            writer.write_u16(l.start_pc);
+            writer.write_u16(l.line_number);
+        }
+        writer.into_bytes()
+    }
+
+    pub fn local_variable_table(&self) -> Vec<u8> {
+        self.local_table(&self.local_variables)
+    }
+
+    pub fn local_variable_type_table(&self) -> Vec<u8> {
+        self.local_table(&self.local_variable_types)
+    }
+
+    // assemble has checked every label is placed
+    fn local_table(&self, locals: &[Local]) -> Vec<u8> {
+        let mut writer = ClassFileWriter::new();
+        writer.write_u16(locals.len() as u16);
+        for l in locals {
+            let start = self.get_offset(l.start).unwrap_or(0);
+            writer.write_u16(start);
+            writer.write_u16(self.get_offset(l.end).unwrap_or(0).wrapping_sub(start));
+            writer.write_u16(l.name_index);
+            writer.write_u16(l.descriptor_index);
+            writer.write_u16(l.index);
+        }
+        writer.into_bytes()
+    }
+
+    // info of a Code attribute around this code
+    pub fn code_attribute(&self, max_stack: u16, max_locals: u16, attributes: &[AttributeInfo]) -> Vec<u8> {
+        let mut writer = ClassFileWriter::new();
+        writer.write_u16(max_stack);
+        writer.write_u16(max_locals);
+        writer.write_u32(self.code.len() as u32);
+        writer.write_bytes(&self.code);
+        writer.write_u16(self.exception_table.len() as u16);
+        for e in &self.exception_table {
+            writer.write_u16(e.start_pc);
+            writer.write_u16(e.end_pc);
+            writer.write_u16(e.handler_pc);
+            writer.write_u16(e.catch_type);
+        }
+        writer.write_attributes(attributes);
+        writer.into_bytes()
+    }
+}
+
+impl Assembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // labels at every branch target, handler, line and local variable range of an existing method, so instructions
+    // can be inserted and the offsets come out right when it is assembled again
+    pub fn from_code(class_file: &ClassFile, code: &Code) -> Self {
+        let mut assembler = Self::new();
+        let mut labels = HashMap::<u32, Label>::new();
+        let mut label_at = |assembler: &mut Assembler, pc: u32| *labels.entry(pc).or_insert_with(|| assembler.new_label());
+
+        // a table can be split over several attributes
+        let mut lines = HashMap::<u16, Vec<u16>>::new();
+        for a in code.attributes(class_file).iter().filter(|a| a.name == "LineNumberTable") {
+            if let AttributeKind::LineNumberTable(table) = a.decode(class_file) {
+                for l in table {
+                    lines.entry(l.start_pc).or_default().push(l.line_number);
+                }
+            }
+        }
+        for e in code.get_exception_table() {
+            let handler = Handler {
+                start: label_at(&mut assembler, e.start_pc as u32),
+                end: label_at(&mut assembler, e.end_pc as u32),
+                handler: label_at(&mut assembler, e.handler_pc as u32),
+                catch_type: e.catch_type,
+            };
+            assembler.handlers.push(handler);
+        }
+        for a in code.get_attributes() {
+            let table_name = class_file.constant_pool.get_item(&a.attribute_name_index);
+            if TYPE_ANNOTATIONS.contains(&table_name.as_str()) {
+                // ones that do not parse cannot be moved with the code, and replace_code leaves them out
+                if let Some(offsets) = type_annotation::code_offsets(&a.info) {
+                    let offsets = offsets.into_iter().map(|(at, range)| {
+                        let start_pc = u16::from_be_bytes([a.info[at], a.info[at + 1]]) as u32;
+                        let end = range.then(|| u16::from_be_bytes([a.info[at + 2], a.info[at + 3]]) as u32);
+                        (at, label_at(&mut assembler, start_pc), end.map(|length| label_at(&mut assembler, start_pc + length)))
+                    }).collect();
+                    assembler.type_annotations.push(CodeTypeAnnotations {
+                        attribute_name_index: a.attribute_name_index.clone(),
+                        info: a.info.clone(),
+                        offsets,
+                    });
+                }
+                continue;
+            }
+            if table_name != "LocalVariableTable" && table_name != "LocalVariableTypeTable" {
+                continue;
+            }
+            let mut reader = AttributeReader::new(&a.info);
+            let count = reader.try_u16().unwrap_or(0);
+            for _ in 0..count {
+                // a truncated table keeps the entries before the end
+                let mut entry = || Ok::<_, DecodeError>([reader.try_u16()?, reader.try_u16()?, reader.try_u16()?, reader.try_u16()?, reader.try_u16()?]);
+                let Ok([start_pc, length, name_index, descriptor_index, index]) = entry() else { break };
+                let local = Local {
+                    start: label_at(&mut assembler, start_pc as u32),
+                    end: label_at(&mut assembler, start_pc as u32 + length as u32),
+                    name_index,
+                    descriptor_index,
+                    index,
+                };
+                match table_name.as_str() {
+                    "LocalVariableTable" => assembler.local_variables.push(local),
+                    _ => assembler.local_variable_types.push(local),
+                }
+            }
+        }
+
+        let mut body = Vec::<(u32, Instruction)>::new();
+        let mut end = 0;
+        for (pc, op) in code.get_instructions() {
+            let pc = pc as u32;
+            let target = |offset: i32| (pc as i32 + offset) as u32;
+            let instruction = match op {
+                Opcode::Ldc(i) => Instruction::Ldc(*i as u16),
+                Opcode::LdcW(i) => Instruction::Ldc(*i),
+                Opcode::Iinc(i, c) => Instruction::Iinc(*i as u16, *c as i16),
+                Opcode::WideIinc(i, c) => Instruction::Iinc(*i, *c),
+                Opcode::Tableswitch(default, low, _, offsets) => Instruction::Tableswitch(
+                    label_at(&mut assembler, target(*default)),
+                    *low,
+                    offsets.iter().map(|o| label_at(&mut assembler, target(*o))).collect(),
+                ),
+                Opcode::Lookupswitch(default, pairs) => Instruction::Lookupswitch(
+                    label_at(&mut assembler, target(*default)),
+                    pairs.iter().map(|(m, o)| (*m, label_at(&mut assembler, target(*o)))).collect(),
+                ),
+                o => match o.branch_offset() {
+                    Some(offset) => Instruction::Branch(o.clone(), label_at(&mut assembler, target(offset))),
+                    None => Instruction::Op(o.clone()),
+                },
+            };
+            body.push((pc, instruction));
+            end = pc + op.length(pc);
+        }
+
+        for (pc, instruction) in body {
+            if let Some(label) = labels.get(&pc) {
+                assembler.instructions.push(Instruction::Label(*label));
+            }
+            for line in lines.get(&(pc as u16)).into_iter().flatten() {
+                assembler.instructions.push(Instruction::Line(*line));
+            }
+            assembler.instructions.push(instruction);
+        }
+        // an exception range can run to the end of the code
+        if let Some(label) = labels.get(&end) {
+            assembler.instructions.push(Instruction::Label(*label));
+        }
+        assembler
+    }
+
+    pub fn new_label(&mut self) -> Label {
+        self.labels += 1;
+        Label(self.labels - 1)
+    }
+
+    pub fn place(&mut self, label: Label) {
+        self.instructions.push(Instruction::Label(label));
+    }
+
+    pub fn push(&mut self, instruction: Instruction) {
+        self.instructions.push(instruction);
+    }
+
+    pub fn emit(&mut self, opcode: Opcode) {
+        self.instructions.push(Instruction::Op(opcode));
+    }
+
+    pub fn branch(&mut self, opcode: Opcode, label: Label) {
+        self.instructions.push(Instruction::Branch(opcode, label));
+    }
+
+    pub fn handler(&mut self, start: Label, end: Label, handler: Label, catch_type: u16) {
+        self.handlers.push(Handler { start, end, handler, catch_type });
+    }
+
+    // index of the instruction that places this label
+    pub fn position(&self, label: Label) -> Option<usize> {
+        self.instructions.iter().position(|i| matches!(i, Instruction::Label(l) if *l == label))
+    }
+
+    pub fn insert(&mut self, index: usize, instruction: Instruction) {
+        self.instructions.insert(index, instruction);
+    }
+
+    fn size(instruction: &Instruction, pc: u32, wide: bool) -> u32 {
+        match instruction {
+            Instruction::Op(o) => o.length(pc),
+            Instruction::Label(_) | Instruction::Line(_) => 0,
+            Instruction::Branch(..) if wide => 5,
+            Instruction::Branch(..) => 3,
+            Instruction::Tableswitch(_, _, targets) => 1 + padding(pc) + 12 + 4 * targets.len() as u32,
+            Instruction::Lookupswitch(_, pairs) => 1 + padding(pc) + 8 + 8 * pairs.len() as u32,
+            Instruction::Ldc(i) if *i <= u8::MAX as u16 => 2,
+            Instruction::Ldc(_) => 3,
+            Instruction::Iinc(i, c) if short_iinc(*i, *c) => 3,
+            Instruction::Iinc(..) => 6,
+        }
+    }
+
+    // offset of every instruction and label, with the branches in wide taking their _w form
+    fn layout(&self, wide: &[bool]) -> Result<(Vec<u32>, Vec<Option<u16>>), AssembleError> {
+        let mut pcs = Vec::<u32>::with_capacity(self.instructions.len());
+        let mut labels = vec![None; self.labels];
+        let mut pc = 0;
+        for (i, instruction) in self.instructions.iter().enumerate() {
+            pcs.push(pc);
+            if let Instruction::Label(label) = instruction {
+                labels[label.0] = Some(pc as u16);
+            }
+            pc += Assembler::size(instruction, pc, wide[i]);
+        }
+        if pc > u16::MAX as u32 {
+            return Err(AssembleError::CodeTooLarge(pc));
+        }
+        pcs.push(pc);
+        Ok((pcs, labels))
+    }
+
+    pub fn assemble(&self) -> Result<Assembled, AssembleError> {
+        // widening a goto moves everything after it, so go round until nothing changes
+        let mut wide = vec![false; self.instructions.len()];
+        let (pcs, labels) = loop {
+            let (pcs, labels) = self.layout(&wide)?;
+            let offset = |label: &Label| -> Result<u32, AssembleError> {
+                labels[label.0].map(|o| o as u32).ok_or(AssembleError::UnplacedLabel(*label))
+            };
+            let mut changed = false;
+            for (i, instruction) in self.instructions.iter().enumerate() {
+                if let Instruction::Branch(op, label) = instruction {
+                    let distance = offset(label)? as i32 - pcs[i] as i32;
+                    if wide[i] || i16::try_from(distance).is_ok() {
+                        continue;
+                    }
+                    match op {
+                        Opcode::Goto(_) | Opcode::Jsr(_) | Opcode::GotoW(_) | Opcode::JsrW(_) => {
+                            wide[i] = true;
+                            changed = true;
+                        }
+                        _ => return Err(AssembleError::BranchOutOfRange(pcs[i] as u16, distance)),
+                    }
+                }
+            }
+            if !changed {
+                break (pcs, labels);
+            }
+        };
+        let offset = |label: &Label| -> Result<i32, AssembleError> {
+            labels[label.0].map(|o| o as i32).ok_or(AssembleError::UnplacedLabel(*label))
+        };
+
+        let mut bytes = Vec::<u8>::with_capacity(pcs[pcs.len() - 1] as usize);
+        let mut line_numbers = Vec::<LineNumber>::new();
+        for (i, instruction) in self.instructions.iter().enumerate() {
+            let pc = pcs[i];
+            let relative = |label: &Label| offset(label).map(|o| o - pc as i32);
+            let opcode = match instruction {
+                Instruction::Op(o) => o.clone(),
+                Instruction::Label(_) => continue,
+                Instruction::Line(line) => {
+                    line_numbers.push(LineNumber { start_pc: pc as u16, line_number: *line });
+                    continue;
+                }
+                Instruction::Branch(op, label) => {
+                    let op = match (op, wide[i]) {
+                        (Opcode::Goto(_) | Opcode::GotoW(_), true) => Opcode::GotoW(0),
+                        (Opcode::Jsr(_) | Opcode::JsrW(_), true) => Opcode::JsrW(0),
+                        (Opcode::GotoW(_), false) => Opcode::Goto(0),
+                        (Opcode::JsrW(_), false) => Opcode::Jsr(0),
+                        (op, _) => op.clone(),
+                    };
+                    op.with_branch_offset(relative(label)?)
+                }
+                Instruction::Tableswitch(default, low, targets) => {
+                    let offsets = targets.iter().map(relative).collect::<Result<Vec<i32>, AssembleError>>()?;
+                    Opcode::Tableswitch(relative(default)?, *low, *low + targets.len() as i32 - 1, offsets)
+                }
+                Instruction::Lookupswitch(default, pairs) => {
+                    let mut pairs = pairs.iter()
+                        .map(|(m, l)| relative(l).map(|o| (*m, o)))
+                        .collect::<Result<Vec<(i32, i32)>, AssembleError>>()?;
+                    // the JVM binary searches the matches
+                    pairs.sort_by_key(|(m, _)| *m);
+                    Opcode::Lookupswitch(relative(default)?, pairs)
+                }
+                Instruction::Ldc(i) if *i <= u8::MAX as u16 => Opcode::Ldc(*i as u8),
+                Instruction::Ldc(i) => Opcode::LdcW(*i),
+                Instruction::Iinc(i, c) if short_iinc(*i, *c) => Opcode::Iinc(*i as u8, *c as i8),
+                Instruction::Iinc(i, c) => Opcode::WideIinc(*i, *c),
+            };
+            opcode.write(pc, &mut bytes);
+        }
+
+        let mut exception_table = Vec::<Exception>::with_capacity(self.handlers.len());
+        for h in &self.handlers {
+            exception_table.push(Exception {
+                start_pc: offset(&h.start)? as u16,
+                end_pc: offset(&h.end)? as u16,
+                handler_pc: offset(&h.handler)? as u16,
+                catch_type: h.catch_type,
+            });
+        }
+
+        for l in self.local_variables.iter().chain(self.local_variable_types.iter()) {
+            offset(&l.start)?;
+            offset(&l.end)?;
+        }
+
+        // an offset that was not at an instruction has no label placed, and its attribute is left out
+        let type_annotations = self.type_annotations.iter().filter_map(|t| {
+            let mut info = t.info.clone();
+            for (at, start, end) in &t.offsets {
+                let start_pc = labels[start.0]?;
+                info[*at..*at + 2].copy_from_slice(&start_pc.to_be_bytes());
+                if let Some(end) = end {
+                    info[*at + 2..*at + 4].copy_from_slice(&labels[end.0]?.wrapping_sub(start_pc).to_be_bytes());
+                }
+            }
+            Some(AttributeInfo { attribute_name_index: t.attribute_name_index.clone(), info })
+        }).collect();
+
+        Ok(Assembled {
+            code: bytes,
+            exception_table,
+            line_numbers,
+            local_variables: self.local_variables.clone(),
+            local_variable_types: self.local_variable_types.clone(),
+            type_annotations,
+            labels,
+        })
+    }
+}
+
+fn padding(pc: u32) -> u32 {
+    (4 - (pc + 1) % 4) % 4
+}
+
+fn short_iinc(index: u16, constant: i16) -> bool {
+    index <= u8::MAX as u16 && i8::try_from(constant).is_ok()
+}
+
+const TYPE_ANNOTATIONS: [&str; 2] = ["RuntimeVisibleTypeAnnotations", "RuntimeInvisibleTypeAnnotations"];
+
+// a method's Code attribute with new code and the other attributes kept. The line and local variable tables are
+// rebuilt, one attribute each where the first one was, and the type annotations moved with the code. The
+// StackMapTable is left out, as its frames are for the old code: ClassFile::compute_stack_map makes a new one once
+// the attribute is in the method with set_code
+pub fn replace_code(class_file: &ClassFile, info: &[u8], assembled: &Assembled) -> Vec<u8> {
+    let code = Code::new(info);
+    let mut type_annotations = assembled.type_annotations.clone();
+    let mut written = Vec::<String>::new();
+    let attributes: Vec<AttributeInfo> = Code::read_attributes(info).into_iter().filter_map(|a| {
+        let name = class_file.constant_pool.get_item(&a.attribute_name_index);
+        let table = match name.as_str() {
+            "LineNumberTable" => assembled.line_number_table(),
+            "LocalVariableTable" => assembled.local_variable_table(),
+            "LocalVariableTypeTable" => assembled.local_variable_type_table(),
+            "StackMapTable" => return None,
+            n if TYPE_ANNOTATIONS.contains(&n) => {
+                let at = type_annotations.iter().position(|t| t.attribute_name_index == a.attribute_name_index)?;
+                return Some(type_annotations.remove(at));
+            }
+            _ => return Some(a),
+        };
+        if written.contains(&name) {
+            return None;
+        }
+        written.push(name);
+        Some(AttributeInfo { attribute_name_index: a.attribute_name_index, info: table })
+    }).collect();
+    assembled.code_attribute(code.get_max_stack(), code.get_max_locals(), &attributes)
+}
@@ -0,0 +1,358 @@
+// module cfg
+// basic blocks of a Code attribute with the edges between them, dominators, loops and DOT output
+use std::collections::HashMap;
+use std::fmt;
+use crate::class_file_reader::ConstantPool;
+use crate::code::Code;
+use crate::code::limits;
+use crate::code::opcode::Opcode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    FallThrough,
+    Branch,             // goto and the taken side of a conditional
+    Switch,
+    Jsr,
+    Ret,                // from a ret to the instruction after a jsr to its subroutine
+    Exception(u16),     // catch_type, 0 catches anything
+}
+
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub start: usize,   // index of the first instruction
+    pub end: usize,     // index after the last instruction
+    pub start_pc: u16,
+    pub end_pc: u16,    // pc after the last instruction
+    pub successors: Vec<(usize, EdgeKind)>,
+    pub predecessors: Vec<(usize, EdgeKind)>,
+}
+
+#[derive(Debug)]
+pub enum CfgError {
+    BadTarget(u16, i64),    // pc, branch target
+    FallsOffEnd(u16),
+}
+
+impl fmt::Display for CfgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CfgError::BadTarget(pc, t) => write!(f, "pc {}: branch target {} is not an instruction", pc, t),
+            CfgError::FallsOffEnd(pc) => write!(f, "pc {}: execution falls off the end of the code", pc),
+        }
+    }
+}
+
+impl std::error::Error for CfgError {}
+
+// block 0 is the entry, blocks are in code order
+pub struct ControlFlowGraph {
+    pub blocks: Vec<Block>,
+}
+
+// a natural loop, all blocks dominated by the header that reach one of the latches without passing it
+#[derive(Debug, Clone)]
+pub struct Loop {
+    pub header: usize,
+    pub latches: Vec<usize>,    // sources of the back edges
+    pub blocks: Vec<usize>,     // in code order, the header included
+}
+
+// immediate dominators, the root and unreachable blocks have none
+pub struct DominatorTree {
+    idom: Vec<Option<usize>>,   // the root is its own, post-dominators have a virtual exit after the blocks
+    blocks: usize,
+}
+
+impl DominatorTree {
+    pub fn get_immediate(&self, block: usize) -> Option<usize> {
+        self.idom[block].filter(|d| *d != block && *d < self.blocks)
+    }
+
+    // every path from the root to b passes through a, a block dominates itself
+    pub fn dominates(&self, a: usize, b: usize) -> bool {
+        let mut x = b;
+        loop {
+            match self.idom[x] {
+                None => return false,
+                Some(_) if x == a => return true,
+                Some(d) if d == x => return false,
+                Some(d) => x = d,
+            }
+        }
+    }
+
+    pub fn get_children(&self, block: usize) -> Vec<usize> {
+        (0..self.blocks).filter(|c| self.get_immediate(*c) == Some(block)).collect()
+    }
+}
+
+// Cooper, Harvey and Kennedy's iteration over reverse postorder
+fn immediate_dominators(count: usize, root: usize, next: &dyn Fn(usize) -> Vec<usize>,
+        previous: &dyn Fn(usize) -> Vec<usize>) -> Vec<Option<usize>> {
+    let mut postorder = Vec::<usize>::with_capacity(count);
+    let mut seen = vec![false; count];
+    let mut stack = vec![(root, next(root), 0)];
+    seen[root] = true;
+    while let Some((node, children, i)) = stack.last_mut() {
+        if *i < children.len() {
+            let child = children[*i];
+            *i += 1;
+            if !seen[child] {
+                seen[child] = true;
+                stack.push((child, next(child), 0));
+            }
+        } else {
+            postorder.push(*node);
+            stack.pop();
+        }
+    }
+    let mut number = vec![usize::MAX; count];
+    for (k, b) in postorder.iter().enumerate() {
+        number[*b] = k;
+    }
+    let mut idom: Vec<Option<usize>> = vec![None; count];
+    idom[root] = Some(root);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for b in postorder.iter().rev().skip(1) {
+            let mut new: Option<usize> = None;
+            for p in previous(*b).into_iter().filter(|p| idom[*p].is_some()) {
+                new = Some(match new {
+                    None => p,
+                    Some(mut n) => {
+                        let mut p = p;
+                        while p != n {
+                            while number[p] < number[n] {
+                                p = idom[p].unwrap();
+                            }
+                            while number[n] < number[p] {
+                                n = idom[n].unwrap();
+                            }
+                        }
+                        p
+                    }
+                });
+            }
+            if idom[*b] != new {
+                idom[*b] = new;
+                changed = true;
+            }
+        }
+    }
+    idom
+}
+
+// a Graphviz quoted string
+pub(crate) fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl ControlFlowGraph {
+    pub fn new(code: &Code) -> Result<ControlFlowGraph, CfgError> {
+        let instructions: Vec<(u16, &Opcode)> = code.get_instructions().collect();
+        if instructions.is_empty() {
+            return Ok(ControlFlowGraph { blocks: Vec::new() });
+        }
+        let position: HashMap<u16, usize> = instructions.iter().enumerate().map(|(i, (pc, _))| (*pc, i)).collect();
+        let index_of = |pc: u16, t: i64| u16::try_from(t).ok().and_then(|t| position.get(&t).copied()).ok_or(CfgError::BadTarget(pc, t));
+
+        // leaders: the entry, branch targets, handlers, the edges of protected ranges and whatever follows a transfer
+        let mut leader = vec![false; instructions.len()];
+        leader[0] = true;
+        for e in code.get_exception_table() {
+            leader[index_of(e.handler_pc, e.handler_pc as i64)?] = true;
+            for pc in [e.start_pc, e.end_pc] {
+                if let Some(i) = position.get(&pc) {
+                    leader[*i] = true;
+                }
+            }
+        }
+        for (i, (pc, o)) in instructions.iter().enumerate() {
+            let (jumps, falls_through) = limits::targets(*pc, o);
+            for t in &jumps {
+                leader[index_of(*pc, *t)?] = true;
+            }
+            if (!falls_through || !jumps.is_empty()) && i + 1 < instructions.len() {
+                leader[i + 1] = true;
+            }
+        }
+
+        let mut blocks = Vec::<Block>::new();
+        let mut block_of = vec![0; instructions.len()];
+        for (i, (pc, _)) in instructions.iter().enumerate() {
+            if leader[i] {
+                blocks.push(Block { start: i, end: i, start_pc: *pc, end_pc: *pc, successors: Vec::new(), predecessors: Vec::new() });
+            }
+            let block = blocks.last_mut().unwrap();
+            block.end = i + 1;
+            block.end_pc = instructions.get(i + 1).map(|(pc, _)| *pc)
+                .unwrap_or_else(|| (*pc as u32 + instructions[i].1.length(*pc as u32)) as u16);
+            block_of[i] = blocks.len() - 1;
+        }
+
+        let mut edges = Vec::<(usize, usize, EdgeKind)>::new();
+        let mut return_sites = Vec::<(usize, usize)>::new();     // subroutine entry, block after the jsr
+        for (b, block) in blocks.iter().enumerate() {
+            let last = block.end - 1;
+            let (pc, o) = instructions[last];
+            let (jumps, falls_through) = limits::targets(pc, o);
+            let kind = match o {
+                Opcode::Tableswitch(..) | Opcode::Lookupswitch(..) => EdgeKind::Switch,
+                Opcode::Jsr(_) | Opcode::JsrW(_) => EdgeKind::Jsr,
+                _ => EdgeKind::Branch,
+            };
+            for t in jumps {
+                edges.push((b, block_of[index_of(pc, t)?], kind));
+            }
+            if falls_through {
+                let next = *block_of.get(last + 1).ok_or(CfgError::FallsOffEnd(pc))?;
+                match o {
+                    Opcode::Jsr(_) | Opcode::JsrW(_) => {
+                        let entry = block_of[index_of(pc, pc as i64 + o.branch_offset().unwrap() as i64)?];
+                        return_sites.push((entry, next));
+                    }
+                    _ => edges.push((b, next, EdgeKind::FallThrough)),
+                }
+            }
+            for e in code.get_exception_table() {
+                if e.start_pc < block.end_pc && block.start_pc < e.end_pc {
+                    edges.push((b, block_of[position[&e.handler_pc]], EdgeKind::Exception(e.catch_type)));
+                }
+            }
+        }
+
+        // a ret goes back after every jsr whose subroutine reaches it, a nested jsr is stepped over to its own
+        // return site and handlers are left to the caller
+        for (entry, site) in &return_sites {
+            let mut seen = vec![false; blocks.len()];
+            let mut work = vec![*entry];
+            seen[*entry] = true;
+            while let Some(b) = work.pop() {
+                let last = blocks[b].end - 1;
+                let mut next: Vec<usize> = edges.iter()
+                    .filter(|(from, _, kind)| *from == b && matches!(kind, EdgeKind::FallThrough | EdgeKind::Branch | EdgeKind::Switch))
+                    .map(|(_, to, _)| *to).collect();
+                match instructions[last].1 {
+                    Opcode::Jsr(_) | Opcode::JsrW(_) => next.extend(block_of.get(last + 1)),
+                    Opcode::Ret(_) | Opcode::Wide(169, _) => edges.push((b, *site, EdgeKind::Ret)),
+                    _ => (),
+                }
+                for n in next {
+                    if !seen[n] {
+                        seen[n] = true;
+                        work.push(n);
+                    }
+                }
+            }
+        }
+
+        for (from, to, kind) in edges {
+            if !blocks[from].successors.contains(&(to, kind)) {
+                blocks[from].successors.push((to, kind));
+                blocks[to].predecessors.push((from, kind));
+            }
+        }
+        Ok(ControlFlowGraph { blocks })
+    }
+
+    // the block holding the instruction at pc
+    pub fn get_block(&self, pc: u16) -> Option<usize> {
+        self.blocks.iter().position(|b| b.start_pc <= pc && pc < b.end_pc)
+    }
+
+    fn successors(&self, block: usize) -> Vec<usize> {
+        self.blocks[block].successors.iter().map(|(b, _)| *b).collect()
+    }
+
+    fn predecessors(&self, block: usize) -> Vec<usize> {
+        self.blocks[block].predecessors.iter().map(|(b, _)| *b).collect()
+    }
+
+    pub fn dominators(&self) -> DominatorTree {
+        let idom = if self.blocks.is_empty() {
+            Vec::new()
+        } else {
+            immediate_dominators(self.blocks.len(), 0, &|b| self.successors(b), &|b| self.predecessors(b))
+        };
+        DominatorTree { idom, blocks: self.blocks.len() }
+    }
+
+    // over the reversed edges from a virtual exit that every block without successors leads to,
+    // blocks that never reach an exit have no post-dominator
+    pub fn post_dominators(&self) -> DominatorTree {
+        let exit = self.blocks.len();
+        let exits: Vec<usize> = (0..exit).filter(|b| self.blocks[*b].successors.is_empty()).collect();
+        let next = |b: usize| if b == exit { exits.clone() } else { self.predecessors(b) };
+        let previous = |b: usize| match b {
+            b if b == exit => Vec::new(),
+            b if self.blocks[b].successors.is_empty() => vec![exit],
+            b => self.successors(b),
+        };
+        DominatorTree { idom: immediate_dominators(exit + 1, exit, &next, &previous), blocks: exit }
+    }
+
+    // natural loops by header, loops entered other than through a header that dominates them are not found
+    pub fn loops(&self) -> Vec<Loop> {
+        let dominators = self.dominators();
+        let mut loops = Vec::<Loop>::new();
+        for header in 0..self.blocks.len() {
+            let mut latches: Vec<usize> = self.predecessors(header).into_iter()
+                .filter(|p| dominators.dominates(header, *p)).collect();
+            latches.sort();
+            latches.dedup();
+            if latches.is_empty() {
+                continue;
+            }
+            let mut member = vec![false; self.blocks.len()];
+            member[header] = true;
+            let mut work = latches.clone();
+            while let Some(b) = work.pop() {
+                if !member[b] {
+                    member[b] = true;
+                    work.extend(self.predecessors(b).into_iter().filter(|p| dominators.dominates(header, *p)));
+                }
+            }
+            let blocks = (0..self.blocks.len()).filter(|b| member[*b]).collect();
+            loops.push(Loop { header, latches, blocks });
+        }
+        loops
+    }
+
+    // Graphviz, one box per block listing its instructions
+    pub fn to_dot(&self, code: &Code, cp: &ConstantPool, name: &str) -> String {
+        let instructions: Vec<(u16, &Opcode)> = code.get_instructions().collect();
+        let headers: Vec<usize> = self.loops().iter().map(|l| l.header).collect();
+        let mut dot = format!("digraph \"{}\" {{\n", escape(name));
+        dot.push_str("  node [shape=box, fontname=\"monospace\"];\n");
+        for (b, block) in self.blocks.iter().enumerate() {
+            let mut label = format!("B{}\\l", b);
+            for (pc, o) in &instructions[block.start..block.end] {
+                let text = match o.branch_offset() {
+                    Some(offset) => format!("{}: {} -> {}", pc, o, *pc as i64 + offset as i64),
+                    None => format!("{}: {}", pc, o),
+                };
+                label.push_str(&escape(&text));
+                label.push_str("\\l");
+            }
+            let style = if headers.contains(&b) { ", style=bold" } else { "" };
+            dot.push_str(&format!("  b{} [label=\"{}\"{}];\n", b, label, style));
+        }
+        for (b, block) in self.blocks.iter().enumerate() {
+            for (to, kind) in &block.successors {
+                let attributes = match kind {
+                    EdgeKind::FallThrough => String::new(),
+                    EdgeKind::Branch => " [color=blue]".to_string(),
+                    EdgeKind::Switch => " [color=darkgreen]".to_string(),
+                    EdgeKind::Jsr => " [label=\"jsr\"]".to_string(),
+                    EdgeKind::Ret => " [style=dotted, label=\"ret\"]".to_string(),
+                    EdgeKind::Exception(0) => " [style=dashed, color=red, label=\"any\"]".to_string(),
+                    EdgeKind::Exception(i) => format!(" [style=dashed, color=red, label=\"{}\"]", escape(&cp.find_class(*i).unwrap_or_else(|| format!("#{}", i)))),
+                };
+                dot.push_str(&format!("  b{} -> b{}{};\n", b, to, attributes));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
@@ -0,0 +1,575 @@
+// module frames
+// StackMapTable frames inferred from the instructions, types merged where paths join
+use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use crate::access_flags::{ACC_INTERFACE, ACC_STATIC};
+use crate::attribute::DecodeError;
+use crate::class_file_reader::{AttributeInfo, ConstantInfo, ConstantPool, Index, Tag};
+use crate::code::{self, Code};
+use crate::code::limits;
+use crate::code::opcode::Opcode;
+use crate::code::stack_map::{self, StackMapFrame, VerificationType};
+use crate::constant_pool_builder::ConstantPoolError;
+use crate::descriptor::{JavaType, MethodDescriptor};
+use crate::ClassFile;
+
+pub(crate) const OBJECT: &str = "java/lang/Object";
+
+// where merging two class types looks for their common superclass
+pub trait ClassHierarchy {
+    // None when the class is not known, Some(None) for a class without a superclass
+    fn get_super_class(&self, name: &str) -> Option<Option<String>>;
+    fn is_interface(&self, name: &str) -> bool;
+
+    // interfaces and classes that cannot be resolved meet at java/lang/Object
+    fn common_super_class(&self, a: &str, b: &str) -> String {
+        if a == b {
+            return a.to_string();
+        }
+        if self.is_interface(a) || self.is_interface(b) {
+            return OBJECT.to_string();
+        }
+        let chain = |name: &str| {
+            let mut chain = vec![name.to_string()];
+            while let Some(Some(s)) = self.get_super_class(chain.last().unwrap()) {
+                if chain.contains(&s) {
+                    break;
+                }
+                chain.push(s);
+            }
+            chain
+        };
+        let supers_of_a = chain(a);
+        chain(b).into_iter().find(|s| supers_of_a.contains(s)).unwrap_or(OBJECT.to_string())
+    }
+}
+
+// knows no classes, every two different class types merge to java/lang/Object
+pub struct ObjectHierarchy;
+
+impl ClassHierarchy for ObjectHierarchy {
+    fn get_super_class(&self, _name: &str) -> Option<Option<String>> {
+        None
+    }
+
+    fn is_interface(&self, _name: &str) -> bool {
+        false
+    }
+}
+
+// class files, or references to them, by internal name
+impl<C: Borrow<ClassFile>> ClassHierarchy for HashMap<String, C> {
+    fn get_super_class(&self, name: &str) -> Option<Option<String>> {
+        self.get(name).map(|c| c.borrow().get_super_class_name())
+    }
+
+    fn is_interface(&self, name: &str) -> bool {
+        self.get(name).is_some_and(|c| c.borrow().access_flags & ACC_INTERFACE != 0)
+    }
+}
+
+#[derive(Debug)]
+pub enum FrameError {
+    BadDescriptor(String),
+    BadConstant(u16, u16),          // pc, pool index
+    BadTarget(u16, i64),            // pc, branch target
+    StackUnderflow(u16),
+    StackMismatch(u16, usize, usize),   // pc, the two depths that meet there
+    FallsOffEnd(u16),
+    Subroutine(u16),                // jsr and ret cannot be described by frames
+    Unreachable(u16),               // a frame is needed where no path arrives
+    NoCode(usize),                  // method index
+    Pool(ConstantPoolError),
+    BadCode(DecodeError),           // the Code attribute does not decode
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FrameError::BadDescriptor(d) => write!(f, "bad method descriptor {}", d),
+            FrameError::BadConstant(pc, i) => write!(f, "pc {}: bad constant pool index {}", pc, i),
+            FrameError::BadTarget(pc, t) => write!(f, "pc {}: branch target {} is not an instruction", pc, t),
+            FrameError::StackUnderflow(pc) => write!(f, "pc {}: operand stack underflow", pc),
+            FrameError::StackMismatch(pc, a, b) => write!(f, "pc {}: operand stack depth {} and {} meet", pc, a, b),
+            FrameError::FallsOffEnd(pc) => write!(f, "pc {}: execution falls off the end of the code", pc),
+            FrameError::Subroutine(pc) => write!(f, "pc {}: jsr and ret are not allowed with a StackMapTable", pc),
+            FrameError::Unreachable(pc) => write!(f, "pc {}: unreachable code needs a frame", pc),
+            FrameError::NoCode(method) => write!(f, "method {} has no Code attribute", method),
+            FrameError::Pool(e) => write!(f, "{}", e),
+            FrameError::BadCode(e) => write!(f, "bad Code attribute: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+impl From<ConstantPoolError> for FrameError {
+    fn from(e: ConstantPoolError) -> Self {
+        FrameError::Pool(e)
+    }
+}
+
+// locals and stack by slot, long and double are followed by a Top
+#[derive(Clone, PartialEq)]
+pub(crate) struct Frame {
+    pub(crate) locals: Vec<VerificationType>,
+    pub(crate) stack: Vec<VerificationType>,
+}
+
+impl Frame {
+    pub(crate) fn push(&mut self, t: VerificationType) {
+        let wide = t.is_wide();
+        self.stack.push(t);
+        if wide {
+            self.stack.push(VerificationType::Top);
+        }
+    }
+
+    fn pop(&mut self, slots: usize, pc: u16) -> Result<Vec<VerificationType>, FrameError> {
+        match self.stack.len().checked_sub(slots) {
+            Some(n) => Ok(self.stack.split_off(n)),
+            None => Err(FrameError::StackUnderflow(pc)),
+        }
+    }
+
+    pub(crate) fn load(&self, index: u16) -> VerificationType {
+        self.locals.get(index as usize).cloned().unwrap_or(VerificationType::Top)
+    }
+
+    pub(crate) fn store(&mut self, index: u16, t: VerificationType) {
+        let i = index as usize;
+        let wide = t.is_wide();
+        let end = i + if wide { 2 } else { 1 };
+        if self.locals.len() < end {
+            self.locals.resize(end, VerificationType::Top);
+        }
+        if i > 0 && self.locals[i - 1].is_wide() {
+            self.locals[i - 1] = VerificationType::Top;
+        }
+        self.locals[i] = t;
+        if wide {
+            self.locals[i + 1] = VerificationType::Top;
+        }
+    }
+
+    // as a frame lists them, one entry for long and double and no Top at the end of the locals
+    pub(crate) fn entries(types: &[VerificationType], trim: bool) -> Vec<VerificationType> {
+        let mut entries = Vec::<VerificationType>::new();
+        let mut i = 0;
+        while i < types.len() {
+            entries.push(types[i].clone());
+            i += if types[i].is_wide() { 2 } else { 1 };
+        }
+        while trim && entries.last() == Some(&VerificationType::Top) {
+            entries.pop();
+        }
+        entries
+    }
+}
+
+pub(crate) fn from_java_type(t: &JavaType) -> VerificationType {
+    match t {
+        JavaType::Long => VerificationType::Long,
+        JavaType::Double => VerificationType::Double,
+        JavaType::Float => VerificationType::Float,
+        JavaType::Class(name) => VerificationType::Object(name.clone()),
+        JavaType::Array(_) => VerificationType::Object(t.to_descriptor()),
+        _ => VerificationType::Integer,
+    }
+}
+
+pub(crate) fn from_descriptor(descriptor: &str) -> Option<VerificationType> {
+    JavaType::from_descriptor(descriptor).map(|t| from_java_type(&t))
+}
+
+pub(crate) fn array_of(name: &str) -> String {
+    match name.starts_with('[') {
+        true => format!("[{}", name),
+        false => format!("[L{};", name),
+    }
+}
+
+// what aaload gives for an array of this type
+fn component(t: &VerificationType) -> VerificationType {
+    match t {
+        VerificationType::Object(name) if name.starts_with('[') => from_descriptor(&name[1..]).unwrap_or(VerificationType::Top),
+        VerificationType::Null => VerificationType::Null,
+        _ => VerificationType::Top,
+    }
+}
+
+fn merge_reference(a: &str, b: &str, hierarchy: &dyn ClassHierarchy) -> String {
+    match (a.strip_prefix('['), b.strip_prefix('[')) {
+        (Some(ca), Some(cb)) if ca.starts_with(['L', '[']) && cb.starts_with(['L', '[']) => {
+            let element = |c: &str| match c.strip_prefix('L') {
+                Some(name) => name.trim_end_matches(';').to_string(),
+                None => c.to_string(),
+            };
+            array_of(&merge_reference(&element(ca), &element(cb), hierarchy))
+        }
+        (None, None) => hierarchy.common_super_class(a, b),
+        _ => OBJECT.to_string(),
+    }
+}
+
+fn merge_type(a: &VerificationType, b: &VerificationType, hierarchy: &dyn ClassHierarchy) -> VerificationType {
+    match (a, b) {
+        (a, b) if a == b => a.clone(),
+        (VerificationType::Null, VerificationType::Object(_)) => b.clone(),
+        (VerificationType::Object(_), VerificationType::Null) => a.clone(),
+        (VerificationType::Object(x), VerificationType::Object(y)) => VerificationType::Object(merge_reference(x, y, hierarchy)),
+        _ => VerificationType::Top,
+    }
+}
+
+fn merge_types(a: &[VerificationType], b: &[VerificationType], hierarchy: &dyn ClassHierarchy) -> Vec<VerificationType> {
+    let len = a.len().max(b.len());
+    let mut merged: Vec<VerificationType> = (0..len).map(|i| match (a.get(i), b.get(i)) {
+        (Some(x), Some(y)) => merge_type(x, y, hierarchy),
+        _ => VerificationType::Top,
+    })
+    .collect();
+    // half of a long or double that lost its first half
+    for i in 0..len {
+        if merged[i].is_wide() && merged.get(i + 1) != Some(&VerificationType::Top) {
+            merged[i] = VerificationType::Top;
+        }
+    }
+    merged
+}
+
+struct Analysis<'a> {
+    cp: &'a ConstantPool,
+    this_class: &'a str,
+    new_classes: HashMap<u16, String>,      // pc of each new and the class it creates
+}
+
+impl Analysis<'_> {
+    fn ldc_type(&self, pc: u16, index: u16) -> Result<VerificationType, FrameError> {
+        let t = match self.cp.constant_info.get((index as usize).wrapping_sub(1)) {
+            Some(ConstantInfo(Tag::Integer, _)) => VerificationType::Integer,
+            Some(ConstantInfo(Tag::Float, _)) => VerificationType::Float,
+            Some(ConstantInfo(Tag::Long, _)) => VerificationType::Long,
+            Some(ConstantInfo(Tag::Double, _)) => VerificationType::Double,
+            Some(ConstantInfo(Tag::String, _)) => VerificationType::Object("java/lang/String".to_string()),
+            Some(ConstantInfo(Tag::Class, _)) => VerificationType::Object("java/lang/Class".to_string()),
+            Some(ConstantInfo(Tag::MethodType, _)) => VerificationType::Object("java/lang/invoke/MethodType".to_string()),
+            Some(ConstantInfo(Tag::MethodHandle, _)) => VerificationType::Object("java/lang/invoke/MethodHandle".to_string()),
+            Some(ConstantInfo(Tag::Dynamic, _)) => self.cp.get_name_and_type(index)
+                .and_then(|(_, d)| from_descriptor(&d))
+                .ok_or(FrameError::BadConstant(pc, index))?,
+            _ => return Err(FrameError::BadConstant(pc, index)),
+        };
+        Ok(t)
+    }
+
+    fn class_name(&self, pc: u16, index: u16) -> Result<String, FrameError> {
+        match self.cp.constant_info.get((index as usize).wrapping_sub(1)) {
+            Some(ConstantInfo(Tag::Class, Index::Single(_))) => self.cp.find_class(index).ok_or(FrameError::BadConstant(pc, index)),
+            _ => Err(FrameError::BadConstant(pc, index)),
+        }
+    }
+
+    // the frame after an instruction
+    fn execute(&self, pc: u16, o: &Opcode, frame: &Frame) -> Result<Frame, FrameError> {
+        let mut out = frame.clone();
+        let (pops, pushes) = limits::stack_effect(self.cp, o).map_err(|i| FrameError::BadConstant(pc, i))?;
+        let popped = out.pop(pops as usize, pc)?;
+        let code = match o {
+            Opcode::Wide(code, _) => *code,
+            o => o.opcode(),
+        };
+        let pushed = match o {
+            Opcode::Aload(i) => out.load(*i as u16),
+            Opcode::Wide(25, i) => out.load(*i),
+            Opcode::Astore(i) => {
+                out.store(*i as u16, popped[0].clone());
+                return Ok(out);
+            }
+            Opcode::Wide(58, i) => {
+                out.store(*i, popped[0].clone());
+                return Ok(out);
+            }
+            Opcode::Istore(i) | Opcode::Lstore(i) | Opcode::Fstore(i) | Opcode::Dstore(i) => {
+                out.store(*i as u16, store_type(code));
+                return Ok(out);
+            }
+            Opcode::Wide(54..=57, i) => {
+                out.store(*i, store_type(code));
+                return Ok(out);
+            }
+            Opcode::Ldc(i) => self.ldc_type(pc, *i as u16)?,
+            Opcode::LdcW(i) | Opcode::Ldc2W(i) => self.ldc_type(pc, *i)?,
+            Opcode::Getfield(i) | Opcode::Getstatic(i) => self.cp.get_name_and_type(*i)
+                .and_then(|(_, d)| from_descriptor(&d))
+                .ok_or(FrameError::BadConstant(pc, *i))?,
+            Opcode::Invokevirtual(i) | Opcode::Invokespecial(i) | Opcode::Invokestatic(i)
+                | Opcode::Invokeinterface(i, _) | Opcode::Invokedynamic(i) => {
+                let (name, descriptor) = self.cp.get_name_and_type(*i).ok_or(FrameError::BadConstant(pc, *i))?;
+                if matches!(o, Opcode::Invokespecial(_)) && name == "<init>" {
+                    self.initialize(pc, &popped[0], &mut out)?;
+                }
+                match MethodDescriptor::from_descriptor(&descriptor) {
+                    Some(d) if d.return_type == JavaType::Void => return Ok(out),
+                    Some(d) => from_java_type(&d.return_type),
+                    None => return Err(FrameError::BadConstant(pc, *i)),
+                }
+            }
+            Opcode::New(_) => VerificationType::Uninitialized(pc),
+            Opcode::Newarray(t) => {
+                let element = match t {
+                    4 => "Z", 5 => "C", 6 => "F", 7 => "D", 8 => "B", 9 => "S", 10 => "I", _ => "J",
+                };
+                VerificationType::Object(format!("[{}", element))
+            }
+            Opcode::Anewarray(i) => VerificationType::Object(array_of(&self.class_name(pc, *i)?)),
+            Opcode::Checkcast(i) | Opcode::Multianewarray(i, _) => VerificationType::Object(self.class_name(pc, *i)?),
+            Opcode::Jsr(_) | Opcode::JsrW(_) | Opcode::Ret(_) | Opcode::Wide(169, _) => return Err(FrameError::Subroutine(pc)),
+            _ => match code {
+                1 => VerificationType::Null,
+                42..=45 => out.load(code as u16 - 42),
+                59..=78 => {
+                    let (base, t) = match code {
+                        59..=62 => (59, VerificationType::Integer),
+                        63..=66 => (63, VerificationType::Long),
+                        67..=70 => (67, VerificationType::Float),
+                        71..=74 => (71, VerificationType::Double),
+                        _ => (75, popped[0].clone()),
+                    };
+                    out.store(code as u16 - base, t);
+                    return Ok(out);
+                }
+                50 => component(&popped[0]),
+                89..=95 => {
+                    // which popped slots go back, bottom first
+                    let order: &[usize] = match code {
+                        89 => &[0, 0],
+                        90 => &[1, 0, 1],
+                        91 => &[2, 0, 1, 2],
+                        92 => &[0, 1, 0, 1],
+                        93 => &[1, 2, 0, 1, 2],
+                        94 => &[2, 3, 0, 1, 2, 3],
+                        _ => &[1, 0],
+                    };
+                    out.stack.extend(order.iter().map(|i| popped[*i].clone()));
+                    return Ok(out);
+                }
+                _ => match pushes {
+                    0 => return Ok(out),
+                    _ => result_type(code),
+                },
+            },
+        };
+        out.push(pushed);
+        Ok(out)
+    }
+
+    // invokespecial <init> turns every copy of the uninitialized object into the class
+    fn initialize(&self, pc: u16, receiver: &VerificationType, frame: &mut Frame) -> Result<(), FrameError> {
+        let class = match receiver {
+            VerificationType::UninitializedThis => self.this_class.to_string(),
+            VerificationType::Uninitialized(new) => match self.new_classes.get(new) {
+                Some(c) => c.clone(),
+                None => return Err(FrameError::BadTarget(pc, *new as i64)),
+            },
+            _ => return Ok(()),
+        };
+        let initialized = VerificationType::Object(class);
+        for t in frame.locals.iter_mut().chain(frame.stack.iter_mut()) {
+            if t == receiver {
+                *t = initialized.clone();
+            }
+        }
+        Ok(())
+    }
+}
+
+fn store_type(code: u8) -> VerificationType {
+    match code {
+        54 => VerificationType::Integer,
+        55 => VerificationType::Long,
+        56 => VerificationType::Float,
+        _ => VerificationType::Double,
+    }
+}
+
+// the value pushed by an instruction that only takes and makes primitives
+fn result_type(code: u8) -> VerificationType {
+    let by_kind = |k: u8| match k % 4 {
+        0 => VerificationType::Integer,
+        1 => VerificationType::Long,
+        2 => VerificationType::Float,
+        _ => VerificationType::Double,
+    };
+    match code {
+        9 | 10 | 22 | 30..=33 | 47 | 133 | 140 | 143 => VerificationType::Long,
+        11..=13 | 23 | 34..=37 | 48 | 134 | 137 | 144 => VerificationType::Float,
+        14 | 15 | 24 | 38..=41 | 49 | 135 | 138 | 141 => VerificationType::Double,
+        96..=119 => by_kind(code - 96),
+        120..=131 => by_kind((code - 120) % 2),
+        _ => VerificationType::Integer,
+    }
+}
+
+// frame at the start of the method, from its descriptor
+pub(crate) fn initial_frame(this_class: &str, name: &str, descriptor: &str, is_static: bool) -> Result<Frame, FrameError> {
+    let method = MethodDescriptor::from_descriptor(descriptor).ok_or(FrameError::BadDescriptor(descriptor.to_string()))?;
+    let mut frame = Frame { locals: Vec::new(), stack: Vec::new() };
+    if !is_static {
+        match name == "<init>" && this_class != OBJECT {
+            true => frame.locals.push(VerificationType::UninitializedThis),
+            false => frame.locals.push(VerificationType::Object(this_class.to_string())),
+        }
+    }
+    for p in &method.parameters {
+        let index = frame.locals.len() as u16;
+        frame.store(index, from_java_type(p));
+    }
+    Ok(frame)
+}
+
+// the frames a StackMapTable needs for this code, in their compressed form
+pub fn compute_frames(cp: &ConstantPool, code: &Code, this_class: &str, name: &str, descriptor: &str, is_static: bool,
+        hierarchy: &dyn ClassHierarchy) -> Result<Vec<StackMapFrame>, FrameError> {
+    let initial = initial_frame(this_class, name, descriptor, is_static)?;
+    let instructions: Vec<(u16, &Opcode)> = code.get_instructions().collect();
+    let position: HashMap<u16, usize> = instructions.iter().enumerate().map(|(i, (pc, _))| (*pc, i)).collect();
+    let index_of = |pc: u16, t: i64| u16::try_from(t).ok().and_then(|t| position.get(&t).copied()).ok_or(FrameError::BadTarget(pc, t));
+    let mut new_classes = HashMap::<u16, String>::new();
+    for (pc, o) in &instructions {
+        if let Opcode::New(i) = o {
+            // one that is not a Class entry is reported at the new
+            new_classes.extend(cp.find_class(*i).map(|c| (*pc, c)));
+        }
+    }
+    let analysis = Analysis { cp, this_class, new_classes };
+
+    let mut handlers = Vec::<(u16, u16, usize, VerificationType)>::new();
+    let mut needs_frame = HashSet::<usize>::new();
+    for e in code.get_exception_table() {
+        let handler = index_of(e.handler_pc, e.handler_pc as i64)?;
+        let catch_type = match e.catch_type {
+            0 => "java/lang/Throwable".to_string(),
+            i => analysis.class_name(e.handler_pc, i)?,
+        };
+        handlers.push((e.start_pc, e.end_pc, handler, VerificationType::Object(catch_type)));
+        needs_frame.insert(handler);
+    }
+    for (i, (pc, o)) in instructions.iter().enumerate() {
+        let (jumps, falls_through) = limits::targets(*pc, o);
+        for t in jumps {
+            needs_frame.insert(index_of(*pc, t)?);
+        }
+        if !falls_through && i + 1 < instructions.len() {
+            needs_frame.insert(i + 1);
+        }
+    }
+
+    let mut frames: Vec<Option<Frame>> = vec![None; instructions.len()];
+    let mut work = Vec::<usize>::new();
+    let merge = |i: usize, incoming: Frame, frames: &mut Vec<Option<Frame>>, work: &mut Vec<usize>| -> Result<(), FrameError> {
+        let merged = match &frames[i] {
+            None => incoming,
+            Some(old) => {
+                if old.stack.len() != incoming.stack.len() {
+                    return Err(FrameError::StackMismatch(instructions[i].0, old.stack.len(), incoming.stack.len()));
+                }
+                let merged = Frame {
+                    locals: merge_types(&old.locals, &incoming.locals, hierarchy),
+                    stack: merge_types(&old.stack, &incoming.stack, hierarchy),
+                };
+                if merged == *old {
+                    return Ok(());
+                }
+                merged
+            }
+        };
+        frames[i] = Some(merged);
+        work.push(i);
+        Ok(())
+    };
+    if !instructions.is_empty() {
+        merge(0, initial.clone(), &mut frames, &mut work)?;
+    }
+    while let Some(i) = work.pop() {
+        let (pc, o) = instructions[i];
+        let frame = frames[i].clone().unwrap();
+        let out = analysis.execute(pc, o, &frame)?;
+        for (start, end, handler, catch_type) in &handlers {
+            if *start <= pc && pc < *end {
+                let locals = merge_types(&frame.locals, &out.locals, hierarchy);
+                merge(*handler, Frame { locals, stack: vec![catch_type.clone()] }, &mut frames, &mut work)?;
+            }
+        }
+        let (jumps, falls_through) = limits::targets(pc, o);
+        for t in jumps {
+            merge(index_of(pc, t)?, out.clone(), &mut frames, &mut work)?;
+        }
+        if falls_through {
+            match instructions.get(i + 1) {
+                Some(_) => merge(i + 1, out, &mut frames, &mut work)?,
+                None => return Err(FrameError::FallsOffEnd(pc)),
+            }
+        }
+    }
+
+    let mut needs_frame: Vec<usize> = needs_frame.into_iter().collect();
+    needs_frame.sort();
+    let mut table = Vec::<StackMapFrame>::new();
+    let mut previous_locals = Frame::entries(&initial.locals, true);
+    let mut previous_pc: Option<u16> = None;
+    for i in needs_frame {
+        let pc = instructions[i].0;
+        let frame = frames[i].as_ref().ok_or(FrameError::Unreachable(pc))?;
+        let locals = Frame::entries(&frame.locals, true);
+        let stack = Frame::entries(&frame.stack, false);
+        let delta = match previous_pc {
+            None => pc,
+            Some(p) => pc - p - 1,
+        };
+        let same_locals = locals == previous_locals;
+        let frame = match (stack.len(), locals.len() as i64 - previous_locals.len() as i64) {
+            (0, _) if same_locals => StackMapFrame::Same(delta),
+            (1, _) if same_locals => StackMapFrame::SameLocals1StackItem(delta, stack[0].clone()),
+            (0, 1..=3) if locals.starts_with(&previous_locals) =>
+                StackMapFrame::Append(delta, locals[previous_locals.len()..].to_vec()),
+            (0, -3..=-1) if previous_locals.starts_with(&locals) =>
+                StackMapFrame::Chop(delta, (previous_locals.len() - locals.len()) as u8),
+            _ => StackMapFrame::Full(delta, locals.clone(), stack),
+        };
+        table.push(frame);
+        previous_locals = locals;
+        previous_pc = Some(pc);
+    }
+    Ok(table)
+}
+
+impl ClassFile {
+    // recompute the StackMapTable of the method at this index in get_methods, dropping it when no frames are needed
+    pub fn compute_stack_map(&mut self, method: usize, hierarchy: &dyn ClassHierarchy) -> Result<(), FrameError> {
+        let m = &self.methods[method];
+        let cp = &self.constant_pool;
+        let info = match m.attributes.iter().find(|a| cp.get_item(&a.attribute_name_index) == "Code") {
+            Some(a) => a.info.clone(),
+            None => return Err(FrameError::NoCode(method)),
+        };
+        let code = Code::try_new(&info).map_err(FrameError::BadCode)?;
+        let name = cp.get_item(&m.name_index);
+        let descriptor = cp.get_item(&m.descriptor_index);
+        let frames = compute_frames(cp, &code, &self.get_class_name(), &name, &descriptor, m.access_flags & ACC_STATIC != 0, hierarchy)?;
+
+        let mut builder = self.constant_pool_builder();
+        let mut attributes: Vec<AttributeInfo> = code.get_attributes().iter()
+            .filter(|a| !cp.is_named(&a.attribute_name_index, "StackMapTable"))
+            .cloned()
+            .collect();
+        if !frames.is_empty() {
+            let info = stack_map::write_stack_map_table(&frames, &mut builder)?;
+            attributes.push(AttributeInfo { attribute_name_index: Index::Single(builder.utf8("StackMapTable")?), info });
+        }
+        self.set_constant_pool(builder.build());
+        self.set_code(method, code::set_attributes(&info, &attributes));
+        Ok(())
+    }
+}
@@ -0,0 +1,270 @@
+// module limits
+// max_stack and max_locals from the instructions, following every path including exception handlers
+use std::collections::HashMap;
+use std::fmt;
+use crate::access_flags::ACC_STATIC;
+use crate::attribute::DecodeError;
+use crate::class_file_reader::ConstantPool;
+use crate::code::Code;
+use crate::code::opcode::Opcode;
+use crate::descriptor::{JavaType, MethodDescriptor};
+use crate::ClassFile;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    pub max_stack: u16,
+    pub max_locals: u16,
+}
+
+#[derive(Debug)]
+pub enum LimitsError {
+    BadDescriptor(String),
+    BadConstant(u16, u16),          // pc, pool index
+    BadTarget(u16, i64),            // pc, branch target
+    StackUnderflow(u16),
+    StackOverflow(u16),
+    StackMismatch(u16, u32, u32),   // pc, the two depths that meet there
+    FallsOffEnd(u16),
+    BadCode(DecodeError),           // the Code attribute does not decode
+}
+
+impl fmt::Display for LimitsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LimitsError::BadDescriptor(d) => write!(f, "bad method descriptor {}", d),
+            LimitsError::BadConstant(pc, i) => write!(f, "pc {}: bad constant pool index {}", pc, i),
+            LimitsError::BadTarget(pc, t) => write!(f, "pc {}: branch target {} is not an instruction", pc, t),
+            LimitsError::StackUnderflow(pc) => write!(f, "pc {}: operand stack underflow", pc),
+            LimitsError::StackOverflow(pc) => write!(f, "pc {}: operand stack deeper than 65535", pc),
+            LimitsError::StackMismatch(pc, a, b) => write!(f, "pc {}: operand stack depth {} and {} meet", pc, a, b),
+            LimitsError::FallsOffEnd(pc) => write!(f, "pc {}: execution falls off the end of the code", pc),
+            LimitsError::BadCode(e) => write!(f, "bad Code attribute: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LimitsError {}
+
+fn member_descriptor(cp: &ConstantPool, index: u16) -> Option<String> {
+    cp.get_name_and_type(index).map(|(_, d)| d)
+}
+
+fn size_of(descriptor: &MethodDescriptor) -> (u32, u32) {
+    let arguments = descriptor.parameters.iter().map(|p| p.size() as u32).sum();
+    (arguments, descriptor.return_type.size() as u32)
+}
+
+// slots popped and pushed, long and double count two, or the pool index that has no usable descriptor
+pub(crate) fn stack_effect(cp: &ConstantPool, o: &Opcode) -> Result<(u32, u32), u16> {
+    let effect = match o {
+        Opcode::Getstatic(i) | Opcode::Putstatic(i) | Opcode::Getfield(i) | Opcode::Putfield(i) => {
+            let size = member_descriptor(cp, *i).and_then(|d| JavaType::from_descriptor(&d)).ok_or(*i)?.size() as u32;
+            match o {
+                Opcode::Getstatic(_) => (0, size),
+                Opcode::Putstatic(_) => (size, 0),
+                Opcode::Getfield(_) => (1, size),
+                _ => (1 + size, 0),
+            }
+        }
+        Opcode::Invokevirtual(i) | Opcode::Invokespecial(i) | Opcode::Invokestatic(i)
+            | Opcode::Invokeinterface(i, _) | Opcode::Invokedynamic(i) => {
+            let descriptor = member_descriptor(cp, *i).and_then(|d| MethodDescriptor::from_descriptor(&d)).ok_or(*i)?;
+            let (arguments, result) = size_of(&descriptor);
+            match o {
+                Opcode::Invokestatic(_) | Opcode::Invokedynamic(_) => (arguments, result),
+                _ => (arguments + 1, result),
+            }
+        }
+        Opcode::Multianewarray(_, dimensions) => (*dimensions as u32, 1),
+        Opcode::Wide(code, _) => match code {
+            21 | 23 | 25 => (0, 1),
+            22 | 24 => (0, 2),
+            54 | 56 | 58 => (1, 0),
+            55 | 57 => (2, 0),
+            _ => (0, 0),
+        },
+        o => match o.opcode() {
+            0 | 132 | 167 | 169 | 177 | 200 | 202 | 254 | 255 => (0, 0),
+            1..=8 | 11..=13 | 16..=19 | 21 | 23 | 25..=29 | 34..=37 | 42..=45 | 168 | 187 | 201 => (0, 1),
+            9 | 10 | 14 | 15 | 20 | 22 | 24 | 30..=33 | 38..=41 => (0, 2),
+            46 | 48 | 50..=53 | 96 | 98 | 100 | 102 | 104 | 106 | 108 | 110 | 112 | 114
+                | 120 | 122 | 124 | 126 | 128 | 130 | 149 | 150 => (2, 1),
+            47 | 49 | 143 | 117 | 119 | 138 => (2, 2),
+            54 | 56 | 58..=62 | 67..=70 | 75..=78 | 87 | 153..=158 | 170..=172 | 174 | 176
+                | 191 | 194 | 195 | 198 | 199 => (1, 0),
+            55 | 57 | 63..=66 | 71..=74 | 88 | 159..=166 | 173 | 175 => (2, 0),
+            79 | 81 | 83..=86 => (3, 0),
+            80 | 82 => (4, 0),
+            89 => (1, 2),
+            90 => (2, 3),
+            91 => (3, 4),
+            92 => (2, 4),
+            93 => (3, 5),
+            94 => (4, 6),
+            95 => (2, 2),
+            97 | 99 | 101 | 103 | 105 | 107 | 109 | 111 | 113 | 115 | 127 | 129 | 131 => (4, 2),
+            116 | 118 | 134 | 139 | 145..=147 | 188 | 189 | 190 | 192 | 193 => (1, 1),
+            121 | 123 | 125 => (3, 2),
+            133 | 135 | 140 | 141 => (1, 2),
+            136 | 137 | 142 | 144 => (2, 1),
+            148 | 151 | 152 => (4, 1),
+            _ => (0, 0),
+        },
+    };
+    Ok(effect)
+}
+
+// first slot after the local an instruction reads or writes
+fn local_end(o: &Opcode) -> Option<u32> {
+    let (index, size) = match o {
+        Opcode::Iload(i) | Opcode::Fload(i) | Opcode::Aload(i) | Opcode::Istore(i) | Opcode::Fstore(i)
+            | Opcode::Astore(i) | Opcode::Ret(i) | Opcode::Iinc(i, _) => (*i as u32, 1),
+        Opcode::Lload(i) | Opcode::Dload(i) | Opcode::Lstore(i) | Opcode::Dstore(i) => (*i as u32, 2),
+        Opcode::WideIinc(i, _) => (*i as u32, 1),
+        Opcode::Wide(code, i) => match code {
+            22 | 24 | 55 | 57 => (*i as u32, 2),
+            _ => (*i as u32, 1),
+        },
+        o => match o.opcode() {
+            26..=29 => (o.opcode() as u32 - 26, 1),
+            30..=33 => (o.opcode() as u32 - 30, 2),
+            34..=37 => (o.opcode() as u32 - 34, 1),
+            38..=41 => (o.opcode() as u32 - 38, 2),
+            42..=45 => (o.opcode() as u32 - 42, 1),
+            59..=62 => (o.opcode() as u32 - 59, 1),
+            63..=66 => (o.opcode() as u32 - 63, 2),
+            67..=70 => (o.opcode() as u32 - 67, 1),
+            71..=74 => (o.opcode() as u32 - 71, 2),
+            75..=78 => (o.opcode() as u32 - 75, 1),
+            _ => return None,
+        },
+    };
+    Some(index + size)
+}
+
+// where control goes after an instruction, and whether it can fall through to the next one
+pub(crate) fn targets(pc: u16, o: &Opcode) -> (Vec<i64>, bool) {
+    let target = |offset: i32| pc as i64 + offset as i64;
+    match o {
+        Opcode::Goto(_) | Opcode::GotoW(_) => (vec![target(o.branch_offset().unwrap())], false),
+        Opcode::Tableswitch(default, _, _, offsets) =>
+            (offsets.iter().chain(std::iter::once(default)).map(|o| target(*o)).collect(), false),
+        Opcode::Lookupswitch(default, pairs) =>
+            (pairs.iter().map(|(_, o)| o).chain(std::iter::once(default)).map(|o| target(*o)).collect(), false),
+        Opcode::Ireturn | Opcode::Lreturn | Opcode::Freturn | Opcode::Dreturn | Opcode::Areturn
+            | Opcode::Return | Opcode::Athrow | Opcode::Ret(_) => (Vec::new(), false),
+        Opcode::Wide(169, _) => (Vec::new(), false),
+        // a subroutine comes back to the instruction after the jsr with the stack it had before
+        o => (o.branch_offset().map(target).into_iter().collect(), true),
+    }
+}
+
+// the depth an instruction is first reached with, every other path must agree
+fn reach(i: usize, d: u32, pc: u16, depth: &mut [Option<u32>], work: &mut Vec<usize>) -> Result<(), LimitsError> {
+    match depth[i] {
+        None => {
+            depth[i] = Some(d);
+            work.push(i);
+            Ok(())
+        }
+        Some(other) if other == d => Ok(()),
+        Some(other) => Err(LimitsError::StackMismatch(pc, other, d)),
+    }
+}
+
+// the method's own descriptor and flags give the locals taken by this and the parameters
+pub fn compute(cp: &ConstantPool, code: &Code, descriptor: &str, is_static: bool) -> Result<Limits, LimitsError> {
+    let method = MethodDescriptor::from_descriptor(descriptor).ok_or(LimitsError::BadDescriptor(descriptor.to_string()))?;
+    let mut max_locals = size_of(&method).0 + if is_static { 0 } else { 1 };
+
+    let instructions: Vec<(u16, &Opcode)> = code.get_instructions().collect();
+    let position: HashMap<u16, usize> = instructions.iter().enumerate().map(|(i, (pc, _))| (*pc, i)).collect();
+    let mut depth: Vec<Option<u32>> = vec![None; instructions.len()];
+    let mut work = Vec::<usize>::new();
+    let mut max_stack = 0;
+
+    if !instructions.is_empty() {
+        reach(0, 0, 0, &mut depth, &mut work)?;
+    }
+    while let Some(i) = work.pop() {
+        let (pc, o) = instructions[i];
+        let d = depth[i].unwrap();
+        max_stack = max_stack.max(d);
+        for e in code.get_exception_table().iter().filter(|e| e.start_pc <= pc && pc < e.end_pc) {
+            let handler = *position.get(&e.handler_pc).ok_or(LimitsError::BadTarget(pc, e.handler_pc as i64))?;
+            reach(handler, 1, e.handler_pc, &mut depth, &mut work)?;
+        }
+        let (pop, push) = stack_effect(cp, o).map_err(|i| LimitsError::BadConstant(pc, i))?;
+        if pop > d {
+            return Err(LimitsError::StackUnderflow(pc));
+        }
+        let after = d - pop + push;
+        if after > u16::MAX as u32 {
+            return Err(LimitsError::StackOverflow(pc));
+        }
+        max_stack = max_stack.max(after);
+        if let Some(end) = local_end(o) {
+            max_locals = max_locals.max(end);
+        }
+        let (jumps, falls_through) = targets(pc, o);
+        for t in jumps {
+            let j = u16::try_from(t).ok().and_then(|t| position.get(&t)).ok_or(LimitsError::BadTarget(pc, t))?;
+            reach(*j, after, t as u16, &mut depth, &mut work)?;
+        }
+        if falls_through {
+            let next = match o {
+                Opcode::Jsr(_) | Opcode::JsrW(_) => d,
+                _ => after,
+            };
+            match instructions.get(i + 1) {
+                Some((next_pc, _)) => reach(i + 1, next, *next_pc, &mut depth, &mut work)?,
+                None => return Err(LimitsError::FallsOffEnd(pc)),
+            }
+        }
+    }
+    Ok(Limits { max_stack: max_stack as u16, max_locals: max_locals.min(u16::MAX as u32) as u16 })
+}
+
+// a method whose Code declares less than its instructions need, or whose instructions cannot be followed
+pub struct LimitsProblem {
+    pub method: String,                     // name and descriptor
+    pub declared: Limits,
+    pub computed: Result<Limits, LimitsError>,
+}
+
+impl fmt::Display for LimitsProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.computed {
+            Ok(computed) => write!(f, "{}: declares max_stack {} max_locals {}, needs {} {}", self.method,
+                self.declared.max_stack, self.declared.max_locals, computed.max_stack, computed.max_locals),
+            Err(e) => write!(f, "{}: {}", self.method, e),
+        }
+    }
+}
+
+pub fn check(class_file: &ClassFile) -> Vec<LimitsProblem> {
+    let cp = &class_file.constant_pool;
+    let mut problems = Vec::<LimitsProblem>::new();
+    for m in &class_file.methods {
+        let descriptor = cp.get_item(&m.descriptor_index);
+        for a in m.attributes.iter().filter(|a| cp.get_item(&a.attribute_name_index) == "Code") {
+            let (code, computed) = match Code::try_new(&a.info) {
+                Ok(code) => {
+                    let computed = compute(cp, &code, &descriptor, m.access_flags & ACC_STATIC != 0);
+                    (code, computed)
+                }
+                Err(e) => (Code::default(), Err(LimitsError::BadCode(e))),
+            };
+            let declared = Limits { max_stack: code.get_max_stack(), max_locals: code.get_max_locals() };
+            let too_small = match &computed {
+                Ok(c) => c.max_stack > declared.max_stack || c.max_locals > declared.max_locals,
+                Err(_) => true,
+            };
+            if too_small {
+                let method = format!("{}{}", cp.get_item(&m.name_index), descriptor);
+                problems.push(LimitsProblem { method, declared, computed });
+            }
+        }
+    }
+    problems
+}
@@ -1,6 +1,9 @@
+use crate::attribute::DecodeError;
 use crate::code::CodeReader;
 use std::fmt;
 
+// branch offsets are relative to the instruction's own pc
+#[derive(Debug, Clone, PartialEq)]
 pub enum Opcode {
         Aaload,	// 50	Load reference from array
         Aastore,	// 83	Store into reference array
@@ -21,7 +24,7 @@ pub enum Opcode {
         Athrow,	// 191	Throw Throwable reference
         Baload,	// 51	Load byte or boolean from array
         Bastore,	// 84	Store into byte or boolean array
-        Bipush(u8),	// 16	Push byte
+        Bipush(i8),	// 16	Push byte
         Breakpoint,	// 202	Reserved for internal usage in debuggers
         Caload,	// 52	Load char from array
         Castore,	// 85	Store into char array
@@ -87,8 +90,8 @@ pub enum Opcode {
         Fsub,	// 102	Subtract float
         Getfield(u16),	// 180	Fetch field from object
         Getstatic(u16),	// 178	Get static field from class
-        Goto(u16),	// 167	Unconditional jump
-        GotoW(u32),	// 200	Unconditional jump (wide index)
+        Goto(i16),	// 167	Unconditional jump
+        GotoW(i32),	// 200	Unconditional jump (wide index)
         I2b,	// 145	Convert int to byte
         I2c,	// 146	Convert int to char
         I2d,	// 135	Convert int to double
@@ -107,23 +110,23 @@ pub enum Opcode {
         Iconst5,	// 8	Push int constant 5
         IconstM1,	// 2	Push int constant -1
         Idiv,	// 108	Divide int
-        IfAcmpeq(u16),	// 165	Jump if reference comparison succeeds
-        IfAcmpne(u16),	// 166	Jump if reference comparison succeeds
-        IfIcmpeq(u16),	// 159	Jump if int comparison succeeds
-        IfIcmpge(u16),	// 162	Jump if int comparison succeeds
-        IfIcmpgt(u16),	// 163	Jump if int comparison succeeds
-        IfIcmple(u16),	// 164	Jump if int comparison succeeds
-        IfIcmplt(u16),	// 161	Jump if int comparison succeeds
-        IfIcmpne(u16),	// 160	Jump if int comparison succeeds
-        Ifeq(u16),	// 153	Jump if int comparison with zero succeeds
-        Ifge(u16),	// 156	Jump if int comparison with zero succeeds
-        Ifgt(u16),	// 157	Jump if int comparison with zero succeeds
-        Ifle(u16),	// 158	Jump if int comparison with zero succeeds
-        Iflt(u16),	// 155	Jump if int comparison with zero succeeds
-        Ifne(u16),	// 154	Jump if int comparison with zero succeeds
-        Ifnonnull(u16),	// 199	Jump if reference not null
-        Ifnull(u16),	// 198	Jump if reference is null
-        Iinc(u16),	// 132	Increment local variable by constant
+        IfAcmpeq(i16),	// 165	Jump if reference comparison succeeds
+        IfAcmpne(i16),	// 166	Jump if reference comparison succeeds
+        IfIcmpeq(i16),	// 159	Jump if int comparison succeeds
+        IfIcmpge(i16),	// 162	Jump if int comparison succeeds
+        IfIcmpgt(i16),	// 163	Jump if int comparison succeeds
+        IfIcmple(i16),	// 164	Jump if int comparison succeeds
+        IfIcmplt(i16),	// 161	Jump if int comparison succeeds
+        IfIcmpne(i16),	// 160	Jump if int comparison succeeds
+        Ifeq(i16),	// 153	Jump if int comparison with zero succeeds
+        Ifge(i16),	// 156	Jump if int comparison with zero succeeds
+        Ifgt(i16),	// 157	Jump if int comparison with zero succeeds
+        Ifle(i16),	// 158	Jump if int comparison with zero succeeds
+        Iflt(i16),	// 155	Jump if int comparison with zero succeeds
+        Ifne(i16),	// 154	Jump if int comparison with zero succeeds
+        Ifnonnull(i16),	// 199	Jump if reference not null
+        Ifnull(i16),	// 198	Jump if reference is null
+        Iinc(u8, i8),	// 132	Increment local variable by constant
         Iload(u8),	// 21	Load int from local variable
         Iload0,	// 26	Load int from local variable
         Iload1,	// 27	Load int from local variable
@@ -133,9 +136,9 @@ pub enum Opcode {
         Impdep2,	// 255	Reserved for internal usage in JVM
         Imul,	// 104	Multiply int
         Ineg,	// 116	Negate int
-        Instanceof,	// 193	Determine if reference is of given type
-        Invokedynamic,	// 186	Invoke a dynamically-computed call site
-        Invokeinterface,	// 185	Invoke interface method
+        Instanceof(u16),	// 193	Determine if reference is of given type
+        Invokedynamic(u16),	// 186	Invoke a dynamically-computed call site
+        Invokeinterface(u16, u8),	// 185	Invoke interface method
         Invokespecial(u16),	// 183	Directly invoke instance (initialization) method of the current class or its supertypes
         Invokestatic(u16),	// 184	Invoke static method
         Invokevirtual(u16),	// 182	Invoke instance method, dispatch based on class
@@ -144,7 +147,7 @@ pub enum Opcode {
         Ireturn,	// 172	Return int from method
         Ishl,	// 120	Shift left int
         Ishr,	// 122	Arithmetic shift right int
-        Istore,	// 54	Store int into local variable
+        Istore(u8),	// 54	Store int into local variable
         Istore0,	// 59	Store int into local variable 0
         Istore1,	// 60	Store int into local variable 1
         Istore2,	// 61	Store int into local variable 2
@@ -152,8 +155,8 @@ pub enum Opcode {
         Isub,	// 100	Subtract int
         Iushr,	// 124	Logical shift right int
         Ixor,	// 130	Boolean XOR int
-        Jsr(u16),	// 168	Jump subroutine
-        JsrW(u32),	// 201	Jump subroutine (wide index)
+        Jsr(i16),	// 168	Jump subroutine
+        JsrW(i32),	// 201	Jump subroutine (wide index)
         L2d,	// 138	Convert long to double
         L2f,	// 137	Convert long to float
         L2i,	// 136	Convert long to int
@@ -168,20 +171,20 @@ pub enum Opcode {
         LdcW(u16),	// 19	Push item from constant pool (wide index)
         Ldc2W(u16),	// 20	Push long or double from constant pool (wide index)
         Ldiv,	// 109	Divide long
-        Lload,	// 22	Load long from local variable
+        Lload(u8),	// 22	Load long from local variable
         Lload0,	// 30	Load long from local variable 0
         Lload1,	// 31	Load long from local variable 1
         Lload2,	// 32	Load long from local variable 2
         Lload3,	// 33	Load long from local variable 3
         Lmul,	// 105	Multiply long
         Lneg,	// 117	Negate long
-        Lookupswitch,	// 171	Access jump table by key match and jump
+        Lookupswitch(i32, Vec<(i32, i32)>),	// 171	Access jump table by key match and jump
         Lor,	// 129	Boolean OR long
         Lrem,	// 113	Remainder long
         Lreturn,	// 173	Return long from method
         Lshl,	// 121	Shift left long
         Lshr,	// 123	Arithmetic shift right long
-        Lstore,	// 55	Store long into local variable
+        Lstore(u8),	// 55	Store long into local variable
         Lstore0,	// 63	Store long into local variable 0
         Lstore1,	// 64	Store long into local variable 1
         Lstore2,	// 65	Store long into local variable 2
@@ -203,10 +206,11 @@ pub enum Opcode {
         Return,	// 177	Return void from method
         Saload,	// 53	Load short from array
         Sastore,	// 86	Store into short array
-        Sipush(u16),	// 17	Push short
+        Sipush(i16),	// 17	Push short
         Swap,	// 95	Swap the top two operand stack values
-        Tableswitch(u32, u32),	// 170	Access jump table by index and jump
-        Wide,/* TO DO */	// 196	Extend local variable index by additional bytes
+        Tableswitch(i32, i32, i32, Vec<i32>),	// 170	Access jump table by index and jump
+        Wide(u8, u16),	// 196	Extend local variable index by additional bytes, the modified opcode and index
+        WideIinc(u16, i16),	// 196	wide iinc
 }
 
 pub fn get_opcode(code: u8, reader: &mut CodeReader) -> Opcode {
@@ -227,13 +231,13 @@ pub fn get_opcode(code: u8, reader: &mut CodeReader) -> Opcode {
             13 => Opcode::Fconst2,
             14 => Opcode::Dconst0,
             15 => Opcode::Dconst1,
-            16 => Opcode::Bipush(reader.read_u8()),
-            17 => Opcode::Sipush(reader.read_u16()),
+            16 => Opcode::Bipush(reader.read_u8() as i8),
+            17 => Opcode::Sipush(reader.read_u16() as i16),
             18 => Opcode::Ldc(reader.read_u8()),
             19 => Opcode::LdcW(reader.read_u16()),
             20 => Opcode::Ldc2W(reader.read_u16()),
             21 => Opcode::Iload(reader.read_u8()),
-            22 => Opcode::Lload,
+            22 => Opcode::Lload(reader.read_u8()),
             23 => Opcode::Fload(reader.read_u8()),
             24 => Opcode::Dload(reader.read_u8()),
             25 => Opcode::Aload(reader.read_u8()),
@@ -265,8 +269,8 @@ pub fn get_opcode(code: u8, reader: &mut CodeReader) -> Opcode {
             51 => Opcode::Baload,
             52 => Opcode::Caload,
             53 => Opcode::Saload,
-            54 => Opcode::Istore,
-            55 => Opcode::Lstore,
+            54 => Opcode::Istore(reader.read_u8()),
+            55 => Opcode::Lstore(reader.read_u8()),
             56 => Opcode::Fstore(reader.read_u8()),
             57 => Opcode::Dstore(reader.read_u8()),
             58 => Opcode::Astore(reader.read_u8()),
@@ -343,7 +347,7 @@ pub fn get_opcode(code: u8, reader: &mut CodeReader) -> Opcode {
             129 => Opcode::Lor,
             130 => Opcode::Ixor,
             131 => Opcode::Lxor,
-            132 => Opcode::Iinc(reader.read_u16()),
+            132 => Opcode::Iinc(reader.read_u8(), reader.read_u8() as i8),
             133 => Opcode::I2l,
             134 => Opcode::I2f,
             135 => Opcode::I2d,
@@ -364,25 +368,38 @@ pub fn get_opcode(code: u8, reader: &mut CodeReader) -> Opcode {
             150 => Opcode::Fcmpg,
             151 => Opcode::Dcmpl,
             152 => Opcode::Dcmpg,
-            153 => Opcode::Ifeq(reader.read_u16()),
-            154 => Opcode::Ifne(reader.read_u16()),
-            155 => Opcode::Iflt(reader.read_u16()),
-            156 => Opcode::Ifge(reader.read_u16()),
-            157 => Opcode::Ifgt(reader.read_u16()),
-            158 => Opcode::Ifle(reader.read_u16()),
-            159 => Opcode::IfIcmpeq(reader.read_u16()),
-            160 => Opcode::IfIcmpne(reader.read_u16()),
-            161 => Opcode::IfIcmplt(reader.read_u16()),
-            162 => Opcode::IfIcmpge(reader.read_u16()),
-            163 => Opcode::IfIcmpgt(reader.read_u16()),
-            164 => Opcode::IfIcmple(reader.read_u16()),
-            165 => Opcode::IfAcmpeq(reader.read_u16()),
-            166 => Opcode::IfAcmpne(reader.read_u16()),
-            167 => Opcode::Goto(reader.read_u16()),
-            168 => Opcode::Jsr(reader.read_u16()),
+            153 => Opcode::Ifeq(reader.read_u16() as i16),
+            154 => Opcode::Ifne(reader.read_u16() as i16),
+            155 => Opcode::Iflt(reader.read_u16() as i16),
+            156 => Opcode::Ifge(reader.read_u16() as i16),
+            157 => Opcode::Ifgt(reader.read_u16() as i16),
+            158 => Opcode::Ifle(reader.read_u16() as i16),
+            159 => Opcode::IfIcmpeq(reader.read_u16() as i16),
+            160 => Opcode::IfIcmpne(reader.read_u16() as i16),
+            161 => Opcode::IfIcmplt(reader.read_u16() as i16),
+            162 => Opcode::IfIcmpge(reader.read_u16() as i16),
+            163 => Opcode::IfIcmpgt(reader.read_u16() as i16),
+            164 => Opcode::IfIcmple(reader.read_u16() as i16),
+            165 => Opcode::IfAcmpeq(reader.read_u16() as i16),
+            166 => Opcode::IfAcmpne(reader.read_u16() as i16),
+            167 => Opcode::Goto(reader.read_u16() as i16),
+            168 => Opcode::Jsr(reader.read_u16() as i16),
             169 => Opcode::Ret(reader.read_u8()),
-            170 => Opcode::Tableswitch(reader.read_u32(), reader.read_u32()), // to fix
-            171 => Opcode::Lookupswitch,
+            170 => {
+                reader.align();
+                let default = reader.read_u32() as i32;
+                let low = reader.read_u32() as i32;
+                let high = reader.read_u32() as i32;
+                let offsets = (low..=high).map_while(|_| reader.try_u32()).map(|o| o as i32).collect();
+                Opcode::Tableswitch(default, low, high, offsets)
+            },
+            171 => {
+                reader.align();
+                let default = reader.read_u32() as i32;
+                let npairs = reader.read_u32();
+                let pairs = (0..npairs).map_while(|_| Some((reader.try_u32()? as i32, reader.try_u32()? as i32))).collect();
+                Opcode::Lookupswitch(default, pairs)
+            },
             172 => Opcode::Ireturn,
             173 => Opcode::Lreturn,
             174 => Opcode::Freturn,
@@ -396,27 +413,47 @@ pub fn get_opcode(code: u8, reader: &mut CodeReader) -> Opcode {
             182 => Opcode::Invokevirtual(reader.read_u16()),
             183 => Opcode::Invokespecial(reader.read_u16()),
             184 => Opcode::Invokestatic(reader.read_u16()),
-            185 => Opcode::Invokeinterface,
-            186 => Opcode::Invokedynamic,
+            185 => {
+                let index = reader.read_u16();
+                let count = reader.read_u8();
+                reader.read_u8();
+                Opcode::Invokeinterface(index, count)
+            },
+            186 => {
+                let index = reader.read_u16();
+                reader.read_u16();
+                Opcode::Invokedynamic(index)
+            },
             187 => Opcode::New(reader.read_u16()),
             188 => Opcode::Newarray(reader.read_u8()),
             189 => Opcode::Anewarray(reader.read_u16()),
             190 => Opcode::Arraylength,
             191 => Opcode::Athrow,
             192 => Opcode::Checkcast(reader.read_u16()),
-            193 => Opcode::Instanceof,
+            193 => Opcode::Instanceof(reader.read_u16()),
             194 => Opcode::Monitorenter,
             195 => Opcode::Monitorexit,
-            196 => Opcode::Wide,
+            196 => {
+                let code = reader.read_u8();
+                let index = reader.read_u16();
+                match code {
+                    132 => Opcode::WideIinc(index, reader.read_u16() as i16),
+                    _ => Opcode::Wide(code, index),
+                }
+            },
             197 => Opcode::Multianewarray(reader.read_u16(),reader.read_u8()),
-            198 => Opcode::Ifnull(reader.read_u16()),
-            199 => Opcode::Ifnonnull(reader.read_u16()),
-            200 => Opcode::GotoW(reader.read_u32()),
-            201 => Opcode::JsrW(reader.read_u32()),
+            198 => Opcode::Ifnull(reader.read_u16() as i16),
+            199 => Opcode::Ifnonnull(reader.read_u16() as i16),
+            200 => Opcode::GotoW(reader.read_u32() as i32),
+            201 => Opcode::JsrW(reader.read_u32() as i32),
             202 => Opcode::Breakpoint,
             254 => Opcode::Impdep1,
             255 => Opcode::Impdep2,
-            203_u8..=253_u8 => todo!(),
+            // undefined, the reader's error makes Code::try_new fail so the Nop is never seen
+            203_u8..=253_u8 => {
+                reader.fail(DecodeError::Tag("opcode", code));
+                Opcode::Nop
+            }
          }
     }
 
@@ -545,7 +582,7 @@ impl fmt::Display for Opcode {
             Opcode::Ifne(i) => write!(f, "ifne {}", i),
             Opcode::Ifnonnull(i) => write!(f, "ifnonnull {}", i),
             Opcode::Ifnull(i) => write!(f, "ifnull {}", i),
-            Opcode::Iinc(i) => write!(f, "iinc {}", i),
+            Opcode::Iinc(i, c) => write!(f, "iinc {} {}", i, c),
             Opcode::Iload(i) => write!(f, "iload {}", i),
             Opcode::Iload0 => write!(f, "iload_0"),
             Opcode::Iload1 => write!(f, "iload_1"),
@@ -555,9 +592,9 @@ impl fmt::Display for Opcode {
             Opcode::Impdep2 => write!(f, "impdep2"),
             Opcode::Imul => write!(f, "imul"),
             Opcode::Ineg => write!(f, "ineg"),
-            Opcode::Instanceof => write!(f, "instanceof"),
-            Opcode::Invokedynamic => write!(f, "invokedynamic"),
-            Opcode::Invokeinterface => write!(f, "invokeinterface"),
+            Opcode::Instanceof(i) => write!(f, "instanceof {}", i),
+            Opcode::Invokedynamic(i) => write!(f, "invokedynamic {}", i),
+            Opcode::Invokeinterface(i, c) => write!(f, "invokeinterface {} {}", i, c),
             Opcode::Invokespecial(i) => write!(f, "invokespecial {}", i),
             Opcode::Invokestatic(i) => write!(f, "invokestatic {}", i),
             Opcode::Invokevirtual(i) => write!(f, "invokevirtual {}", i),
@@ -566,7 +603,7 @@ impl fmt::Display for Opcode {
             Opcode::Ireturn => write!(f, "ireturn"),
             Opcode::Ishl => write!(f, "ishl"),
             Opcode::Ishr => write!(f, "ishr"),
-            Opcode::Istore => write!(f, "istore"),
+            Opcode::Istore(i) => write!(f, "istore {}", i),
             Opcode::Istore0 => write!(f, "istore_0"),
             Opcode::Istore1 => write!(f, "istore_1"),
             Opcode::Istore2 => write!(f, "istore_2"),
@@ -590,20 +627,26 @@ impl fmt::Display for Opcode {
             Opcode::LdcW(i) => write!(f, "ldc_w {}", i),
             Opcode::Ldc2W(i) => write!(f, "ldc2_w {}", i),
             Opcode::Ldiv => write!(f, "ldiv"),
-            Opcode::Lload => write!(f, "lload"),
+            Opcode::Lload(i) => write!(f, "lload {}", i),
             Opcode::Lload0 => write!(f, "lload_0"),
             Opcode::Lload1 => write!(f, "lload_1"),
             Opcode::Lload2 => write!(f, "lload_2"),
             Opcode::Lload3 => write!(f, "lload_3"),
             Opcode::Lmul => write!(f, "lmul"),
             Opcode::Lneg => write!(f, "lneg"),
-            Opcode::Lookupswitch => write!(f, "lookupswitch"),
+            Opcode::Lookupswitch(default, pairs) => {
+                write!(f, "lookupswitch {{")?;
+                for (m, o) in pairs {
+                    write!(f, " {}: {}", m, o)?;
+                }
+                write!(f, " default: {} }}", default)
+            },
             Opcode::Lor => write!(f, "lor"),
             Opcode::Lrem => write!(f, "lrem"),
             Opcode::Lreturn => write!(f, "lreturn"),
             Opcode::Lshl => write!(f, "lshl"),
             Opcode::Lshr => write!(f, "lshr"),
-            Opcode::Lstore => write!(f, "lstore"),
+            Opcode::Lstore(i) => write!(f, "lstore {}", i),
             Opcode::Lstore0 => write!(f, "lstore_0"),
             Opcode::Lstore1 => write!(f, "lstore_1"),
             Opcode::Lstore2 => write!(f, "lstore_2"),
@@ -627,8 +670,392 @@ impl fmt::Display for Opcode {
             Opcode::Sastore => write!(f, "sastore"),
             Opcode::Sipush(i) => write!(f, "sipush {}", i),
             Opcode::Swap => write!(f, "swap"),
-            Opcode::Tableswitch(i, j) => write!(f, "tableswitch {}{}", i, j), /* TODO */
-            Opcode::Wide => write!(f, "wide"),
+            Opcode::Tableswitch(default, low, high, offsets) => {
+                write!(f, "tableswitch {} to {} {{", low, high)?;
+                for o in offsets {
+                    write!(f, " {}", o)?;
+                }
+                write!(f, " default: {} }}", default)
+            },
+            Opcode::Wide(code, i) => write!(f, "wide {} {}", wide_name(*code), i),
+            Opcode::WideIinc(i, c) => write!(f, "wide iinc {} {}", i, c),
+        }
+    }
+}
+
+// the instruction with this mnemonic and zero operands, decoded from zero bytes
+pub fn from_mnemonic(name: &str) -> Option<Opcode> {
+    let zeros = [0u8; 32];
+    (0..=202u8).chain(254..=255).find_map(|code| {
+        let mut reader = CodeReader::new(&zeros);
+        reader.set_length(zeros.len() as u32);
+        let o = get_opcode(code, &mut reader);
+        match o.mnemonic() == name {
+            true => Some(o),
+            false => None,
+        }
+    })
+}
+
+fn wide_name(code: u8) -> &'static str {
+    match code {
+        21 => "iload",
+        22 => "lload",
+        23 => "fload",
+        24 => "dload",
+        25 => "aload",
+        54 => "istore",
+        55 => "lstore",
+        56 => "fstore",
+        57 => "dstore",
+        58 => "astore",
+        169 => "ret",
+        _ => "?",
+    }
+}
+
+impl Opcode {
+    pub fn mnemonic(&self) -> String {
+        match self {
+            Opcode::Wide(..) | Opcode::WideIinc(..) => "wide".to_string(),
+            o => o.to_string().split(' ').next().unwrap_or("").to_string(),
+        }
+    }
+
+    pub fn opcode(&self) -> u8 {
+        match self {
+            Opcode::Nop => 0,
+            Opcode::AconstNull => 1,
+            Opcode::IconstM1 => 2,
+            Opcode::Iconst0 => 3,
+            Opcode::Iconst1 => 4,
+            Opcode::Iconst2 => 5,
+            Opcode::Iconst3 => 6,
+            Opcode::Iconst4 => 7,
+            Opcode::Iconst5 => 8,
+            Opcode::Lconst0 => 9,
+            Opcode::Lconst1 => 10,
+            Opcode::Fconst0 => 11,
+            Opcode::Fconst1 => 12,
+            Opcode::Fconst2 => 13,
+            Opcode::Dconst0 => 14,
+            Opcode::Dconst1 => 15,
+            Opcode::Bipush(..) => 16,
+            Opcode::Sipush(..) => 17,
+            Opcode::Ldc(..) => 18,
+            Opcode::LdcW(..) => 19,
+            Opcode::Ldc2W(..) => 20,
+            Opcode::Iload(..) => 21,
+            Opcode::Lload(..) => 22,
+            Opcode::Fload(..) => 23,
+            Opcode::Dload(..) => 24,
+            Opcode::Aload(..) => 25,
+            Opcode::Iload0 => 26,
+            Opcode::Iload1 => 27,
+            Opcode::Iload2 => 28,
+            Opcode::Iload3 => 29,
+            Opcode::Lload0 => 30,
+            Opcode::Lload1 => 31,
+            Opcode::Lload2 => 32,
+            Opcode::Lload3 => 33,
+            Opcode::Fload0 => 34,
+            Opcode::Fload1 => 35,
+            Opcode::Fload2 => 36,
+            Opcode::Fload3 => 37,
+            Opcode::Dload0 => 38,
+            Opcode::Dload1 => 39,
+            Opcode::Dload2 => 40,
+            Opcode::Dload3 => 41,
+            Opcode::Aload0 => 42,
+            Opcode::Aload1 => 43,
+            Opcode::Aload2 => 44,
+            Opcode::Aload3 => 45,
+            Opcode::Iaload => 46,
+            Opcode::Laload => 47,
+            Opcode::Faload => 48,
+            Opcode::Daload => 49,
+            Opcode::Aaload => 50,
+            Opcode::Baload => 51,
+            Opcode::Caload => 52,
+            Opcode::Saload => 53,
+            Opcode::Istore(..) => 54,
+            Opcode::Lstore(..) => 55,
+            Opcode::Fstore(..) => 56,
+            Opcode::Dstore(..) => 57,
+            Opcode::Astore(..) => 58,
+            Opcode::Istore0 => 59,
+            Opcode::Istore1 => 60,
+            Opcode::Istore2 => 61,
+            Opcode::Istore3 => 62,
+            Opcode::Lstore0 => 63,
+            Opcode::Lstore1 => 64,
+            Opcode::Lstore2 => 65,
+            Opcode::Lstore3 => 66,
+            Opcode::Fstore0 => 67,
+            Opcode::Fstore1 => 68,
+            Opcode::Fstore2 => 69,
+            Opcode::Fstore3 => 70,
+            Opcode::Dstore0 => 71,
+            Opcode::Dstore1 => 72,
+            Opcode::Dstore2 => 73,
+            Opcode::Dstore3 => 74,
+            Opcode::Astore0 => 75,
+            Opcode::Astore1 => 76,
+            Opcode::Astore2 => 77,
+            Opcode::Astore3 => 78,
+            Opcode::Iastore => 79,
+            Opcode::Lastore => 80,
+            Opcode::Fastore => 81,
+            Opcode::Dastore => 82,
+            Opcode::Aastore => 83,
+            Opcode::Bastore => 84,
+            Opcode::Castore => 85,
+            Opcode::Sastore => 86,
+            Opcode::Pop => 87,
+            Opcode::Pop2 => 88,
+            Opcode::Dup => 89,
+            Opcode::DupX1 => 90,
+            Opcode::DupX2 => 91,
+            Opcode::Dup2 => 92,
+            Opcode::Dup2X1 => 93,
+            Opcode::Dup2X2 => 94,
+            Opcode::Swap => 95,
+            Opcode::Iadd => 96,
+            Opcode::Ladd => 97,
+            Opcode::Fadd => 98,
+            Opcode::Dadd => 99,
+            Opcode::Isub => 100,
+            Opcode::Lsub => 101,
+            Opcode::Fsub => 102,
+            Opcode::Dsub => 103,
+            Opcode::Imul => 104,
+            Opcode::Lmul => 105,
+            Opcode::Fmul => 106,
+            Opcode::Dmul => 107,
+            Opcode::Idiv => 108,
+            Opcode::Ldiv => 109,
+            Opcode::Fdiv => 110,
+            Opcode::Ddiv => 111,
+            Opcode::Irem => 112,
+            Opcode::Lrem => 113,
+            Opcode::Frem => 114,
+            Opcode::Drem => 115,
+            Opcode::Ineg => 116,
+            Opcode::Lneg => 117,
+            Opcode::Fneg => 118,
+            Opcode::Dneg => 119,
+            Opcode::Ishl => 120,
+            Opcode::Lshl => 121,
+            Opcode::Ishr => 122,
+            Opcode::Lshr => 123,
+            Opcode::Iushr => 124,
+            Opcode::Lushr => 125,
+            Opcode::Iand => 126,
+            Opcode::Land => 127,
+            Opcode::Ior => 128,
+            Opcode::Lor => 129,
+            Opcode::Ixor => 130,
+            Opcode::Lxor => 131,
+            Opcode::Iinc(..) => 132,
+            Opcode::I2l => 133,
+            Opcode::I2f => 134,
+            Opcode::I2d => 135,
+            Opcode::L2i => 136,
+            Opcode::L2f => 137,
+            Opcode::L2d => 138,
+            Opcode::F2i => 139,
+            Opcode::F2l => 140,
+            Opcode::F2d => 141,
+            Opcode::D2i => 142,
+            Opcode::D2l => 143,
+            Opcode::D2f => 144,
+            Opcode::I2b => 145,
+            Opcode::I2c => 146,
+            Opcode::I2s => 147,
+            Opcode::Lcmp => 148,
+            Opcode::Fcmpl => 149,
+            Opcode::Fcmpg => 150,
+            Opcode::Dcmpl => 151,
+            Opcode::Dcmpg => 152,
+            Opcode::Ifeq(..) => 153,
+            Opcode::Ifne(..) => 154,
+            Opcode::Iflt(..) => 155,
+            Opcode::Ifge(..) => 156,
+            Opcode::Ifgt(..) => 157,
+            Opcode::Ifle(..) => 158,
+            Opcode::IfIcmpeq(..) => 159,
+            Opcode::IfIcmpne(..) => 160,
+            Opcode::IfIcmplt(..) => 161,
+            Opcode::IfIcmpge(..) => 162,
+            Opcode::IfIcmpgt(..) => 163,
+            Opcode::IfIcmple(..) => 164,
+            Opcode::IfAcmpeq(..) => 165,
+            Opcode::IfAcmpne(..) => 166,
+            Opcode::Goto(..) => 167,
+            Opcode::Jsr(..) => 168,
+            Opcode::Ret(..) => 169,
+            Opcode::Tableswitch(..) => 170,
+            Opcode::Lookupswitch(..) => 171,
+            Opcode::Ireturn => 172,
+            Opcode::Lreturn => 173,
+            Opcode::Freturn => 174,
+            Opcode::Dreturn => 175,
+            Opcode::Areturn => 176,
+            Opcode::Return => 177,
+            Opcode::Getstatic(..) => 178,
+            Opcode::Putstatic(..) => 179,
+            Opcode::Getfield(..) => 180,
+            Opcode::Putfield(..) => 181,
+            Opcode::Invokevirtual(..) => 182,
+            Opcode::Invokespecial(..) => 183,
+            Opcode::Invokestatic(..) => 184,
+            Opcode::Invokeinterface(..) => 185,
+            Opcode::Invokedynamic(..) => 186,
+            Opcode::New(..) => 187,
+            Opcode::Newarray(..) => 188,
+            Opcode::Anewarray(..) => 189,
+            Opcode::Arraylength => 190,
+            Opcode::Athrow => 191,
+            Opcode::Checkcast(..) => 192,
+            Opcode::Instanceof(..) => 193,
+            Opcode::Monitorenter => 194,
+            Opcode::Monitorexit => 195,
+            Opcode::Wide(..) | Opcode::WideIinc(..) => 196,
+            Opcode::Multianewarray(..) => 197,
+            Opcode::Ifnull(..) => 198,
+            Opcode::Ifnonnull(..) => 199,
+            Opcode::GotoW(..) => 200,
+            Opcode::JsrW(..) => 201,
+            Opcode::Breakpoint => 202,
+            Opcode::Impdep1 => 254,
+            Opcode::Impdep2 => 255,
+        }
+    }
+
+    // bytes after the opcode up to the next 4 byte boundary of the code
+    fn padding(pc: u32) -> u32 {
+        (4 - (pc + 1) % 4) % 4
+    }
+
+    // encoded size at pc, only the switches depend on where they are
+    pub fn length(&self, pc: u32) -> u32 {
+        1 + match self {
+            Opcode::Aload(_) | Opcode::Astore(_) | Opcode::Bipush(_) | Opcode::Dload(_) | Opcode::Dstore(_)
+                | Opcode::Fload(_) | Opcode::Fstore(_) | Opcode::Iload(_) | Opcode::Istore(_) | Opcode::Lload(_)
+                | Opcode::Lstore(_) | Opcode::Ldc(_) | Opcode::Newarray(_) | Opcode::Ret(_) => 1,
+            Opcode::Multianewarray(..) => 3,
+            Opcode::Invokeinterface(..) | Opcode::Invokedynamic(_) | Opcode::GotoW(_) | Opcode::JsrW(_) => 4,
+            Opcode::Wide(..) => 3,
+            Opcode::WideIinc(..) => 5,
+            Opcode::Tableswitch(_, _, _, offsets) => Opcode::padding(pc) + 12 + 4 * offsets.len() as u32,
+            Opcode::Lookupswitch(_, pairs) => Opcode::padding(pc) + 8 + 8 * pairs.len() as u32,
+            o if o.has_u16_operand() => 2,
+            _ => 0,
+        }
+    }
+
+    fn has_u16_operand(&self) -> bool {
+        matches!(self, Opcode::Anewarray(_) | Opcode::Checkcast(_) | Opcode::Getfield(_) | Opcode::Getstatic(_)
+            | Opcode::Putfield(_) | Opcode::Putstatic(_) | Opcode::Instanceof(_) | Opcode::Invokespecial(_)
+            | Opcode::Invokestatic(_) | Opcode::Invokevirtual(_) | Opcode::LdcW(_) | Opcode::Ldc2W(_)
+            | Opcode::New(_) | Opcode::Sipush(_) | Opcode::Iinc(..)) || self.branch_offset().is_some()
+    }
+
+    // offset of goto, jsr and the conditional branches, switches have several
+    pub fn branch_offset(&self) -> Option<i32> {
+        match self {
+            Opcode::IfAcmpeq(o) | Opcode::IfAcmpne(o) | Opcode::IfIcmpeq(o) | Opcode::IfIcmpge(o)
+                | Opcode::IfIcmpgt(o) | Opcode::IfIcmple(o) | Opcode::IfIcmplt(o) | Opcode::IfIcmpne(o)
+                | Opcode::Ifeq(o) | Opcode::Ifge(o) | Opcode::Ifgt(o) | Opcode::Ifle(o) | Opcode::Iflt(o)
+                | Opcode::Ifne(o) | Opcode::Ifnonnull(o) | Opcode::Ifnull(o) | Opcode::Goto(o)
+                | Opcode::Jsr(o) => Some(*o as i32),
+            Opcode::GotoW(o) | Opcode::JsrW(o) => Some(*o),
+            _ => None,
+        }
+    }
+
+    // the same branch with another offset, goto and jsr can be given the _w form
+    pub fn with_branch_offset(&self, offset: i32) -> Opcode {
+        let o = offset as i16;
+        match self {
+            Opcode::IfAcmpeq(_) => Opcode::IfAcmpeq(o),
+            Opcode::IfAcmpne(_) => Opcode::IfAcmpne(o),
+            Opcode::IfIcmpeq(_) => Opcode::IfIcmpeq(o),
+            Opcode::IfIcmpge(_) => Opcode::IfIcmpge(o),
+            Opcode::IfIcmpgt(_) => Opcode::IfIcmpgt(o),
+            Opcode::IfIcmple(_) => Opcode::IfIcmple(o),
+            Opcode::IfIcmplt(_) => Opcode::IfIcmplt(o),
+            Opcode::IfIcmpne(_) => Opcode::IfIcmpne(o),
+            Opcode::Ifeq(_) => Opcode::Ifeq(o),
+            Opcode::Ifge(_) => Opcode::Ifge(o),
+            Opcode::Ifgt(_) => Opcode::Ifgt(o),
+            Opcode::Ifle(_) => Opcode::Ifle(o),
+            Opcode::Iflt(_) => Opcode::Iflt(o),
+            Opcode::Ifne(_) => Opcode::Ifne(o),
+            Opcode::Ifnonnull(_) => Opcode::Ifnonnull(o),
+            Opcode::Ifnull(_) => Opcode::Ifnull(o),
+            Opcode::Goto(_) => Opcode::Goto(o),
+            Opcode::Jsr(_) => Opcode::Jsr(o),
+            Opcode::GotoW(_) => Opcode::GotoW(offset),
+            Opcode::JsrW(_) => Opcode::JsrW(offset),
+            o => o.clone(),
+        }
+    }
+
+    // the instruction as it goes in the code array at pc
+    pub fn write(&self, pc: u32, bytes: &mut Vec<u8>) {
+        bytes.push(self.opcode());
+        match self {
+            Opcode::Aload(i) | Opcode::Astore(i) | Opcode::Dload(i) | Opcode::Dstore(i) | Opcode::Fload(i)
+                | Opcode::Fstore(i) | Opcode::Iload(i) | Opcode::Istore(i) | Opcode::Lload(i) | Opcode::Lstore(i)
+                | Opcode::Ldc(i) | Opcode::Newarray(i) | Opcode::Ret(i) => bytes.push(*i),
+            Opcode::Bipush(i) => bytes.push(*i as u8),
+            Opcode::Sipush(i) => bytes.extend_from_slice(&i.to_be_bytes()),
+            Opcode::Iinc(i, c) => bytes.extend_from_slice(&[*i, *c as u8]),
+            Opcode::Anewarray(i) | Opcode::Checkcast(i) | Opcode::Getfield(i) | Opcode::Getstatic(i)
+                | Opcode::Putfield(i) | Opcode::Putstatic(i) | Opcode::Instanceof(i) | Opcode::Invokespecial(i)
+                | Opcode::Invokestatic(i) | Opcode::Invokevirtual(i) | Opcode::LdcW(i) | Opcode::Ldc2W(i)
+                | Opcode::New(i) => bytes.extend_from_slice(&i.to_be_bytes()),
+            Opcode::Multianewarray(i, d) => {
+                bytes.extend_from_slice(&i.to_be_bytes());
+                bytes.push(*d);
+            }
+            Opcode::Invokeinterface(i, c) => {
+                bytes.extend_from_slice(&i.to_be_bytes());
+                bytes.extend_from_slice(&[*c, 0]);
+            }
+            Opcode::Invokedynamic(i) => {
+                bytes.extend_from_slice(&i.to_be_bytes());
+                bytes.extend_from_slice(&[0, 0]);
+            }
+            Opcode::GotoW(o) | Opcode::JsrW(o) => bytes.extend_from_slice(&o.to_be_bytes()),
+            Opcode::Wide(code, i) => {
+                bytes.push(*code);
+                bytes.extend_from_slice(&i.to_be_bytes());
+            }
+            Opcode::WideIinc(i, c) => {
+                bytes.push(132);
+                bytes.extend_from_slice(&i.to_be_bytes());
+                bytes.extend_from_slice(&c.to_be_bytes());
+            }
+            Opcode::Tableswitch(default, low, high, offsets) => {
+                bytes.extend(std::iter::repeat_n(0, Opcode::padding(pc) as usize));
+                for v in [*default, *low, *high].iter().chain(offsets.iter()) {
+                    bytes.extend_from_slice(&v.to_be_bytes());
+                }
+            }
+            Opcode::Lookupswitch(default, pairs) => {
+                bytes.extend(std::iter::repeat_n(0, Opcode::padding(pc) as usize));
+                bytes.extend_from_slice(&default.to_be_bytes());
+                bytes.extend_from_slice(&(pairs.len() as u32).to_be_bytes());
+                for (m, o) in pairs {
+                    bytes.extend_from_slice(&m.to_be_bytes());
+                    bytes.extend_from_slice(&o.to_be_bytes());
+                }
+            }
+            o => if let Some(offset) = o.branch_offset() {
+                bytes.extend_from_slice(&(offset as i16).to_be_bytes());
+            }
         }
     }
 }
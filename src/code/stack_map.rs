@@ -0,0 +1,182 @@
+// module stack_map
+// StackMapTable frames (JVMS 4.7.4)
+use std::fmt;
+use crate::attribute::DecodeError;
+use crate::{AttributeReader, ClassFile};
+use crate::class_file_writer::ClassFileWriter;
+use crate::constant_pool_builder::{ConstantPoolBuilder, ConstantPoolError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationType {
+    Top,
+    Integer,
+    Float,
+    Double,
+    Long,
+    Null,
+    UninitializedThis,
+    Object(String),      // internal name, or array descriptor
+    Uninitialized(u16),  // offset of the new instruction
+}
+
+impl VerificationType {
+    fn new(reader: &mut AttributeReader, class_file: &ClassFile) -> Result<Self, DecodeError> {
+        let tag = reader.try_u8()?;
+        Ok(match tag {
+            0 => VerificationType::Top,
+            1 => VerificationType::Integer,
+            2 => VerificationType::Float,
+            3 => VerificationType::Double,
+            4 => VerificationType::Long,
+            5 => VerificationType::Null,
+            6 => VerificationType::UninitializedThis,
+            7 => {
+                let index = reader.try_u16()?;
+                VerificationType::Object(class_file.constant_pool.find_class(index).ok_or(DecodeError::Constant(index))?)
+            }
+            8 => VerificationType::Uninitialized(reader.try_u16()?),
+            _ => return Err(DecodeError::Tag("verification type", tag)),
+        })
+    }
+
+    fn write(&self, writer: &mut ClassFileWriter, builder: &mut ConstantPoolBuilder) -> Result<(), ConstantPoolError> {
+        match self {
+            VerificationType::Top => writer.write_u8(0),
+            VerificationType::Integer => writer.write_u8(1),
+            VerificationType::Float => writer.write_u8(2),
+            VerificationType::Double => writer.write_u8(3),
+            VerificationType::Long => writer.write_u8(4),
+            VerificationType::Null => writer.write_u8(5),
+            VerificationType::UninitializedThis => writer.write_u8(6),
+            VerificationType::Object(name) => {
+                writer.write_u8(7);
+                writer.write_u16(builder.class(name)?);
+            }
+            VerificationType::Uninitialized(offset) => {
+                writer.write_u8(8);
+                writer.write_u16(*offset);
+            }
+        }
+        Ok(())
+    }
+
+    // long and double take two locals or stack slots
+    pub fn is_wide(&self) -> bool {
+        matches!(self, VerificationType::Long | VerificationType::Double)
+    }
+}
+
+impl fmt::Display for VerificationType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerificationType::Top => write!(f, "top"),
+            VerificationType::Integer => write!(f, "int"),
+            VerificationType::Float => write!(f, "float"),
+            VerificationType::Double => write!(f, "double"),
+            VerificationType::Long => write!(f, "long"),
+            VerificationType::Null => write!(f, "null"),
+            VerificationType::UninitializedThis => write!(f, "uninitialized this"),
+            VerificationType::Object(name) => write!(f, "{}", name),
+            VerificationType::Uninitialized(offset) => write!(f, "uninitialized {}", offset),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum StackMapFrame {
+    Same(u16),                                                    // 0-63, 251 same_frame_extended
+    SameLocals1StackItem(u16, VerificationType),                  // 64-127, 247 extended
+    Chop(u16, u8),                                                // 248-250, locals removed
+    Append(u16, Vec<VerificationType>),                           // 252-254
+    Full(u16, Vec<VerificationType>, Vec<VerificationType>),      // 255 locals, stack
+}
+
+impl StackMapFrame {
+    fn new(reader: &mut AttributeReader, class_file: &ClassFile) -> Result<Self, DecodeError> {
+        let frame_type = reader.try_u8()?;
+        Ok(match frame_type {
+            0..=63 => StackMapFrame::Same(frame_type as u16),
+            64..=127 => StackMapFrame::SameLocals1StackItem(frame_type as u16 - 64, VerificationType::new(reader, class_file)?),
+            247 => {
+                let offset_delta = reader.try_u16()?;
+                StackMapFrame::SameLocals1StackItem(offset_delta, VerificationType::new(reader, class_file)?)
+            }
+            248..=250 => StackMapFrame::Chop(reader.try_u16()?, 251 - frame_type),
+            251 => StackMapFrame::Same(reader.try_u16()?),
+            252..=254 => {
+                let offset_delta = reader.try_u16()?;
+                let locals = (0..frame_type - 251).map(|_| VerificationType::new(reader, class_file)).collect::<Result<_, _>>()?;
+                StackMapFrame::Append(offset_delta, locals)
+            }
+            255 => {
+                let offset_delta = reader.try_u16()?;
+                let count = reader.try_u16()?;
+                let locals = (0..count).map(|_| VerificationType::new(reader, class_file)).collect::<Result<_, _>>()?;
+                let count = reader.try_u16()?;
+                let stack = (0..count).map(|_| VerificationType::new(reader, class_file)).collect::<Result<_, _>>()?;
+                StackMapFrame::Full(offset_delta, locals, stack)
+            }
+            _ => return Err(DecodeError::Tag("stack map frame type", frame_type)),
+        })
+    }
+
+    pub fn offset_delta(&self) -> u16 {
+        match self {
+            StackMapFrame::Same(d) | StackMapFrame::SameLocals1StackItem(d, _) | StackMapFrame::Chop(d, _)
+                | StackMapFrame::Append(d, _) | StackMapFrame::Full(d, _, _) => *d,
+        }
+    }
+}
+
+// the shortest form of each frame type, class names interned through builder
+pub fn write_stack_map_table(frames: &[StackMapFrame], builder: &mut ConstantPoolBuilder) -> Result<Vec<u8>, ConstantPoolError> {
+    let mut writer = ClassFileWriter::new();
+    writer.write_u16(frames.len() as u16);
+    for frame in frames {
+        match frame {
+            StackMapFrame::Same(d) if *d < 64 => writer.write_u8(*d as u8),
+            StackMapFrame::Same(d) => {
+                writer.write_u8(251);
+                writer.write_u16(*d);
+            }
+            StackMapFrame::SameLocals1StackItem(d, t) => {
+                match *d < 64 {
+                    true => writer.write_u8(64 + *d as u8),
+                    false => {
+                        writer.write_u8(247);
+                        writer.write_u16(*d);
+                    }
+                }
+                t.write(&mut writer, builder)?;
+            }
+            StackMapFrame::Chop(d, k) => {
+                writer.write_u8(251 - k);
+                writer.write_u16(*d);
+            }
+            StackMapFrame::Append(d, locals) => {
+                writer.write_u8(251 + locals.len() as u8);
+                writer.write_u16(*d);
+                for t in locals {
+                    t.write(&mut writer, builder)?;
+                }
+            }
+            StackMapFrame::Full(d, locals, stack) => {
+                writer.write_u8(255);
+                writer.write_u16(*d);
+                for types in [locals, stack] {
+                    writer.write_u16(types.len() as u16);
+                    for t in types {
+                        t.write(&mut writer, builder)?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(writer.into_bytes())
+}
+
+pub fn get_stack_map_table(class_file: &ClassFile, info: &[u8]) -> Result<Vec<StackMapFrame>, DecodeError> {
+    let mut reader = AttributeReader::new(info);
+    let count = reader.try_u16()?;
+    (0..count).map(|_| StackMapFrame::new(&mut reader, class_file)).collect()
+}
@@ -0,0 +1,832 @@
+// module verifier
+// verification by type checking against the StackMapTable (JVMS 4.10.1), protected member access is not checked
+use std::collections::HashMap;
+use std::fmt;
+use crate::access_flags::ACC_STATIC;
+use crate::attribute::DecodeError;
+use crate::class_file_reader::{ConstantInfo, ConstantPool, Index, Tag};
+use crate::code::Code;
+use crate::code::frames::{self, ClassHierarchy, Frame, OBJECT};
+use crate::code::limits;
+use crate::code::opcode::Opcode;
+use crate::code::stack_map::{self, StackMapFrame, VerificationType};
+use crate::descriptor::{JavaType, MethodDescriptor};
+use crate::ClassFile;
+
+const THROWABLE: &str = "java/lang/Throwable";
+
+#[derive(Debug)]
+pub enum VerifyError {
+    BadType(String, VerificationType),      // expected, found
+    StackUnderflow,
+    StackOverflow(u16),                     // max_stack
+    BadLocal(u16),                          // index past max_locals
+    SplitValue,                             // only half of a long or double
+    NoFrame(u16),                           // pc that needs a stack map frame
+    BadFrame(String),
+    FrameLocal(u16, u16, VerificationType, VerificationType),     // frame pc, local, expected, found
+    FrameStack(u16, u16, VerificationType, VerificationType),     // frame pc, stack slot, expected, found
+    FrameDepth(u16, usize, usize),          // frame pc, expected and found stack depth
+    BadConstant(u16),
+    BadTarget(i64),
+    BadReturn(String),                      // the method's return descriptor
+    BadInit(String),                        // class of the <init> called
+    BadSpecial(String),                     // class of a method invokespecial cannot call from here
+    ThisUninitialized,
+    FallsOffEnd,
+    Subroutine,
+    BadDescriptor(String),
+    BadCode(DecodeError),                   // the Code attribute does not decode
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyError::BadType(expected, found) => write!(f, "expected {}, found {}", expected, found),
+            VerifyError::StackUnderflow => write!(f, "operand stack underflow"),
+            VerifyError::StackOverflow(max) => write!(f, "operand stack deeper than max_stack {}", max),
+            VerifyError::BadLocal(i) => write!(f, "local {} is past max_locals", i),
+            VerifyError::SplitValue => write!(f, "takes half of a long or double"),
+            VerifyError::NoFrame(pc) => write!(f, "no stack map frame at {}", pc),
+            VerifyError::BadFrame(s) => write!(f, "bad stack map frame: {}", s),
+            VerifyError::FrameLocal(pc, i, expected, found) =>
+                write!(f, "frame at {}: local {} expected {}, found {}", pc, i, expected, found),
+            VerifyError::FrameStack(pc, i, expected, found) =>
+                write!(f, "frame at {}: stack {} expected {}, found {}", pc, i, expected, found),
+            VerifyError::FrameDepth(pc, expected, found) =>
+                write!(f, "frame at {}: expected stack depth {}, found {}", pc, expected, found),
+            VerifyError::BadConstant(i) => write!(f, "bad constant pool index {}", i),
+            VerifyError::BadTarget(t) => write!(f, "branch target {} is not an instruction", t),
+            VerifyError::BadReturn(d) => write!(f, "return does not match the method's return type {}", d),
+            VerifyError::BadInit(class) => write!(f, "bad call of {}.<init>", class),
+            VerifyError::BadSpecial(class) => write!(f, "invokespecial of {} which this class does not extend", class),
+            VerifyError::ThisUninitialized => write!(f, "constructor returns before this is initialized"),
+            VerifyError::FallsOffEnd => write!(f, "execution falls off the end of the code"),
+            VerifyError::Subroutine => write!(f, "jsr and ret are not allowed with a StackMapTable"),
+            VerifyError::BadDescriptor(d) => write!(f, "bad method descriptor {}", d),
+            VerifyError::BadCode(e) => write!(f, "bad Code attribute: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+// where verification of a method stopped
+#[derive(Debug)]
+pub struct VerifyProblem {
+    pub method: String,         // name and descriptor
+    pub pc: u16,
+    pub instruction: String,
+    pub error: VerifyError,
+}
+
+impl fmt::Display for VerifyProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: pc {} {}: {}", self.method, self.pc, self.instruction, self.error)
+    }
+}
+
+impl std::error::Error for VerifyProblem {}
+
+// what an instruction takes from the stack or a local
+enum Expect {
+    Type(VerificationType),             // a primitive, or anything assignable to the class
+    Reference,                          // initialized or not
+    Initialized,
+    ArrayOf(&'static [&'static str]),   // one of these array descriptors
+    ReferenceArray,
+    Array,
+}
+
+impl fmt::Display for Expect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expect::Type(t) => write!(f, "{}", t),
+            Expect::Reference => write!(f, "reference"),
+            Expect::Initialized => write!(f, "initialized reference"),
+            Expect::ArrayOf(d) => write!(f, "{}", d.join(" or ")),
+            Expect::ReferenceArray => write!(f, "array of references"),
+            Expect::Array => write!(f, "array"),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Primitive {
+    Int,
+    Long,
+    Float,
+    Double,
+}
+
+impl Primitive {
+    fn by_kind(k: u8) -> Primitive {
+        [Primitive::Int, Primitive::Long, Primitive::Float, Primitive::Double][k as usize % 4]
+    }
+
+    fn verification_type(self) -> VerificationType {
+        match self {
+            Primitive::Int => VerificationType::Integer,
+            Primitive::Long => VerificationType::Long,
+            Primitive::Float => VerificationType::Float,
+            Primitive::Double => VerificationType::Double,
+        }
+    }
+}
+
+// operands, deepest first, and result of the instructions that only take and make primitives
+fn primitive_signature(code: u8) -> Option<(Vec<Primitive>, Option<Primitive>)> {
+    use Primitive::*;
+    let signature = match code {
+        0 | 167 | 200 => (vec![], None),
+        2..=8 | 16 | 17 => (vec![], Some(Int)),
+        9 | 10 => (vec![], Some(Long)),
+        11..=13 => (vec![], Some(Float)),
+        14 | 15 => (vec![], Some(Double)),
+        96..=115 => (vec![Primitive::by_kind(code - 96); 2], Some(Primitive::by_kind(code - 96))),
+        116..=119 => (vec![Primitive::by_kind(code - 116)], Some(Primitive::by_kind(code - 116))),
+        120 | 122 | 124 => (vec![Int, Int], Some(Int)),
+        121 | 123 | 125 => (vec![Long, Int], Some(Long)),
+        126 | 128 | 130 => (vec![Int, Int], Some(Int)),
+        127 | 129 | 131 => (vec![Long, Long], Some(Long)),
+        133 => (vec![Int], Some(Long)),
+        134 => (vec![Int], Some(Float)),
+        135 => (vec![Int], Some(Double)),
+        136 => (vec![Long], Some(Int)),
+        137 => (vec![Long], Some(Float)),
+        138 => (vec![Long], Some(Double)),
+        139 => (vec![Float], Some(Int)),
+        140 => (vec![Float], Some(Long)),
+        141 => (vec![Float], Some(Double)),
+        142 => (vec![Double], Some(Int)),
+        143 => (vec![Double], Some(Long)),
+        144 => (vec![Double], Some(Float)),
+        145..=147 => (vec![Int], Some(Int)),
+        148 => (vec![Long, Long], Some(Int)),
+        149 | 150 => (vec![Float, Float], Some(Int)),
+        151 | 152 => (vec![Double, Double], Some(Int)),
+        153..=158 | 170 | 171 => (vec![Int], None),
+        159..=164 => (vec![Int, Int], None),
+        _ => return None,
+    };
+    Some(signature)
+}
+
+// the class of an array's elements from the descriptor after its [, None for primitive elements
+fn element(component: &str) -> Option<String> {
+    match component.strip_prefix('L') {
+        Some(name) => Some(name.trim_end_matches(';').to_string()),
+        None if component.starts_with('[') => Some(component.to_string()),
+        None => None,
+    }
+}
+
+// classes the hierarchy does not know are taken on trust, interfaces accept any class as the JVM's verifier does
+fn is_subclass(a: &str, b: &str, hierarchy: &dyn ClassHierarchy) -> bool {
+    if a == b || b == OBJECT {
+        return true;
+    }
+    if !b.starts_with('[') && (hierarchy.get_super_class(b).is_none() || hierarchy.is_interface(b)) {
+        return !a.starts_with('[') || b == "java/lang/Cloneable" || b == "java/io/Serializable";
+    }
+    match (a.strip_prefix('['), b.strip_prefix('[')) {
+        (Some(ca), Some(cb)) => match (element(ca), element(cb)) {
+            (Some(ea), Some(eb)) => is_subclass(&ea, &eb, hierarchy),
+            _ => ca == cb,
+        },
+        (Some(_), None) => b == "java/lang/Cloneable" || b == "java/io/Serializable",
+        (None, Some(_)) => false,
+        (None, None) => {
+            let mut seen = vec![a.to_string()];
+            loop {
+                match hierarchy.get_super_class(seen.last().unwrap()) {
+                    None => return true,
+                    Some(None) => return false,
+                    Some(Some(s)) if s == b => return true,
+                    Some(Some(s)) if seen.contains(&s) => return false,
+                    Some(Some(s)) => seen.push(s),
+                }
+            }
+        }
+    }
+}
+
+fn is_assignable(from: &VerificationType, to: &VerificationType, hierarchy: &dyn ClassHierarchy) -> bool {
+    match (from, to) {
+        (a, b) if a == b => true,
+        (_, VerificationType::Top) => true,
+        (VerificationType::Null, VerificationType::Object(_)) => true,
+        (VerificationType::Object(a), VerificationType::Object(b)) => is_subclass(a, b, hierarchy),
+        _ => false,
+    }
+}
+
+fn is_reference(t: &VerificationType) -> bool {
+    matches!(t, VerificationType::Null | VerificationType::Object(_) | VerificationType::Uninitialized(_)
+        | VerificationType::UninitializedThis)
+}
+
+// locals by slot from the StackMapTable's entries
+fn slots(entries: &[VerificationType]) -> Vec<VerificationType> {
+    let mut frame = Frame { locals: Vec::new(), stack: Vec::new() };
+    for t in entries {
+        frame.push(t.clone());
+    }
+    frame.stack
+}
+
+struct Verifier<'a> {
+    cp: &'a ConstantPool,
+    hierarchy: &'a dyn ClassHierarchy,
+    this_class: String,
+    super_class: Option<String>,
+    is_init: bool,
+    return_type: JavaType,
+    max_stack: usize,
+    max_locals: usize,
+    new_classes: HashMap<u16, String>,      // pc of each new and the class it creates
+}
+
+impl Verifier<'_> {
+    fn accepts(&self, t: &VerificationType, expect: &Expect) -> bool {
+        match expect {
+            Expect::Type(e) => is_assignable(t, e, self.hierarchy),
+            Expect::Reference => is_reference(t),
+            Expect::Initialized => matches!(t, VerificationType::Null | VerificationType::Object(_)),
+            Expect::ArrayOf(descriptors) => match t {
+                VerificationType::Null => true,
+                VerificationType::Object(d) => descriptors.contains(&d.as_str()),
+                _ => false,
+            },
+            Expect::ReferenceArray => match t {
+                VerificationType::Null => true,
+                VerificationType::Object(d) => d.starts_with("[L") || d.starts_with("[["),
+                _ => false,
+            },
+            Expect::Array => match t {
+                VerificationType::Null => true,
+                VerificationType::Object(d) => d.starts_with('['),
+                _ => false,
+            },
+        }
+    }
+
+    // the value on top, both slots of a long or double
+    fn pop(&self, frame: &mut Frame, expect: Expect) -> Result<VerificationType, VerifyError> {
+        let t = match frame.stack.pop() {
+            None => return Err(VerifyError::StackUnderflow),
+            Some(VerificationType::Top) => match frame.stack.pop() {
+                Some(t) if t.is_wide() => t,
+                _ => return Err(VerifyError::SplitValue),
+            },
+            Some(t) => t,
+        };
+        match self.accepts(&t, &expect) {
+            true => Ok(t),
+            false => Err(VerifyError::BadType(expect.to_string(), t)),
+        }
+    }
+
+    fn push(&self, frame: &mut Frame, t: VerificationType) -> Result<(), VerifyError> {
+        frame.push(t);
+        match frame.stack.len() > self.max_stack {
+            true => Err(VerifyError::StackOverflow(self.max_stack as u16)),
+            false => Ok(()),
+        }
+    }
+
+    fn local(&self, index: u16, wide: bool) -> Result<(), VerifyError> {
+        match index as usize + if wide { 2 } else { 1 } > self.max_locals {
+            true => Err(VerifyError::BadLocal(index)),
+            false => Ok(()),
+        }
+    }
+
+    fn load(&self, frame: &mut Frame, index: u16, expect: Expect) -> Result<(), VerifyError> {
+        let wide = matches!(expect, Expect::Type(VerificationType::Long | VerificationType::Double));
+        self.local(index, wide)?;
+        let t = frame.load(index);
+        if !self.accepts(&t, &expect) {
+            return Err(VerifyError::BadType(expect.to_string(), t));
+        }
+        self.push(frame, t)
+    }
+
+    fn store(&self, frame: &mut Frame, index: u16, expect: Expect) -> Result<(), VerifyError> {
+        let t = self.pop(frame, expect)?;
+        self.local(index, t.is_wide())?;
+        frame.store(index, t);
+        Ok(())
+    }
+
+    fn increment(&self, frame: &Frame, index: u16) -> Result<(), VerifyError> {
+        self.local(index, false)?;
+        match frame.load(index) {
+            VerificationType::Integer => Ok(()),
+            t => Err(VerifyError::BadType(VerificationType::Integer.to_string(), t)),
+        }
+    }
+
+    fn class_name(&self, index: u16) -> Result<String, VerifyError> {
+        match self.cp.constant_info.get((index as usize).wrapping_sub(1)) {
+            Some(ConstantInfo(Tag::Class, Index::Single(_))) => self.cp.find_class(index).ok_or(VerifyError::BadConstant(index)),
+            _ => Err(VerifyError::BadConstant(index)),
+        }
+    }
+
+    fn ldc_type(&self, index: u16, wide: bool) -> Result<VerificationType, VerifyError> {
+        let t = match self.cp.constant_info.get((index as usize).wrapping_sub(1)) {
+            Some(ConstantInfo(Tag::Integer, _)) => VerificationType::Integer,
+            Some(ConstantInfo(Tag::Float, _)) => VerificationType::Float,
+            Some(ConstantInfo(Tag::Long, _)) => VerificationType::Long,
+            Some(ConstantInfo(Tag::Double, _)) => VerificationType::Double,
+            Some(ConstantInfo(Tag::String, _)) => VerificationType::Object("java/lang/String".to_string()),
+            Some(ConstantInfo(Tag::Class, _)) => VerificationType::Object("java/lang/Class".to_string()),
+            Some(ConstantInfo(Tag::MethodType, _)) => VerificationType::Object("java/lang/invoke/MethodType".to_string()),
+            Some(ConstantInfo(Tag::MethodHandle, _)) => VerificationType::Object("java/lang/invoke/MethodHandle".to_string()),
+            Some(ConstantInfo(Tag::Dynamic, _)) => self.cp.get_name_and_type(index)
+                .and_then(|(_, d)| frames::from_descriptor(&d))
+                .ok_or(VerifyError::BadConstant(index))?,
+            _ => return Err(VerifyError::BadConstant(index)),
+        };
+        match t.is_wide() == wide {
+            true => Ok(t),
+            false => Err(VerifyError::BadConstant(index)),
+        }
+    }
+
+    fn member(&self, index: u16) -> Result<(String, String, String), VerifyError> {
+        self.cp.get_member(index).ok_or(VerifyError::BadConstant(index))
+    }
+
+    fn field_type(&self, index: u16) -> Result<(String, VerificationType), VerifyError> {
+        let (class, _, descriptor) = self.member(index)?;
+        let t = frames::from_descriptor(&descriptor).ok_or(VerifyError::BadConstant(index))?;
+        Ok((class, t))
+    }
+
+    // pop and replace the object an <init> is called on
+    fn initialize(&self, frame: &mut Frame, class: &str) -> Result<(), VerifyError> {
+        let receiver = self.pop(frame, Expect::Reference)?;
+        let initialized = match &receiver {
+            VerificationType::UninitializedThis
+                if class == self.this_class || Some(class) == self.super_class.as_deref() => &self.this_class,
+            VerificationType::Uninitialized(new) if self.new_classes.get(new).map(|c| c.as_str()) == Some(class) => class,
+            VerificationType::UninitializedThis | VerificationType::Uninitialized(_) =>
+                return Err(VerifyError::BadInit(class.to_string())),
+            t => return Err(VerifyError::BadType("uninitialized object".to_string(), t.clone())),
+        };
+        let initialized = VerificationType::Object(initialized.to_string());
+        for t in frame.locals.iter_mut().chain(frame.stack.iter_mut()) {
+            if *t == receiver {
+                *t = initialized.clone();
+            }
+        }
+        Ok(())
+    }
+
+    fn invoke(&self, frame: &mut Frame, o: &Opcode, index: u16) -> Result<(), VerifyError> {
+        let (class, name, descriptor) = match o {
+            Opcode::Invokedynamic(_) => {
+                let (name, descriptor) = self.cp.get_name_and_type(index).ok_or(VerifyError::BadConstant(index))?;
+                (String::new(), name, descriptor)
+            }
+            _ => self.member(index)?,
+        };
+        let method = MethodDescriptor::from_descriptor(&descriptor).ok_or(VerifyError::BadConstant(index))?;
+        if name.starts_with('<') && !(name == "<init>" && matches!(o, Opcode::Invokespecial(_))) {
+            return Err(VerifyError::BadConstant(index));
+        }
+        for p in method.parameters.iter().rev() {
+            self.pop(frame, Expect::Type(frames::from_java_type(p)))?;
+        }
+        match o {
+            Opcode::Invokespecial(_) if name == "<init>" => self.initialize(frame, &class)?,
+            Opcode::Invokespecial(_) => {
+                if !is_subclass(&self.this_class, &class, self.hierarchy) {
+                    return Err(VerifyError::BadSpecial(class));
+                }
+                self.pop(frame, Expect::Type(VerificationType::Object(self.this_class.clone())))?;
+            }
+            Opcode::Invokevirtual(_) | Opcode::Invokeinterface(..) => {
+                self.pop(frame, Expect::Type(VerificationType::Object(class)))?;
+            }
+            _ => (),
+        }
+        if method.return_type != JavaType::Void {
+            self.push(frame, frames::from_java_type(&method.return_type))?;
+        }
+        Ok(())
+    }
+
+    fn returns(&self, frame: &mut Frame, code: u8) -> Result<(), VerifyError> {
+        let expected = match &self.return_type {
+            JavaType::Void => None,
+            t => Some(frames::from_java_type(t)),
+        };
+        let matches = matches!((code, &expected),
+            (172, Some(VerificationType::Integer)) | (173, Some(VerificationType::Long))
+                | (174, Some(VerificationType::Float)) | (175, Some(VerificationType::Double))
+                | (176, Some(VerificationType::Object(_))) | (177, None));
+        if !matches {
+            return Err(VerifyError::BadReturn(self.return_type.to_descriptor()));
+        }
+        if let Some(t) = expected {
+            self.pop(frame, Expect::Type(t))?;
+        }
+        if self.is_init && frame.locals.contains(&VerificationType::UninitializedThis) {
+            return Err(VerifyError::ThisUninitialized);
+        }
+        Ok(())
+    }
+
+    // the frame after an instruction, the stack is checked for every value taken
+    fn execute(&self, pc: u16, o: &Opcode, frame: &Frame) -> Result<Frame, VerifyError> {
+        use VerificationType as V;
+        let mut out = frame.clone();
+        let f = &mut out;
+        let code = match o {
+            Opcode::Wide(code, _) => *code,
+            o => o.opcode(),
+        };
+        if let Some((operands, result)) = primitive_signature(code) {
+            for p in operands.iter().rev() {
+                self.pop(f, Expect::Type(p.verification_type()))?;
+            }
+            if let Some(r) = result {
+                self.push(f, r.verification_type())?;
+            }
+            return Ok(out);
+        }
+        match o {
+            Opcode::Iload(i) => self.load(f, *i as u16, Expect::Type(V::Integer))?,
+            Opcode::Lload(i) => self.load(f, *i as u16, Expect::Type(V::Long))?,
+            Opcode::Fload(i) => self.load(f, *i as u16, Expect::Type(V::Float))?,
+            Opcode::Dload(i) => self.load(f, *i as u16, Expect::Type(V::Double))?,
+            Opcode::Aload(i) => self.load(f, *i as u16, Expect::Reference)?,
+            Opcode::Istore(i) => self.store(f, *i as u16, Expect::Type(V::Integer))?,
+            Opcode::Lstore(i) => self.store(f, *i as u16, Expect::Type(V::Long))?,
+            Opcode::Fstore(i) => self.store(f, *i as u16, Expect::Type(V::Float))?,
+            Opcode::Dstore(i) => self.store(f, *i as u16, Expect::Type(V::Double))?,
+            Opcode::Astore(i) => self.store(f, *i as u16, Expect::Reference)?,
+            Opcode::Wide(code, i) => match code {
+                21 => self.load(f, *i, Expect::Type(V::Integer))?,
+                22 => self.load(f, *i, Expect::Type(V::Long))?,
+                23 => self.load(f, *i, Expect::Type(V::Float))?,
+                24 => self.load(f, *i, Expect::Type(V::Double))?,
+                25 => self.load(f, *i, Expect::Reference)?,
+                54 => self.store(f, *i, Expect::Type(V::Integer))?,
+                55 => self.store(f, *i, Expect::Type(V::Long))?,
+                56 => self.store(f, *i, Expect::Type(V::Float))?,
+                57 => self.store(f, *i, Expect::Type(V::Double))?,
+                58 => self.store(f, *i, Expect::Reference)?,
+                _ => return Err(VerifyError::Subroutine),
+            },
+            Opcode::Iinc(i, _) => self.increment(f, *i as u16)?,
+            Opcode::WideIinc(i, _) => self.increment(f, *i)?,
+            Opcode::Ldc(i) => self.push(f, self.ldc_type(*i as u16, false)?)?,
+            Opcode::LdcW(i) => self.push(f, self.ldc_type(*i, false)?)?,
+            Opcode::Ldc2W(i) => self.push(f, self.ldc_type(*i, true)?)?,
+            Opcode::Getstatic(i) => {
+                let (_, t) = self.field_type(*i)?;
+                self.push(f, t)?;
+            }
+            Opcode::Putstatic(i) => {
+                let (_, t) = self.field_type(*i)?;
+                self.pop(f, Expect::Type(t))?;
+            }
+            Opcode::Getfield(i) => {
+                let (class, t) = self.field_type(*i)?;
+                self.pop(f, Expect::Type(V::Object(class)))?;
+                self.push(f, t)?;
+            }
+            Opcode::Putfield(i) => {
+                let (class, t) = self.field_type(*i)?;
+                self.pop(f, Expect::Type(t))?;
+                // a constructor may set its own fields before calling super
+                let own = self.is_init && class == self.this_class && f.stack.last() == Some(&V::UninitializedThis);
+                match own {
+                    true => f.stack.truncate(f.stack.len() - 1),
+                    false => {
+                        self.pop(f, Expect::Type(V::Object(class)))?;
+                    }
+                }
+            }
+            Opcode::Invokevirtual(i) | Opcode::Invokespecial(i) | Opcode::Invokestatic(i)
+                | Opcode::Invokeinterface(i, _) | Opcode::Invokedynamic(i) => self.invoke(f, o, *i)?,
+            Opcode::New(i) => {
+                let class = self.class_name(*i)?;
+                if class.starts_with('[') {
+                    return Err(VerifyError::BadConstant(*i));
+                }
+                self.push(f, V::Uninitialized(pc))?;
+            }
+            Opcode::Newarray(t) => {
+                self.pop(f, Expect::Type(V::Integer))?;
+                let element = match t {
+                    4 => "Z", 5 => "C", 6 => "F", 7 => "D", 8 => "B", 9 => "S", 10 => "I", _ => "J",
+                };
+                self.push(f, V::Object(format!("[{}", element)))?;
+            }
+            Opcode::Anewarray(i) => {
+                self.pop(f, Expect::Type(V::Integer))?;
+                self.push(f, V::Object(frames::array_of(&self.class_name(*i)?)))?;
+            }
+            Opcode::Multianewarray(i, dimensions) => {
+                let class = self.class_name(*i)?;
+                if *dimensions == 0 || class.chars().take_while(|c| *c == '[').count() < *dimensions as usize {
+                    return Err(VerifyError::BadConstant(*i));
+                }
+                for _ in 0..*dimensions {
+                    self.pop(f, Expect::Type(V::Integer))?;
+                }
+                self.push(f, V::Object(class))?;
+            }
+            Opcode::Checkcast(i) => {
+                self.pop(f, Expect::Initialized)?;
+                self.push(f, V::Object(self.class_name(*i)?))?;
+            }
+            Opcode::Instanceof(i) => {
+                self.class_name(*i)?;
+                self.pop(f, Expect::Initialized)?;
+                self.push(f, V::Integer)?;
+            }
+            Opcode::Jsr(_) | Opcode::JsrW(_) | Opcode::Ret(_) => return Err(VerifyError::Subroutine),
+            _ => match code {
+                1 => self.push(f, V::Null)?,
+                26..=29 => self.load(f, code as u16 - 26, Expect::Type(V::Integer))?,
+                30..=33 => self.load(f, code as u16 - 30, Expect::Type(V::Long))?,
+                34..=37 => self.load(f, code as u16 - 34, Expect::Type(V::Float))?,
+                38..=41 => self.load(f, code as u16 - 38, Expect::Type(V::Double))?,
+                42..=45 => self.load(f, code as u16 - 42, Expect::Reference)?,
+                59..=62 => self.store(f, code as u16 - 59, Expect::Type(V::Integer))?,
+                63..=66 => self.store(f, code as u16 - 63, Expect::Type(V::Long))?,
+                67..=70 => self.store(f, code as u16 - 67, Expect::Type(V::Float))?,
+                71..=74 => self.store(f, code as u16 - 71, Expect::Type(V::Double))?,
+                75..=78 => self.store(f, code as u16 - 75, Expect::Reference)?,
+                46..=53 => {
+                    self.pop(f, Expect::Type(V::Integer))?;
+                    let (array, element) = match code {
+                        46 => (Expect::ArrayOf(&["[I"]), Some(V::Integer)),
+                        47 => (Expect::ArrayOf(&["[J"]), Some(V::Long)),
+                        48 => (Expect::ArrayOf(&["[F"]), Some(V::Float)),
+                        49 => (Expect::ArrayOf(&["[D"]), Some(V::Double)),
+                        50 => (Expect::ReferenceArray, None),
+                        51 => (Expect::ArrayOf(&["[B", "[Z"]), Some(V::Integer)),
+                        52 => (Expect::ArrayOf(&["[C"]), Some(V::Integer)),
+                        _ => (Expect::ArrayOf(&["[S"]), Some(V::Integer)),
+                    };
+                    let array = self.pop(f, array)?;
+                    let element = match (element, &array) {
+                        (Some(t), _) => t,
+                        (None, V::Object(d)) => frames::from_descriptor(&d[1..]).unwrap_or(V::Top),
+                        (None, _) => V::Null,
+                    };
+                    self.push(f, element)?;
+                }
+                79..=86 => {
+                    let (value, array) = match code {
+                        79 => (Expect::Type(V::Integer), Expect::ArrayOf(&["[I"])),
+                        80 => (Expect::Type(V::Long), Expect::ArrayOf(&["[J"])),
+                        81 => (Expect::Type(V::Float), Expect::ArrayOf(&["[F"])),
+                        82 => (Expect::Type(V::Double), Expect::ArrayOf(&["[D"])),
+                        83 => (Expect::Initialized, Expect::ReferenceArray),
+                        84 => (Expect::Type(V::Integer), Expect::ArrayOf(&["[B", "[Z"])),
+                        85 => (Expect::Type(V::Integer), Expect::ArrayOf(&["[C"])),
+                        _ => (Expect::Type(V::Integer), Expect::ArrayOf(&["[S"])),
+                    };
+                    self.pop(f, value)?;
+                    self.pop(f, Expect::Type(V::Integer))?;
+                    self.pop(f, array)?;
+                }
+                87..=95 => {
+                    // slot counts from the top between values that must not be split, and what goes back
+                    let (boundaries, order): (&[usize], &[usize]) = match code {
+                        87 => (&[1], &[]),
+                        88 => (&[2], &[]),
+                        89 => (&[1], &[0, 0]),
+                        90 => (&[1, 2], &[1, 0, 1]),
+                        91 => (&[1, 3], &[2, 0, 1, 2]),
+                        92 => (&[2], &[0, 1, 0, 1]),
+                        93 => (&[2, 3], &[1, 2, 0, 1, 2]),
+                        94 => (&[2, 4], &[2, 3, 0, 1, 2, 3]),
+                        _ => (&[1, 2], &[1, 0]),
+                    };
+                    let taken = *boundaries.last().unwrap();
+                    let len = f.stack.len();
+                    if len < taken {
+                        return Err(VerifyError::StackUnderflow);
+                    }
+                    if boundaries.iter().any(|b| len - b > 0 && f.stack[len - b - 1].is_wide()) {
+                        return Err(VerifyError::SplitValue);
+                    }
+                    let popped = f.stack.split_off(len - taken);
+                    f.stack.extend(order.iter().map(|i| popped[*i].clone()));
+                    if f.stack.len() > self.max_stack {
+                        return Err(VerifyError::StackOverflow(self.max_stack as u16));
+                    }
+                }
+                165 | 166 => {
+                    self.pop(f, Expect::Reference)?;
+                    self.pop(f, Expect::Reference)?;
+                }
+                198 | 199 => {
+                    self.pop(f, Expect::Reference)?;
+                }
+                172..=177 => self.returns(f, code)?,
+                190 => {
+                    self.pop(f, Expect::Array)?;
+                    self.push(f, V::Integer)?;
+                }
+                191 => {
+                    self.pop(f, Expect::Type(V::Object(THROWABLE.to_string())))?;
+                }
+                194 | 195 => {
+                    self.pop(f, Expect::Initialized)?;
+                }
+                _ => return Err(VerifyError::BadConstant(code as u16)),
+            },
+        }
+        Ok(out)
+    }
+
+    // frame assignable to the one recorded at pc, locals past the end are Top
+    fn check_frame(&self, frame: &Frame, target: &Frame, pc: u16) -> Result<(), VerifyError> {
+        if frame.stack.len() != target.stack.len() {
+            return Err(VerifyError::FrameDepth(pc, target.stack.len(), frame.stack.len()));
+        }
+        for i in 0..frame.locals.len().max(target.locals.len()) {
+            let from = frame.load(i as u16);
+            let to = target.load(i as u16);
+            if !is_assignable(&from, &to, self.hierarchy) {
+                return Err(VerifyError::FrameLocal(pc, i as u16, to, from));
+            }
+        }
+        for (i, (from, to)) in frame.stack.iter().zip(target.stack.iter()).enumerate() {
+            if !is_assignable(from, to, self.hierarchy) {
+                return Err(VerifyError::FrameStack(pc, i as u16, to.clone(), from.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+// check one method, the first problem found stops it
+pub fn verify_method(class_file: &ClassFile, method: usize, hierarchy: &dyn ClassHierarchy) -> Result<(), VerifyProblem> {
+    let cp = &class_file.constant_pool;
+    let m = &class_file.methods[method];
+    let name = cp.get_item(&m.name_index);
+    let descriptor = cp.get_item(&m.descriptor_index);
+    let problem = |pc: u16, instruction: String, error: VerifyError| VerifyProblem {
+        method: format!("{}{}", name, descriptor), pc, instruction, error,
+    };
+    let info = match m.attributes.iter().find(|a| cp.get_item(&a.attribute_name_index) == "Code") {
+        Some(a) => &a.info,
+        None => return Ok(()),
+    };
+    let code = Code::try_new(info).map_err(|e| problem(0, String::new(), VerifyError::BadCode(e)))?;
+    let this_class = class_file.get_class_name();
+    let initial = frames::initial_frame(&this_class, &name, &descriptor, m.access_flags & ACC_STATIC != 0)
+        .map_err(|_| problem(0, String::new(), VerifyError::BadDescriptor(descriptor.clone())))?;
+    let method_descriptor = MethodDescriptor::from_descriptor(&descriptor).unwrap();
+    let instructions: Vec<(u16, &Opcode)> = code.get_instructions().collect();
+    let text = |i: usize| instructions.get(i).map(|(_, o)| o.to_string()).unwrap_or_default();
+    let position: HashMap<u16, usize> = instructions.iter().enumerate().map(|(i, (pc, _))| (*pc, i)).collect();
+    let mut new_classes = HashMap::<u16, String>::new();
+    for (pc, o) in &instructions {
+        if let Opcode::New(i) = o {
+            // one that is not a Class entry is reported at the new
+            new_classes.extend(cp.find_class(*i).map(|c| (*pc, c)));
+        }
+    }
+    let verifier = Verifier {
+        cp,
+        hierarchy,
+        this_class: this_class.clone(),
+        super_class: class_file.get_super_class_name(),
+        is_init: name == "<init>",
+        return_type: method_descriptor.return_type,
+        max_stack: code.get_max_stack() as usize,
+        max_locals: code.get_max_locals() as usize,
+        new_classes,
+    };
+    if initial.locals.len() > verifier.max_locals {
+        return Err(problem(0, text(0), VerifyError::BadLocal(initial.locals.len() as u16 - 1)));
+    }
+
+    // the StackMapTable by instruction, locals and stack by slot
+    let mut recorded = HashMap::<usize, Frame>::new();
+    let table = code.get_attributes().iter()
+        .find(|a| cp.is_named(&a.attribute_name_index, "StackMapTable"))
+        .map(|a| stack_map::get_stack_map_table(class_file, &a.info))
+        .transpose()
+        .map_err(|e| problem(0, String::new(), VerifyError::BadFrame(e.to_string())))?
+        .unwrap_or_default();
+    let mut locals = Frame::entries(&initial.locals, true);
+    let mut offset: Option<u16> = None;
+    for frame in table {
+        let pc = match offset {
+            None => Some(frame.offset_delta()),
+            Some(o) => o.checked_add(frame.offset_delta()).and_then(|p| p.checked_add(1)),
+        };
+        let bad = |s: String| problem(pc.unwrap_or(u16::MAX), String::new(), VerifyError::BadFrame(s));
+        let pc = pc.ok_or_else(|| bad("offset past the end of the code".to_string()))?;
+        let stack = match frame {
+            StackMapFrame::Same(_) => Vec::new(),
+            StackMapFrame::SameLocals1StackItem(_, t) => vec![t],
+            StackMapFrame::Chop(_, k) => {
+                if locals.len() < k as usize {
+                    return Err(bad(format!("chops {} locals from {}", k, locals.len())));
+                }
+                locals.truncate(locals.len() - k as usize);
+                Vec::new()
+            }
+            StackMapFrame::Append(_, more) => {
+                locals.extend(more);
+                Vec::new()
+            }
+            StackMapFrame::Full(_, l, s) => {
+                locals = l;
+                s
+            }
+        };
+        let i = *position.get(&pc).ok_or_else(|| bad(format!("offset {} is not an instruction", pc)))?;
+        let frame = Frame { locals: slots(&locals), stack: slots(&stack) };
+        if frame.locals.len() > verifier.max_locals {
+            return Err(bad(format!("{} locals, max_locals is {}", frame.locals.len(), verifier.max_locals)));
+        }
+        if frame.stack.len() > verifier.max_stack {
+            return Err(bad(format!("stack depth {}, max_stack is {}", frame.stack.len(), verifier.max_stack)));
+        }
+        for t in frame.locals.iter().chain(frame.stack.iter()) {
+            if let VerificationType::Uninitialized(new) = t {
+                if !verifier.new_classes.contains_key(new) {
+                    return Err(bad(format!("uninitialized {} is not a new instruction", new)));
+                }
+            }
+        }
+        recorded.insert(i, frame);
+        offset = Some(pc);
+    }
+
+    let mut handlers = Vec::<(u16, u16, usize, VerificationType)>::new();
+    for e in code.get_exception_table() {
+        let at = |error| problem(e.handler_pc, text(position.get(&e.handler_pc).copied().unwrap_or(usize::MAX)), error);
+        let handler = *position.get(&e.handler_pc).ok_or_else(|| at(VerifyError::BadTarget(e.handler_pc as i64)))?;
+        let catch_type = match e.catch_type {
+            0 => VerificationType::Object(THROWABLE.to_string()),
+            i => VerificationType::Object(verifier.class_name(i).map_err(at)?),
+        };
+        if !is_assignable(&catch_type, &VerificationType::Object(THROWABLE.to_string()), hierarchy) {
+            return Err(at(VerifyError::BadType(THROWABLE.to_string(), catch_type)));
+        }
+        handlers.push((e.start_pc, e.end_pc, handler, catch_type));
+    }
+
+    let mut current = Some(initial);
+    for (i, (pc, o)) in instructions.iter().enumerate() {
+        let at = |error| problem(*pc, o.to_string(), error);
+        let frame = match (current.take(), recorded.get(&i)) {
+            (Some(frame), Some(target)) => {
+                verifier.check_frame(&frame, target, *pc).map_err(at)?;
+                target.clone()
+            }
+            (None, Some(target)) => target.clone(),
+            (Some(frame), None) => frame,
+            (None, None) => return Err(at(VerifyError::NoFrame(*pc))),
+        };
+        // a store is checked against handlers with the locals it started with, everything else with the locals after
+        let out = verifier.execute(*pc, o, &frame).map_err(at)?;
+        let stores = matches!(o.opcode(), 54..=78) || matches!(o, Opcode::Wide(54..=58, _));
+        for (start, end, handler, catch_type) in &handlers {
+            if *start <= *pc && *pc < *end {
+                let handler_pc = instructions[*handler].0;
+                let target = recorded.get(handler).ok_or(VerifyError::NoFrame(handler_pc)).map_err(at)?;
+                let locals = if stores { frame.locals.clone() } else { out.locals.clone() };
+                let incoming = Frame { locals, stack: vec![catch_type.clone()] };
+                verifier.check_frame(&incoming, target, handler_pc).map_err(at)?;
+            }
+        }
+        let (jumps, falls_through) = limits::targets(*pc, o);
+        for t in jumps {
+            let j = u16::try_from(t).ok().and_then(|t| position.get(&t)).ok_or(VerifyError::BadTarget(t)).map_err(at)?;
+            let target = recorded.get(j).ok_or(VerifyError::NoFrame(t as u16)).map_err(at)?;
+            verifier.check_frame(&out, target, t as u16).map_err(at)?;
+        }
+        if falls_through {
+            if i + 1 == instructions.len() {
+                return Err(at(VerifyError::FallsOffEnd));
+            }
+            current = Some(out);
+        }
+    }
+    Ok(())
+}
+
+// every method of a class, type checking needs major version 50 or later so older classes are passed over
+pub fn verify(class_file: &ClassFile, hierarchy: &dyn ClassHierarchy) -> Vec<VerifyProblem> {
+    if class_file.version.1 < 50 {
+        return Vec::new();
+    }
+    (0..class_file.methods.len()).filter_map(|m| verify_method(class_file, m, hierarchy).err()).collect()
+}
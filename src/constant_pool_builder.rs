@@ -0,0 +1,274 @@
+// module constant_pool_builder
+// interns constant pool entries, an entry already in the pool keeps its index
+use std::collections::HashMap;
+use std::fmt;
+use crate::class_file_reader::{encode_modified_utf8, ConstantInfo, ConstantPool, Index, LiteralInfo, Tag};
+
+// constant_pool_count is a u16 and counts from 1
+const MAX_ENTRIES: usize = 65534;
+
+#[derive(Debug)]
+pub enum ConstantPoolError {
+    Full,
+    Utf8Length(usize),      // modified UTF-8 bytes of a string too long for a Utf8 entry
+}
+
+impl fmt::Display for ConstantPoolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConstantPoolError::Full => write!(f, "constant pool has more than {} entries", MAX_ENTRIES),
+            ConstantPoolError::Utf8Length(len) => write!(f, "a Utf8 constant of {} bytes, more than 65535", len),
+        }
+    }
+}
+
+impl std::error::Error for ConstantPoolError {}
+
+// what makes two entries the same, floats by their bits so NaN and -0.0 are kept apart
+#[derive(PartialEq, Eq, Hash)]
+enum Key {
+    Utf8(Vec<u8>),
+    Integer(u32),
+    Float(u32),
+    Long(u64),
+    Double(u64),
+    Class(u16),
+    String(u16),
+    FieldRef(u16, u16),
+    MethodRef(u16, u16),
+    InterfaceMethodRef(u16, u16),
+    NameAndType(u16, u16),
+    MethodHandle(u8, u16),
+    MethodType(u16),
+    Dynamic(u16, u16),
+    InvokeDynamic(u16, u16),
+    Module(u16),
+    Package(u16),
+}
+
+pub struct ConstantPoolBuilder {
+    pool: ConstantPool,
+    entries: HashMap<Key, u16>,
+}
+
+impl Default for ConstantPoolBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConstantPoolBuilder {
+    pub fn new() -> Self {
+        Self {
+            pool: ConstantPool::empty(),
+            entries: HashMap::new(),
+        }
+    }
+
+    // start from a class's pool, every existing entry stays where it is
+    pub fn from_pool(pool: &ConstantPool) -> Self {
+        let mut builder = Self {
+            pool: pool.clone(),
+            entries: HashMap::new(),
+        };
+        for i in 0..pool.constant_info.len() {
+            let index = i as u16 + 1;
+            if let Some(key) = builder.key(index) {
+                builder.entries.entry(key).or_insert(index);
+            }
+        }
+        builder
+    }
+
+    fn key(&self, index: u16) -> Option<Key> {
+        let ConstantInfo(tag, info) = self.pool.get_constant(index);
+        let key = match (tag, info) {
+            (Tag::Utf8, _) => Key::Utf8(self.pool.get_utf8_bytes(index)),
+            (Tag::Integer, _) | (Tag::Float, _) | (Tag::Long, _) | (Tag::Double, _) => match self.pool.get_literal(index) {
+                LiteralInfo::Integer(v) => Key::Integer(*v),
+                LiteralInfo::Float(v) => Key::Float(v.to_bits()),
+                LiteralInfo::Long(v) => Key::Long(*v),
+                LiteralInfo::Double(v) => Key::Double(v.to_bits()),
+                LiteralInfo::String(_) => return None,
+            },
+            (Tag::Class, Index::Single(i)) => Key::Class(*i),
+            (Tag::String, Index::Single(i)) => Key::String(*i),
+            (Tag::FieldRef, Index::Ref(i1, i2)) => Key::FieldRef(*i1, *i2),
+            (Tag::MethodRef, Index::Ref(i1, i2)) => Key::MethodRef(*i1, *i2),
+            (Tag::InterfaceMethodRef, Index::Ref(i1, i2)) => Key::InterfaceMethodRef(*i1, *i2),
+            (Tag::NameAndType, Index::Pair(i1, i2)) => Key::NameAndType(*i1, *i2),
+            (Tag::MethodHandle, Index::MethodHandle(kind, i)) => Key::MethodHandle(*kind, *i),
+            (Tag::MethodType, Index::Single(i)) => Key::MethodType(*i),
+            (Tag::Dynamic, Index::Dynamic(i1, i2)) => Key::Dynamic(*i1, *i2),
+            (Tag::InvokeDynamic, Index::Dynamic(i1, i2)) => Key::InvokeDynamic(*i1, *i2),
+            (Tag::Module, Index::Single(i)) => Key::Module(*i),
+            (Tag::Package, Index::Single(i)) => Key::Package(*i),
+            _ => return None,
+        };
+        Some(key)
+    }
+
+    // the existing index, or a new entry at the end of the pool
+    fn intern(&mut self, key: Key, tag: Tag, index: Index, literal: Option<LiteralInfo>) -> Result<u16, ConstantPoolError> {
+        if let Some(i) = self.entries.get(&key) {
+            return Ok(*i);
+        }
+        let wide = tag == Tag::Long || tag == Tag::Double;
+        let slots = if wide { 2 } else { 1 };
+        if self.pool.constant_info.len() + slots > MAX_ENTRIES {
+            return Err(ConstantPoolError::Full);
+        }
+        let new_index = self.pool.constant_info.len() as u16 + 1;
+        let index = match index {
+            Index::Single(0) => Index::Single(new_index),
+            index => index,
+        };
+        self.pool.constant_info.push(ConstantInfo(tag, index));
+        if wide {
+            self.pool.constant_info.push(ConstantInfo(Tag::Unusable, Index::Single(0)));
+        }
+        if let Some(literal) = literal {
+            self.pool.literal_pool.insert(new_index, literal);
+        }
+        self.entries.insert(key, new_index);
+        Ok(new_index)
+    }
+
+    // the entries an entry refers to are added with it, or none of them when one cannot be
+    fn all_or_nothing<F: FnOnce(&mut Self) -> Result<u16, ConstantPoolError>>(&mut self, add: F) -> Result<u16, ConstantPoolError> {
+        let len = self.pool.constant_info.len();
+        let result = add(self);
+        if result.is_err() {
+            self.pool.constant_info.truncate(len);
+            self.pool.literal_pool.retain(|i, _| *i as usize <= len);
+            self.entries.retain(|_, i| *i as usize <= len);
+        }
+        result
+    }
+
+    pub fn utf8(&mut self, s: &str) -> Result<u16, ConstantPoolError> {
+        let bytes = encode_modified_utf8(s);
+        if bytes.len() > u16::MAX as usize {
+            return Err(ConstantPoolError::Utf8Length(bytes.len()));
+        }
+        self.intern(Key::Utf8(bytes), Tag::Utf8, Index::Single(0), Some(LiteralInfo::String(s.to_string())))
+    }
+
+    pub fn integer(&mut self, v: i32) -> Result<u16, ConstantPoolError> {
+        self.intern(Key::Integer(v as u32), Tag::Integer, Index::Single(0), Some(LiteralInfo::Integer(v as u32)))
+    }
+
+    pub fn float(&mut self, v: f32) -> Result<u16, ConstantPoolError> {
+        self.intern(Key::Float(v.to_bits()), Tag::Float, Index::Single(0), Some(LiteralInfo::Float(v)))
+    }
+
+    pub fn long(&mut self, v: i64) -> Result<u16, ConstantPoolError> {
+        self.intern(Key::Long(v as u64), Tag::Long, Index::Single(0), Some(LiteralInfo::Long(v as u64)))
+    }
+
+    pub fn double(&mut self, v: f64) -> Result<u16, ConstantPoolError> {
+        self.intern(Key::Double(v.to_bits()), Tag::Double, Index::Single(0), Some(LiteralInfo::Double(v)))
+    }
+
+    // internal name, or an array descriptor
+    pub fn class(&mut self, name: &str) -> Result<u16, ConstantPoolError> {
+        self.all_or_nothing(|b| {
+            let i = b.utf8(name)?;
+            b.intern(Key::Class(i), Tag::Class, Index::Single(i), None)
+        })
+    }
+
+    pub fn string(&mut self, s: &str) -> Result<u16, ConstantPoolError> {
+        self.all_or_nothing(|b| {
+            let i = b.utf8(s)?;
+            b.intern(Key::String(i), Tag::String, Index::Single(i), None)
+        })
+    }
+
+    pub fn name_and_type(&mut self, name: &str, descriptor: &str) -> Result<u16, ConstantPoolError> {
+        self.all_or_nothing(|b| {
+            let n = b.utf8(name)?;
+            let d = b.utf8(descriptor)?;
+            b.intern(Key::NameAndType(n, d), Tag::NameAndType, Index::Pair(n, d), None)
+        })
+    }
+
+    pub fn field_ref(&mut self, class: &str, name: &str, descriptor: &str) -> Result<u16, ConstantPoolError> {
+        self.all_or_nothing(|b| {
+            let (c, nt) = b.member(class, name, descriptor)?;
+            b.intern(Key::FieldRef(c, nt), Tag::FieldRef, Index::Ref(c, nt), None)
+        })
+    }
+
+    pub fn method_ref(&mut self, class: &str, name: &str, descriptor: &str) -> Result<u16, ConstantPoolError> {
+        self.all_or_nothing(|b| {
+            let (c, nt) = b.member(class, name, descriptor)?;
+            b.intern(Key::MethodRef(c, nt), Tag::MethodRef, Index::Ref(c, nt), None)
+        })
+    }
+
+    pub fn interface_method_ref(&mut self, class: &str, name: &str, descriptor: &str) -> Result<u16, ConstantPoolError> {
+        self.all_or_nothing(|b| {
+            let (c, nt) = b.member(class, name, descriptor)?;
+            b.intern(Key::InterfaceMethodRef(c, nt), Tag::InterfaceMethodRef, Index::Ref(c, nt), None)
+        })
+    }
+
+    fn member(&mut self, class: &str, name: &str, descriptor: &str) -> Result<(u16, u16), ConstantPoolError> {
+        Ok((self.class(class)?, self.name_and_type(name, descriptor)?))
+    }
+
+    // reference_kind 1-9 and the index of the Fieldref, Methodref or InterfaceMethodref it refers to
+    pub fn method_handle(&mut self, kind: u8, reference: u16) -> Result<u16, ConstantPoolError> {
+        self.intern(Key::MethodHandle(kind, reference), Tag::MethodHandle, Index::MethodHandle(kind, reference), None)
+    }
+
+    pub fn method_type(&mut self, descriptor: &str) -> Result<u16, ConstantPoolError> {
+        self.all_or_nothing(|b| {
+            let i = b.utf8(descriptor)?;
+            b.intern(Key::MethodType(i), Tag::MethodType, Index::Single(i), None)
+        })
+    }
+
+    // bootstrap is an index into the BootstrapMethods attribute
+    pub fn dynamic(&mut self, bootstrap: u16, name: &str, descriptor: &str) -> Result<u16, ConstantPoolError> {
+        self.all_or_nothing(|b| {
+            let nt = b.name_and_type(name, descriptor)?;
+            b.intern(Key::Dynamic(bootstrap, nt), Tag::Dynamic, Index::Dynamic(bootstrap, nt), None)
+        })
+    }
+
+    pub fn invoke_dynamic(&mut self, bootstrap: u16, name: &str, descriptor: &str) -> Result<u16, ConstantPoolError> {
+        self.all_or_nothing(|b| {
+            let nt = b.name_and_type(name, descriptor)?;
+            b.intern(Key::InvokeDynamic(bootstrap, nt), Tag::InvokeDynamic, Index::Dynamic(bootstrap, nt), None)
+        })
+    }
+
+    pub fn module(&mut self, name: &str) -> Result<u16, ConstantPoolError> {
+        self.all_or_nothing(|b| {
+            let i = b.utf8(name)?;
+            b.intern(Key::Module(i), Tag::Module, Index::Single(i), None)
+        })
+    }
+
+    pub fn package(&mut self, name: &str) -> Result<u16, ConstantPoolError> {
+        self.all_or_nothing(|b| {
+            let i = b.utf8(name)?;
+            b.intern(Key::Package(i), Tag::Package, Index::Single(i), None)
+        })
+    }
+
+    // constant_pool_count as written to the class file
+    pub fn count(&self) -> u16 {
+        self.pool.constant_info.len() as u16 + 1
+    }
+
+    pub fn get_pool(&self) -> &ConstantPool {
+        &self.pool
+    }
+
+    pub fn build(self) -> ConstantPool {
+        self.pool
+    }
+}
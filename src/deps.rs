@@ -0,0 +1,486 @@
+// module deps
+// class and package dependencies from the constant pool, descriptors, signatures and annotations, grouped by package,
+// archive or module, with the package cycles and the JDK internal API a class path uses
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use serde::{Serialize, Serializer};
+use crate::attribute::AttributeKind;
+use crate::class_file_reader::{ConstantInfo, Index, Tag};
+use crate::class_path::{ClassPath, Origin};
+use crate::code::Code;
+use crate::code::cfg::escape;
+use crate::hierarchy::package;
+use crate::{Annotation, Attribute, ClassFile, ElementValue};
+
+// packages of the JDK that are internal wherever the module they are in cannot be looked up
+const INTERNAL_PACKAGES: [&str; 2] = ["sun/", "jdk/internal/"];
+
+// the module that exports sun.misc.Unsafe and the other critical internal API
+const UNSUPPORTED_MODULE: &str = "jdk.unsupported";
+
+// the classes named in a descriptor or signature, Ljava/util/Map<TK;TV;>.Entry<...>; names java/util/Map$Entry
+struct SignatureReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    classes: &'a mut BTreeSet<String>,
+}
+
+impl SignatureReader<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn identifier(&mut self, ends: &[u8]) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(|b| !ends.contains(&b)) {
+            self.pos += 1;
+        }
+        String::from_utf8_lossy(&self.bytes[start..self.pos]).to_string()
+    }
+
+    // the whole descriptor or signature, of a field, method or class
+    fn read(&mut self) -> Option<()> {
+        if self.peek() == Some(b'<') {
+            self.pos += 1;
+            while self.peek()? != b'>' {
+                self.identifier(b":");
+                while self.peek()? == b':' {
+                    self.pos += 1;
+                    if self.peek()? != b':' && self.peek()? != b'>' {
+                        self.reference()?;
+                    }
+                }
+            }
+            self.pos += 1;
+        }
+        if self.peek() == Some(b'(') {
+            self.pos += 1;
+            while self.peek()? != b')' {
+                self.java_type()?;
+            }
+            self.pos += 1;
+        }
+        while self.peek().is_some() {
+            if self.peek() == Some(b'^') {
+                self.pos += 1;
+            }
+            self.java_type()?;
+        }
+        Some(())
+    }
+
+    fn java_type(&mut self) -> Option<()> {
+        match self.peek()? {
+            b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' | b'V' => {
+                self.pos += 1;
+                Some(())
+            }
+            _ => self.reference(),
+        }
+    }
+
+    fn reference(&mut self) -> Option<()> {
+        match self.peek()? {
+            b'L' => {
+                self.pos += 1;
+                let mut name = self.identifier(b";<.");
+                loop {
+                    if self.peek()? == b'<' {
+                        self.pos += 1;
+                        while self.peek()? != b'>' {
+                            match self.peek()? {
+                                b'*' => self.pos += 1,
+                                b'+' | b'-' => {
+                                    self.pos += 1;
+                                    self.reference()?;
+                                }
+                                _ => self.reference()?,
+                            }
+                        }
+                        self.pos += 1;
+                    }
+                    match self.peek()? {
+                        b'.' => {
+                            self.pos += 1;
+                            name = format!("{}${}", name, self.identifier(b";<."));
+                        }
+                        b';' => break,
+                        _ => return None,
+                    }
+                }
+                self.pos += 1;
+                self.classes.insert(name);
+                Some(())
+            }
+            b'T' => {
+                self.identifier(b";");
+                self.pos += 1;
+                Some(())
+            }
+            b'[' => {
+                self.pos += 1;
+                self.java_type()
+            }
+            _ => None,
+        }
+    }
+}
+
+fn add_signature(classes: &mut BTreeSet<String>, signature: &str) {
+    SignatureReader { bytes: signature.as_bytes(), pos: 0, classes }.read();
+}
+
+fn add_annotation(classes: &mut BTreeSet<String>, annotation: &Annotation) {
+    add_signature(classes, annotation.get_type());
+    for pair in annotation.get_value_pairs() {
+        add_element_value(classes, pair.get_value());
+    }
+}
+
+fn add_element_value(classes: &mut BTreeSet<String>, value: &ElementValue) {
+    match value {
+        ElementValue::Const(_, _) => (),
+        ElementValue::Enum(descriptor, _) | ElementValue::Class(descriptor) => add_signature(classes, descriptor),
+        ElementValue::Annotation(annotation) => add_annotation(classes, annotation),
+        ElementValue::Array(values) => values.iter().for_each(|v| add_element_value(classes, v)),
+    }
+}
+
+// the types of the signatures and annotations of a class, field, method or Code attribute list
+fn add_attributes(classes: &mut BTreeSet<String>, class_file: &ClassFile, attributes: &[Attribute]) {
+    for attribute in attributes {
+        match attribute.decode(class_file) {
+            AttributeKind::Signature(signature) => add_signature(classes, &signature),
+            AttributeKind::RuntimeVisibleAnnotations(annotations) | AttributeKind::RuntimeInvisibleAnnotations(annotations) =>
+                annotations.iter().for_each(|a| add_annotation(classes, a)),
+            AttributeKind::RuntimeVisibleParameterAnnotations(parameters) | AttributeKind::RuntimeInvisibleParameterAnnotations(parameters) =>
+                parameters.iter().flatten().for_each(|a| add_annotation(classes, a)),
+            AttributeKind::RuntimeVisibleTypeAnnotations(annotations) | AttributeKind::RuntimeInvisibleTypeAnnotations(annotations) =>
+                annotations.iter().for_each(|a| add_annotation(classes, a.get_annotation())),
+            AttributeKind::AnnotationDefault(value) => add_element_value(classes, &value),
+            AttributeKind::Code(_) => add_attributes(classes, class_file, &class_file.get_attributes_vec(&Code::read_attributes(&attribute.info))),
+            _ => (),
+        }
+    }
+}
+
+// every class a class refers to, without itself
+pub fn get_dependencies(class_file: &ClassFile) -> BTreeSet<String> {
+    let mut classes = get_type_references(class_file);
+    let cp = class_file.get_constant_pool();
+    // owners of the Fieldref, Methodref and InterfaceMethodref entries are Class entries
+    for constant in &cp.constant_info {
+        if let ConstantInfo(Tag::Class, Index::Single(n)) = constant {
+            match cp.find_utf8(*n) {
+                Some(name) if name.starts_with('[') => add_signature(&mut classes, &name),
+                Some(name) => {
+                    classes.insert(name);
+                }
+                None => (),
+            }
+        }
+    }
+    classes.remove(&class_file.get_class_name());
+    classes
+}
+
+// the classes in the descriptors, signatures and annotations of a class, those of the Fieldref, Methodref,
+// InterfaceMethodref, Dynamic and InvokeDynamic entries are in NameAndType entries
+pub(crate) fn get_type_references(class_file: &ClassFile) -> BTreeSet<String> {
+    let mut classes = BTreeSet::new();
+    let cp = class_file.get_constant_pool();
+    for constant in &cp.constant_info {
+        if let ConstantInfo(Tag::NameAndType, Index::Pair(_, d)) | ConstantInfo(Tag::MethodType, Index::Single(d)) = constant {
+            if let Some(descriptor) = cp.find_utf8(*d) {
+                add_signature(&mut classes, &descriptor);
+            }
+        }
+    }
+    for field in &class_file.fields {
+        if let Some(descriptor) = cp.find_utf8(index(&field.descriptor_index)) {
+            add_signature(&mut classes, &descriptor);
+        }
+        add_attributes(&mut classes, class_file, &class_file.get_attributes_vec(&field.attributes));
+    }
+    for method in &class_file.methods {
+        if let Some(descriptor) = cp.find_utf8(index(&method.descriptor_index)) {
+            add_signature(&mut classes, &descriptor);
+        }
+        add_attributes(&mut classes, class_file, &class_file.get_attributes_vec(&method.attributes));
+    }
+    add_attributes(&mut classes, class_file, &class_file.get_attributes_vec(&class_file.attributes));
+    for component in class_file.record_components().into_iter().flatten() {
+        add_signature(&mut classes, component.get_descriptor());
+        add_attributes(&mut classes, class_file, component.get_attributes());
+    }
+    classes
+}
+
+fn index(index: &Index) -> u16 {
+    match index {
+        Index::Single(i) => *i,
+        _ => 0,
+    }
+}
+
+// edges from each class, or package or archive once grouped, to the ones it depends on
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    pub nodes: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl DependencyGraph {
+    pub fn new() -> Self {
+        DependencyGraph::default()
+    }
+
+    pub fn add(&mut self, class_file: &ClassFile) {
+        self.nodes.entry(class_file.get_class_name()).or_default().extend(get_dependencies(class_file));
+    }
+
+    // the graph between the groups of the classes, without the edges inside a group
+    pub fn group_by<F: Fn(&str) -> String>(&self, key: F) -> DependencyGraph {
+        let mut grouped = DependencyGraph::new();
+        for (from, targets) in &self.nodes {
+            let group = key(from);
+            let edges = grouped.nodes.entry(group.clone()).or_default();
+            edges.extend(targets.iter().map(|t| key(t)).filter(|t| *t != group));
+        }
+        grouped
+    }
+
+    pub fn by_package(&self) -> DependencyGraph {
+        self.group_by(|class| package(class).to_string())
+    }
+
+    // strongly connected components of more than one node, each sorted, by Tarjan's algorithm
+    pub fn find_cycles(&self) -> Vec<Vec<String>> {
+        let mut tarjan = Tarjan { graph: self, index: HashMap::new(), low: HashMap::new(), stack: Vec::new(), cycles: Vec::new() };
+        for node in self.nodes.keys() {
+            if !tarjan.index.contains_key(node.as_str()) {
+                tarjan.visit(node);
+            }
+        }
+        let mut cycles = tarjan.cycles;
+        cycles.iter_mut().for_each(|c| c.sort());
+        cycles.sort();
+        cycles
+    }
+}
+
+struct Tarjan<'a> {
+    graph: &'a DependencyGraph,
+    index: HashMap<&'a str, usize>,
+    low: HashMap<&'a str, usize>,
+    stack: Vec<&'a str>,
+    cycles: Vec<Vec<String>>,
+}
+
+impl<'a> Tarjan<'a> {
+    // iterative, package graphs of large class paths are deep
+    fn visit(&mut self, root: &'a str) {
+        let mut work: Vec<(&'a str, Vec<&'a str>)> = Vec::new();
+        self.enter(root, &mut work);
+        while let Some((node, pending)) = work.last_mut() {
+            let node = *node;
+            match pending.pop() {
+                Some(next) if !self.index.contains_key(next) => self.enter(next, &mut work),
+                Some(next) => {
+                    if self.stack.contains(&next) {
+                        let low = self.low[node].min(self.index[next]);
+                        self.low.insert(node, low);
+                    }
+                }
+                None => {
+                    work.pop();
+                    if let Some((parent, _)) = work.last() {
+                        let low = self.low[parent].min(self.low[node]);
+                        self.low.insert(parent, low);
+                    }
+                    if self.low[node] == self.index[node] {
+                        let at = self.stack.iter().rposition(|n| *n == node).unwrap();
+                        let component: Vec<String> = self.stack.drain(at..).map(str::to_string).collect();
+                        if component.len() > 1 {
+                            self.cycles.push(component);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn enter(&mut self, node: &'a str, work: &mut Vec<(&'a str, Vec<&'a str>)>) {
+        let i = self.index.len();
+        self.index.insert(node, i);
+        self.low.insert(node, i);
+        self.stack.push(node);
+        let edges = self.graph.nodes.get(node).map_or(Vec::new(), |e| e.iter().map(String::as_str).collect());
+        work.push((node, edges));
+    }
+}
+
+// a class that depends on JDK internal API, the class it uses and the module that has it
+#[derive(Debug, Serialize)]
+pub struct InternalUse {
+    #[serde(serialize_with = "serialize_dotted")]
+    pub from: String,
+    #[serde(serialize_with = "serialize_dotted")]
+    pub to: String,
+    pub module: Option<String>,
+}
+
+// uses of classes in JDK packages their module does not export to everyone, or in jdk.unsupported. Without the
+// module, a class not on the class path, the sun and jdk.internal packages are taken as internal
+pub fn find_internal_uses(graph: &DependencyGraph, class_path: &ClassPath) -> Vec<InternalUse> {
+    let mut exported = HashMap::<String, Option<BTreeSet<String>>>::new();
+    let mut uses = Vec::new();
+    for (from, targets) in &graph.nodes {
+        let from_origin = class_path.get_origin(from).map(|o| o.to_string());
+        for to in targets {
+            let module = match class_path.get_origin(to) {
+                Some(Origin::Module(m)) if Some(&m) != from_origin.as_ref() => Some(m),
+                Some(_) => continue,
+                None => None,
+            };
+            let internal = match &module {
+                Some(m) if m == UNSUPPORTED_MODULE => true,
+                Some(m) => {
+                    let exports = exported.entry(m.clone()).or_insert_with(|| {
+                        let module_info = class_path.get_module_info(m)?;
+                        let module = module_info.get_class_attributes().module?;
+                        Some(module.exports.into_iter().filter(|e| e.to.is_empty()).map(|e| e.package).collect())
+                    });
+                    match exports {
+                        Some(exports) => !exports.contains(&package(to).replace('/', ".")),   // the descriptor has binary names
+                        None => INTERNAL_PACKAGES.iter().any(|p| to.starts_with(p)),
+                    }
+                }
+                None => INTERNAL_PACKAGES.iter().any(|p| to.starts_with(p)),
+            };
+            if internal {
+                uses.push(InternalUse { from: from.clone(), to: to.clone(), module });
+            }
+        }
+    }
+    uses
+}
+
+// the graph at one granularity with what is found about it, printed as text, Graphviz or JSON
+pub struct DependencyReport {
+    pub by: String,                             // class, package or archive
+    pub graph: DependencyGraph,
+    pub origins: BTreeMap<String, String>,      // archive or module of each node that is not analyzed
+    pub cycles: Vec<Vec<String>>,               // between packages
+    pub internal: Vec<InternalUse>,
+}
+
+// binary name of a class or package, java.lang.String, with a name for the unnamed package
+fn dotted(name: &str) -> String {
+    match name {
+        "" => "<unnamed>".to_string(),
+        n => n.replace('/', "."),
+    }
+}
+
+fn serialize_dotted<S: Serializer>(name: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&dotted(name))
+}
+
+// an edge of the JSON report, with where the target is from when it is not analyzed, null when it is not found
+#[derive(Serialize)]
+struct Dependency<'a> {
+    from: String,
+    to: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    origin: Option<Option<&'a str>>,
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    by: &'a str,
+    dependencies: Vec<Dependency<'a>>,
+    cycles: Vec<Vec<String>>,
+    internal: &'a [InternalUse],
+}
+
+impl DependencyReport {
+    fn name(&self, node: &str) -> String {
+        match self.by.as_str() {
+            "archive" => node.to_string(),
+            _ => dotted(node),
+        }
+    }
+
+    fn origin(&self, node: &str) -> &str {
+        match self.by.as_str() {
+            "archive" => "",
+            _ => self.origins.get(node).map_or("not found", |o| o.as_str()),
+        }
+    }
+
+    // one line for each dependency, then the cycles and the JDK internal API use
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (from, targets) in &self.graph.nodes {
+            for to in targets {
+                let origin = match self.graph.nodes.contains_key(to) {
+                    true => "",
+                    false => self.origin(to),
+                };
+                text.push_str(format!("{} -> {} {}", self.name(from), self.name(to), origin).trim_end());
+                text.push('\n');
+            }
+        }
+        if !self.cycles.is_empty() {
+            text.push_str("Package cycles\n");
+            for cycle in &self.cycles {
+                text.push_str(&format!("  {}\n", cycle.iter().map(|p| dotted(p)).collect::<Vec<_>>().join(", ")));
+            }
+        }
+        if !self.internal.is_empty() {
+            text.push_str("JDK internal API\n");
+            for u in &self.internal {
+                text.push_str(&format!("  {} -> {} {}\n", dotted(&u.from), dotted(&u.to), u.module.as_deref().unwrap_or("not found")));
+            }
+        }
+        text
+    }
+
+    // Graphviz, nodes in a package cycle filled
+    pub fn to_dot(&self) -> String {
+        let cyclic: BTreeSet<&String> = self.cycles.iter().flatten().collect();
+        let mut dot = format!("digraph \"{}\" {{\n", escape(&self.by));
+        for (from, targets) in &self.graph.nodes {
+            let style = match self.by == "package" && cyclic.contains(from) {
+                true => ", style=filled, fillcolor=orange",
+                false => "",
+            };
+            dot.push_str(&format!("  \"{}\" [shape=box{}];\n", escape(&self.name(from)), style));
+            for to in targets {
+                dot.push_str(&format!("  \"{}\" -> \"{}\";\n", escape(&self.name(from)), escape(&self.name(to))));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_json(&self) -> String {
+        let dependencies = self.graph.nodes.iter()
+            .flat_map(|(from, targets)| targets.iter().map(move |to| (from, to)))
+            .map(|(from, to)| Dependency {
+                from: self.name(from),
+                to: self.name(to),
+                origin: match self.graph.nodes.contains_key(to) || self.by == "archive" {
+                    true => None,
+                    false => Some(self.origins.get(to).map(String::as_str)),
+                },
+            })
+            .collect();
+        let report = JsonReport {
+            by: &self.by,
+            dependencies,
+            cycles: self.cycles.iter().map(|c| c.iter().map(|p| dotted(p)).collect()).collect(),
+            internal: &self.internal,
+        };
+        serde_json::to_string(&report).unwrap_or_default() + "\n"
+    }
+}
@@ -0,0 +1,136 @@
+// module descriptor
+// field and method descriptors (JVMS 4.3)
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JavaType {
+    Byte,
+    Char,
+    Double,
+    Float,
+    Int,
+    Long,
+    Short,
+    Boolean,
+    Void,
+    Class(String),  // internal form java/lang/String
+    Array(Box<JavaType>),
+}
+
+impl JavaType {
+    // None when the descriptor is malformed or has trailing characters
+    pub fn from_descriptor(descriptor: &str) -> Option<JavaType> {
+        let mut chars = descriptor.chars().peekable();
+        let t = JavaType::parse(&mut chars)?;
+        match chars.next() {
+            None if t != JavaType::Void => Some(t),
+            _ => None,
+        }
+    }
+
+    fn parse(chars: &mut Peekable<Chars>) -> Option<JavaType> {
+        match chars.next()? {
+            'B' => Some(JavaType::Byte),
+            'C' => Some(JavaType::Char),
+            'D' => Some(JavaType::Double),
+            'F' => Some(JavaType::Float),
+            'I' => Some(JavaType::Int),
+            'J' => Some(JavaType::Long),
+            'S' => Some(JavaType::Short),
+            'Z' => Some(JavaType::Boolean),
+            'V' => Some(JavaType::Void),
+            'L' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next()? {
+                        ';' => break,
+                        c => name.push(c),
+                    }
+                }
+                match name.is_empty() {
+                    true => None,
+                    false => Some(JavaType::Class(name)),
+                }
+            }
+            '[' => match JavaType::parse(chars)? {
+                JavaType::Void => None,
+                t => Some(JavaType::Array(Box::new(t))),
+            }
+            _ => None,
+        }
+    }
+
+    // local variable slots, long and double take two
+    pub fn size(&self) -> u16 {
+        match self {
+            JavaType::Long | JavaType::Double => 2,
+            JavaType::Void => 0,
+            _ => 1,
+        }
+    }
+
+    pub fn to_descriptor(&self) -> String {
+        match self {
+            JavaType::Byte => "B".to_string(),
+            JavaType::Char => "C".to_string(),
+            JavaType::Double => "D".to_string(),
+            JavaType::Float => "F".to_string(),
+            JavaType::Int => "I".to_string(),
+            JavaType::Long => "J".to_string(),
+            JavaType::Short => "S".to_string(),
+            JavaType::Boolean => "Z".to_string(),
+            JavaType::Void => "V".to_string(),
+            JavaType::Class(name) => format!("L{};", name),
+            JavaType::Array(t) => format!("[{}", t.to_descriptor()),
+        }
+    }
+}
+
+// java source form: int, java.lang.String[]
+impl fmt::Display for JavaType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JavaType::Byte => write!(f, "byte"),
+            JavaType::Char => write!(f, "char"),
+            JavaType::Double => write!(f, "double"),
+            JavaType::Float => write!(f, "float"),
+            JavaType::Int => write!(f, "int"),
+            JavaType::Long => write!(f, "long"),
+            JavaType::Short => write!(f, "short"),
+            JavaType::Boolean => write!(f, "boolean"),
+            JavaType::Void => write!(f, "void"),
+            JavaType::Class(name) => write!(f, "{}", name.replace('/', ".")),
+            JavaType::Array(t) => write!(f, "{}[]", t),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodDescriptor {
+    pub parameters: Vec<JavaType>,
+    pub return_type: JavaType,
+}
+
+impl MethodDescriptor {
+    pub fn from_descriptor(descriptor: &str) -> Option<MethodDescriptor> {
+        let mut chars = descriptor.chars().peekable();
+        if chars.next()? != '(' {
+            return None;
+        }
+        let mut parameters = Vec::<JavaType>::new();
+        while *chars.peek()? != ')' {
+            match JavaType::parse(&mut chars)? {
+                JavaType::Void => return None,
+                t => parameters.push(t),
+            }
+        }
+        chars.next();
+        let return_type = JavaType::parse(&mut chars)?;
+        match chars.next() {
+            None => Some(MethodDescriptor { parameters, return_type }),
+            Some(_) => None,
+        }
+    }
+}
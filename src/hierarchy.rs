@@ -0,0 +1,307 @@
+// module hierarchy
+// reflection over a class path, supertypes and subtypes, assignability and member lookup by JVMS 5.4.3 resolution and
+// 5.4.6 selection. Classes are read from the class path only when a question needs them
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::rc::Rc;
+use crate::access_flags::*;
+use crate::class_file_reader::{Index, LiteralInfo};
+use crate::class_path::ClassPath;
+use crate::code::frames::ClassHierarchy;
+use crate::ClassFile;
+
+const OBJECT: &str = "java/lang/Object";
+
+// a method or field and the class that declares it
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Member {
+    pub class: String,
+    pub name: String,
+    pub descriptor: String,
+    pub access_flags: u16,
+}
+
+impl fmt::Display for Member {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}:{}", self.class, self.name, self.descriptor)
+    }
+}
+
+impl Member {
+    fn is(&self, flag: u16) -> bool {
+        self.access_flags & flag != 0
+    }
+}
+
+// package of a class by internal name, empty for the unnamed package
+pub fn package(class: &str) -> &str {
+    class.rsplit_once('/').map_or("", |(p, _)| p)
+}
+
+// element type of an array descriptor as a class name or descriptor, [Ljava/lang/String; to java/lang/String, [[I to [I,
+// and whether it is a reference
+fn component(array: &str) -> Option<(&str, bool)> {
+    let c = array.strip_prefix('[')?;
+    match c.strip_prefix('L').and_then(|c| c.strip_suffix(';')) {
+        Some(class) => Some((class, true)),
+        None => Some((c, c.starts_with('['))),
+    }
+}
+
+pub struct Hierarchy {
+    class_path: ClassPath,
+    subtypes: RefCell<Option<HashMap<String, Vec<String>>>>,    // direct subclasses, subinterfaces and implementations
+}
+
+impl Hierarchy {
+    pub fn new(class_path: ClassPath) -> Self {
+        Hierarchy { class_path, subtypes: RefCell::new(None) }
+    }
+
+    pub fn get_class_path(&self) -> &ClassPath {
+        &self.class_path
+    }
+
+    fn class(&self, name: &str) -> Option<Rc<ClassFile>> {
+        self.class_path.get_class(name)
+    }
+
+    fn is_interface_class(&self, name: &str) -> bool {
+        self.class(name).is_some_and(|c| c.access_flags & ACC_INTERFACE != 0)
+    }
+
+    fn members(class_file: &ClassFile, methods: bool) -> Vec<Member> {
+        let cp = &class_file.constant_pool;
+        let class = class_file.get_class_name();
+        let member = |flags: u16, name, descriptor| Member {
+            class: class.clone(),
+            name: cp.get_item(name),
+            descriptor: cp.get_item(descriptor),
+            access_flags: flags,
+        };
+        match methods {
+            true => class_file.methods.iter().map(|m| member(m.access_flags, &m.name_index, &m.descriptor_index)).collect(),
+            false => class_file.fields.iter().map(|f| member(f.access_flags, &f.name_index, &f.descriptor_index)).collect(),
+        }
+    }
+
+    // compared in the constant pool, selection over a large class path asks for a lot of these
+    fn declared_method(&self, class: &str, name: &str, descriptor: &str) -> Option<Member> {
+        let class_file = self.class(class)?;
+        let cp = &class_file.constant_pool;
+        let is = |index: &Index, s: &str| matches!(index, Index::Single(i) if matches!(cp.get_literal(*i), LiteralInfo::String(v) if v == s));
+        let method = class_file.methods.iter().find(|m| is(&m.name_index, name) && is(&m.descriptor_index, descriptor))?;
+        Some(Member {
+            class: class.to_string(),
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+            access_flags: method.access_flags,
+        })
+    }
+
+    // from the direct superclass up to java/lang/Object, stopping at a class the class path does not have
+    pub fn superclasses(&self, name: &str) -> Vec<String> {
+        let mut chain = Vec::<String>::new();
+        let mut current = name.to_string();
+        while let Some(s) = self.class(&current).and_then(|c| c.get_super_class_name()) {
+            if s == name || chain.contains(&s) {
+                break;
+            }
+            chain.push(s.clone());
+            current = s;
+        }
+        chain
+    }
+
+    // interfaces implemented or extended directly, through superclasses or through other interfaces, nearest first
+    pub fn all_interfaces(&self, name: &str) -> Vec<String> {
+        let mut found = Vec::<String>::new();
+        let mut pending: VecDeque<String> = VecDeque::new();
+        for class in [name.to_string()].into_iter().chain(self.superclasses(name)) {
+            pending.extend(self.class(&class).map(|c| c.get_interface_names()).unwrap_or_default());
+        }
+        while let Some(i) = pending.pop_front() {
+            if found.contains(&i) || i == name {
+                continue;
+            }
+            pending.extend(self.class(&i).map(|c| c.get_interface_names()).unwrap_or_default());
+            found.push(i);
+        }
+        found
+    }
+
+    // every class and interface below this one, nearest first, the first call reads all of the class path
+    pub fn subtypes(&self, name: &str) -> Vec<String> {
+        if self.subtypes.borrow().is_none() {
+            let mut index = HashMap::<String, Vec<String>>::new();
+            for class in self.class_path.get_class_names() {
+                // parsed without being cached, only the supertypes are kept
+                let Ok(Some(class_file)) = self.class_path.read_class(&class) else { continue };
+                for s in class_file.get_super_class_name().into_iter().chain(class_file.get_interface_names()) {
+                    index.entry(s).or_default().push(class.clone());
+                }
+            }
+            *self.subtypes.borrow_mut() = Some(index);
+        }
+        let index = self.subtypes.borrow();
+        let index = index.as_ref().unwrap();
+        let mut found = Vec::<String>::new();
+        let mut seen = HashSet::<&str>::from([name]);
+        let mut pending = VecDeque::from([name]);
+        while let Some(t) = pending.pop_front() {
+            for s in index.get(t).into_iter().flatten() {
+                if seen.insert(s) {
+                    found.push(s.clone());
+                    pending.push_back(s);
+                }
+            }
+        }
+        found
+    }
+
+    // whether a value of type from can be used as a to, as checkcast decides, for class names and array descriptors.
+    // Classes the class path does not have are not assignable to anything but themselves and java/lang/Object
+    pub fn is_assignable(&self, from: &str, to: &str) -> bool {
+        if from == to || to == OBJECT {
+            return true;
+        }
+        match (component(from), component(to)) {
+            (Some(_), None) => to == "java/lang/Cloneable" || to == "java/io/Serializable",
+            (None, Some(_)) => false,
+            (Some((a, true)), Some((b, true))) => self.is_assignable(a, b),
+            (Some(_), Some(_)) => false,
+            (None, None) => match self.is_interface_class(to) {
+                true => self.all_interfaces(from).iter().any(|i| i == to),
+                false => self.superclasses(from).iter().any(|s| s == to),
+            },
+        }
+    }
+
+    // JVMS 5.4.3.3 and 5.4.3.4, the method a Methodref or InterfaceMethodref naming this class resolves to
+    pub fn find_method(&self, class: &str, name: &str, descriptor: &str) -> Option<Member> {
+        let class = if class.starts_with('[') { OBJECT } else { class };
+        if self.is_interface_class(class) {
+            if let Some(m) = self.declared_method(class, name, descriptor) {
+                return Some(m);
+            }
+            let object = self.declared_method(OBJECT, name, descriptor);
+            if let Some(m) = object.filter(|m| m.is(ACC_PUBLIC) && !m.is(ACC_STATIC)) {
+                return Some(m);
+            }
+        } else {
+            for c in [class.to_string()].into_iter().chain(self.superclasses(class)) {
+                if let Some(m) = self.declared_method(&c, name, descriptor).or_else(|| self.signature_polymorphic(&c, name)) {
+                    return Some(m);
+                }
+            }
+        }
+        let candidates = self.maximally_specific(class, name, descriptor);
+        let concrete: Vec<&Member> = candidates.iter().filter(|m| !m.is(ACC_ABSTRACT)).collect();
+        match concrete.as_slice() {
+            [m] => Some((*m).clone()),
+            _ => candidates.into_iter().next(),
+        }
+    }
+
+    // MethodHandle.invoke and the like take any descriptor
+    fn signature_polymorphic(&self, class: &str, name: &str) -> Option<Member> {
+        if class != "java/lang/invoke/MethodHandle" && class != "java/lang/invoke/VarHandle" {
+            return None;
+        }
+        let methods = Hierarchy::members(&*self.class(class)?, true);
+        let mut matching = methods.into_iter().filter(|m| m.name == name && m.is(ACC_VARARGS) && m.is(ACC_NATIVE)
+            && m.descriptor.starts_with("([Ljava/lang/Object;)"));
+        match (matching.next(), matching.next()) {
+            (Some(m), None) => Some(m),
+            _ => None,
+        }
+    }
+
+    // the superinterface methods of the class that no other one from a subinterface hides
+    fn maximally_specific(&self, class: &str, name: &str, descriptor: &str) -> Vec<Member> {
+        let candidates: Vec<Member> = self.all_interfaces(class).iter()
+            .filter_map(|i| self.declared_method(i, name, descriptor))
+            .filter(|m| !m.is(ACC_PRIVATE) && !m.is(ACC_STATIC))
+            .collect();
+        candidates.iter()
+            .filter(|m| !candidates.iter().any(|other| other.class != m.class && self.all_interfaces(&other.class).contains(&m.class)))
+            .cloned()
+            .collect()
+    }
+
+    // JVMS 5.4.6, the method invokevirtual or invokeinterface runs for a receiver of this class
+    pub fn select_method(&self, receiver: &str, resolved: &Member) -> Option<Member> {
+        if resolved.is(ACC_PRIVATE) {
+            return Some(resolved.clone());
+        }
+        let receiver = if receiver.starts_with('[') { OBJECT } else { receiver };
+        for c in [receiver.to_string()].into_iter().chain(self.superclasses(receiver)) {
+            if let Some(m) = self.declared_method(&c, &resolved.name, &resolved.descriptor) {
+                if !m.is(ACC_STATIC) && (m == *resolved || self.can_override(&m, resolved)) {
+                    return Some(m);
+                }
+            }
+        }
+        let candidates = self.maximally_specific(receiver, &resolved.name, &resolved.descriptor);
+        let mut concrete = candidates.into_iter().filter(|m| !m.is(ACC_ABSTRACT));
+        match (concrete.next(), concrete.next()) {
+            (Some(m), None) => Some(m),
+            _ => None,
+        }
+    }
+
+    // JVMS 5.4.5, a package private method is overridden from its own package or through a method that overrides it
+    fn can_override(&self, method: &Member, overridden: &Member) -> bool {
+        if method.name != overridden.name || method.descriptor != overridden.descriptor || method.is(ACC_PRIVATE) {
+            return false;
+        }
+        if overridden.is(ACC_PUBLIC) || overridden.is(ACC_PROTECTED) || package(&method.class) == package(&overridden.class) {
+            return true;
+        }
+        self.superclasses(&method.class).iter()
+            .take_while(|s| **s != overridden.class)
+            .filter_map(|s| self.declared_method(s, &method.name, &method.descriptor))
+            .any(|between| !between.is(ACC_STATIC) && self.can_override(method, &between) && self.can_override(&between, overridden))
+    }
+
+    // JVMS 5.4.3.2, the field a Fieldref naming this class resolves to, the class, then its superinterfaces, then superclasses
+    pub fn find_field(&self, class: &str, name: &str, descriptor: &str) -> Option<Member> {
+        self.find_field_from(class, name, descriptor, &mut HashSet::new())
+    }
+
+    fn find_field_from(&self, class: &str, name: &str, descriptor: &str, seen: &mut HashSet<String>) -> Option<Member> {
+        if !seen.insert(class.to_string()) {
+            return None;
+        }
+        let class_file = self.class(class)?;
+        let declared = Hierarchy::members(&class_file, false).into_iter().find(|f| f.name == name && f.descriptor == descriptor);
+        declared
+            .or_else(|| class_file.get_interface_names().iter().find_map(|i| self.find_field_from(i, name, descriptor, seen)))
+            .or_else(|| self.find_field_from(&class_file.get_super_class_name()?, name, descriptor, seen))
+    }
+
+    // the superclass methods this one overrides and the interface methods it implements, nearest first
+    pub fn overrides(&self, method: &Member) -> Vec<Member> {
+        if method.is(ACC_PRIVATE) || method.is(ACC_STATIC) || method.name.starts_with('<') {
+            return Vec::new();
+        }
+        let overridden = self.superclasses(&method.class).into_iter()
+            .filter_map(|s| self.declared_method(&s, &method.name, &method.descriptor))
+            .filter(|m| !m.is(ACC_STATIC) && self.can_override(method, m));
+        let implemented = self.all_interfaces(&method.class).into_iter()
+            .filter_map(|i| self.declared_method(&i, &method.name, &method.descriptor))
+            .filter(|m| !m.is(ACC_PRIVATE) && !m.is(ACC_STATIC));
+        overridden.chain(implemented).collect()
+    }
+}
+
+impl ClassHierarchy for Hierarchy {
+    fn get_super_class(&self, name: &str) -> Option<Option<String>> {
+        self.class_path.get_super_class(name)
+    }
+
+    fn is_interface(&self, name: &str) -> bool {
+        self.is_interface_class(name)
+    }
+}
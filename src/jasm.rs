@@ -0,0 +1,1159 @@
+// module jasm
+// Jasmin/Krakatau style text for a whole class, disassemble writes it and assemble reads it back
+//
+// .version 61 0
+// .class public super Hello
+// .super java/lang/Object
+// .const #1 = Methodref #2 #3       the pool as it is, so raw attributes keep their indices
+// .method public static main ([Ljava/lang/String;)V
+// .code stack 2 locals 1           or just .code to compute them
+//     getstatic Field java/lang/System out Ljava/io/PrintStream;
+//     ldc String "hello"
+//     invokevirtual Method java/io/PrintStream println (Ljava/lang/String;)V
+//     return
+// .end code
+// .end method
+// .sourcefile "Hello.java"
+// .end class
+//
+// instructions refer to the pool by value, entries missing from .const are added
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Write};
+use crate::access_flags::*;
+use crate::class_file_reader::{AttributeInfo, ConstantInfo, ConstantPool, FieldInfo, Index, JavaVersion, LiteralInfo, MethodInfo, Tag};
+use crate::class_file_writer::ClassFileWriter;
+use crate::code::{self, Code};
+use crate::code::limits;
+use crate::code::assembler::{AssembleError, Assembler, Instruction, Label};
+use crate::code::opcode::{self, Opcode};
+use crate::constant_pool_builder::{ConstantPoolBuilder, ConstantPoolError};
+use crate::ClassFile;
+
+pub(crate) const CLASS_KEYWORDS: [(u16, &str); 9] = [
+    (ACC_PUBLIC, "public"),
+    (ACC_FINAL, "final"),
+    (ACC_SUPER, "super"),
+    (ACC_INTERFACE, "interface"),
+    (ACC_ABSTRACT, "abstract"),
+    (ACC_SYNTHETIC, "synthetic"),
+    (ACC_ANNOTATION, "annotation"),
+    (ACC_ENUM, "enum"),
+    (ACC_MODULE, "module"),
+];
+
+pub(crate) const FIELD_KEYWORDS: [(u16, &str); 9] = [
+    (ACC_PUBLIC, "public"),
+    (ACC_PRIVATE, "private"),
+    (ACC_PROTECTED, "protected"),
+    (ACC_STATIC, "static"),
+    (ACC_FINAL, "final"),
+    (ACC_VOLATILE, "volatile"),
+    (ACC_TRANSIENT, "transient"),
+    (ACC_SYNTHETIC, "synthetic"),
+    (ACC_ENUM, "enum"),
+];
+
+pub(crate) const METHOD_KEYWORDS: [(u16, &str); 12] = [
+    (ACC_PUBLIC, "public"),
+    (ACC_PRIVATE, "private"),
+    (ACC_PROTECTED, "protected"),
+    (ACC_STATIC, "static"),
+    (ACC_FINAL, "final"),
+    (ACC_SYNCHRONIZED, "synchronized"),
+    (ACC_BRIDGE, "bridge"),
+    (ACC_VARARGS, "varargs"),
+    (ACC_NATIVE, "native"),
+    (ACC_ABSTRACT, "abstract"),
+    (ACC_STRICT, "strict"),
+    (ACC_SYNTHETIC, "synthetic"),
+];
+
+const HANDLE_KINDS: [&str; 9] = [
+    "getField", "getStatic", "putField", "putStatic", "invokeVirtual",
+    "invokeStatic", "invokeSpecial", "newInvokeSpecial", "invokeInterface",
+];
+
+const ARRAY_TYPES: [&str; 8] = ["boolean", "char", "float", "double", "byte", "short", "int", "long"];
+
+// bytes per quoted hex string of a raw attribute
+const HEX_CHUNK: usize = 32;
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub(crate) fn flags_text(flags: u16, keywords: &[(u16, &str)]) -> String {
+    let mut words: Vec<String> = keywords.iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|(_, name)| name.to_string())
+        .collect();
+    let rest = keywords.iter().fold(flags, |f, (flag, _)| f & !flag);
+    if rest != 0 {
+        words.push(format!("0x{:04x}", rest));
+    }
+    words.iter().map(|w| format!("{} ", w)).collect()
+}
+
+fn quote(s: &str) -> String {
+    let mut q = String::with_capacity(s.len() + 2);
+    q.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => q.push_str("\\\""),
+            '\\' => q.push_str("\\\\"),
+            '\n' => q.push_str("\\n"),
+            '\r' => q.push_str("\\r"),
+            '\t' => q.push_str("\\t"),
+            c if c.is_control() => { let _ = write!(q, "\\u{{{:x}}}", c as u32); }
+            c => q.push(c),
+        }
+    }
+    q.push('"');
+    q
+}
+
+// names are written bare unless they would not read back as one token
+fn name(s: &str) -> String {
+    let plain = !s.is_empty() && !s.starts_with([';', '.', '#']) && !s.ends_with(':')
+        && !s.chars().any(|c| c.is_whitespace() || c.is_control() || c == '"');
+    match plain {
+        true => s.to_string(),
+        false => quote(s),
+    }
+}
+
+// a constant by value, as ldc and the bootstrap arguments use them
+pub(crate) fn constant_text(cp: &ConstantPool, index: u16) -> String {
+    let ConstantInfo(tag, info) = cp.get_constant(index);
+    let member = |i1: u16, i2: u16| -> String {
+        let (n, d) = match cp.get_constant(i2) {
+            ConstantInfo(_, Index::Pair(n, d)) => (*n, *d),
+            _ => (0, 0),
+        };
+        format!("{} {} {}", name(&cp.get_name(i1)), name(&utf8(cp, n)), name(&utf8(cp, d)))
+    };
+    match (tag, info) {
+        (Tag::Utf8, _) => format!("Utf8 {}", quote(&utf8(cp, index))),
+        (Tag::Integer | Tag::Float | Tag::Long | Tag::Double, _) => literal_text(cp.get_literal(index)),
+        (Tag::Class, Index::Single(i)) => format!("Class {}", name(&utf8(cp, *i))),
+        (Tag::String, Index::Single(i)) => format!("String {}", quote(&utf8(cp, *i))),
+        (Tag::FieldRef, Index::Ref(i1, i2)) => format!("Field {}", member(*i1, *i2)),
+        (Tag::MethodRef, Index::Ref(i1, i2)) => format!("Method {}", member(*i1, *i2)),
+        (Tag::InterfaceMethodRef, Index::Ref(i1, i2)) => format!("InterfaceMethod {}", member(*i1, *i2)),
+        (Tag::NameAndType, Index::Pair(n, d)) => format!("NameAndType {} {}", name(&utf8(cp, *n)), name(&utf8(cp, *d))),
+        (Tag::MethodHandle, Index::MethodHandle(kind, i)) =>
+            format!("MethodHandle {} {}", HANDLE_KINDS.get(*kind as usize - 1).unwrap_or(&"?"), constant_text(cp, *i)),
+        (Tag::MethodType, Index::Single(i)) => format!("MethodType {}", name(&utf8(cp, *i))),
+        (Tag::Dynamic | Tag::InvokeDynamic, Index::Dynamic(bsm, nt)) => {
+            let keyword = if *tag == Tag::Dynamic { "Dynamic" } else { "InvokeDynamic" };
+            match cp.get_constant(*nt) {
+                ConstantInfo(_, Index::Pair(n, d)) => format!("{} {} {} {}", keyword, bsm, name(&utf8(cp, *n)), name(&utf8(cp, *d))),
+                _ => format!("{} {} ? ?", keyword, bsm),
+            }
+        }
+        (Tag::Module, Index::Single(i)) => format!("Module {}", name(&utf8(cp, *i))),
+        (Tag::Package, Index::Single(i)) => format!("Package {}", name(&utf8(cp, *i))),
+        _ => format!("#{}", index),
+    }
+}
+
+// an entry that repeats an earlier one is referred to by index, by value it would become the earlier one
+fn duplicates(cp: &ConstantPool) -> HashSet<u16> {
+    let mut first = HashSet::<String>::new();
+    (1..=cp.constant_info.len() as u16)
+        .filter(|i| pool_entry_text(cp, *i).is_some_and(|text| !first.insert(text)))
+        .collect()
+}
+
+fn operand_text(cp: &ConstantPool, duplicates: &HashSet<u16>, index: u16) -> String {
+    match duplicates.contains(&index) {
+        true => format!("#{}", index),
+        false => constant_text(cp, index),
+    }
+}
+
+fn class_text(cp: &ConstantPool, duplicates: &HashSet<u16>, index: u16) -> String {
+    match duplicates.contains(&index) {
+        true => format!("#{}", index),
+        false => name(&cp.get_name(index)),
+    }
+}
+
+fn utf8(cp: &ConstantPool, index: u16) -> String {
+    cp.get_item(&Index::Single(index))
+}
+
+// floats by value, NaNs other than the usual one by their bits
+pub(crate) fn literal_text(literal: &LiteralInfo) -> String {
+    match literal {
+        LiteralInfo::Integer(v) => format!("Int {}", *v as i32),
+        LiteralInfo::Float(v) if v.is_nan() => format!("FloatBits 0x{:08x}", v.to_bits()),
+        LiteralInfo::Float(v) => format!("Float {:?}", v),
+        LiteralInfo::Long(v) => format!("Long {}", *v as i64),
+        LiteralInfo::Double(v) if v.is_nan() => format!("DoubleBits 0x{:016x}", v.to_bits()),
+        LiteralInfo::Double(v) => format!("Double {:?}", v),
+        LiteralInfo::String(s) => format!("Utf8 {}", quote(s)),
+    }
+}
+
+// a pool entry as it is, with the indices it refers to
+fn pool_entry_text(cp: &ConstantPool, index: u16) -> Option<String> {
+    let ConstantInfo(tag, info) = cp.get_constant(index);
+    let text = match (tag, info) {
+        (Tag::Unusable, _) => return None,
+        (Tag::Utf8 | Tag::Integer | Tag::Float | Tag::Long | Tag::Double, _) => literal_text(cp.get_literal(index)),
+        (Tag::Class, Index::Single(i)) => format!("Class #{}", i),
+        (Tag::String, Index::Single(i)) => format!("String #{}", i),
+        (Tag::FieldRef, Index::Ref(i1, i2)) => format!("Fieldref #{} #{}", i1, i2),
+        (Tag::MethodRef, Index::Ref(i1, i2)) => format!("Methodref #{} #{}", i1, i2),
+        (Tag::InterfaceMethodRef, Index::Ref(i1, i2)) => format!("InterfaceMethodref #{} #{}", i1, i2),
+        (Tag::NameAndType, Index::Pair(i1, i2)) => format!("NameAndType #{} #{}", i1, i2),
+        (Tag::MethodHandle, Index::MethodHandle(kind, i)) => format!("MethodHandle {} #{}", kind, i),
+        (Tag::MethodType, Index::Single(i)) => format!("MethodType #{}", i),
+        (Tag::Dynamic, Index::Dynamic(bsm, i)) => format!("Dynamic {} #{}", bsm, i),
+        (Tag::InvokeDynamic, Index::Dynamic(bsm, i)) => format!("InvokeDynamic {} #{}", bsm, i),
+        (Tag::Module, Index::Single(i)) => format!("Module #{}", i),
+        (Tag::Package, Index::Single(i)) => format!("Package #{}", i),
+        _ => return None,
+    };
+    Some(text)
+}
+
+fn raw_attribute_text(out: &mut String, indent: &str, attribute_name: &str, info: &[u8]) {
+    let _ = write!(out, "{}.attribute {}", indent, name(attribute_name));
+    if info.is_empty() {
+        out.push_str(" \"\"");
+    }
+    for chunk in info.chunks(HEX_CHUNK) {
+        let hex: String = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+        let _ = write!(out, "\n{}    \"{}\"", indent, hex);
+    }
+    out.push('\n');
+}
+
+pub fn disassemble(class_file: &ClassFile) -> String {
+    let cp = &class_file.constant_pool;
+    let mut out = String::new();
+    let _ = writeln!(out, ".version {} {}", class_file.version.1, class_file.version.0);
+    let _ = writeln!(out, ".class {}{}", flags_text(class_file.access_flags, &CLASS_KEYWORDS), name(&class_file.get_class_name()));
+    if let Some(super_class) = class_file.get_super_class_name() {
+        let _ = writeln!(out, ".super {}", name(&super_class));
+    }
+    for interface in class_file.get_interface_names() {
+        let _ = writeln!(out, ".implements {}", name(&interface));
+    }
+    out.push('\n');
+    let duplicates = duplicates(cp);
+    for i in 0..cp.constant_info.len() {
+        let index = i as u16 + 1;
+        if let Some(text) = pool_entry_text(cp, index) {
+            let _ = writeln!(out, ".const #{} = {}", index, text);
+        }
+    }
+    for f in &class_file.fields {
+        let _ = writeln!(out, "\n.field {}{} {}", flags_text(f.access_flags, &FIELD_KEYWORDS),
+            name(&cp.get_item(&f.name_index)), name(&cp.get_item(&f.descriptor_index)));
+        for a in &f.attributes {
+            attribute_text(&mut out, class_file, &duplicates, a, "    ");
+        }
+        out.push_str(".end field\n");
+    }
+    for m in &class_file.methods {
+        let _ = writeln!(out, "\n.method {}{} {}", flags_text(m.access_flags, &METHOD_KEYWORDS),
+            name(&cp.get_item(&m.name_index)), name(&cp.get_item(&m.descriptor_index)));
+        for a in &m.attributes {
+            match cp.get_item(&a.attribute_name_index).as_str() {
+                "Code" => code_text(&mut out, class_file, &duplicates, &a.info),
+                _ => attribute_text(&mut out, class_file, &duplicates, a, "    "),
+            }
+        }
+        out.push_str(".end method\n");
+    }
+    out.push('\n');
+    for a in &class_file.attributes {
+        attribute_text(&mut out, class_file, &duplicates, a, "");
+    }
+    out.push_str(".end class\n");
+    out
+}
+
+fn attribute_text(out: &mut String, class_file: &ClassFile, duplicates: &HashSet<u16>, a: &AttributeInfo, indent: &str) {
+    let cp = &class_file.constant_pool;
+    let attribute_name = cp.get_item(&a.attribute_name_index);
+    // the u16 indexes of the attribute, directives only for the ones that name the right kind of constant
+    let indexes: Vec<u16> = a.info.chunks_exact(2).map(|b| u16::from_be_bytes([b[0], b[1]])).collect();
+    let _ = match (attribute_name.as_str(), a.info.len()) {
+        ("SourceFile", 2) if cp.find_utf8(indexes[0]).is_some() =>
+            writeln!(out, "{}.sourcefile {}", indent, quote(&utf8(cp, indexes[0]))),
+        ("Signature", 2) if cp.find_utf8(indexes[0]).is_some() =>
+            writeln!(out, "{}.signature {}", indent, quote(&utf8(cp, indexes[0]))),
+        ("ConstantValue", 2) if cp.find_constant_value(indexes[0]).is_some() =>
+            writeln!(out, "{}.constantvalue {}", indent, operand_text(cp, duplicates, indexes[0])),
+        ("Deprecated", 0) => writeln!(out, "{}.deprecated", indent),
+        ("Synthetic", 0) => writeln!(out, "{}.synthetic", indent),
+        ("Exceptions", len) if len >= 2 && len == 2 + 2 * indexes[0] as usize
+                && indexes[1..].iter().all(|i| cp.find_class(*i).is_some()) => {
+            for i in &indexes[1..] {
+                let _ = writeln!(out, "{}.throws {}", indent, class_text(cp, duplicates, *i));
+            }
+            Ok(())
+        }
+        _ => {
+            raw_attribute_text(out, indent, &attribute_name, &a.info);
+            Ok(())
+        }
+    };
+}
+
+fn label(pc: u32) -> String {
+    format!("L{}", pc)
+}
+
+fn code_text(out: &mut String, class_file: &ClassFile, duplicates: &HashSet<u16>, info: &[u8]) {
+    let cp = &class_file.constant_pool;
+    let code = Code::new(info);
+    let instructions: Vec<(u32, &Opcode)> = code.get_instructions().map(|(pc, o)| (pc as u32, o)).collect();
+    let end = instructions.last().map(|(pc, o)| pc + o.length(*pc)).unwrap_or(0);
+    let starts: HashSet<u32> = instructions.iter().map(|(pc, _)| *pc).chain(std::iter::once(end)).collect();
+
+    let mut labels = HashSet::<u32>::new();
+    for (pc, o) in &instructions {
+        let target = |offset: i32| (*pc as i32 + offset) as u32;
+        match o {
+            Opcode::Tableswitch(default, _, _, offsets) => {
+                labels.insert(target(*default));
+                labels.extend(offsets.iter().map(|o| target(*o)));
+            }
+            Opcode::Lookupswitch(default, pairs) => {
+                labels.insert(target(*default));
+                labels.extend(pairs.iter().map(|(_, o)| target(*o)));
+            }
+            o => labels.extend(o.branch_offset().map(target)),
+        }
+    }
+    for e in code.get_exception_table() {
+        labels.extend([e.start_pc as u32, e.end_pc as u32, e.handler_pc as u32]);
+    }
+
+    // line and local variable tables become .line and .var when every offset is an instruction
+    let attributes = code.get_attributes();
+    let is_named = |a: &AttributeInfo, n: &str| cp.get_item(&a.attribute_name_index) == n;
+    let mut lines = HashMap::<u32, Vec<u16>>::new();
+    let mut inline = HashSet::<usize>::new();
+    if attributes.iter().filter(|a| is_named(a, "LineNumberTable")).count() == 1 {
+        let (i, a) = attributes.iter().enumerate().find(|(_, a)| is_named(a, "LineNumberTable")).unwrap();
+        let table = code::get_line_number_table(&a.info).unwrap_or_default();
+        if !table.is_empty() && a.info.len() == 2 + 4 * table.len() && table.iter().all(|l| starts.contains(&(l.start_pc as u32))) {
+            for l in table {
+                lines.entry(l.start_pc as u32).or_default().push(l.line_number);
+            }
+            inline.insert(i);
+        }
+    }
+    for (i, a) in attributes.iter().enumerate() {
+        if is_named(a, "LocalVariableTable") || is_named(a, "LocalVariableTypeTable") {
+            let table = code::get_local_variable_table(class_file, &a.info).unwrap_or_default();
+            let fits = !table.is_empty() && a.info.len() == 2 + 10 * table.len() && table.iter().all(|v| {
+                starts.contains(&(v.start_pc as u32)) && starts.contains(&(v.start_pc as u32 + v.length as u32))
+            });
+            if fits {
+                for v in &table {
+                    labels.extend([v.start_pc as u32, v.start_pc as u32 + v.length as u32]);
+                }
+                inline.insert(i);
+            }
+        }
+    }
+
+    let _ = writeln!(out, ".code stack {} locals {}", code.get_max_stack(), code.get_max_locals());
+    for (pc, o) in &instructions {
+        if labels.contains(pc) {
+            let _ = writeln!(out, "{}:", label(*pc));
+        }
+        for line in lines.get(pc).into_iter().flatten() {
+            let _ = writeln!(out, "    .line {}", line);
+        }
+        let _ = writeln!(out, "    {}", instruction_text(cp, duplicates, *pc, o));
+    }
+    if labels.contains(&end) {
+        let _ = writeln!(out, "{}:", label(end));
+    }
+    for e in code.get_exception_table() {
+        let catch_type = match e.catch_type {
+            0 => "any".to_string(),
+            i => class_text(cp, duplicates, i),
+        };
+        let _ = writeln!(out, "    .catch {} from {} to {} using {}", catch_type,
+            label(e.start_pc as u32), label(e.end_pc as u32), label(e.handler_pc as u32));
+    }
+    for (i, a) in attributes.iter().enumerate() {
+        if inline.contains(&i) {
+            if is_named(a, "LineNumberTable") {
+                continue;
+            }
+            let directive = if is_named(a, "LocalVariableTable") { ".var" } else { ".vartype" };
+            for v in code::get_local_variable_table(class_file, &a.info).unwrap_or_default() {
+                let _ = writeln!(out, "    {} {} is {} {} from {} to {}", directive, v.index, name(&v.name), name(&v.descriptor),
+                    label(v.start_pc as u32), label(v.start_pc as u32 + v.length as u32));
+            }
+        } else {
+            raw_attribute_text(out, "    ", &cp.get_item(&a.attribute_name_index), &a.info);
+        }
+    }
+    out.push_str(".end code\n");
+}
+
+pub(crate) fn instruction_text(cp: &ConstantPool, duplicates: &HashSet<u16>, pc: u32, o: &Opcode) -> String {
+    let target = |offset: i32| label((pc as i32 + offset) as u32);
+    let mnemonic = o.mnemonic();
+    match o {
+        Opcode::Getfield(i) | Opcode::Getstatic(i) | Opcode::Putfield(i) | Opcode::Putstatic(i)
+            | Opcode::Invokevirtual(i) | Opcode::Invokespecial(i) | Opcode::Invokestatic(i)
+            | Opcode::Invokedynamic(i) | Opcode::Ldc2W(i) | Opcode::LdcW(i) => format!("{} {}", mnemonic, operand_text(cp, duplicates, *i)),
+        Opcode::Ldc(i) => format!("{} {}", mnemonic, operand_text(cp, duplicates, *i as u16)),
+        Opcode::Invokeinterface(i, count) => format!("{} {} {}", mnemonic, operand_text(cp, duplicates, *i), count),
+        Opcode::New(i) | Opcode::Anewarray(i) | Opcode::Checkcast(i) | Opcode::Instanceof(i) =>
+            format!("{} {}", mnemonic, class_text(cp, duplicates, *i)),
+        Opcode::Multianewarray(i, dimensions) => format!("{} {} {}", mnemonic, class_text(cp, duplicates, *i), dimensions),
+        Opcode::Newarray(t) => match ARRAY_TYPES.get((*t as usize).wrapping_sub(4)) {
+            Some(t) => format!("{} {}", mnemonic, t),
+            None => format!("{} {}", mnemonic, t),
+        },
+        Opcode::Tableswitch(default, low, _, offsets) => {
+            let mut text = format!("{} {}", mnemonic, low);
+            for o in offsets {
+                let _ = write!(text, "\n        {}", target(*o));
+            }
+            let _ = write!(text, "\n        default {}", target(*default));
+            text
+        }
+        Opcode::Lookupswitch(default, pairs) => {
+            let mut text = mnemonic;
+            for (m, o) in pairs {
+                let _ = write!(text, "\n        {} {}", m, target(*o));
+            }
+            let _ = write!(text, "\n        default {}", target(*default));
+            text
+        }
+        o => match o.branch_offset() {
+            Some(offset) => format!("{} {}", mnemonic, target(offset)),
+            None => o.to_string(),
+        },
+    }
+}
+
+struct Token {
+    text: String,
+    quoted: bool,
+    line: usize,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::<Token>::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    while let Some(&c) = chars.peek() {
+        if c == '\n' {
+            line += 1;
+            chars.next();
+        } else if c.is_whitespace() {
+            chars.next();
+        } else if c == ';' {
+            while chars.peek().is_some_and(|c| *c != '\n') {
+                chars.next();
+            }
+        } else if c == '"' {
+            chars.next();
+            let mut s = String::new();
+            loop {
+                match chars.next() {
+                    None => return Err(ParseError { line, message: "unterminated string".to_string() }),
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => s.push('\n'),
+                        Some('r') => s.push('\r'),
+                        Some('t') => s.push('\t'),
+                        Some('u') => {
+                            let hex: String = chars.by_ref().skip(1).take_while(|c| *c != '}').collect();
+                            match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                                Some(c) => s.push(c),
+                                None => return Err(ParseError { line, message: format!("bad escape \\u{{{}}}", hex) }),
+                            }
+                        }
+                        Some(c) => s.push(c),
+                        None => (),
+                    },
+                    Some(c) => {
+                        if c == '\n' {
+                            line += 1;
+                        }
+                        s.push(c)
+                    }
+                }
+            }
+            tokens.push(Token { text: s, quoted: true, line });
+        } else {
+            let mut s = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '"' {
+                    break;
+                }
+                s.push(c);
+                chars.next();
+            }
+            tokens.push(Token { text: s, quoted: false, line });
+        }
+    }
+    Ok(tokens)
+}
+
+enum CodeAttribute {
+    Lines,
+    Vars(Vec<(u16, u16, u16, Label, Label)>),       // index, name, descriptor, from, to
+    VarTypes(Vec<(u16, u16, u16, Label, Label)>),
+    Raw(AttributeInfo),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    builder: ConstantPoolBuilder,
+}
+
+impl Parser {
+    // line of the token just read, which is the one an error is about
+    fn line(&self) -> usize {
+        self.tokens.get(self.pos.saturating_sub(1)).or(self.tokens.last()).map(|t| t.line).unwrap_or(0)
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, ParseError> {
+        Err(ParseError { line: self.line(), message })
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|t| t.text.as_str())
+    }
+
+    // a directive is a bare token starting with a dot
+    fn at_directive(&self) -> bool {
+        self.tokens.get(self.pos).is_some_and(|t| !t.quoted && t.text.starts_with('.'))
+    }
+
+    fn next(&mut self) -> Result<String, ParseError> {
+        match self.tokens.get(self.pos) {
+            Some(t) => {
+                self.pos += 1;
+                Ok(t.text.clone())
+            }
+            None => self.error("unexpected end of file".to_string()),
+        }
+    }
+
+    fn expect(&mut self, word: &str) -> Result<(), ParseError> {
+        let t = self.next()?;
+        match t == word {
+            true => Ok(()),
+            false => self.error(format!("expected {} but found {}", word, t)),
+        }
+    }
+
+    fn number<T: std::str::FromStr>(&mut self) -> Result<T, ParseError> {
+        let t = self.next()?;
+        let parsed = match t.strip_prefix("0x") {
+            Some(hex) => i64::from_str_radix(hex, 16).ok().and_then(|v| v.to_string().parse().ok()),
+            None => t.parse().ok(),
+        };
+        match parsed {
+            Some(v) => Ok(v),
+            None => self.error(format!("bad number {}", t)),
+        }
+    }
+
+    fn pool(&self, r: Result<u16, ConstantPoolError>) -> Result<u16, ParseError> {
+        r.or_else(|e| self.error(e.to_string()))
+    }
+
+    fn utf8(&mut self) -> Result<u16, ParseError> {
+        let s = self.next()?;
+        let r = self.builder.utf8(&s);
+        self.pool(r)
+    }
+
+    fn class(&mut self) -> Result<u16, ParseError> {
+        if let Some(i) = self.index()? {
+            return Ok(i);
+        }
+        let s = self.next()?;
+        let r = self.builder.class(&s);
+        self.pool(r)
+    }
+
+    // flags then the given number of names, up to the next directive
+    fn header(&mut self, keywords: &[(u16, &str)], names: usize) -> Result<(u16, Vec<String>), ParseError> {
+        let mut words = Vec::<String>::new();
+        while self.peek().is_some() && !self.at_directive() {
+            words.push(self.next()?);
+        }
+        if words.len() < names {
+            return self.error(format!("expected {} names", names));
+        }
+        let rest = words.split_off(words.len() - names);
+        let mut flags = 0;
+        for w in words {
+            flags |= match (keywords.iter().find(|(_, k)| *k == w), w.strip_prefix("0x")) {
+                (Some((flag, _)), _) => *flag,
+                (None, Some(hex)) => u16::from_str_radix(hex, 16).or_else(|_| self.error(format!("bad flags {}", w)))?,
+                _ => return self.error(format!("unknown flag {}", w)),
+            };
+        }
+        Ok((flags, rest))
+    }
+
+    // a constant by value, added to the pool if it is not there
+    fn constant(&mut self) -> Result<u16, ParseError> {
+        if let Some(i) = self.index()? {
+            return Ok(i);
+        }
+        let kind = self.next()?;
+        let r = match kind.as_str() {
+            "Utf8" => return self.utf8(),
+            "Int" => { let v = self.number::<i32>()?; self.builder.integer(v) }
+            "Float" => { let v = self.float::<f32>()?; self.builder.float(v) }
+            "FloatBits" => { let v = self.number::<u32>()?; self.builder.float(f32::from_bits(v)) }
+            "Long" => { let v = self.number::<i64>()?; self.builder.long(v) }
+            "Double" => { let v = self.float::<f64>()?; self.builder.double(v) }
+            "DoubleBits" => { let v = self.bits64()?; self.builder.double(f64::from_bits(v)) }
+            "Class" => return self.class(),
+            "String" => { let s = self.next()?; self.builder.string(&s) }
+            "Field" | "Method" | "InterfaceMethod" => {
+                let (c, n, d) = (self.next()?, self.next()?, self.next()?);
+                match kind.as_str() {
+                    "Field" => self.builder.field_ref(&c, &n, &d),
+                    "Method" => self.builder.method_ref(&c, &n, &d),
+                    _ => self.builder.interface_method_ref(&c, &n, &d),
+                }
+            }
+            "NameAndType" => { let (n, d) = (self.next()?, self.next()?); self.builder.name_and_type(&n, &d) }
+            "MethodHandle" => {
+                let k = self.next()?;
+                let kind = match HANDLE_KINDS.iter().position(|h| *h == k) {
+                    Some(i) => i as u8 + 1,
+                    None => return self.error(format!("unknown method handle kind {}", k)),
+                };
+                let reference = self.constant()?;
+                self.builder.method_handle(kind, reference)
+            }
+            "MethodType" => { let d = self.next()?; self.builder.method_type(&d) }
+            "Dynamic" | "InvokeDynamic" => {
+                let bsm = self.number::<u16>()?;
+                let (n, d) = (self.next()?, self.next()?);
+                match kind.as_str() {
+                    "Dynamic" => self.builder.dynamic(bsm, &n, &d),
+                    _ => self.builder.invoke_dynamic(bsm, &n, &d),
+                }
+            }
+            "Module" => { let n = self.next()?; self.builder.module(&n) }
+            "Package" => { let n = self.next()?; self.builder.package(&n) }
+            _ => return self.error(format!("unknown constant {}", kind)),
+        };
+        self.pool(r)
+    }
+
+    fn float<T: std::str::FromStr>(&mut self) -> Result<T, ParseError> {
+        let t = self.next()?;
+        t.parse().or_else(|_| self.error(format!("bad number {}", t)))
+    }
+
+    fn bits64(&mut self) -> Result<u64, ParseError> {
+        let t = self.next()?;
+        match t.strip_prefix("0x").map(|h| u64::from_str_radix(h, 16)) {
+            Some(Ok(v)) => Ok(v),
+            _ => self.error(format!("bad bits {}", t)),
+        }
+    }
+
+    // #n refers to the .const entry n itself
+    fn index(&mut self) -> Result<Option<u16>, ParseError> {
+        match self.tokens.get(self.pos) {
+            Some(t) if !t.quoted && t.text.starts_with('#') => self.pool_index().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn pool_index(&mut self) -> Result<u16, ParseError> {
+        let t = self.next()?;
+        match t.strip_prefix('#').and_then(|i| i.parse().ok()) {
+            Some(i) => Ok(i),
+            None => self.error(format!("expected a pool index but found {}", t)),
+        }
+    }
+
+    // .const #n = entry, with the indices of the entries it refers to
+    fn pool_entry(&mut self) -> Result<(u16, ConstantInfo, Option<LiteralInfo>), ParseError> {
+        let index = self.pool_index()?;
+        self.expect("=")?;
+        let kind = self.next()?;
+        let single = |p: &mut Parser, tag: Tag| -> Result<(ConstantInfo, Option<LiteralInfo>), ParseError> {
+            Ok((ConstantInfo(tag, Index::Single(p.pool_index()?)), None))
+        };
+        let literal = |tag: Tag, literal: LiteralInfo| (ConstantInfo(tag, Index::Single(index)), Some(literal));
+        let (info, literal) = match kind.as_str() {
+            "Utf8" => literal(Tag::Utf8, LiteralInfo::String(self.next()?)),
+            "Int" => literal(Tag::Integer, LiteralInfo::Integer(self.number::<i32>()? as u32)),
+            "Float" => literal(Tag::Float, LiteralInfo::Float(self.float()?)),
+            "FloatBits" => literal(Tag::Float, LiteralInfo::Float(f32::from_bits(self.number()?))),
+            "Long" => literal(Tag::Long, LiteralInfo::Long(self.number::<i64>()? as u64)),
+            "Double" => literal(Tag::Double, LiteralInfo::Double(self.float()?)),
+            "DoubleBits" => literal(Tag::Double, LiteralInfo::Double(f64::from_bits(self.bits64()?))),
+            "Class" => single(self, Tag::Class)?,
+            "String" => single(self, Tag::String)?,
+            "MethodType" => single(self, Tag::MethodType)?,
+            "Module" => single(self, Tag::Module)?,
+            "Package" => single(self, Tag::Package)?,
+            "Fieldref" => (ConstantInfo(Tag::FieldRef, Index::Ref(self.pool_index()?, self.pool_index()?)), None),
+            "Methodref" => (ConstantInfo(Tag::MethodRef, Index::Ref(self.pool_index()?, self.pool_index()?)), None),
+            "InterfaceMethodref" => (ConstantInfo(Tag::InterfaceMethodRef, Index::Ref(self.pool_index()?, self.pool_index()?)), None),
+            "NameAndType" => (ConstantInfo(Tag::NameAndType, Index::Pair(self.pool_index()?, self.pool_index()?)), None),
+            "MethodHandle" => (ConstantInfo(Tag::MethodHandle, Index::MethodHandle(self.number()?, self.pool_index()?)), None),
+            "Dynamic" => (ConstantInfo(Tag::Dynamic, Index::Dynamic(self.number()?, self.pool_index()?)), None),
+            "InvokeDynamic" => (ConstantInfo(Tag::InvokeDynamic, Index::Dynamic(self.number()?, self.pool_index()?)), None),
+            _ => return self.error(format!("unknown pool entry {}", kind)),
+        };
+        Ok((index, info, literal))
+    }
+
+    // every .const in the file, before anything is added by value
+    fn read_pool(&mut self) -> Result<ConstantPool, ParseError> {
+        let mut entries = Vec::<Option<ConstantInfo>>::new();
+        let mut pool = ConstantPool::empty();
+        while self.pos < self.tokens.len() {
+            if self.at_directive() && self.peek() == Some(".const") {
+                self.pos += 1;
+                let (index, info, literal) = self.pool_entry()?;
+                if index == 0 {
+                    return self.error("pool index 0".to_string());
+                }
+                let wide = info.0 == Tag::Long || info.0 == Tag::Double;
+                let slot = index as usize - 1;
+                let needed = slot + if wide { 2 } else { 1 };
+                if entries.len() < needed {
+                    entries.resize(needed, None);
+                }
+                entries[slot] = Some(info);
+                if wide {
+                    entries[slot + 1] = Some(ConstantInfo(Tag::Unusable, Index::Single(0)));
+                }
+                if let Some(literal) = literal {
+                    pool.literal_pool.insert(index, literal);
+                }
+            } else {
+                self.pos += 1;
+            }
+        }
+        self.pos = 0;
+        for (i, e) in entries.into_iter().enumerate() {
+            match e {
+                Some(e) => pool.constant_info.push(e),
+                None => return Err(ParseError { line: 0, message: format!("pool entry #{} is missing", i + 1) }),
+            }
+        }
+        Ok(pool)
+    }
+
+    fn skip_pool_entry(&mut self) -> Result<(), ParseError> {
+        self.pool_entry().map(|_| ())
+    }
+
+    fn raw_attribute(&mut self) -> Result<AttributeInfo, ParseError> {
+        let name_index = self.utf8()?;
+        let mut info = Vec::<u8>::new();
+        while self.tokens.get(self.pos).is_some_and(|t| t.quoted) {
+            let hex = self.next()?;
+            for i in (0..hex.len()).step_by(2) {
+                match hex.get(i..i + 2).map(|b| u8::from_str_radix(b, 16)) {
+                    Some(Ok(b)) => info.push(b),
+                    _ => return self.error(format!("bad hex {}", hex)),
+                }
+            }
+        }
+        Ok(AttributeInfo { attribute_name_index: Index::Single(name_index), info })
+    }
+
+    fn named_attribute(&mut self, attribute_name: &str, info: Vec<u8>) -> Result<AttributeInfo, ParseError> {
+        let r = self.builder.utf8(attribute_name);
+        Ok(AttributeInfo { attribute_name_index: Index::Single(self.pool(r)?), info })
+    }
+
+    // the attribute directives of a class, field or method up to .end or the next member
+    // method is the descriptor and whether it is static, for a .code that leaves out its limits
+    fn attributes(&mut self, end: &str, method: Option<(&str, bool)>) -> Result<Vec<AttributeInfo>, ParseError> {
+        let mut attributes = Vec::<AttributeInfo>::new();
+        let mut throws: Option<(usize, Vec<u16>)> = None;
+        loop {
+            if !self.at_directive() {
+                match self.peek() {
+                    None if end == ".end class" => break,
+                    t => return Err(ParseError {
+                        line: self.tokens.get(self.pos).map_or(self.line(), |t| t.line),
+                        message: format!("expected a directive but found {}", t.unwrap_or("end of file")),
+                    }),
+                }
+            }
+            let directive = self.next()?;
+            match directive.as_str() {
+                ".end" => {
+                    let what = self.next()?;
+                    match end == format!(".end {}", what) {
+                        true => break,
+                        false => return self.error(format!("expected {} but found .end {}", end, what)),
+                    }
+                }
+                ".const" if end == ".end class" => self.skip_pool_entry()?,
+                ".field" | ".method" if end == ".end class" => {
+                    self.pos -= 1;
+                    break;
+                }
+                ".sourcefile" | ".signature" => {
+                    let i = self.utf8()?;
+                    let n = if directive == ".sourcefile" { "SourceFile" } else { "Signature" };
+                    attributes.push(self.named_attribute(n, i.to_be_bytes().to_vec())?);
+                }
+                ".constantvalue" => {
+                    let i = self.constant()?;
+                    attributes.push(self.named_attribute("ConstantValue", i.to_be_bytes().to_vec())?);
+                }
+                ".deprecated" => attributes.push(self.named_attribute("Deprecated", Vec::new())?),
+                ".synthetic" => attributes.push(self.named_attribute("Synthetic", Vec::new())?),
+                ".throws" => {
+                    let class = self.class()?;
+                    match throws.as_mut() {
+                        Some((_, classes)) => classes.push(class),
+                        None => {
+                            attributes.push(self.named_attribute("Exceptions", Vec::new())?);
+                            throws = Some((attributes.len() - 1, vec![class]));
+                        }
+                    }
+                }
+                ".attribute" => attributes.push(self.raw_attribute()?),
+                ".code" if method.is_some() => {
+                    let info = self.code(method.unwrap())?;
+                    attributes.push(self.named_attribute("Code", info)?);
+                }
+                _ => return self.error(format!("unexpected {}", directive)),
+            }
+        }
+        if let Some((i, classes)) = throws {
+            let mut writer = ClassFileWriter::new();
+            writer.write_u16(classes.len() as u16);
+            classes.iter().for_each(|c| writer.write_u16(*c));
+            attributes[i].info = writer.into_bytes();
+        }
+        Ok(attributes)
+    }
+
+    // .code stack N locals M, or just .code to have them computed
+    fn code(&mut self, (descriptor, is_static): (&str, bool)) -> Result<Vec<u8>, ParseError> {
+        let line = self.line();
+        let declared = match self.peek() {
+            Some("stack") => {
+                self.pos += 1;
+                let max_stack = self.number::<u16>()?;
+                self.expect("locals")?;
+                Some((max_stack, self.number::<u16>()?))
+            }
+            _ => None,
+        };
+
+        let mut assembler = Assembler::new();
+        let mut labels = HashMap::<String, Label>::new();
+        let mut label = |assembler: &mut Assembler, name: String| *labels.entry(name).or_insert_with(|| assembler.new_label());
+        let mut code_attributes = Vec::<CodeAttribute>::new();
+        loop {
+            let t = self.next()?;
+            if let Some(l) = t.strip_suffix(':') {
+                let l = label(&mut assembler, l.to_string());
+                assembler.place(l);
+                continue;
+            }
+            match t.as_str() {
+                ".end" => {
+                    self.expect("code")?;
+                    break;
+                }
+                ".line" => {
+                    let line = self.number()?;
+                    if !code_attributes.iter().any(|a| matches!(a, CodeAttribute::Lines)) {
+                        code_attributes.push(CodeAttribute::Lines);
+                    }
+                    assembler.push(Instruction::Line(line));
+                }
+                ".catch" => {
+                    let catch_type = match self.peek() {
+                        Some("any") => { self.pos += 1; 0 }
+                        _ => self.class()?,
+                    };
+                    self.expect("from")?;
+                    let start = label(&mut assembler, self.next()?);
+                    self.expect("to")?;
+                    let end = label(&mut assembler, self.next()?);
+                    self.expect("using")?;
+                    let handler = label(&mut assembler, self.next()?);
+                    assembler.handler(start, end, handler, catch_type);
+                }
+                ".var" | ".vartype" => {
+                    let index = self.number()?;
+                    self.expect("is")?;
+                    let (n, d) = (self.utf8()?, self.utf8()?);
+                    self.expect("from")?;
+                    let from = label(&mut assembler, self.next()?);
+                    self.expect("to")?;
+                    let to = label(&mut assembler, self.next()?);
+                    let var = (index, n, d, from, to);
+                    let found = code_attributes.iter_mut().find_map(|a| match (a, t.as_str()) {
+                        (CodeAttribute::Vars(v), ".var") | (CodeAttribute::VarTypes(v), ".vartype") => Some(v),
+                        _ => None,
+                    });
+                    match (found, t.as_str()) {
+                        (Some(v), _) => v.push(var),
+                        (None, ".var") => code_attributes.push(CodeAttribute::Vars(vec![var])),
+                        (None, _) => code_attributes.push(CodeAttribute::VarTypes(vec![var])),
+                    }
+                }
+                ".attribute" => code_attributes.push(CodeAttribute::Raw(self.raw_attribute()?)),
+                _ => {
+                    let instruction = self.instruction(&t, &mut |a, n| label(a, n), &mut assembler)?;
+                    assembler.push(instruction);
+                }
+            }
+        }
+
+        let assembled = match assembler.assemble() {
+            Ok(a) => a,
+            Err(AssembleError::UnplacedLabel(l)) => {
+                let n = labels.iter().find(|(_, v)| **v == l).map(|(k, _)| k.clone()).unwrap_or_default();
+                return self.error(format!("label {} is never placed", n));
+            }
+            Err(e) => return self.error(e.to_string()),
+        };
+        let mut attributes = Vec::<AttributeInfo>::new();
+        for a in code_attributes {
+            let table_name = match a {
+                CodeAttribute::Vars(_) => "LocalVariableTable",
+                _ => "LocalVariableTypeTable",
+            };
+            let (attribute_name, info) = match a {
+                CodeAttribute::Lines => ("LineNumberTable", assembled.line_number_table()),
+                CodeAttribute::Vars(vars) | CodeAttribute::VarTypes(vars) => {
+                    let mut writer = ClassFileWriter::new();
+                    writer.write_u16(vars.len() as u16);
+                    for (index, n, d, from, to) in &vars {
+                        let start = assembled.get_offset(*from).unwrap_or(0);
+                        writer.write_u16(start);
+                        writer.write_u16(assembled.get_offset(*to).unwrap_or(0).wrapping_sub(start));
+                        writer.write_u16(*n);
+                        writer.write_u16(*d);
+                        writer.write_u16(*index);
+                    }
+                    (table_name, writer.into_bytes())
+                }
+                CodeAttribute::Raw(a) => {
+                    attributes.push(a);
+                    continue;
+                }
+            };
+            attributes.push(self.named_attribute(attribute_name, info)?);
+        }
+        let (max_stack, max_locals) = match declared {
+            Some(d) => d,
+            None => {
+                let code = Code::new(&assembled.code_attribute(0, 0, &[]));
+                match limits::compute(self.builder.get_pool(), &code, descriptor, is_static) {
+                    Ok(l) => (l.max_stack, l.max_locals),
+                    Err(e) => return Err(ParseError { line, message: e.to_string() }),
+                }
+            }
+        };
+        Ok(assembled.code_attribute(max_stack, max_locals, &attributes))
+    }
+
+    fn instruction(&mut self, mnemonic: &str, label: &mut dyn FnMut(&mut Assembler, String) -> Label,
+            assembler: &mut Assembler) -> Result<Instruction, ParseError> {
+        let template = match opcode::from_mnemonic(mnemonic) {
+            Some(o) => o,
+            None => return self.error(format!("unknown instruction {}", mnemonic)),
+        };
+        let instruction = match template {
+            Opcode::Aload(_) => Instruction::Op(Opcode::Aload(self.number()?)),
+            Opcode::Astore(_) => Instruction::Op(Opcode::Astore(self.number()?)),
+            Opcode::Dload(_) => Instruction::Op(Opcode::Dload(self.number()?)),
+            Opcode::Dstore(_) => Instruction::Op(Opcode::Dstore(self.number()?)),
+            Opcode::Fload(_) => Instruction::Op(Opcode::Fload(self.number()?)),
+            Opcode::Fstore(_) => Instruction::Op(Opcode::Fstore(self.number()?)),
+            Opcode::Iload(_) => Instruction::Op(Opcode::Iload(self.number()?)),
+            Opcode::Istore(_) => Instruction::Op(Opcode::Istore(self.number()?)),
+            Opcode::Lload(_) => Instruction::Op(Opcode::Lload(self.number()?)),
+            Opcode::Lstore(_) => Instruction::Op(Opcode::Lstore(self.number()?)),
+            Opcode::Ret(_) => Instruction::Op(Opcode::Ret(self.number()?)),
+            Opcode::Bipush(_) => Instruction::Op(Opcode::Bipush(self.number()?)),
+            Opcode::Sipush(_) => Instruction::Op(Opcode::Sipush(self.number()?)),
+            Opcode::Getfield(_) => Instruction::Op(Opcode::Getfield(self.constant()?)),
+            Opcode::Getstatic(_) => Instruction::Op(Opcode::Getstatic(self.constant()?)),
+            Opcode::Putfield(_) => Instruction::Op(Opcode::Putfield(self.constant()?)),
+            Opcode::Putstatic(_) => Instruction::Op(Opcode::Putstatic(self.constant()?)),
+            Opcode::Invokevirtual(_) => Instruction::Op(Opcode::Invokevirtual(self.constant()?)),
+            Opcode::Invokespecial(_) => Instruction::Op(Opcode::Invokespecial(self.constant()?)),
+            Opcode::Invokestatic(_) => Instruction::Op(Opcode::Invokestatic(self.constant()?)),
+            Opcode::Invokedynamic(_) => Instruction::Op(Opcode::Invokedynamic(self.constant()?)),
+            Opcode::Invokeinterface(..) => {
+                let i = self.constant()?;
+                Instruction::Op(Opcode::Invokeinterface(i, self.number()?))
+            }
+            Opcode::New(_) => Instruction::Op(Opcode::New(self.class()?)),
+            Opcode::Anewarray(_) => Instruction::Op(Opcode::Anewarray(self.class()?)),
+            Opcode::Checkcast(_) => Instruction::Op(Opcode::Checkcast(self.class()?)),
+            Opcode::Instanceof(_) => Instruction::Op(Opcode::Instanceof(self.class()?)),
+            Opcode::Multianewarray(..) => {
+                let i = self.class()?;
+                Instruction::Op(Opcode::Multianewarray(i, self.number()?))
+            }
+            Opcode::Newarray(_) => {
+                let t = self.next()?;
+                match (ARRAY_TYPES.iter().position(|a| *a == t), t.parse::<u8>()) {
+                    (Some(i), _) => Instruction::Op(Opcode::Newarray(i as u8 + 4)),
+                    (None, Ok(n)) => Instruction::Op(Opcode::Newarray(n)),
+                    _ => return self.error(format!("unknown array type {}", t)),
+                }
+            }
+            Opcode::Ldc(_) => Instruction::Ldc(self.constant()?),
+            Opcode::LdcW(_) => Instruction::Op(Opcode::LdcW(self.constant()?)),
+            Opcode::Ldc2W(_) => Instruction::Op(Opcode::Ldc2W(self.constant()?)),
+            Opcode::Iinc(..) => {
+                let i = self.number()?;
+                Instruction::Iinc(i, self.number()?)
+            }
+            Opcode::Wide(..) => {
+                let m = self.next()?;
+                let index = self.number()?;
+                match opcode::from_mnemonic(&m) {
+                    Some(Opcode::Iinc(..)) => Instruction::Op(Opcode::WideIinc(index, self.number()?)),
+                    Some(o) => Instruction::Op(Opcode::Wide(o.opcode(), index)),
+                    None => return self.error(format!("unknown instruction {}", m)),
+                }
+            }
+            Opcode::Tableswitch(..) => {
+                let low = self.number()?;
+                let mut targets = Vec::<Label>::new();
+                while self.peek() != Some("default") {
+                    targets.push(label(assembler, self.next()?));
+                }
+                self.pos += 1;
+                Instruction::Tableswitch(label(assembler, self.next()?), low, targets)
+            }
+            Opcode::Lookupswitch(..) => {
+                let mut pairs = Vec::<(i32, Label)>::new();
+                while self.peek() != Some("default") {
+                    let m = self.number()?;
+                    pairs.push((m, label(assembler, self.next()?)));
+                }
+                self.pos += 1;
+                Instruction::Lookupswitch(label(assembler, self.next()?), pairs)
+            }
+            o if o.branch_offset().is_some() => Instruction::Branch(o, label(assembler, self.next()?)),
+            o => Instruction::Op(o),
+        };
+        Ok(instruction)
+    }
+
+    fn class_file(&mut self) -> Result<ClassFile, ParseError> {
+        let mut version = JavaVersion(0, 52);
+        let mut access_flags = 0;
+        let mut this_class = None;
+        let mut super_class = 0;
+        let mut interfaces = Vec::<Index>::new();
+        let mut fields = Vec::<FieldInfo>::new();
+        let mut methods = Vec::<MethodInfo>::new();
+        let mut attributes = Vec::<AttributeInfo>::new();
+
+        while self.peek().is_some() {
+            match self.peek() {
+                Some(".version") => {
+                    self.pos += 1;
+                    let major = self.number()?;
+                    version = JavaVersion(self.number()?, major);
+                }
+                Some(".class") => {
+                    self.pos += 1;
+                    let (flags, names) = self.header(&CLASS_KEYWORDS, 1)?;
+                    access_flags = flags;
+                    let r = self.builder.class(&names[0]);
+                    this_class = Some(self.pool(r)?);
+                }
+                Some(".super") => {
+                    self.pos += 1;
+                    super_class = self.class()?;
+                }
+                Some(".implements") => {
+                    self.pos += 1;
+                    interfaces.push(Index::Single(self.class()?));
+                }
+                Some(".const") => {
+                    self.pos += 1;
+                    self.skip_pool_entry()?;
+                }
+                Some(".field") | Some(".method") => {
+                    let method = self.next()? == ".method";
+                    let keywords: &[(u16, &str)] = if method { &METHOD_KEYWORDS } else { &FIELD_KEYWORDS };
+                    let (flags, names) = self.header(keywords, 2)?;
+                    let r = self.builder.utf8(&names[0]);
+                    let name_index = Index::Single(self.pool(r)?);
+                    let r = self.builder.utf8(&names[1]);
+                    let descriptor_index = Index::Single(self.pool(r)?);
+                    let member_attributes = match method {
+                        true => self.attributes(".end method", Some((&names[1], flags & ACC_STATIC != 0)))?,
+                        false => self.attributes(".end field", None)?,
+                    };
+                    match method {
+                        true => methods.push(MethodInfo { access_flags: flags, name_index, descriptor_index, attributes: member_attributes }),
+                        false => fields.push(FieldInfo { access_flags: flags, name_index, descriptor_index, attributes: member_attributes }),
+                    }
+                }
+                _ => attributes.extend(self.attributes(".end class", None)?),
+            }
+        }
+
+        let this_class = match this_class {
+            Some(c) => c,
+            None => return Err(ParseError { line: 0, message: "no .class".to_string() }),
+        };
+        let builder = std::mem::take(&mut self.builder);
+        Ok(ClassFile {
+            version,
+            constant_pool: builder.build(),
+            access_flags,
+            this_class: Index::Single(this_class),
+            super_class: Index::Single(super_class),
+            interfaces,
+            fields,
+            methods,
+            attributes,
+            decoders: Vec::new(),
+        })
+    }
+}
+
+pub fn assemble(text: &str) -> Result<ClassFile, ParseError> {
+    let mut parser = Parser { tokens: tokenize(text)?, pos: 0, builder: ConstantPoolBuilder::new() };
+    let pool = parser.read_pool()?;
+    parser.builder = ConstantPoolBuilder::from_pool(&pool);
+    parser.class_file()
+}
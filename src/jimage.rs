@@ -0,0 +1,318 @@
+// module jimage
+// the JDK's runtime image, $JAVA_HOME/lib/modules, resources named /java.base/java/lang/Object.class
+// only the header and index are held in memory, resources are read from the file as they are asked for
+use std::cell::RefCell;
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use crate::zip;
+
+const MAGIC: u32 = 0xcafedada;
+const HEADER_SIZE: usize = 28;
+const HASH_MULTIPLIER: u32 = 0x01000193;
+const COMPRESSED_MAGIC: u32 = 0xcafefafa;
+const COMPRESSED_HEADER_SIZE: usize = 29;
+
+// location attribute kinds
+const ATTRIBUTE_END: u8 = 0;
+const ATTRIBUTE_MODULE: usize = 1;
+const ATTRIBUTE_PARENT: usize = 2;
+const ATTRIBUTE_BASE: usize = 3;
+const ATTRIBUTE_EXTENSION: usize = 4;
+const ATTRIBUTE_OFFSET: usize = 5;
+const ATTRIBUTE_COMPRESSED: usize = 6;
+const ATTRIBUTE_UNCOMPRESSED: usize = 7;
+
+// compact-cp, strings moved out of the constant pool into the image's string table
+const EXTERNALIZED_STRING: u8 = 23;
+const EXTERNALIZED_STRING_DESCRIPTOR: u8 = 25;
+
+#[derive(Debug)]
+pub enum ImageError {
+    Io(std::io::Error),
+    NotImage,
+    Version(u16, u16),
+    BadIndex,               // tables outside the file
+    BadResource(String),    // resource data or its compression damaged
+    Unsupported(String, String),    // resource, decompressor
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImageError::Io(e) => write!(f, "{}", e),
+            ImageError::NotImage => write!(f, "not a jimage file"),
+            ImageError::Version(major, minor) => write!(f, "jimage version {}.{} is not supported", major, minor),
+            ImageError::BadIndex => write!(f, "damaged jimage index"),
+            ImageError::BadResource(name) => write!(f, "damaged resource {}", name),
+            ImageError::Unsupported(name, decompressor) => write!(f, "resource {} uses decompressor {}", name, decompressor),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+pub struct Image {
+    file: RefCell<File>,
+    big_endian: bool,
+    index: Vec<u8>,     // header, redirect and offset tables, locations and strings
+    table_length: usize,
+    locations: usize,   // where each table starts in index
+    strings: usize,
+}
+
+// ImageStringsReader.hashCode, over the modified UTF-8 of the name
+fn hash(name: &str, seed: u32) -> u32 {
+    let mut h = seed;
+    for b in name.bytes() {
+        h = h.wrapping_mul(HASH_MULTIPLIER) ^ b as u32;
+    }
+    h & 0x7fffffff
+}
+
+impl Image {
+    pub fn open(path: &Path) -> Result<Self, ImageError> {
+        let mut file = File::open(path).map_err(ImageError::Io)?;
+        let mut header = [0u8; HEADER_SIZE];
+        file.read_exact(&mut header).map_err(|_| ImageError::NotImage)?;
+        let big_endian = match header[..4] {
+            [0xca, 0xfe, 0xda, 0xda] => true,
+            [0xda, 0xda, 0xfe, 0xca] => false,
+            _ => return Err(ImageError::NotImage),
+        };
+        let field = |i: usize| {
+            let b = [header[4 * i], header[4 * i + 1], header[4 * i + 2], header[4 * i + 3]];
+            if big_endian { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) }
+        };
+        debug_assert_eq!(field(0), MAGIC);
+        let (major, minor) = ((field(1) >> 16) as u16, field(1) as u16);
+        if major != 1 {
+            return Err(ImageError::Version(major, minor));
+        }
+        let table_length = field(4) as usize;
+        let locations = HEADER_SIZE + 8 * table_length;
+        let strings = locations + field(5) as usize;
+        let mut index = header.to_vec();
+        index.resize(strings + field(6) as usize, 0);
+        file.read_exact(&mut index[HEADER_SIZE..]).map_err(|_| ImageError::BadIndex)?;
+        Ok(Image { file: RefCell::new(file), big_endian, index, table_length, locations, strings })
+    }
+
+    fn u32_at(&self, at: usize) -> Option<u32> {
+        let b: [u8; 4] = self.index.get(at..at + 4)?.try_into().ok()?;
+        Some(if self.big_endian { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) })
+    }
+
+    // NUL terminated modified UTF-8 in the string table
+    fn string_bytes(&self, offset: usize) -> Option<&[u8]> {
+        let start = self.strings + offset;
+        let rest = self.index.get(start..)?;
+        Some(&rest[..rest.iter().position(|b| *b == 0)?])
+    }
+
+    fn string(&self, offset: usize) -> String {
+        String::from_utf8_lossy(self.string_bytes(offset).unwrap_or_default()).to_string()
+    }
+
+    // attribute values of the location at offset, indexed by kind
+    fn location(&self, offset: usize) -> Option<[usize; 8]> {
+        let mut attributes = [0usize; 8];
+        let mut at = self.locations + offset;
+        loop {
+            let byte = *self.index.get(at)?;
+            let kind = byte >> 3;
+            if kind == ATTRIBUTE_END {
+                return Some(attributes);
+            }
+            let length = (byte & 7) as usize + 1;
+            let value = self.index.get(at + 1..at + 1 + length)?.iter().fold(0usize, |v, b| v << 8 | *b as usize);
+            *attributes.get_mut(kind as usize)? = value;
+            at += 1 + length;
+        }
+    }
+
+    fn location_name(&self, attributes: &[usize; 8]) -> String {
+        let mut name = String::new();
+        if attributes[ATTRIBUTE_MODULE] != 0 {
+            name.push_str(&format!("/{}/", self.string(attributes[ATTRIBUTE_MODULE])));
+        }
+        if attributes[ATTRIBUTE_PARENT] != 0 {
+            name.push_str(&format!("{}/", self.string(attributes[ATTRIBUTE_PARENT])));
+        }
+        name.push_str(&self.string(attributes[ATTRIBUTE_BASE]));
+        if attributes[ATTRIBUTE_EXTENSION] != 0 {
+            name.push_str(&format!(".{}", self.string(attributes[ATTRIBUTE_EXTENSION])));
+        }
+        name
+    }
+
+    fn find(&self, name: &str) -> Option<[usize; 8]> {
+        if self.table_length == 0 {
+            return None;
+        }
+        let redirect = self.u32_at(HEADER_SIZE + 4 * (hash(name, HASH_MULTIPLIER) as usize % self.table_length))? as i32;
+        let i = match redirect {
+            0 => return None,
+            r if r < 0 => (-r - 1) as usize,
+            r => hash(name, r as u32) as usize % self.table_length,
+        };
+        let offset = self.u32_at(HEADER_SIZE + 4 * self.table_length + 4 * i)? as usize;
+        let attributes = self.location(offset)?;
+        match self.location_name(&attributes) == name {
+            true => Some(attributes),
+            false => None,
+        }
+    }
+
+    // every resource, /module/path/name.extension, in table order
+    pub fn get_names(&self) -> Vec<String> {
+        (0..self.table_length)
+            .filter_map(|i| self.u32_at(HEADER_SIZE + 4 * self.table_length + 4 * i))
+            .filter_map(|offset| self.location(offset as usize))
+            .map(|attributes| self.location_name(&attributes))
+            .collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.find(name).is_some()
+    }
+
+    // the resource by name, None when there is no such resource
+    pub fn read_entry(&self, name: &str) -> Option<Result<Vec<u8>, ImageError>> {
+        let attributes = self.find(name)?;
+        Some(self.read(name, &attributes))
+    }
+
+    fn read(&self, name: &str, attributes: &[usize; 8]) -> Result<Vec<u8>, ImageError> {
+        let stored = match attributes[ATTRIBUTE_COMPRESSED] {
+            0 => attributes[ATTRIBUTE_UNCOMPRESSED],
+            compressed => compressed,
+        };
+        let mut bytes = vec![0u8; stored];
+        let mut file = self.file.borrow_mut();
+        file.seek(SeekFrom::Start((self.index.len() + attributes[ATTRIBUTE_OFFSET]) as u64)).map_err(ImageError::Io)?;
+        file.read_exact(&mut bytes).map_err(|_| ImageError::BadResource(name.to_string()))?;
+        if attributes[ATTRIBUTE_COMPRESSED] == 0 {
+            return Ok(bytes);
+        }
+        // compression may be stacked, each layer with its own header
+        let order = |b: &[u8]| -> u64 {
+            match self.big_endian {
+                true => b.iter().fold(0, |v, x| v << 8 | *x as u64),
+                false => b.iter().rev().fold(0, |v, x| v << 8 | *x as u64),
+            }
+        };
+        while bytes.len() >= COMPRESSED_HEADER_SIZE && order(&bytes[..4]) == COMPRESSED_MAGIC as u64 {
+            let size = order(&bytes[12..20]) as usize;
+            let decompressor = self.string(order(&bytes[20..24]) as usize);
+            let content = &bytes[COMPRESSED_HEADER_SIZE..];
+            let decompressed = match decompressor.as_str() {
+                // zlib stream from java.util.zip.Deflater
+                "zip" => content.get(2..).and_then(|deflated| zip::inflate(deflated, size)),
+                "compact-cp" => self.expand_strings(content),
+                _ => return Err(ImageError::Unsupported(name.to_string(), decompressor)),
+            };
+            bytes = decompressed.ok_or_else(|| ImageError::BadResource(name.to_string()))?;
+        }
+        match bytes.len() == attributes[ATTRIBUTE_UNCOMPRESSED] {
+            true => Ok(bytes),
+            false => Err(ImageError::BadResource(name.to_string())),
+        }
+    }
+
+    // StringSharingDecompressor, put the strings a class file's constant pool shares with the image back
+    fn expand_strings(&self, content: &[u8]) -> Option<Vec<u8>> {
+        let mut input = Input { bytes: content, at: 0 };
+        let mut out = Vec::with_capacity(content.len() * 2);
+        out.extend_from_slice(input.take(8)?);
+        let count = input.u16()?;
+        out.extend_from_slice(&count.to_be_bytes());
+        let utf8 = |out: &mut Vec<u8>, s: &[u8]| {
+            out.push(1);
+            out.extend_from_slice(&(s.len() as u16).to_be_bytes());
+            out.extend_from_slice(s);
+        };
+        let mut i = 1;
+        while i < count {
+            let tag = input.take(1)?[0];
+            match tag {
+                1 => {
+                    let length = input.u16()? as usize;
+                    utf8(&mut out, input.take(length)?);
+                }
+                EXTERNALIZED_STRING => {
+                    let offset = input.compressed_int()?;
+                    utf8(&mut out, self.string_bytes(offset)?);
+                }
+                EXTERNALIZED_STRING_DESCRIPTOR => {
+                    // the descriptor with each class name held as package and simple name
+                    let descriptor = self.string_bytes(input.compressed_int()?)?;
+                    let length = input.compressed_int()?;
+                    let mut indexes = Input { bytes: input.take(length)?, at: 0 };
+                    let mut expanded = Vec::new();
+                    for c in descriptor {
+                        expanded.push(*c);
+                        if *c == b'L' {
+                            let package = self.string_bytes(indexes.compressed_int()?)?;
+                            if !package.is_empty() {
+                                expanded.extend_from_slice(package);
+                                expanded.push(b'/');
+                            }
+                            expanded.extend_from_slice(self.string_bytes(indexes.compressed_int()?)?);
+                        }
+                    }
+                    utf8(&mut out, &expanded);
+                }
+                _ => {
+                    let size = match tag {
+                        3 | 4 | 9..=12 | 17 | 18 => 4,
+                        5 | 6 => 8,
+                        7 | 8 | 16 | 19 | 20 => 2,
+                        15 => 3,
+                        _ => return None,
+                    };
+                    if tag == 5 || tag == 6 {
+                        i += 1;
+                    }
+                    out.push(tag);
+                    out.extend_from_slice(input.take(size)?);
+                }
+            }
+            i += 1;
+        }
+        out.extend_from_slice(&content[input.at..]);
+        Some(out)
+    }
+}
+
+struct Input<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Input<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let taken = self.bytes.get(self.at..self.at + n)?;
+        self.at += n;
+        Some(taken)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        let b = self.take(2)?;
+        Some(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    // CompressIndexes, a byte with the top bit set holds the length in the next two bits and the high value bits in the rest
+    fn compressed_int(&mut self) -> Option<usize> {
+        let header = self.take(1)?[0];
+        let (length, mut value) = match header & 0x80 != 0 {
+            true => ((header >> 5) as usize & 3, (header & 0x1f) as usize),
+            false => (4, header as usize),
+        };
+        for b in self.take(length.checked_sub(1)?)? {
+            value = value << 8 | *b as usize;
+        }
+        Some(value)
+    }
+}
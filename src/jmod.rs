@@ -0,0 +1,123 @@
+// module jmod
+// jmod files from $JAVA_HOME/jmods, a 4 byte JM header and a zip with an entry directory for each section
+use std::fmt;
+use std::path::Path;
+use crate::class_file_reader::ClassFileError;
+use crate::zip::{ZipError, ZipFile};
+use crate::ClassFile;
+
+const JMOD_MAGIC: [u8; 4] = [b'J', b'M', 1, 0];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Classes,
+    Config,
+    HeaderFiles,
+    LegalNotices,
+    ManPages,
+    NativeLibraries,
+    NativeCommands,
+}
+
+// directory of each section in the zip
+const SECTIONS: [(&str, Section); 7] = [
+    ("classes/", Section::Classes),
+    ("conf/", Section::Config),
+    ("include/", Section::HeaderFiles),
+    ("legal/", Section::LegalNotices),
+    ("man/", Section::ManPages),
+    ("lib/", Section::NativeLibraries),
+    ("bin/", Section::NativeCommands),
+];
+
+impl fmt::Display for Section {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Section::Classes => write!(f, "classes"),
+            Section::Config => write!(f, "conf"),
+            Section::HeaderFiles => write!(f, "include"),
+            Section::LegalNotices => write!(f, "legal"),
+            Section::ManPages => write!(f, "man"),
+            Section::NativeLibraries => write!(f, "lib"),
+            Section::NativeCommands => write!(f, "bin"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum JmodError {
+    Io(std::io::Error),
+    NotJmod,
+    Zip(ZipError),
+    Class(ClassFileError),
+}
+
+impl fmt::Display for JmodError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JmodError::Io(e) => write!(f, "{}", e),
+            JmodError::NotJmod => write!(f, "not a jmod file"),
+            JmodError::Zip(e) => write!(f, "{}", e),
+            JmodError::Class(e) => write!(f, "module-info.class: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for JmodError {}
+
+pub struct Jmod {
+    zip: ZipFile,
+}
+
+impl Jmod {
+    pub fn open(path: &Path) -> Result<Self, JmodError> {
+        Jmod::from_bytes(std::fs::read(path).map_err(JmodError::Io)?)
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, JmodError> {
+        if !bytes.starts_with(&JMOD_MAGIC) {
+            return Err(JmodError::NotJmod);
+        }
+        let zip = ZipFile::from_bytes(bytes).map_err(|e| match e {
+            ZipError::NotZip => JmodError::NotJmod,
+            e => JmodError::Zip(e),
+        })?;
+        Ok(Jmod { zip })
+    }
+
+    // files of every section, with their path inside the section
+    pub fn get_entries(&self) -> Vec<(Section, &str)> {
+        self.zip.get_entries().iter()
+            .filter(|e| !e.name.ends_with('/'))
+            .filter_map(|e| SECTIONS.iter().find_map(|(dir, section)| Some((*section, e.name.strip_prefix(dir)?))))
+            .collect()
+    }
+
+    // internal names of the classes, without module-info
+    pub fn get_class_names(&self) -> Vec<String> {
+        self.get_entries().into_iter()
+            .filter(|(section, name)| *section == Section::Classes && *name != "module-info.class")
+            .filter_map(|(_, name)| Some(name.strip_suffix(".class")?.to_string()))
+            .collect()
+    }
+
+    pub fn contains(&self, section: Section, name: &str) -> bool {
+        SECTIONS.iter().any(|(dir, s)| *s == section && self.zip.get_entry(&format!("{}{}", dir, name)).is_some())
+    }
+
+    // a file of the section, None when there is no such file
+    pub fn read_entry(&self, section: Section, name: &str) -> Option<Result<Vec<u8>, ZipError>> {
+        let (dir, _) = SECTIONS.iter().find(|(_, s)| *s == section)?;
+        self.zip.read_entry(&format!("{}{}", dir, name))
+    }
+
+    // class file by internal name, java/lang/Object
+    pub fn read_class(&self, name: &str) -> Option<Result<Vec<u8>, ZipError>> {
+        self.read_entry(Section::Classes, &format!("{}.class", name))
+    }
+
+    pub fn get_module_info(&self) -> Option<Result<ClassFile, JmodError>> {
+        Some(self.read_class("module-info")?.map_err(JmodError::Zip)
+            .and_then(|bytes| ClassFile::try_from_bytes(bytes).map_err(JmodError::Class)))
+    }
+}
@@ -0,0 +1,570 @@
+// module json
+// the whole parsed class as JSON through serde: the constant pool resolved, flags as a number and names, members, Code
+// with its instructions decoded, and every attribute typed. docs/json-schema.md describes it, SCHEMA_VERSION changes
+// with anything that is not an addition
+use std::collections::HashSet;
+use serde::{Serialize, Serializer};
+use crate::access_flags::*;
+use crate::attribute::{AttributeKind, BootstrapMethod};
+use crate::class_file_reader::{ConstantInfo, ConstantPool, Index, LiteralInfo, Tag};
+use crate::code::{self, Code};
+use crate::code::opcode::Opcode;
+use crate::code::stack_map::StackMapFrame;
+use crate::jasm;
+use crate::module;
+use crate::{Annotation, Attribute, ClassFile, ElementValue};
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+const HANDLE_KINDS: [&str; 9] = [
+    "getField", "getStatic", "putField", "putStatic", "invokeVirtual",
+    "invokeStatic", "invokeSpecial", "newInvokeSpecial", "invokeInterface",
+];
+
+#[derive(Serialize)]
+struct Flags {
+    value: u16,
+    names: Vec<&'static str>,
+}
+
+impl Flags {
+    fn new(value: u16, names: fn(u16) -> Vec<&'static str>) -> Self {
+        Flags { value, names: names(value) }
+    }
+}
+
+#[derive(Serialize)]
+struct Version {
+    major: u16,
+    minor: u16,
+}
+
+#[derive(Serialize)]
+struct Class {
+    schema_version: u32,
+    version: Version,
+    access_flags: Flags,
+    this_class: String,
+    super_class: Option<String>,
+    interfaces: Vec<String>,
+    constant_pool: Vec<Constant>,
+    fields: Vec<Member>,
+    methods: Vec<Member>,
+    attributes: Vec<AttributeJson>,
+}
+
+#[derive(Serialize)]
+struct Member {
+    name: String,
+    descriptor: String,
+    access_flags: Flags,
+    attributes: Vec<AttributeJson>,
+}
+
+// NaN and the infinities have no JSON number, their value is null and bits has them
+#[derive(Serialize)]
+#[serde(tag = "tag")]
+enum Constant {
+    Utf8 { index: u16, value: String },
+    Integer { index: u16, value: i32 },
+    Float { index: u16, value: Option<f32>, bits: u32 },
+    Long { index: u16, value: i64 },
+    Double { index: u16, value: Option<f64>, bits: u64 },
+    Class { index: u16, name: String },
+    String { index: u16, value: String },
+    Fieldref { index: u16, class: String, name: String, descriptor: String },
+    Methodref { index: u16, class: String, name: String, descriptor: String },
+    InterfaceMethodref { index: u16, class: String, name: String, descriptor: String },
+    NameAndType { index: u16, name: String, descriptor: String },
+    MethodHandle { index: u16, reference_kind: u8, reference_kind_name: &'static str, reference_index: u16, reference: String },
+    MethodType { index: u16, descriptor: String },
+    Dynamic { index: u16, bootstrap_method_attr_index: u16, name: String, descriptor: String },
+    InvokeDynamic { index: u16, bootstrap_method_attr_index: u16, name: String, descriptor: String },
+    Module { index: u16, name: String },
+    Package { index: u16, name: String },
+}
+
+fn constant(cp: &ConstantPool, index: u16) -> Option<Constant> {
+    let ConstantInfo(tag, info) = cp.get_constant(index);
+    let utf8 = |i: u16| cp.get_item(&Index::Single(i));
+    let (name, descriptor) = cp.get_name_and_type(index).unwrap_or_default();
+    let constant = match (tag, info) {
+        (Tag::Utf8, _) => Constant::Utf8 { index, value: utf8(index) },
+        (Tag::Integer | Tag::Float | Tag::Long | Tag::Double, _) => match cp.get_literal(index) {
+            LiteralInfo::Integer(v) => Constant::Integer { index, value: *v as i32 },
+            LiteralInfo::Float(v) => Constant::Float { index, value: Some(*v).filter(|v| v.is_finite()), bits: v.to_bits() },
+            LiteralInfo::Long(v) => Constant::Long { index, value: *v as i64 },
+            LiteralInfo::Double(v) => Constant::Double { index, value: Some(*v).filter(|v| v.is_finite()), bits: v.to_bits() },
+            LiteralInfo::String(v) => Constant::Utf8 { index, value: v.clone() },
+        },
+        (Tag::Class, Index::Single(i)) => Constant::Class { index, name: utf8(*i) },
+        (Tag::String, Index::Single(i)) => Constant::String { index, value: utf8(*i) },
+        (Tag::FieldRef | Tag::MethodRef | Tag::InterfaceMethodRef, Index::Ref(..)) => {
+            let (class, name, descriptor) = cp.get_member(index).unwrap_or_default();
+            match tag {
+                Tag::FieldRef => Constant::Fieldref { index, class, name, descriptor },
+                Tag::MethodRef => Constant::Methodref { index, class, name, descriptor },
+                _ => Constant::InterfaceMethodref { index, class, name, descriptor },
+            }
+        }
+        (Tag::NameAndType, Index::Pair(n, d)) => Constant::NameAndType { index, name: utf8(*n), descriptor: utf8(*d) },
+        (Tag::MethodHandle, Index::MethodHandle(kind, i)) => Constant::MethodHandle {
+            index,
+            reference_kind: *kind,
+            reference_kind_name: HANDLE_KINDS.get((*kind as usize).wrapping_sub(1)).unwrap_or(&"?"),
+            reference_index: *i,
+            reference: jasm::constant_text(cp, *i),
+        },
+        (Tag::MethodType, Index::Single(i)) => Constant::MethodType { index, descriptor: utf8(*i) },
+        (Tag::Dynamic, Index::Dynamic(bsm, _)) => Constant::Dynamic { index, bootstrap_method_attr_index: *bsm, name, descriptor },
+        (Tag::InvokeDynamic, Index::Dynamic(bsm, _)) =>
+            Constant::InvokeDynamic { index, bootstrap_method_attr_index: *bsm, name, descriptor },
+        (Tag::Module, Index::Single(i)) => Constant::Module { index, name: utf8(*i) },
+        (Tag::Package, Index::Single(i)) => Constant::Package { index, name: utf8(*i) },
+        _ => return None,
+    };
+    Some(constant)
+}
+
+// a constant an instruction or attribute refers to, its index and its value as the disassembler writes it
+#[derive(Serialize)]
+struct ConstantRef {
+    index: u16,
+    value: String,
+}
+
+impl ConstantRef {
+    fn new(cp: &ConstantPool, index: u16) -> Self {
+        ConstantRef { index, value: jasm::constant_text(cp, index) }
+    }
+}
+
+// an int, float, long or double is a JSON number, a string a JSON string
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Literal {
+    Integer(i64),
+    Float(Option<f32>),
+    Double(Option<f64>),
+    String(String),
+}
+
+fn literal(value: &LiteralInfo) -> Literal {
+    match value {
+        LiteralInfo::Integer(v) => Literal::Integer(*v as i32 as i64),
+        LiteralInfo::Long(v) => Literal::Integer(*v as i64),
+        LiteralInfo::Float(v) => Literal::Float(Some(*v).filter(|v| v.is_finite())),
+        LiteralInfo::Double(v) => Literal::Double(Some(*v).filter(|v| v.is_finite())),
+        LiteralInfo::String(s) => Literal::String(s.clone()),
+    }
+}
+
+#[derive(Serialize)]
+struct Instruction {
+    pc: u32,
+    opcode: u8,
+    mnemonic: String,
+    operands: Vec<i64>,             // as the class file has them, branch offsets relative
+    text: String,                   // constants by value, branch targets as L and the pc
+    #[serde(skip_serializing_if = "Option::is_none")]
+    constant: Option<ConstantRef>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<u32>,            // a branch target pc
+    #[serde(skip_serializing_if = "Option::is_none")]
+    switch: Option<Switch>,
+}
+
+#[derive(Serialize)]
+struct Switch {
+    default: u32,
+    cases: Vec<(i32, u32)>,         // match and target pc
+}
+
+fn instruction(cp: &ConstantPool, pc: u32, o: &Opcode) -> Instruction {
+    let target = |offset: i32| (pc as i32 + offset) as u32;
+    let index = match o {
+        Opcode::Getfield(i) | Opcode::Getstatic(i) | Opcode::Putfield(i) | Opcode::Putstatic(i)
+            | Opcode::Invokevirtual(i) | Opcode::Invokespecial(i) | Opcode::Invokestatic(i) | Opcode::Invokeinterface(i, _)
+            | Opcode::Invokedynamic(i) | Opcode::Ldc2W(i) | Opcode::LdcW(i) | Opcode::New(i) | Opcode::Anewarray(i)
+            | Opcode::Checkcast(i) | Opcode::Instanceof(i) | Opcode::Multianewarray(i, _) => Some(*i),
+        Opcode::Ldc(i) => Some(*i as u16),
+        _ => None,
+    };
+    let (switch, operands) = match o {
+        Opcode::Tableswitch(default, low, high, offsets) => {
+            let cases = offsets.iter().enumerate().map(|(i, o)| (low + i as i32, target(*o))).collect();
+            let operands = [*default, *low, *high].into_iter().chain(offsets.iter().copied()).map(i64::from).collect();
+            (Some(Switch { default: target(*default), cases }), operands)
+        }
+        Opcode::Lookupswitch(default, pairs) => {
+            let cases = pairs.iter().map(|(m, o)| (*m, target(*o))).collect();
+            let operands = std::iter::once(*default).chain(pairs.iter().flat_map(|(m, o)| [*m, *o])).map(i64::from).collect();
+            (Some(Switch { default: target(*default), cases }), operands)
+        }
+        o => (None, o.to_string().split(' ').filter_map(|w| w.parse().ok()).collect()),
+    };
+    Instruction {
+        pc,
+        opcode: o.opcode(),
+        mnemonic: o.mnemonic(),
+        operands,
+        text: jasm::instruction_text(cp, &HashSet::new(), pc, o).replace("\n        ", " "),
+        constant: index.map(|i| ConstantRef::new(cp, i)),
+        target: o.branch_offset().map(target),
+        switch,
+    }
+}
+
+#[derive(Serialize)]
+struct ExceptionHandler {
+    start_pc: u16,
+    end_pc: u16,
+    handler_pc: u16,
+    catch_type: Option<String>,     // null catches everything
+}
+
+#[derive(Serialize)]
+struct InnerClass {
+    inner_class: String,
+    outer_class: Option<String>,
+    inner_name: Option<String>,
+    access_flags: Flags,
+}
+
+#[derive(Serialize)]
+struct LineNumber {
+    start_pc: u16,
+    line_number: u16,
+}
+
+#[derive(Serialize)]
+struct LocalVariable {
+    start_pc: u16,
+    length: u16,
+    name: String,
+    descriptor: String,             // the signature in a LocalVariableTypeTable
+    index: u16,
+}
+
+#[derive(Serialize)]
+struct Frame {
+    frame_type: &'static str,
+    offset_delta: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chopped: Option<u8>,
+    locals: Vec<String>,            // int, float, long, double, top, null, uninitialized this, uninitialized and the
+    stack: Vec<String>,             // pc of its new, or a class name
+}
+
+fn frame(f: &StackMapFrame) -> Frame {
+    let types = |v: &[crate::code::stack_map::VerificationType]| v.iter().map(|t| t.to_string()).collect();
+    let (frame_type, offset_delta, chopped, locals, stack) = match f {
+        StackMapFrame::Same(d) => ("same", *d, None, Vec::new(), Vec::new()),
+        StackMapFrame::SameLocals1StackItem(d, t) => ("same_locals_1_stack_item", *d, None, Vec::new(), vec![t.to_string()]),
+        StackMapFrame::Chop(d, k) => ("chop", *d, Some(*k), Vec::new(), Vec::new()),
+        StackMapFrame::Append(d, l) => ("append", *d, None, types(l), Vec::new()),
+        StackMapFrame::Full(d, l, s) => ("full", *d, None, types(l), types(s)),
+    };
+    Frame { frame_type, offset_delta, chopped, locals, stack }
+}
+
+#[derive(Serialize)]
+struct AnnotationJson {
+    r#type: String,
+    elements: Vec<Element>,
+}
+
+#[derive(Serialize)]
+struct Element {
+    name: String,
+    value: ElementJson,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum ElementJson {
+    Const { tag: char, value: Literal },
+    Enum { r#type: String, name: String },
+    Class { descriptor: String },
+    Annotation { annotation: AnnotationJson },
+    Array { values: Vec<ElementJson> },
+}
+
+fn annotation(a: &Annotation) -> AnnotationJson {
+    AnnotationJson {
+        r#type: a.get_type().clone(),
+        elements: a.get_value_pairs().iter().map(|p| Element { name: p.get_name().clone(), value: element(p.get_value()) }).collect(),
+    }
+}
+
+fn element(value: &ElementValue) -> ElementJson {
+    match value {
+        ElementValue::Const(tag, v) => ElementJson::Const { tag: *tag, value: literal(v) },
+        ElementValue::Enum(t, n) => ElementJson::Enum { r#type: t.clone(), name: n.clone() },
+        ElementValue::Class(d) => ElementJson::Class { descriptor: d.clone() },
+        ElementValue::Annotation(a) => ElementJson::Annotation { annotation: annotation(a) },
+        ElementValue::Array(v) => ElementJson::Array { values: v.iter().map(element).collect() },
+    }
+}
+
+#[derive(Serialize)]
+struct TypeAnnotation {
+    target_type: u8,
+    target: String,                 // the target_info as javap writes it
+    type_path: Vec<String>,
+    annotation: AnnotationJson,
+}
+
+#[derive(Serialize)]
+struct Bootstrap {
+    method: ConstantRef,
+    arguments: Vec<ConstantRef>,
+}
+
+#[derive(Serialize)]
+struct MethodParameter {
+    name: Option<String>,
+    access_flags: u16,
+}
+
+#[derive(Serialize)]
+struct Requires {
+    name: String,
+    flags: Flags,
+    version: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Exports {
+    package: String,
+    flags: Flags,
+    to: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Provides {
+    service: String,
+    with: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct RecordComponent {
+    name: String,
+    descriptor: String,
+    attributes: Vec<AttributeJson>,
+}
+
+// every attribute has its name, the fields after it depend on the name
+#[derive(Serialize)]
+#[serde(tag = "name")]
+enum AttributeJson {
+    ConstantValue { value: Literal },
+    Code {
+        max_stack: u16,
+        max_locals: u16,
+        instructions: Vec<Instruction>,
+        exception_table: Vec<ExceptionHandler>,
+        attributes: Vec<AttributeJson>,
+    },
+    StackMapTable { entries: Vec<Frame> },
+    Exceptions { exceptions: Vec<String> },
+    InnerClasses { classes: Vec<InnerClass> },
+    EnclosingMethod { class: String, method: Option<String> },
+    Synthetic,
+    Signature { signature: String },
+    SourceFile { source_file: String },
+    SourceDebugExtension { debug_extension: String },
+    LineNumberTable { line_numbers: Vec<LineNumber> },
+    LocalVariableTable { local_variables: Vec<LocalVariable> },
+    LocalVariableTypeTable { local_variables: Vec<LocalVariable> },
+    Deprecated,
+    RuntimeVisibleAnnotations { annotations: Vec<AnnotationJson> },
+    RuntimeInvisibleAnnotations { annotations: Vec<AnnotationJson> },
+    RuntimeVisibleParameterAnnotations { parameters: Vec<Vec<AnnotationJson>> },
+    RuntimeInvisibleParameterAnnotations { parameters: Vec<Vec<AnnotationJson>> },
+    RuntimeVisibleTypeAnnotations { annotations: Vec<TypeAnnotation> },
+    RuntimeInvisibleTypeAnnotations { annotations: Vec<TypeAnnotation> },
+    AnnotationDefault { value: ElementJson },
+    BootstrapMethods { methods: Vec<Bootstrap> },
+    MethodParameters { parameters: Vec<MethodParameter> },
+    Module {
+        module: String,
+        flags: Flags,
+        version: Option<String>,
+        requires: Vec<Requires>,
+        exports: Vec<Exports>,
+        opens: Vec<Exports>,
+        uses: Vec<String>,
+        provides: Vec<Provides>,
+    },
+    ModulePackages { packages: Vec<String> },
+    ModuleMainClass { main_class: String },
+    NestHost { host_class: String },
+    NestMembers { classes: Vec<String> },
+    Record { components: Vec<RecordComponent> },
+    PermittedSubclasses { classes: Vec<String> },
+    // an attribute the JVMS does not define, or one from a registered decoder, as hex
+    #[serde(untagged)]
+    Other { name: String, info: String },
+}
+
+fn attributes(class_file: &ClassFile, attributes: &[Attribute]) -> Vec<AttributeJson> {
+    attributes.iter().map(|a| attribute(class_file, a)).collect()
+}
+
+fn annotations(v: Vec<Annotation>) -> Vec<AnnotationJson> {
+    v.iter().map(annotation).collect()
+}
+
+fn type_annotations(v: Vec<crate::type_annotation::TypeAnnotation>) -> Vec<TypeAnnotation> {
+    v.iter().map(|t| TypeAnnotation {
+        target_type: t.get_target().target_type(),
+        target: t.get_target().to_string(),
+        type_path: t.get_type_path().iter().map(|p| p.to_string()).collect(),
+        annotation: annotation(t.get_annotation()),
+    })
+    .collect()
+}
+
+fn local_variables(class_file: &ClassFile, info: &[u8]) -> Vec<LocalVariable> {
+    code::get_local_variable_table(class_file, info).unwrap_or_default().into_iter()
+        .map(|v| LocalVariable { start_pc: v.start_pc, length: v.length, name: v.name, descriptor: v.descriptor, index: v.index })
+        .collect()
+}
+
+fn bootstrap(cp: &ConstantPool, b: &BootstrapMethod) -> Bootstrap {
+    Bootstrap {
+        method: ConstantRef::new(cp, b.method_ref),
+        arguments: b.arguments.iter().map(|a| ConstantRef::new(cp, *a)).collect(),
+    }
+}
+
+fn attribute(class_file: &ClassFile, a: &Attribute) -> AttributeJson {
+    let cp = &class_file.constant_pool;
+    match a.decode(class_file) {
+        AttributeKind::ConstantValue(v) => AttributeJson::ConstantValue { value: literal(&v) },
+        AttributeKind::Code(code) => code_attribute(class_file, &code),
+        AttributeKind::StackMapTable(frames) => AttributeJson::StackMapTable { entries: frames.iter().map(frame).collect() },
+        AttributeKind::Exceptions(exceptions) => AttributeJson::Exceptions { exceptions },
+        AttributeKind::InnerClasses(v) => AttributeJson::InnerClasses {
+            classes: v.into_iter().map(|i| InnerClass {
+                inner_class: i.inner_class,
+                outer_class: i.outer_class,
+                inner_name: i.inner_name,
+                access_flags: Flags::new(i.flags, nested_class_flag_names),
+            })
+            .collect(),
+        },
+        AttributeKind::EnclosingMethod(class, method) => AttributeJson::EnclosingMethod { class, method },
+        AttributeKind::Synthetic => AttributeJson::Synthetic,
+        AttributeKind::Signature(signature) => AttributeJson::Signature { signature },
+        AttributeKind::SourceFile(source_file) => AttributeJson::SourceFile { source_file },
+        AttributeKind::SourceDebugExtension(debug_extension) => AttributeJson::SourceDebugExtension { debug_extension },
+        AttributeKind::LineNumberTable(v) => AttributeJson::LineNumberTable {
+            line_numbers: v.iter().map(|l| LineNumber { start_pc: l.start_pc, line_number: l.line_number }).collect(),
+        },
+        AttributeKind::LocalVariableTable(_) => AttributeJson::LocalVariableTable { local_variables: local_variables(class_file, &a.info) },
+        AttributeKind::LocalVariableTypeTable(_) =>
+            AttributeJson::LocalVariableTypeTable { local_variables: local_variables(class_file, &a.info) },
+        AttributeKind::Deprecated => AttributeJson::Deprecated,
+        AttributeKind::RuntimeVisibleAnnotations(v) => AttributeJson::RuntimeVisibleAnnotations { annotations: annotations(v) },
+        AttributeKind::RuntimeInvisibleAnnotations(v) => AttributeJson::RuntimeInvisibleAnnotations { annotations: annotations(v) },
+        AttributeKind::RuntimeVisibleParameterAnnotations(v) =>
+            AttributeJson::RuntimeVisibleParameterAnnotations { parameters: v.into_iter().map(annotations).collect() },
+        AttributeKind::RuntimeInvisibleParameterAnnotations(v) =>
+            AttributeJson::RuntimeInvisibleParameterAnnotations { parameters: v.into_iter().map(annotations).collect() },
+        AttributeKind::RuntimeVisibleTypeAnnotations(v) => AttributeJson::RuntimeVisibleTypeAnnotations { annotations: type_annotations(v) },
+        AttributeKind::RuntimeInvisibleTypeAnnotations(v) =>
+            AttributeJson::RuntimeInvisibleTypeAnnotations { annotations: type_annotations(v) },
+        AttributeKind::AnnotationDefault(v) => AttributeJson::AnnotationDefault { value: element(&v) },
+        AttributeKind::BootstrapMethods(v) => AttributeJson::BootstrapMethods { methods: v.iter().map(|b| bootstrap(cp, b)).collect() },
+        AttributeKind::MethodParameters(v) => AttributeJson::MethodParameters {
+            parameters: v.into_iter().map(|p| MethodParameter { name: p.name, access_flags: p.flags }).collect(),
+        },
+        AttributeKind::Module(m) => AttributeJson::Module {
+            module: m.name,
+            flags: Flags::new(m.flags, module::module_flag_names),
+            version: m.version,
+            requires: m.requires.into_iter()
+                .map(|r| Requires { name: r.name, flags: Flags::new(r.flags, module::requires_flag_names), version: r.version })
+                .collect(),
+            exports: m.exports.into_iter()
+                .map(|e| Exports { package: e.package, flags: Flags::new(e.flags, module::exports_flag_names), to: e.to })
+                .collect(),
+            opens: m.opens.into_iter()
+                .map(|e| Exports { package: e.package, flags: Flags::new(e.flags, module::exports_flag_names), to: e.to })
+                .collect(),
+            uses: m.uses,
+            provides: m.provides.into_iter().map(|p| Provides { service: p.service, with: p.with }).collect(),
+        },
+        AttributeKind::ModulePackages(packages) => AttributeJson::ModulePackages { packages },
+        AttributeKind::ModuleMainClass(main_class) => AttributeJson::ModuleMainClass { main_class },
+        AttributeKind::NestHost(host_class) => AttributeJson::NestHost { host_class },
+        AttributeKind::NestMembers(classes) => AttributeJson::NestMembers { classes },
+        AttributeKind::Record(v) => AttributeJson::Record {
+            components: v.iter().map(|c| RecordComponent {
+                name: c.get_name().clone(),
+                descriptor: c.get_descriptor().clone(),
+                attributes: attributes(class_file, c.get_attributes()),
+            })
+            .collect(),
+        },
+        AttributeKind::PermittedSubclasses(classes) => AttributeJson::PermittedSubclasses { classes },
+        AttributeKind::Custom(..) | AttributeKind::Unknown(..) => AttributeJson::Other {
+            name: a.name.clone(),
+            info: a.info.iter().map(|b| format!("{:02x}", b)).collect(),
+        },
+    }
+}
+
+fn code_attribute(class_file: &ClassFile, code: &Code) -> AttributeJson {
+    let cp = &class_file.constant_pool;
+    AttributeJson::Code {
+        max_stack: code.get_max_stack(),
+        max_locals: code.get_max_locals(),
+        instructions: code.get_instructions().map(|(pc, o)| instruction(cp, pc as u32, o)).collect(),
+        exception_table: code.get_exception_table().iter().map(|e| ExceptionHandler {
+            start_pc: e.start_pc,
+            end_pc: e.end_pc,
+            handler_pc: e.handler_pc,
+            catch_type: (e.catch_type != 0).then(|| cp.get_name(e.catch_type)),
+        })
+        .collect(),
+        attributes: attributes(class_file, &class_file.get_attributes_vec(code.get_attributes())),
+    }
+}
+
+impl Serialize for ClassFile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let cp = &self.constant_pool;
+        let member = |name: &String, descriptor: &String, flags: Flags, a: &[Attribute]| Member {
+            name: name.clone(),
+            descriptor: descriptor.clone(),
+            access_flags: flags,
+            attributes: attributes(self, a),
+        };
+        Class {
+            schema_version: SCHEMA_VERSION,
+            version: Version { major: self.version.1, minor: self.version.0 },
+            access_flags: Flags::new(self.access_flags, class_flag_names),
+            this_class: self.get_class_name(),
+            super_class: self.get_super_class_name(),
+            interfaces: self.get_interface_names(),
+            constant_pool: (1..=cp.constant_info.len() as u16).filter_map(|i| constant(cp, i)).collect(),
+            fields: self.get_fields().iter()
+                .map(|f| member(f.get_name(), f.get_descriptor(), Flags::new(*f.get_flags(), field_flag_names), f.get_attributes()))
+                .collect(),
+            methods: self.get_methods().iter()
+                .map(|m| member(m.get_name(), m.get_descriptor(), Flags::new(*m.get_flags(), method_flag_names), m.get_attributes()))
+                .collect(),
+            attributes: attributes(self, &self.get_attributes_vec(&self.attributes)),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl ClassFile {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
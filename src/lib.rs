@@ -1,9 +1,41 @@
 // module jcfreader
 use std::slice::Iter;
-use std::io::Read;
+use std::collections::HashMap;
 
 pub mod class_file_reader;
-use crate::class_file_reader::{Index, ConstantPool, FieldInfo, MethodInfo, AttributeInfo, JavaVersion, LiteralInfo, ClassFileReader};
+pub mod class_path;
+pub mod class_file_writer;
+pub mod constant_pool_builder;
+pub mod access_flags;
+pub mod api_diff;
+pub mod attribute;
+pub mod call_graph;
+pub mod class_diff;
+pub mod code;
+pub mod deps;
+pub mod descriptor;
+pub mod hierarchy;
+pub mod jasm;
+pub mod jimage;
+pub mod json;
+pub mod jmod;
+pub mod module;
+pub mod record;
+pub mod smap;
+pub mod type_annotation;
+pub mod unused;
+pub mod validate;
+pub mod zip;
+use crate::class_file_reader::{Index, ConstantPool, ConstantInfo, Tag, ClassFileError, FieldInfo, MethodInfo, AttributeInfo, JavaVersion, LiteralInfo, ClassFileReader};
+use crate::type_annotation::TypeAnnotation;
+use crate::module::ModuleDescriptor;
+use crate::record::RecordComponent;
+use crate::access_flags::*;
+use crate::descriptor::{JavaType, MethodDescriptor};
+use crate::code::Code;
+use crate::smap::Smap;
+use crate::constant_pool_builder::ConstantPoolBuilder;
+use crate::attribute::{AttributeDecoder, AttributeKind, DecodeError, BootstrapMethod, InnerClass, MethodParameter};
 
 const JAVAP_FILE_NOT_FOUND: i32 = 1;
 const JAVA_MAGIC: u32 = 0xcafebabe;
@@ -18,49 +50,252 @@ pub enum Dump {
 pub struct ClassFile {
     version             : JavaVersion,
     constant_pool       : ConstantPool,
-    _access_flags       : u16, // to do  AccessFlags struct??
-    _this_class         : Index,
-    _super_class        : Index,
+    access_flags        : u16, // to do  AccessFlags struct??
+    this_class          : Index,
+    super_class         : Index,
     interfaces          : Vec<Index>,
     fields              : Vec<FieldInfo>,
     methods             : Vec<MethodInfo>,
     pub attributes      : Vec<AttributeInfo>,
+    decoders            : Vec<Box<dyn AttributeDecoder>>,
 }
 
 // needs to return a Result<ClassFile><Error>  for any errors found..
 
 impl ClassFile {
     pub fn new(file_name: &String, dump: Dump) -> Self {
-        let mut reader = ClassFileReader::new(file_name, dump);
+        match ClassFile::read(ClassFileReader::new(file_name, dump)) {
+            Ok(class_file) => class_file,
+            Err(e) => {
+                eprintln!("jcfr: {}: {}", file_name, e);
+                std::process::exit(JAVAP_FILE_NOT_FOUND);
+            }
+        }
+    }
+
+    // a class already in memory, from a jar entry, jimage or ClassFile::to_bytes
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        match ClassFile::try_from_bytes(bytes) {
+            Ok(class_file) => class_file,
+            Err(e) => {
+                eprintln!("jcfr: {}", e);
+                std::process::exit(JAVAP_FILE_NOT_FOUND);
+            }
+        }
+    }
+
+    // as from_bytes, with an error rather than an exit for bytes that are not a class file
+    pub fn try_from_bytes(bytes: Vec<u8>) -> Result<Self, ClassFileError> {
+        ClassFile::read(ClassFileReader::from_bytes("<bytes>", bytes, Dump::None))
+    }
+
+    fn read(mut reader: ClassFileReader) -> Result<Self, ClassFileError> {
         if reader.context("magic").read_u32() != JAVA_MAGIC {
-            eprintln!("jcfr: Not a java class file {}", reader.file_name);
-                        std::process::exit(JAVAP_FILE_NOT_FOUND);
+            return Err(reader.error.take().unwrap_or(ClassFileError::NotClass));
         };
 
-        Self {
+        let class_file = Self {
             version         : JavaVersion(reader.context("minor").read_u16(), reader.context("major").read_u16()),
             constant_pool   : ConstantPool::new(&mut reader),
-            _access_flags   : reader.context("access flags").read_u16(),
-            _this_class     : reader.read_constant_index(),
-            _super_class    : reader.read_constant_index(),
+            access_flags    : reader.context("access flags").read_u16(),
+            this_class      : reader.read_constant_index(),
+            super_class     : reader.read_constant_index(),
             interfaces      : reader.read_interfaces(),
             fields          : reader.read_fields(),
             methods         : reader.read_methods(),
             attributes      : reader.read_attributes(),
-      }
+            decoders        : Vec::new(),
+        };
+        if let Some(error) = reader.error {
+            return Err(error);
+        }
+        // the names every user of a class asks for first
+        let is_class = |index: u16| matches!(class_file.constant_pool.find_constant(index),
+            Some(ConstantInfo(Tag::Class, Index::Single(n))) if class_file.constant_pool.find_utf8(*n).is_some());
+        let super_class = class_file.index_of(&class_file.super_class);
+        if !is_class(class_file.index_of(&class_file.this_class)) || (super_class != 0 && !is_class(super_class))
+                || !class_file.interfaces.iter().all(|i| is_class(class_file.index_of(i))) {
+            return Err(ClassFileError::BadClass);
+        }
+        // and the names get_fields, get_methods and the attribute lists look up
+        let is_utf8 = |index: &Index| class_file.constant_pool.find_utf8(class_file.index_of(index)).is_some();
+        let named = |attributes: &[AttributeInfo]| attributes.iter().all(|a| is_utf8(&a.attribute_name_index));
+        let mut members = class_file.fields.iter().map(|f| (&f.name_index, &f.descriptor_index, &f.attributes))
+            .chain(class_file.methods.iter().map(|m| (&m.name_index, &m.descriptor_index, &m.attributes)));
+        if !members.all(|(name, descriptor, attributes)| is_utf8(name) && is_utf8(descriptor) && named(attributes))
+                || !named(&class_file.attributes) {
+            return Err(ClassFileError::BadName);
+        }
+        Ok(class_file)
+   }
+
+   // decoder for a custom attribute, used by Attribute::decode ahead of the JVMS ones
+   pub fn register_decoder(&mut self, decoder: Box<dyn AttributeDecoder>) {
+       self.decoders.push(decoder);
+   }
+
+   pub fn get_access_flags(&self) -> u16 {
+       self.access_flags
    }
 
-   // pub fn get_access_flags()
-   // pub fn get_class_name()
-   // pub fn get_super_class_name()
+   // names are in internal form java/lang/Object
+   pub fn get_class_name(&self) -> String {
+       self.constant_pool.get_name(self.index_of(&self.this_class))
+   }
+
+   // None for java/lang/Object and module-info
+   pub fn get_super_class_name(&self) -> Option<String> {
+       match self.index_of(&self.super_class) {
+           0 => None,
+           index => Some(self.constant_pool.get_name(index)),
+       }
+   }
+
+   pub fn get_interface_names(&self) -> Vec<String> {
+       self.interfaces.iter().map(|i| self.constant_pool.get_name(self.index_of(i))).collect()
+   }
+
+   fn index_of(&self, index: &Index) -> u16 {
+       match index {
+           Index::Single(i) => *i,
+           _ => 0,
+       }
+   }
+
+   pub fn record_components(&self) -> Option<Vec<RecordComponent>> {
+       match attribute::find(self, &self.get_attributes_vec(&self.attributes), "Record") {
+           Some(AttributeKind::Record(components)) => Some(components),
+           _ => None,
+       }
+   }
+
+   pub fn permitted_subclasses(&self) -> Option<Vec<String>> {
+       match attribute::find(self, &self.get_attributes_vec(&self.attributes), "PermittedSubclasses") {
+           Some(AttributeKind::PermittedSubclasses(classes)) => Some(classes),
+           _ => None,
+       }
+   }
+
+   // java like declaration, sealed interface Shape permits Circle, Square
+   pub fn get_declaration(&self) -> String {
+       let flags = self.access_flags;
+       let name = self.get_class_name().replace('/', ".");
+       if flags & ACC_MODULE != 0 {
+           return "module-info".to_string();
+       }
+       let record = self.record_components();
+       let permitted = self.permitted_subclasses();
+       let interface = flags & ACC_INTERFACE != 0;
+
+       let mut words = Vec::<String>::new();
+       if flags & ACC_PUBLIC != 0 {
+           words.push("public".to_string());
+       }
+       if flags & ACC_ABSTRACT != 0 && !interface {
+           words.push("abstract".to_string());
+       }
+       if flags & ACC_FINAL != 0 && flags & ACC_ENUM == 0 && record.is_none() {
+           words.push("final".to_string());
+       }
+       if permitted.is_some() {
+           words.push("sealed".to_string());
+       }
+       let kind = if flags & ACC_ANNOTATION != 0 {
+           "@interface"
+       } else if interface {
+           "interface"
+       } else if flags & ACC_ENUM != 0 {
+           "enum"
+       } else if record.is_some() {
+           "record"
+       } else {
+           "class"
+       };
+       words.push(kind.to_string());
+
+       let mut declaration = words.join(" ") + " " + &name;
+       if let Some(components) = &record {
+           let components: Vec<String> = components.iter()
+               .map(|c| match c.get_type() {
+                   Some(t) => format!("{} {}", t, c.get_name()),
+                   None => format!("{} {}", c.get_descriptor(), c.get_name()),
+               })
+               .collect();
+           declaration += &format!("({})", components.join(", "));
+       }
+
+       let interfaces: Vec<String> = self.get_interface_names().iter()
+           .filter(|i| i.as_str() != "java/lang/annotation/Annotation")
+           .map(|i| i.replace('/', "."))
+           .collect();
+       if !interface {
+           if let Some(super_class) = self.get_super_class_name() {
+               if !matches!(super_class.as_str(), "java/lang/Object" | "java/lang/Enum" | "java/lang/Record") {
+                   declaration += &format!(" extends {}", super_class.replace('/', "."));
+               }
+           }
+       }
+       if !interfaces.is_empty() {
+           let keyword = if interface { "extends" } else { "implements" };
+           declaration += &format!(" {} {}", keyword, interfaces.join(", "));
+       }
+       if let Some(permitted) = &permitted {
+           let permitted: Vec<String> = permitted.iter().map(|p| p.replace('/', ".")).collect();
+           declaration += &format!(" permits {}", permitted.join(", "));
+       }
+       declaration
+   }
 
    pub fn get_version(&self) -> String {
        self.version.to_string()
    }
 
+   pub fn set_version(&mut self, minor: u16, major: u16) {
+       self.version = JavaVersion(minor, major);
+   }
+
+   pub fn get_constant_pool(&self) -> &ConstantPool {
+       &self.constant_pool
+   }
+
+   // a builder over this class's pool, put back with set_constant_pool
+   pub fn constant_pool_builder(&self) -> ConstantPoolBuilder {
+       ConstantPoolBuilder::from_pool(&self.constant_pool)
+   }
+
+   pub fn set_constant_pool(&mut self, constant_pool: ConstantPool) {
+       self.constant_pool = constant_pool;
+   }
+
+   // replace the Code attribute of the method at this index in get_methods
+   pub fn set_code(&mut self, method: usize, info: Vec<u8>) {
+       let cp = &self.constant_pool;
+       if let Some(a) = self.methods[method].attributes.iter_mut().find(|a| cp.get_item(&a.attribute_name_index) == "Code") {
+           a.info = info;
+       }
+   }
+
+   // drop every attribute with this name from the class, its fields, methods and their Code
+   pub fn remove_attributes(&mut self, name: &str) {
+       let cp = &self.constant_pool;
+       let keep = |a: &AttributeInfo| cp.get_item(&a.attribute_name_index) != name;
+       self.attributes.retain(keep);
+       for f in self.fields.iter_mut() {
+           f.attributes.retain(keep);
+       }
+       for m in self.methods.iter_mut() {
+           m.attributes.retain(keep);
+           for a in m.attributes.iter_mut() {
+               if cp.get_item(&a.attribute_name_index) == "Code" {
+                   a.info = code::remove_attributes(&a.info, |c| keep(c));
+               }
+           }
+       }
+   }
+
   // to do transofrm the Index  struct to an expanded version and 
   // pass back a vector of better things 
-   pub fn get_interfaces(&self) -> Iter<Index> {
+   pub fn get_interfaces(&self) -> Iter<'_, Index> {
        self.interfaces.iter()
    }
    
@@ -70,6 +305,7 @@ impl ClassFile {
               flags: m.access_flags, 
               name: self.constant_pool.get_item(&m.name_index),
               descriptor: self.constant_pool.get_item(&m.descriptor_index),
+              attributes: self.get_attributes_vec(&m.attributes),
            }
         })
         .collect()
@@ -77,24 +313,96 @@ impl ClassFile {
 
    pub fn get_methods(&self) -> Vec<Method> {
        self.methods.iter().map(|m| {
+           let attributes = self.get_attributes_vec(&m.attributes);
            Method {
               flags: m.access_flags, 
               name: self.constant_pool.get_item(&m.name_index),
               descriptor: self.constant_pool.get_item(&m.descriptor_index),
-              attributes: self.get_attributes_vec(&m.attributes),
+              method_parameters: self.get_method_parameters(&attributes),
+              local_variable_names: self.get_local_variable_names(&attributes),
+              exceptions: self.get_exceptions(&attributes),
+              attributes,
            }
         })
         .collect()
    }
 
+   // MethodParameters, name and access flags of each parameter
+   fn get_method_parameters(&self, attributes: &[Attribute]) -> Vec<MethodParameter> {
+       match attribute::find(self, attributes, "MethodParameters") {
+           Some(AttributeKind::MethodParameters(parameters)) => parameters,
+           _ => Vec::new(),
+       }
+   }
+
+   // LocalVariableTable names in scope at the start of the method, by slot
+   fn get_local_variable_names(&self, attributes: &[Attribute]) -> HashMap<u16, String> {
+       let mut names = HashMap::<u16, String>::new();
+       if let Some(a) = attributes.iter().find(|a| a.name == "Code") {
+           let code_attributes = self.get_attributes_vec(&Code::read_attributes(&a.info));
+           for c in code_attributes.iter().filter(|c| c.name == "LocalVariableTable") {
+               for v in code::get_local_variable_table(self, &c.info).unwrap_or_default() {
+                   if v.start_pc == 0 {
+                       names.insert(v.index, v.name);
+                   }
+               }
+           }
+       }
+       names
+   }
+
+   // Exceptions, the checked exceptions a method declares
+   fn get_exceptions(&self, attributes: &[Attribute]) -> Vec<String> {
+       match attribute::find(self, attributes, "Exceptions") {
+           Some(AttributeKind::Exceptions(exceptions)) => exceptions,
+           _ => Vec::new(),
+       }
+   }
+
+   pub fn get_smap(&self) -> Option<Smap> {
+       self.get_attributes_vec(&self.attributes).iter()
+           .find(|a| a.name == "SourceDebugExtension")
+           .and_then(|a| Smap::parse(&String::from_utf8_lossy(&a.info)))
+   }
+
+   // source file and line of a pc, through the LineNumberTable and then the SMAP when there is one
+   pub fn get_source_line(&self, method: &Method, pc: u16) -> Option<(String, u32)> {
+       let code = method.get_attributes().iter().find(|a| a.name == "Code")?;
+       // the table can be split over several attributes
+       let table: Vec<code::LineNumber> = self.get_attributes_vec(&Code::read_attributes(&code.info)).iter()
+           .filter(|a| a.name == "LineNumberTable")
+           .flat_map(|a| code::get_line_number_table(&a.info).unwrap_or_default())
+           .collect();
+       let line = code::get_line_number(&table, pc)? as u32;
+       // a line the SMAP does not map is one of the class's own source file
+       match self.get_smap().and_then(|smap| smap.remap(line)) {
+           Some(mapped) => Some(mapped),
+           None => Some((self.get_class_attributes().source_file?, line)),
+       }
+   }
+
    pub fn get_class_attributes(&self) -> ClassAttributes {
        ClassAttributes::new(self)
    }
 
-   pub fn get_attributes_vec(&self, a: &Vec<AttributeInfo>) -> Vec<Attribute> {
+   // RuntimeVisibleTypeAnnotations and RuntimeInvisibleTypeAnnotations from
+   // any attribute list, field, method or Code
+   pub fn get_type_annotations(&self, attributes: &[Attribute]) -> TypeAnnotations {
+       let mut type_annotations = TypeAnnotations::default();
+       if let Some(AttributeKind::RuntimeVisibleTypeAnnotations(v)) = attribute::find(self, attributes, "RuntimeVisibleTypeAnnotations") {
+           type_annotations.runtime_visible = v;
+       }
+       if let Some(AttributeKind::RuntimeInvisibleTypeAnnotations(v)) = attribute::find(self, attributes, "RuntimeInvisibleTypeAnnotations") {
+           type_annotations.runtime_invisible = v;
+       }
+       type_annotations
+   }
+
+   pub fn get_attributes_vec(&self, a: &[AttributeInfo]) -> Vec<Attribute> {
       a.iter().map(|i| {
           Attribute {
-               name: self.constant_pool.get_item(&i.attribute_name_index),
+               // read checks the class's and members' names, one in a Code attribute that is not Utf8 is empty
+               name: self.constant_pool.find_utf8(self.index_of(&i.attribute_name_index)).unwrap_or_default(),
                info: i.info.to_owned(),
          }
      })
@@ -113,6 +421,7 @@ impl ClassFile {
 
 } // ClassFile
 
+#[derive(Clone)]
 pub struct Attribute {
     pub name: String,
     pub info: Vec<u8>,
@@ -123,6 +432,16 @@ pub struct Method {
     name: String,
     descriptor: String,
     attributes: Vec<Attribute>, 
+    method_parameters: Vec<MethodParameter>,
+    local_variable_names: HashMap<u16, String>,
+    exceptions: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Parameter {
+    pub name: Option<String>,
+    pub ty: JavaType,
+    pub flags: u16,
 }
 
 impl Method {
@@ -139,13 +458,79 @@ impl Method {
     {
         &self.attributes
     }
+
+    // types from the descriptor, names and flags from MethodParameters (-parameters)
+    // and then the LocalVariableTable (-g)
+    pub fn parameters(&self) -> Vec<Parameter> {
+        let descriptor = match MethodDescriptor::from_descriptor(&self.descriptor) {
+            Some(d) => d,
+            None => return Vec::new(),
+        };
+        // MethodParameters that does not have one entry for each parameter cannot be matched up with them
+        let method_parameters = match self.method_parameters.len() == descriptor.parameters.len() {
+            true => &self.method_parameters[..],
+            false => &[],
+        };
+        let mut slot = if self.flags & ACC_STATIC != 0 { 0 } else { 1 };
+        descriptor.parameters.into_iter().enumerate().map(|(i, ty)| {
+            let (name, flags) = match method_parameters.get(i) {
+                Some(MethodParameter { name: Some(name), flags }) => (Some(name.clone()), *flags),
+                Some(MethodParameter { name: None, flags }) => (self.local_variable_names.get(&slot).cloned(), *flags),
+                None => (self.local_variable_names.get(&slot).cloned(), 0),
+            };
+            slot += ty.size();
+            Parameter { name, ty, flags }
+        })
+        .collect()
+    }
+
+    // internal names of the declared checked exceptions
+    pub fn throws(&self) -> &Vec<String> {
+        &self.exceptions
+    }
+
+    // java like declaration, public static int method1(long arg1, long arg2) throws java.io.IOException
+    pub fn get_declaration(&self) -> String {
+        let mut words = method_modifiers(self.flags);
+        let return_type = match MethodDescriptor::from_descriptor(&self.descriptor) {
+            Some(d) => d.return_type.to_string(),
+            None => self.descriptor.clone(),
+        };
+        if self.name != "<init>" && self.name != "<clinit>" {
+            words.push(return_type);
+        }
+        let parameters = self.parameters();
+        let last = parameters.len();
+        let parameters: Vec<String> = parameters.iter().enumerate().map(|(i, p)| {
+            let mut ty = p.ty.to_string();
+            if self.flags & ACC_VARARGS != 0 && i + 1 == last && ty.ends_with("[]") {
+                ty = ty[..ty.len() - 2].to_string() + "...";
+            }
+            let mut parameter = String::new();
+            if p.flags & ACC_FINAL != 0 {
+                parameter += "final ";
+            }
+            parameter += &ty;
+            if let Some(name) = &p.name {
+                parameter += &format!(" {}", name);
+            }
+            parameter
+        })
+        .collect();
+        let mut declaration = format!("{} {}({})", words.join(" "), self.name, parameters.join(", "));
+        if !self.exceptions.is_empty() {
+            let exceptions: Vec<String> = self.exceptions.iter().map(|e| e.replace('/', ".")).collect();
+            declaration += &format!(" throws {}", exceptions.join(", "));
+        }
+        declaration.trim_start().to_string()
+    }
 }
 
 pub struct Field {
     flags: u16,
     name: String,
     descriptor: String,
-
+    attributes: Vec<Attribute>,
 }
 
 impl Field {
@@ -158,6 +543,16 @@ impl Field {
     pub fn get_descriptor(&self) -> &String {
         &self.descriptor
     }
+    pub fn get_attributes(&self) -> &Vec<Attribute>
+    {
+        &self.attributes
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TypeAnnotations {
+    pub runtime_visible: Vec<TypeAnnotation>,
+    pub runtime_invisible: Vec<TypeAnnotation>,
 }
 
 // runtime annotation
@@ -168,118 +563,208 @@ pub struct Annotation {
 }
 
 impl Annotation {
-    fn new(reader: &mut AnnotationReader, class_file: &ClassFile) -> Self {
-        let index = reader.read_u16();
-        let r#type = class_file.constant_pool.get_item(&Index::Single(index));
+    fn new(reader: &mut AttributeReader, class_file: &ClassFile) -> Result<Self, DecodeError> {
+        let r#type = utf8(class_file, reader.try_u16()?)?;
         let mut pairs = Vec::<ValuePair>::new();
         
-        let num = reader.read_u16();
+        let num = reader.try_u16()?;
 
         for _ in 0..num {
-            pairs.push(ValuePair::new(reader, class_file));
+            pairs.push(ValuePair::new(reader, class_file)?);
         }
 
-        Self {
+        Ok(Self {
             r#type,
             value_pair: pairs,
-        }
+        })
+    }
+
+    pub fn get_type(&self) -> &String {
+        &self.r#type
+    }
+
+    pub fn get_value_pairs(&self) -> &Vec<ValuePair> {
+        &self.value_pair
     }
 }
 
 #[derive(Debug)]
-struct ValuePair {
+pub struct ValuePair {
     name: String,
-    // value_index: u16, // enum of possible values
-    value: LiteralInfo,
+    value: ElementValue,
 }
 
 impl ValuePair {
-    fn new(reader: &mut AnnotationReader, class_file: &ClassFile) -> Self {
-        let index = reader.read_u16();
-        let name = class_file.constant_pool.get_item(&Index::Single(index));
-        let tag = char::from(reader.read_u8());
+    fn new(reader: &mut AttributeReader, class_file: &ClassFile) -> Result<Self, DecodeError> {
+        let name = utf8(class_file, reader.try_u16()?)?;
 
-        Self {
+        Ok(Self {
             name,
-            value : match tag {
-                'B' | 'C' | 'D' | 'F' | 'I' | 'J' | 'S' | 'Z' | 's' =>  {
-                    class_file.constant_pool.get_literal(reader.read_u16()).clone()
+            value : ElementValue::new(reader, class_file)?,
+        })
+    }
+
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn get_value(&self) -> &ElementValue {
+        &self.value
+    }
+}
+
+#[derive(Debug)]
+pub enum ElementValue {
+    Const(char, LiteralInfo),
+    Enum(String, String),
+    Class(String),
+    Annotation(Annotation),
+    Array(Vec<ElementValue>),
+}
+
+impl ElementValue {
+    fn new(reader: &mut AttributeReader, class_file: &ClassFile) -> Result<Self, DecodeError> {
+        let tag = reader.try_u8()?;
+        Ok(match char::from(tag) {
+            c @ ('B' | 'C' | 'D' | 'F' | 'I' | 'J' | 'S' | 'Z' | 's') =>  {
+                let index = reader.try_u16()?;
+                let value = class_file.constant_pool.literal_pool.get(&index).ok_or(DecodeError::Constant(index))?;
+                ElementValue::Const(c, value.clone())
+            }
+            'e' => {
+                let type_name = utf8(class_file, reader.try_u16()?)?;
+                let const_name = utf8(class_file, reader.try_u16()?)?;
+                ElementValue::Enum(type_name, const_name)
+            }
+            'c' => ElementValue::Class(utf8(class_file, reader.try_u16()?)?),
+            '@' => ElementValue::Annotation(Annotation::new(reader, class_file)?),
+            '[' => {
+                let num = reader.try_u16()?;
+                let mut values = Vec::<ElementValue>::with_capacity(num as usize);
+                for _ in 0..num {
+                    values.push(ElementValue::new(reader, class_file)?);
                 }
-                _ => todo!(),
-            },
-        }
+                ElementValue::Array(values)
+            }
+            _ => return Err(DecodeError::Tag("element_value tag", tag)),
+        })
     }
 }
 
+// a Utf8 entry for the decoders that report a bad index
+pub(crate) fn utf8(class_file: &ClassFile, index: u16) -> Result<String, DecodeError> {
+    class_file.constant_pool.find_utf8(index).ok_or(DecodeError::Constant(index))
+}
+
+// the name of a Class entry, in internal form
+pub(crate) fn class_name(class_file: &ClassFile, index: u16) -> Result<String, DecodeError> {
+    class_file.constant_pool.find_class(index).ok_or(DecodeError::Constant(index))
+}
+
 #[derive(Default)]
 pub struct ClassAttributes {
     pub source_file: Option<String>,
     pub runtime_visible_annotations: Option<Vec<Annotation>>,
-    // pub bootstrap_methods: Option<Vec<BootStrapMethods>>,
-    // etc
+    pub runtime_invisible_annotations: Option<Vec<Annotation>>,
+    pub runtime_visible_type_annotations: Option<Vec<TypeAnnotation>>,
+    pub runtime_invisible_type_annotations: Option<Vec<TypeAnnotation>>,
+    pub module: Option<ModuleDescriptor>,
+    pub module_packages: Option<Vec<String>>,
+    pub module_main_class: Option<String>,
+    pub record: Option<Vec<RecordComponent>>,
+    pub permitted_subclasses: Option<Vec<String>>,
+    pub source_debug_extension: Option<String>,
+    pub bootstrap_methods: Option<Vec<BootstrapMethod>>,
+    pub inner_classes: Option<Vec<InnerClass>>,
+    pub enclosing_method: Option<(String, Option<String>)>,
+    pub nest_host: Option<String>,
+    pub nest_members: Option<Vec<String>>,
+    pub signature: Option<String>,
+    pub deprecated: bool,
+    pub synthetic: bool,
 }
 
 impl ClassAttributes {
     fn new(class_file: &ClassFile) -> Self {
-        let mut source_file : Option<String> = None;
-        let mut runtime_visible_annotations : Option<Vec<Annotation>> = None;
-
-        for a in &class_file.attributes {
-            let name = &class_file.constant_pool.get_item(&a.attribute_name_index);
-                match name.as_str() {
-                    "SourceFile" => {
-                        let index = u16::from_be_bytes(a.info[0..2].try_into().unwrap());
-                        source_file = Some(class_file.constant_pool.get_item(&Index::Single(index)));
-                    },
-                    "RuntimeVisibleAnnotations" => { 
-                        runtime_visible_annotations = Some(ClassAttributes::get_annotations(class_file, &a.info));
-                    }
-                    "SourceDebugExtension" => (),
-                    "InnerClasses" => (),
-                    "BootStrapMethods" => {
-                        // bootstrap_methods = Some(ClassAttributes::get_bootstrap_methods(class_file, &a.info));
-                    }
-                    &_ => todo!("{}", name),
+        let mut attributes = ClassAttributes::default();
+
+        for a in class_file.get_attributes_vec(&class_file.attributes) {
+            match a.decode(class_file) {
+                AttributeKind::SourceFile(s) => attributes.source_file = Some(s),
+                AttributeKind::RuntimeVisibleAnnotations(v) => attributes.runtime_visible_annotations = Some(v),
+                AttributeKind::RuntimeInvisibleAnnotations(v) => attributes.runtime_invisible_annotations = Some(v),
+                AttributeKind::RuntimeVisibleTypeAnnotations(v) => attributes.runtime_visible_type_annotations = Some(v),
+                AttributeKind::RuntimeInvisibleTypeAnnotations(v) => attributes.runtime_invisible_type_annotations = Some(v),
+                AttributeKind::Module(m) => attributes.module = Some(m),
+                AttributeKind::ModulePackages(v) => attributes.module_packages = Some(v),
+                AttributeKind::ModuleMainClass(c) => attributes.module_main_class = Some(c),
+                AttributeKind::Record(v) => attributes.record = Some(v),
+                AttributeKind::PermittedSubclasses(v) => attributes.permitted_subclasses = Some(v),
+                AttributeKind::SourceDebugExtension(s) => attributes.source_debug_extension = Some(s),
+                AttributeKind::BootstrapMethods(v) => attributes.bootstrap_methods = Some(v),
+                AttributeKind::InnerClasses(v) => attributes.inner_classes = Some(v),
+                AttributeKind::EnclosingMethod(c, m) => attributes.enclosing_method = Some((c, m)),
+                AttributeKind::NestHost(c) => attributes.nest_host = Some(c),
+                AttributeKind::NestMembers(v) => attributes.nest_members = Some(v),
+                AttributeKind::Signature(s) => attributes.signature = Some(s),
+                AttributeKind::Deprecated => attributes.deprecated = true,
+                AttributeKind::Synthetic => attributes.synthetic = true,
+                // ModuleHashes, ModuleTarget and the like from jlink, or registered decoders
+                _ => (),
             }
         }
-        Self {
-           source_file, runtime_visible_annotations,
+        if let Some(m) = attributes.module.as_mut() {
+            m.packages = attributes.module_packages.clone().unwrap_or_default();
+            m.main_class = attributes.module_main_class.clone();
         }
+        attributes
     }
 
-    fn get_annotations(class_file: &ClassFile, info: &Vec<u8>) -> Vec<Annotation> {
-        let mut reader = AnnotationReader::new(info);
-        let num = reader.read_u16();
+    pub fn get_annotations(class_file: &ClassFile, info: &[u8]) -> Result<Vec<Annotation>, DecodeError> {
+        let mut reader = AttributeReader::new(info);
+        let num = reader.try_u16()?;
         let mut annotations = Vec::<Annotation>::with_capacity(num as usize);
         for _ in 0..num {
-            let annotation = Annotation::new(&mut reader, class_file);
+            let annotation = Annotation::new(&mut reader, class_file)?;
             annotations.push(annotation);
         }
-        annotations
+        Ok(annotations)
     }
 }
 
-struct AnnotationReader<'a> {
+struct AttributeReader<'a> {
     bytes: &'a [u8],
 }
 
-impl <'a>AnnotationReader<'a> {
-    fn new(info: &'a Vec<u8>) -> Self {
+impl <'a>AttributeReader<'a> {
+    fn new(info: &'a [u8]) -> Self {
        Self {
            bytes: info 
        }
     }
 
-    fn read_u16(&mut self) -> u16 {
-        let mut buf = [0; 2];
-        self.bytes.read_exact(&mut buf).unwrap();
-        u16::from_be_bytes(buf)
+    // reads past the end are an error, for Attribute::decode to keep the bytes as Unknown
+    fn try_u8(&mut self) -> Result<u8, DecodeError> {
+        let (&byte, rest) = self.bytes.split_first().ok_or(DecodeError::Truncated)?;
+        self.bytes = rest;
+        Ok(byte)
+    }
+
+    fn try_u16(&mut self) -> Result<u16, DecodeError> {
+        Ok(u16::from_be_bytes([self.try_u8()?, self.try_u8()?]))
+    }
+
+    fn try_u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_be_bytes([self.try_u8()?, self.try_u8()?, self.try_u8()?, self.try_u8()?]))
     }
 
-    fn read_u8(&mut self) -> u8 {
-        let mut buf = [0; 1];
-        self.bytes.read_exact(&mut buf).unwrap();
-        u8::from_be_bytes(buf)
+    fn try_bytes(&mut self, len: usize) -> Result<Vec<u8>, DecodeError> {
+        if len > self.bytes.len() {
+            return Err(DecodeError::Truncated);
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes.to_vec())
     }
 }
 
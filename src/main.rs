@@ -1,21 +1,80 @@
-pub mod code;
-
 // jcfreader
+use std::collections::HashMap;
 use std::env;
-use jcfreader::ClassFile;
+use std::path::Path;
+use jcfreader::{ClassFile, TypeAnnotations};
 use jcfreader::Dump;
-use crate::code::Code;
+use jcfreader::jasm;
+use jcfreader::code::{Code, limits};
+use jcfreader::code::cfg::ControlFlowGraph;
+use jcfreader::code::verifier;
+use jcfreader::attribute::HasAttributes;
+use jcfreader::class_path::{self, ClassPath};
+use jcfreader::jmod::Jmod;
+use jcfreader::deps::{self, DependencyGraph, DependencyReport};
+use jcfreader::call_graph::{self, Algorithm, CallGraph};
+use jcfreader::hierarchy::{self, Hierarchy};
+use jcfreader::unused;
+use jcfreader::api_diff::{self, Api, Severity};
+use jcfreader::class_diff;
 
 fn main() {
     let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(|a| a.as_str()) {
+        Some("disasm") => return disasm(&args[1..]),
+        Some("asm") => return asm(&args[1..]),
+        Some("cfg") => return cfg(&args[1..]),
+        Some("verify") => return verify(&args[1..]),
+        Some("validate") => return validate(&args[1..]),
+        Some("jmod") => return jmod(&args[1..]),
+        Some("deps") => return deps(&args[1..]),
+        Some("callgraph") => return callgraph(&args[1..]),
+        Some("unused") => return unused(&args[1..]),
+        Some("api-diff") => return api_diff(&args[1..]),
+        Some("diff") => return diff(&args[1..]),
+        _ => (),
+    }
     let mut dump :Dump = Dump::None;
     let mut file_name :Option<String> = None;
-    for arg in args {
-        println!("{}", arg);
-        if arg == *"-hex" {
+    let mut class_path: Option<ClassPath> = None;
+    // the whole class as JSON and nothing else
+    let json = args.iter().any(|a| a == "--json");
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !json {
+            println!("{}", arg);
+        }
+        if arg == "--json" {
+            continue;
+        } else if arg == "-hex" {
             dump = Dump::Hex;
-        } else if arg == *"-byte" {
+        } else if arg == "-byte" {
             dump = Dump::Byte;
+        } else if ["-cp", "-classpath", "--class-path", "--module-path", "-p", "--system", "--multi-release"].contains(&arg.as_str()) {
+            let Some(value) = args.next() else {
+                eprintln!("jcfr: {} needs a value", arg);
+                std::process::exit(1);
+            };
+            let class_path = class_path.get_or_insert_with(ClassPath::new);
+            let added = match arg.as_str() {
+                "--module-path" | "-p" => class_path.add_module_path(value),
+                "--system" => class_path.add_runtime_image(Path::new(value)),
+                "--multi-release" => match value.parse() {
+                    Ok(release) => {
+                        class_path.set_release(release);
+                        Ok(())
+                    }
+                    Err(_) => {
+                        eprintln!("jcfr: bad release {}", value);
+                        std::process::exit(1);
+                    }
+                },
+                _ => class_path.add_class_path(value),
+            };
+            if let Err(e) = added {
+                eprintln!("jcfr: {}", e);
+                std::process::exit(1);
+            }
         } else {
             file_name = Some(arg.to_string());
         }
@@ -25,29 +84,596 @@ fn main() {
         println!("jcfr: Filename not specified");
         std::process::exit(1);
     }
-    println!("filename: {}", file_name.as_ref().unwrap());
+    if !json {
+        println!("filename: {}", file_name.as_ref().unwrap());
+    }
 
-    let class_file = ClassFile::new(&file_name.unwrap(), dump);
+    // with a class path the name is a class, com.strl.test.test1, rather than a file
+    let class_file = match class_path {
+        Some(class_path) => {
+            let name = class_path::internal_name(file_name.as_ref().unwrap());
+            match class_path.read_class(&name) {
+                Ok(Some(class_file)) => class_file,
+                Ok(None) => {
+                    eprintln!("jcfr: class not found: {}", file_name.unwrap());
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("jcfr: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => ClassFile::new(&file_name.unwrap(), dump),
+    };
+    if json {
+        match serde_json::to_string_pretty(&class_file) {
+            Ok(text) => println!("{}", text),
+            Err(e) => {
+                eprintln!("jcfr: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
     println!("{}", class_file.get_version());
+    println!("{}", class_file.get_declaration());
     println!("Fields");
     for field in class_file.get_fields() {
         println!("{}: {}", field.get_name(), field.get_descriptor());
+        print_common_attributes(&class_file, &field);
+        print_type_annotations(&class_file.get_type_annotations(field.get_attributes()));
     }
     println!("Methods");
     for method in class_file.get_methods() {
         println!("Method: {}: {}", method.get_name(), method.get_descriptor());
+        println!("{}", method.get_declaration());
+        print_common_attributes(&class_file, &method);
             for attr in  method.get_attributes().iter() {
                 println!("{}", attr.name);
                 if attr.name == "Code" {
                    let x = Code::new(&attr.info);
-                   for o in x.code {
+                   for o in &x.code {
                        println!("{}", o);
                    }
+                   let code_attributes = class_file.get_attributes_vec(x.get_attributes());
+                   print_type_annotations(&class_file.get_type_annotations(&code_attributes));
                 }
             }
+            print_type_annotations(&class_file.get_type_annotations(method.get_attributes()));
+            if let Some((source_file, line)) = class_file.get_source_line(&method, 0) {
+                println!("Source {}:{}", source_file, line);
+            }
+    }
+    for problem in limits::check(&class_file) {
+        println!("Limits {}", problem);
     }
     let attr = class_file.get_class_attributes();
+    print_common_attributes(&class_file, &class_file);
     println!("SourceFile {:?}", attr.source_file);
     println!("RuntimeVisibleAnnotations {:?}", attr.runtime_visible_annotations);
+    for t in attr.runtime_visible_type_annotations.iter().flatten() {
+        println!("RuntimeVisibleTypeAnnotation {}", t);
+    }
+    for t in attr.runtime_invisible_type_annotations.iter().flatten() {
+        println!("RuntimeInvisibleTypeAnnotation {}", t);
+    }
+    if let Some(smap) = class_file.get_smap() {
+        println!("SourceDebugExtension {} default stratum {}", smap.output_file, smap.default_stratum);
+        for stratum in &smap.strata {
+            for file in &stratum.files {
+                println!("  {} {} {} {}", stratum.id, file.id, file.name, file.path.as_deref().unwrap_or(""));
+            }
+        }
+    }
+    if let Some(module) = &attr.module {
+        println!("{}", module);
+        if !module.packages.is_empty() {
+            println!("ModulePackages {}", module.packages.join(", "));
+        }
+        if let Some(main_class) = &module.main_class {
+            println!("ModuleMainClass {}", main_class);
+        }
+    }
+}
+
+// jcfreader disasm X.class > X.j
+fn disasm(args: &[String]) {
+    let file_name = match args.first() {
+        Some(f) => f,
+        None => {
+            eprintln!("jcfr: Filename not specified");
+            std::process::exit(1);
+        }
+    };
+    let class_file = ClassFile::new(file_name, Dump::None);
+    print!("{}", jasm::disassemble(&class_file));
+}
+
+// jcfreader asm X.j [-o X.class]
+fn asm(args: &[String]) {
+    let mut file_name: Option<&String> = None;
+    let mut output: Option<String> = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "-o" {
+            output = args.next().cloned();
+        } else {
+            file_name = Some(arg);
+        }
+    }
+    let file_name = match file_name {
+        Some(f) => f,
+        None => {
+            eprintln!("jcfr: Filename not specified");
+            std::process::exit(1);
+        }
+    };
+    let text = match std::fs::read_to_string(file_name) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("jcfr: {}: {}", file_name, e);
+            std::process::exit(1);
+        }
+    };
+    let class_file = match jasm::assemble(&text) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("jcfr: {}: {}", file_name, e);
+            std::process::exit(1);
+        }
+    };
+    let output = output.unwrap_or_else(|| Path::new(file_name).with_extension("class").to_string_lossy().to_string());
+    let bytes = match class_file.try_to_bytes() {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("jcfr: {}: {}", file_name, e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = std::fs::write(&output, bytes) {
+        eprintln!("jcfr: {}: {}", output, e);
+        std::process::exit(1);
+    }
+}
+
+// jcfreader cfg X.class 'name(descriptor)' > X.dot, the name alone will do when it is not overloaded
+fn cfg(args: &[String]) {
+    let (file_name, method) = match args {
+        [f, m] => (f, m),
+        _ => {
+            eprintln!("jcfr: usage: cfg X.class 'name(descriptor)'");
+            std::process::exit(1);
+        }
+    };
+    let class_file = ClassFile::new(file_name, Dump::None);
+    let methods = class_file.get_methods();
+    let matching: Vec<_> = methods.iter()
+        .filter(|m| method == m.get_name() || *method == format!("{}{}", m.get_name(), m.get_descriptor()))
+        .collect();
+    let found = match matching.as_slice() {
+        [m] => m,
+        [] => {
+            eprintln!("jcfr: {}: no method {}", file_name, method);
+            std::process::exit(1);
+        }
+        _ => {
+            eprintln!("jcfr: {}: {} is overloaded, give its descriptor", file_name, method);
+            std::process::exit(1);
+        }
+    };
+    let code = match found.get_attributes().iter().find(|a| a.name == "Code").map(|a| Code::try_new(&a.info)) {
+        Some(Ok(code)) => code,
+        Some(Err(e)) => {
+            eprintln!("jcfr: {}: {}: bad Code attribute: {}", file_name, method, e);
+            std::process::exit(1);
+        }
+        None => {
+            eprintln!("jcfr: {}: {} has no code", file_name, method);
+            std::process::exit(1);
+        }
+    };
+    match ControlFlowGraph::new(&code) {
+        Ok(graph) => {
+            let name = format!("{}.{}{}", class_file.get_class_name(), found.get_name(), found.get_descriptor());
+            print!("{}", graph.to_dot(&code, class_file.get_constant_pool(), &name));
+        }
+        Err(e) => {
+            eprintln!("jcfr: {}: {}: {}", file_name, method, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// jcfreader verify A.class B.class ..., the classes given are the hierarchy subclassing is checked against
+fn verify(args: &[String]) {
+    if args.is_empty() {
+        eprintln!("jcfr: Filename not specified");
+        std::process::exit(1);
+    }
+    let classes: Vec<ClassFile> = args.iter().map(|f| ClassFile::new(f, Dump::None)).collect();
+    // each class is verified, the first of a name is the one subclassing is checked against, as on a class path
+    let mut hierarchy = HashMap::<String, &ClassFile>::new();
+    for class_file in &classes {
+        hierarchy.entry(class_file.get_class_name()).or_insert(class_file);
+    }
+    let mut failed = false;
+    for (file_name, class_file) in args.iter().zip(&classes) {
+        for problem in verifier::verify(class_file, &hierarchy) {
+            println!("{}: {}", file_name, problem);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+// jcfreader validate A.class B.class ..., the format checks of JVMS 4.8 on each class
+fn validate(args: &[String]) {
+    if args.is_empty() {
+        eprintln!("jcfr: Filename not specified");
+        std::process::exit(1);
+    }
+    let mut failed = false;
+    for file_name in args {
+        for diagnostic in ClassFile::new(file_name, Dump::None).validate() {
+            println!("{}: {}", file_name, diagnostic);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+// jcfreader jmod X.jmod, the module descriptor and the files of each section
+fn jmod(args: &[String]) {
+    let file_name = match args.first() {
+        Some(f) => f,
+        None => {
+            eprintln!("jcfr: Filename not specified");
+            std::process::exit(1);
+        }
+    };
+    let jmod = match Jmod::open(Path::new(file_name)) {
+        Ok(j) => j,
+        Err(e) => {
+            eprintln!("jcfr: {}: {}", file_name, e);
+            std::process::exit(1);
+        }
+    };
+    match jmod.get_module_info() {
+        Some(Ok(module_info)) => {
+            if let Some(module) = &module_info.get_class_attributes().module {
+                println!("{}", module);
+                if !module.packages.is_empty() {
+                    println!("ModulePackages {}", module.packages.join(", "));
+                }
+                if let Some(main_class) = &module.main_class {
+                    println!("ModuleMainClass {}", main_class);
+                }
+            }
+        }
+        Some(Err(e)) => {
+            eprintln!("jcfr: {}: {}", file_name, e);
+            std::process::exit(1);
+        }
+        None => println!("no module-info.class"),
+    }
+    for (section, name) in jmod.get_entries() {
+        println!("{}/{}", section, name);
+    }
+}
+
+// jcfreader deps [-cp path] [--module-path path] [--system jdk] [--by class|package|archive] [--dot|--json] app.jar ...,
+// the inputs, jars, directories, jmods or classes, are analyzed and looked up before the class path
+fn deps(args: &[String]) {
+    let mut inputs = Vec::<&String>::new();
+    let mut lookup = Vec::<(&str, &String)>::new();
+    let mut by = "package";
+    let mut format = "text";
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-cp" | "-classpath" | "--class-path" | "--module-path" | "-p" | "--system" | "--by" => {
+                let Some(value) = args.next() else {
+                    eprintln!("jcfr: {} needs a value", arg);
+                    std::process::exit(1);
+                };
+                match arg.as_str() {
+                    "--by" if ["class", "package", "archive"].contains(&value.as_str()) => by = value,
+                    "--by" => {
+                        eprintln!("jcfr: --by is class, package or archive, not {}", value);
+                        std::process::exit(1);
+                    }
+                    _ => lookup.push((arg, value)),
+                }
+            }
+            "--dot" | "--json" => format = &arg[2..],
+            _ => inputs.push(arg),
+        }
+    }
+    if inputs.is_empty() {
+        eprintln!("jcfr: Filename not specified");
+        std::process::exit(1);
+    }
+
+    let (classes, class_path) = read_inputs(&inputs, &lookup);
+
+    let mut graph = DependencyGraph::new();
+    let mut inputs = HashMap::<String, String>::new();
+    for (class_file, input) in &classes {
+        graph.add(class_file);
+        inputs.insert(class_file.get_class_name(), input.clone());
+    }
+    let origin = |class: &str| inputs.get(class).cloned()
+        .or_else(|| class_path.get_origin(class).map(|o| o.to_string()));
+    let key = |class: &str| match by {
+        "package" => hierarchy::package(class).to_string(),
+        "archive" => origin(class).unwrap_or_else(|| "not found".to_string()),
+        _ => class.to_string(),
+    };
+    let mut origins = std::collections::BTreeMap::<String, String>::new();
+    for class in graph.nodes.values().flatten() {
+        if let Some(o) = origin(class) {
+            origins.entry(key(class)).or_insert(o);
+        }
+    }
+    let report = DependencyReport {
+        by: by.to_string(),
+        cycles: graph.by_package().find_cycles(),
+        internal: deps::find_internal_uses(&graph, &class_path),
+        graph: match by {
+            "class" => graph,
+            _ => graph.group_by(key),
+        },
+        origins,
+    };
+    match format {
+        "dot" => print!("{}", report.to_dot()),
+        "json" => print!("{}", report.to_json()),
+        _ => print!("{}", report.to_text()),
+    }
+}
+
+// jcfreader callgraph [-cp path] [--module-path path] [--system jdk] [--rta] [--entry class.method[:descriptor]]
+// [--entry-annotation type] [--callers method | --callees method | --reachable] [--dot|--json] app.jar ...,
+// main methods and the methods with the annotations are the entry points RTA and --reachable start from
+fn callgraph(args: &[String]) {
+    let mut inputs = Vec::<&String>::new();
+    let mut lookup = Vec::<(&str, &String)>::new();
+    let mut algorithm = Algorithm::Cha;
+    let mut entries = Vec::<&String>::new();
+    let mut annotations = Vec::<String>::new();
+    let mut query: Option<(&str, Option<&String>)> = None;
+    let mut format = "text";
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-cp" | "-classpath" | "--class-path" | "--module-path" | "-p" | "--system" | "--entry" | "--entry-annotation"
+                | "--callers" | "--callees" => {
+                let Some(value) = args.next() else {
+                    eprintln!("jcfr: {} needs a value", arg);
+                    std::process::exit(1);
+                };
+                match arg.as_str() {
+                    "--entry" => entries.push(value),
+                    "--entry-annotation" => annotations.push(class_path::internal_name(value)),
+                    "--callers" | "--callees" => query = Some((&arg[2..], Some(value))),
+                    _ => lookup.push((arg, value)),
+                }
+            }
+            "--rta" => algorithm = Algorithm::Rta,
+            "--reachable" => query = Some(("reachable", None)),
+            "--dot" | "--json" => format = &arg[2..],
+            _ => inputs.push(arg),
+        }
+    }
+    if inputs.is_empty() {
+        eprintln!("jcfr: Filename not specified");
+        std::process::exit(1);
+    }
+    if let Some(input) = inputs.iter().find(|i| i.ends_with(".class")) {
+        eprintln!("jcfr: {}: callgraph reads jars, directories and jmods", input);
+        std::process::exit(1);
+    }
+
+    let (classes, class_path) = read_inputs(&inputs, &lookup);
+    let names: Vec<String> = classes.iter().map(|(c, _)| c.get_class_name()).collect();
+    drop(classes);
+    let hierarchy = Hierarchy::new(class_path);
+    let mut entry_points = call_graph::find_entry_points(&hierarchy, &names, &annotations);
+    for entry in entries {
+        let found = call_graph::find_declared_methods(&hierarchy, entry);
+        if found.is_empty() {
+            eprintln!("jcfr: no method {}", entry);
+            std::process::exit(1);
+        }
+        entry_points.extend(found);
+    }
+    let graph = CallGraph::new(&hierarchy, &names, &entry_points, algorithm);
+
+    match query {
+        Some(("reachable", _)) => {
+            let reachable = graph.reachable(&entry_points);
+            for method in graph.get_methods() {
+                let reached = if reachable.contains(method) { "reachable" } else { "unreachable" };
+                println!("{} {}", reached, method);
+            }
+        }
+        Some((direction, Some(name))) => {
+            let methods = graph.find_methods(name);
+            if methods.is_empty() {
+                eprintln!("jcfr: no method {}", name);
+                std::process::exit(1);
+            }
+            for method in methods {
+                println!("{}", method);
+                let related = if direction == "callers" { graph.callers(method) } else { graph.callees(method) };
+                for m in related {
+                    println!("  {}", m);
+                }
+            }
+        }
+        _ => match format {
+            "dot" => print!("{}", graph.to_dot()),
+            "json" => print!("{}", graph.to_json()),
+            _ => print!("{}", graph.to_text()),
+        },
+    }
+}
+
+// jcfreader unused [--root class | --root package.*] app.jar ..., the private members, classes and Class constants
+// that can go, the classes with main methods are roots too
+fn unused(args: &[String]) {
+    let mut inputs = Vec::<&String>::new();
+    let mut roots = Vec::<String>::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--root" {
+            match args.next() {
+                Some(root) => match root.strip_suffix(".*") {
+                    Some(package) => roots.push(format!("{}/", package.replace('.', "/"))),
+                    None => roots.push(class_path::internal_name(root)),
+                },
+                None => {
+                    eprintln!("jcfr: --root needs a value");
+                    std::process::exit(1);
+                }
+            }
+        } else {
+            inputs.push(arg);
+        }
+    }
+    if inputs.is_empty() {
+        eprintln!("jcfr: Filename not specified");
+        std::process::exit(1);
+    }
+    let (classes, _) = read_inputs(&inputs, &[]);
+    let classes: Vec<ClassFile> = classes.into_iter().map(|(c, _)| c).collect();
+    let found = unused::find_unused(&classes, &roots);
+    let mut text = String::new();
+    for u in &found {
+        text.push_str(&format!("{}\n", u));
+    }
+    print!("{}", text);
+    println!("{} bytes removable", found.iter().map(|u| u.size).sum::<usize>());
+}
+
+// jcfreader api-diff [-cp path] [--module-path path] [--system jdk] old.jar new.jar, the API changes classified as
+// breaking, source-only or compatible, exits with 1 when one breaks pre-existing binaries
+fn api_diff(args: &[String]) {
+    let mut inputs = Vec::<&String>::new();
+    let mut lookup = Vec::<(&str, &String)>::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-cp" | "-classpath" | "--class-path" | "--module-path" | "-p" | "--system" => match args.next() {
+                Some(value) => lookup.push((arg, value)),
+                None => {
+                    eprintln!("jcfr: {} needs a value", arg);
+                    std::process::exit(1);
+                }
+            },
+            _ => inputs.push(arg),
+        }
+    }
+    let [old, new] = inputs.as_slice() else {
+        eprintln!("jcfr: api-diff compares an old and a new jar");
+        std::process::exit(1);
+    };
+    let read = |input: &String| {
+        let (classes, class_path) = read_inputs(&[input], &lookup);
+        let classes: Vec<ClassFile> = classes.into_iter().map(|(c, _)| c).collect();
+        let hierarchy = Hierarchy::new(class_path);
+        (Api::new(&hierarchy, &classes), hierarchy)
+    };
+    let (old, _) = read(old);
+    let (new, hierarchy) = read(new);
+    let changes = api_diff::diff(&old, &new, &hierarchy);
+    let mut text = String::new();
+    for change in &changes {
+        text.push_str(&format!("{}\n", change));
+    }
+    print!("{}", text);
+    let count = |severity| changes.iter().filter(|c| c.severity == severity).count();
+    println!("{} breaking, {} source-only, {} compatible", count(Severity::Breaking), count(Severity::SourceOnly), count(Severity::Compatible));
+    if count(Severity::Breaking) > 0 {
+        std::process::exit(1);
+    }
+}
+
+// jcfreader diff A.class B.class, what changed from A to B with the constant pool compared by value and the code
+// aligned by instruction, exits with 1 when they differ
+fn diff(args: &[String]) {
+    let [old, new] = args else {
+        eprintln!("jcfr: diff compares two classes");
+        std::process::exit(1);
+    };
+    let text = class_diff::diff(&ClassFile::new(old, Dump::None), &ClassFile::new(new, Dump::None));
+    print!("{}", text);
+    if !text.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+// every class of the inputs, jars, directories, jmods or classes, with the input it is from, and a class path of the
+// inputs, that are not classes, followed by the -cp, --module-path and --system options
+fn read_inputs(inputs: &[&String], lookup: &[(&str, &String)]) -> (Vec<(ClassFile, String)>, ClassPath) {
+    let mut classes = Vec::<(ClassFile, String)>::new();
+    let mut class_path = ClassPath::new();
+    for input in inputs {
+        if input.ends_with(".class") {
+            classes.push((ClassFile::new(input, Dump::None), input.to_string()));
+            continue;
+        }
+        let mut input_path = ClassPath::new();
+        if let Err(e) = input_path.add(Path::new(input)).and_then(|_| class_path.add(Path::new(input))) {
+            eprintln!("jcfr: {}", e);
+            std::process::exit(1);
+        }
+        // a class that cannot be read is reported and left out, as it is when found through the class path
+        for name in input_path.get_class_names() {
+            match input_path.read_class(&name) {
+                Ok(Some(class_file)) => classes.push((class_file, input.to_string())),
+                Ok(None) => (),
+                Err(e) => eprintln!("jcfr: {}", e),
+            }
+        }
+    }
+    for &(option, value) in lookup {
+        let added = match option {
+            "--module-path" | "-p" => class_path.add_module_path(value),
+            "--system" => class_path.add_runtime_image(Path::new(value)),
+            _ => class_path.add_class_path(value),
+        };
+        if let Err(e) = added {
+            eprintln!("jcfr: {}", e);
+            std::process::exit(1);
+        }
+    }
+    (classes, class_path)
+}
+
+fn print_common_attributes<T: HasAttributes>(class_file: &ClassFile, item: &T) {
+    if let Some(signature) = item.signature(class_file) {
+        println!("Signature {}", signature);
+    }
+    if item.is_deprecated(class_file) {
+        println!("Deprecated");
+    }
+    if item.is_synthetic(class_file) {
+        println!("Synthetic");
+    }
+}
+
+fn print_type_annotations(type_annotations: &TypeAnnotations) {
+    for t in &type_annotations.runtime_visible {
+        println!("RuntimeVisibleTypeAnnotation {}", t);
+    }
+    for t in &type_annotations.runtime_invisible {
+        println!("RuntimeInvisibleTypeAnnotation {}", t);
+    }
 }
@@ -0,0 +1,208 @@
+// module module
+// Module, ModulePackages and ModuleMainClass attributes of module-info.class
+use std::fmt;
+use crate::access_flags::{flag_names, ACC_MANDATED, ACC_SYNTHETIC};
+use crate::attribute::{optional, DecodeError};
+use crate::class_file_reader::Tag;
+use crate::{utf8, AttributeReader, ClassFile};
+
+pub const ACC_OPEN: u16 = 0x0020;
+pub const ACC_TRANSITIVE: u16 = 0x0020;
+pub const ACC_STATIC_PHASE: u16 = 0x0040;
+
+// JVMS 4.7.25, the module, each requires, and each exports or opens
+const MODULE_FLAGS: [(u16, &str); 3] = [(ACC_OPEN, "ACC_OPEN"), (ACC_SYNTHETIC, "ACC_SYNTHETIC"), (ACC_MANDATED, "ACC_MANDATED")];
+const REQUIRES_FLAGS: [(u16, &str); 4] = [
+    (ACC_TRANSITIVE, "ACC_TRANSITIVE"),
+    (ACC_STATIC_PHASE, "ACC_STATIC_PHASE"),
+    (ACC_SYNTHETIC, "ACC_SYNTHETIC"),
+    (ACC_MANDATED, "ACC_MANDATED"),
+];
+const EXPORTS_FLAGS: [(u16, &str); 2] = [(ACC_SYNTHETIC, "ACC_SYNTHETIC"), (ACC_MANDATED, "ACC_MANDATED")];
+
+pub fn module_flag_names(flags: u16) -> Vec<&'static str> {
+    flag_names(flags, &MODULE_FLAGS)
+}
+
+pub fn requires_flag_names(flags: u16) -> Vec<&'static str> {
+    flag_names(flags, &REQUIRES_FLAGS)
+}
+
+pub fn exports_flag_names(flags: u16) -> Vec<&'static str> {
+    flag_names(flags, &EXPORTS_FLAGS)
+}
+
+#[derive(Debug)]
+pub struct Requires {
+    pub name: String,
+    pub flags: u16,
+    pub version: Option<String>,
+}
+
+// exports and opens share a layout
+#[derive(Debug)]
+pub struct Exports {
+    pub package: String,
+    pub flags: u16,
+    pub to: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Provides {
+    pub service: String,
+    pub with: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ModuleDescriptor {
+    pub name: String,
+    pub flags: u16,
+    pub version: Option<String>,
+    pub requires: Vec<Requires>,
+    pub exports: Vec<Exports>,
+    pub opens: Vec<Exports>,
+    pub uses: Vec<String>,
+    pub provides: Vec<Provides>,
+    pub packages: Vec<String>,      // ModulePackages
+    pub main_class: Option<String>, // ModuleMainClass
+}
+
+impl ModuleDescriptor {
+    pub fn new(class_file: &ClassFile, info: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = AttributeReader::new(info);
+        let name = module_name(class_file, reader.try_u16()?)?;
+        let flags = reader.try_u16()?;
+        let version = optional(reader.try_u16()?, |i| utf8(class_file, i))?;
+
+        let count = reader.try_u16()?;
+        let mut requires = Vec::<Requires>::with_capacity(count as usize);
+        for _ in 0..count {
+            requires.push(Requires {
+                name: module_name(class_file, reader.try_u16()?)?,
+                flags: reader.try_u16()?,
+                version: optional(reader.try_u16()?, |i| utf8(class_file, i))?,
+            });
+        }
+
+        let exports = ModuleDescriptor::read_exports(class_file, &mut reader)?;
+        let opens = ModuleDescriptor::read_exports(class_file, &mut reader)?;
+
+        let count = reader.try_u16()?;
+        let mut uses = Vec::<String>::with_capacity(count as usize);
+        for _ in 0..count {
+            uses.push(dotted_name(class_file, reader.try_u16()?, Tag::Class)?);
+        }
+
+        let count = reader.try_u16()?;
+        let mut provides = Vec::<Provides>::with_capacity(count as usize);
+        for _ in 0..count {
+            let service = dotted_name(class_file, reader.try_u16()?, Tag::Class)?;
+            let with_count = reader.try_u16()?;
+            let mut with = Vec::<String>::with_capacity(with_count as usize);
+            for _ in 0..with_count {
+                with.push(dotted_name(class_file, reader.try_u16()?, Tag::Class)?);
+            }
+            provides.push(Provides { service, with });
+        }
+
+        Ok(Self {
+            name, flags, version, requires, exports, opens, uses, provides,
+            ..Default::default()
+        })
+    }
+
+    fn read_exports(class_file: &ClassFile, reader: &mut AttributeReader) -> Result<Vec<Exports>, DecodeError> {
+        let count = reader.try_u16()?;
+        let mut exports = Vec::<Exports>::with_capacity(count as usize);
+        for _ in 0..count {
+            let package = dotted_name(class_file, reader.try_u16()?, Tag::Package)?;
+            let flags = reader.try_u16()?;
+            let to_count = reader.try_u16()?;
+            let mut to = Vec::<String>::with_capacity(to_count as usize);
+            for _ in 0..to_count {
+                to.push(module_name(class_file, reader.try_u16()?)?);
+            }
+            exports.push(Exports { package, flags, to });
+        }
+        Ok(exports)
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.flags & ACC_OPEN != 0
+    }
+}
+
+pub fn get_module_packages(class_file: &ClassFile, info: &[u8]) -> Result<Vec<String>, DecodeError> {
+    let mut reader = AttributeReader::new(info);
+    let count = reader.try_u16()?;
+    let mut packages = Vec::<String>::with_capacity(count as usize);
+    for _ in 0..count {
+        packages.push(dotted_name(class_file, reader.try_u16()?, Tag::Package)?);
+    }
+    Ok(packages)
+}
+
+pub fn get_module_main_class(class_file: &ClassFile, info: &[u8]) -> Result<String, DecodeError> {
+    let mut reader = AttributeReader::new(info);
+    dotted_name(class_file, reader.try_u16()?, Tag::Class)
+}
+
+fn module_name(class_file: &ClassFile, index: u16) -> Result<String, DecodeError> {
+    class_file.constant_pool.find_named(index, Tag::Module).ok_or(DecodeError::Constant(index))
+}
+
+// packages and classes are stored in internal form
+fn dotted_name(class_file: &ClassFile, index: u16, tag: Tag) -> Result<String, DecodeError> {
+    let name = class_file.constant_pool.find_named(index, tag).ok_or(DecodeError::Constant(index))?;
+    Ok(name.replace('/', "."))
+}
+
+// module-info.java style
+impl fmt::Display for ModuleDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_open() {
+            write!(f, "open ")?;
+        }
+        write!(f, "module {}", self.name)?;
+        if let Some(version) = &self.version {
+            write!(f, "@{}", version)?;
+        }
+        writeln!(f, " {{")?;
+
+        for r in &self.requires {
+            write!(f, "    requires ")?;
+            if r.flags & ACC_TRANSITIVE != 0 {
+                write!(f, "transitive ")?;
+            }
+            if r.flags & ACC_STATIC_PHASE != 0 {
+                write!(f, "static ")?;
+            }
+            write!(f, "{};", r.name)?;
+            if r.flags & ACC_MANDATED != 0 {
+                write!(f, " // mandated")?;
+            }
+            writeln!(f)?;
+        }
+        for e in &self.exports {
+            write_exports(f, "exports", e)?;
+        }
+        for o in &self.opens {
+            write_exports(f, "opens", o)?;
+        }
+        for u in &self.uses {
+            writeln!(f, "    uses {};", u)?;
+        }
+        for p in &self.provides {
+            writeln!(f, "    provides {} with {};", p.service, p.with.join(", "))?;
+        }
+        write!(f, "}}")
+    }
+}
+
+fn write_exports(f: &mut fmt::Formatter, keyword: &str, e: &Exports) -> fmt::Result {
+    write!(f, "    {} {}", keyword, e.package)?;
+    if !e.to.is_empty() {
+        write!(f, " to {}", e.to.join(", "))?;
+    }
+    writeln!(f, ";")
+}
@@ -0,0 +1,57 @@
+// module record
+// Record and PermittedSubclasses attributes
+use crate::attribute::DecodeError;
+use crate::descriptor::JavaType;
+use crate::{class_name, utf8, Attribute, AttributeReader, ClassFile};
+
+pub struct RecordComponent {
+    name: String,
+    descriptor: String,
+    attributes: Vec<Attribute>,
+}
+
+impl RecordComponent {
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn get_descriptor(&self) -> &String {
+        &self.descriptor
+    }
+
+    pub fn get_type(&self) -> Option<JavaType> {
+        JavaType::from_descriptor(&self.descriptor)
+    }
+
+    pub fn get_attributes(&self) -> &Vec<Attribute> {
+        &self.attributes
+    }
+}
+
+pub fn get_record_components(class_file: &ClassFile, info: &[u8]) -> Result<Vec<RecordComponent>, DecodeError> {
+    let mut reader = AttributeReader::new(info);
+    let count = reader.try_u16()?;
+    let mut components = Vec::<RecordComponent>::with_capacity(count as usize);
+    for _ in 0..count {
+        let name = utf8(class_file, reader.try_u16()?)?;
+        let descriptor = utf8(class_file, reader.try_u16()?)?;
+        let attributes_count = reader.try_u16()?;
+        let mut attributes = Vec::<Attribute>::with_capacity(attributes_count as usize);
+        for _ in 0..attributes_count {
+            let name = utf8(class_file, reader.try_u16()?)?;
+            let length = reader.try_u32()?;
+            attributes.push(Attribute {
+                name,
+                info: reader.try_bytes(length as usize)?,
+            });
+        }
+        components.push(RecordComponent { name, descriptor, attributes });
+    }
+    Ok(components)
+}
+
+pub fn get_permitted_subclasses(class_file: &ClassFile, info: &[u8]) -> Result<Vec<String>, DecodeError> {
+    let mut reader = AttributeReader::new(info);
+    let count = reader.try_u16()?;
+    (0..count).map(|_| class_name(class_file, reader.try_u16()?)).collect()
+}
@@ -0,0 +1,168 @@
+// module smap
+// JSR-045 source map held in the SourceDebugExtension attribute
+// Kotlin inline functions and JSP pages map their output lines back to the original sources with it
+
+#[derive(Debug)]
+pub struct FileInfo {
+    pub id: u32,
+    pub name: String,
+    pub path: Option<String>,
+}
+
+// InputStartLine#LineFileID,RepeatCount:OutputStartLine,OutputLineIncrement
+#[derive(Debug)]
+pub struct LineInfo {
+    pub input_start_line: u32,
+    pub file_id: u32,
+    pub repeat_count: u32,
+    pub output_start_line: u32,
+    pub output_line_increment: u32,
+}
+
+impl LineInfo {
+    // input line for an output line covered by this entry
+    fn map(&self, line: u32) -> Option<u32> {
+        if line < self.output_start_line {
+            return None;
+        }
+        let offset = line - self.output_start_line;
+        match self.output_line_increment {
+            0 if offset == 0 => Some(self.input_start_line),
+            0 => None,
+            // each of repeat_count input lines covers inc output lines, without multiplying out to more than a u32
+            inc if offset / inc < self.repeat_count => self.input_start_line.checked_add(offset / inc),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Stratum {
+    pub id: String,
+    pub files: Vec<FileInfo>,
+    pub lines: Vec<LineInfo>,
+}
+
+impl Stratum {
+    pub fn get_file(&self, id: u32) -> Option<&FileInfo> {
+        self.files.iter().find(|f| f.id == id)
+    }
+
+    // None for a line no LineInfo covers, which is a line of the output file itself
+    pub fn remap(&self, line: u32) -> Option<(String, u32)> {
+        self.lines.iter().find_map(|l| {
+            let input = l.map(line)?;
+            let file = self.get_file(l.file_id)?;
+            Some((file.name.clone(), input))
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Smap {
+    pub output_file: String,
+    pub default_stratum: String,
+    pub strata: Vec<Stratum>,
+}
+
+enum Section {
+    None,
+    File,
+    Line,
+    Other,
+}
+
+impl Smap {
+    // None when the text is not an SMAP
+    pub fn parse(text: &str) -> Option<Smap> {
+        let mut lines = text.lines().map(|l| l.trim_end_matches('\r'));
+        if lines.next()? != "SMAP" {
+            return None;
+        }
+        let output_file = lines.next()?.to_string();
+        let default_stratum = lines.next()?.to_string();
+        let mut strata = Vec::<Stratum>::new();
+        let mut section = Section::None;
+        let mut last_file_id = 0;
+        let mut pending_file: Option<usize> = None;
+
+        for line in lines {
+            if let Some(rest) = line.strip_prefix('*') {
+                pending_file = None;
+                let mut words = rest.split_whitespace();
+                match words.next() {
+                    Some("S") => {
+                        strata.push(Stratum { id: words.next()?.to_string(), files: Vec::new(), lines: Vec::new() });
+                        last_file_id = 0;
+                        section = Section::None;
+                    }
+                    Some("F") => section = Section::File,
+                    Some("L") => section = Section::Line,
+                    Some("E") => section = Section::None,
+                    // vendor, embedded and future sections are skipped
+                    _ => section = Section::Other,
+                }
+                continue;
+            }
+            let stratum = match strata.last_mut() {
+                Some(s) => s,
+                None => continue,
+            };
+            match section {
+                Section::File => {
+                    if let Some(i) = pending_file.take() {
+                        stratum.files[i].path = Some(line.to_string());
+                        continue;
+                    }
+                    let (with_path, entry) = match line.strip_prefix('+') {
+                        Some(e) => (true, e.trim_start()),
+                        None => (false, line),
+                    };
+                    let (id, name) = entry.split_once(' ')?;
+                    stratum.files.push(FileInfo { id: id.parse().ok()?, name: name.trim().to_string(), path: None });
+                    if with_path {
+                        pending_file = Some(stratum.files.len() - 1);
+                    }
+                }
+                Section::Line => {
+                    if line.is_empty() {
+                        continue;
+                    }
+                    let info = Smap::parse_line_info(line, last_file_id)?;
+                    last_file_id = info.file_id;
+                    stratum.lines.push(info);
+                }
+                Section::None | Section::Other => (),
+            }
+        }
+
+        Some(Smap { output_file, default_stratum, strata })
+    }
+
+    // the LineFileID carries over from the previous entry when missing
+    fn parse_line_info(line: &str, last_file_id: u32) -> Option<LineInfo> {
+        let (input, output) = line.split_once(':')?;
+        let (input, repeat_count) = match input.split_once(',') {
+            Some((i, r)) => (i, r.trim().parse().ok()?),
+            None => (input, 1),
+        };
+        let (input_start_line, file_id) = match input.split_once('#') {
+            Some((l, f)) => (l.trim().parse().ok()?, f.trim().parse().ok()?),
+            None => (input.trim().parse().ok()?, last_file_id),
+        };
+        let (output_start_line, output_line_increment) = match output.split_once(',') {
+            Some((o, i)) => (o.trim().parse().ok()?, i.trim().parse().ok()?),
+            None => (output.trim().parse().ok()?, 1),
+        };
+        Some(LineInfo { input_start_line, file_id, repeat_count, output_start_line, output_line_increment })
+    }
+
+    pub fn get_stratum(&self, id: &str) -> Option<&Stratum> {
+        self.strata.iter().find(|s| s.id == id)
+    }
+
+    // output line (from the LineNumberTable) to source file and line in the default stratum, None as Stratum::remap
+    pub fn remap(&self, line: u32) -> Option<(String, u32)> {
+        self.get_stratum(&self.default_stratum)?.remap(line)
+    }
+}
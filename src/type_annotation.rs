@@ -0,0 +1,301 @@
+// module type_annotation
+// RuntimeVisibleTypeAnnotations / RuntimeInvisibleTypeAnnotations (JSR 308)
+use std::fmt;
+use crate::attribute::DecodeError;
+use crate::{Annotation, AttributeReader, ClassFile};
+
+#[derive(Debug)]
+pub struct LocalVarTarget {
+    pub start_pc: u16,
+    pub length: u16,
+    pub index: u16,
+}
+
+// target_type with its target_info
+#[derive(Debug)]
+pub enum TargetInfo {
+    ClassTypeParameter(u8),                        // 0x00
+    MethodTypeParameter(u8),                       // 0x01
+    Supertype(u16),                                // 0x10 65535 is the superclass
+    ClassTypeParameterBound(u8, u8),               // 0x11 type parameter, bound
+    MethodTypeParameterBound(u8, u8),              // 0x12
+    Field,                                         // 0x13 field or record component
+    Return,                                        // 0x14 return type or new object
+    Receiver,                                      // 0x15
+    FormalParameter(u8),                           // 0x16
+    Throws(u16),                                   // 0x17
+    LocalVariable(Vec<LocalVarTarget>),            // 0x40
+    ResourceVariable(Vec<LocalVarTarget>),         // 0x41
+    ExceptionParameter(u16),                       // 0x42 exception table index
+    Instanceof(u16),                               // 0x43 offset
+    New(u16),                                      // 0x44
+    ConstructorReference(u16),                     // 0x45
+    MethodReference(u16),                          // 0x46
+    Cast(u16, u8),                                 // 0x47 offset, type argument
+    ConstructorInvocationTypeArgument(u16, u8),    // 0x48
+    MethodInvocationTypeArgument(u16, u8),         // 0x49
+    ConstructorReferenceTypeArgument(u16, u8),     // 0x4a
+    MethodReferenceTypeArgument(u16, u8),          // 0x4b
+}
+
+impl TargetInfo {
+    fn new(reader: &mut AttributeReader) -> Result<Self, DecodeError> {
+        let target_type = reader.try_u8()?;
+        Ok(match target_type {
+            0x00 => TargetInfo::ClassTypeParameter(reader.try_u8()?),
+            0x01 => TargetInfo::MethodTypeParameter(reader.try_u8()?),
+            0x10 => TargetInfo::Supertype(reader.try_u16()?),
+            0x11 => TargetInfo::ClassTypeParameterBound(reader.try_u8()?, reader.try_u8()?),
+            0x12 => TargetInfo::MethodTypeParameterBound(reader.try_u8()?, reader.try_u8()?),
+            0x13 => TargetInfo::Field,
+            0x14 => TargetInfo::Return,
+            0x15 => TargetInfo::Receiver,
+            0x16 => TargetInfo::FormalParameter(reader.try_u8()?),
+            0x17 => TargetInfo::Throws(reader.try_u16()?),
+            0x40 => TargetInfo::LocalVariable(TargetInfo::read_localvar_table(reader)?),
+            0x41 => TargetInfo::ResourceVariable(TargetInfo::read_localvar_table(reader)?),
+            0x42 => TargetInfo::ExceptionParameter(reader.try_u16()?),
+            0x43 => TargetInfo::Instanceof(reader.try_u16()?),
+            0x44 => TargetInfo::New(reader.try_u16()?),
+            0x45 => TargetInfo::ConstructorReference(reader.try_u16()?),
+            0x46 => TargetInfo::MethodReference(reader.try_u16()?),
+            0x47 => TargetInfo::Cast(reader.try_u16()?, reader.try_u8()?),
+            0x48 => TargetInfo::ConstructorInvocationTypeArgument(reader.try_u16()?, reader.try_u8()?),
+            0x49 => TargetInfo::MethodInvocationTypeArgument(reader.try_u16()?, reader.try_u8()?),
+            0x4a => TargetInfo::ConstructorReferenceTypeArgument(reader.try_u16()?, reader.try_u8()?),
+            0x4b => TargetInfo::MethodReferenceTypeArgument(reader.try_u16()?, reader.try_u8()?),
+            _ => return Err(DecodeError::Tag("target_type", target_type)),
+        })
+    }
+
+    fn read_localvar_table(reader: &mut AttributeReader) -> Result<Vec<LocalVarTarget>, DecodeError> {
+        let num = reader.try_u16()?;
+        let mut table = Vec::<LocalVarTarget>::with_capacity(num as usize);
+        for _ in 0..num {
+            table.push(LocalVarTarget {
+                start_pc: reader.try_u16()?,
+                length: reader.try_u16()?,
+                index: reader.try_u16()?,
+            });
+        }
+        Ok(table)
+    }
+
+    pub fn target_type(&self) -> u8 {
+        match self {
+            TargetInfo::ClassTypeParameter(_) => 0x00,
+            TargetInfo::MethodTypeParameter(_) => 0x01,
+            TargetInfo::Supertype(_) => 0x10,
+            TargetInfo::ClassTypeParameterBound(_, _) => 0x11,
+            TargetInfo::MethodTypeParameterBound(_, _) => 0x12,
+            TargetInfo::Field => 0x13,
+            TargetInfo::Return => 0x14,
+            TargetInfo::Receiver => 0x15,
+            TargetInfo::FormalParameter(_) => 0x16,
+            TargetInfo::Throws(_) => 0x17,
+            TargetInfo::LocalVariable(_) => 0x40,
+            TargetInfo::ResourceVariable(_) => 0x41,
+            TargetInfo::ExceptionParameter(_) => 0x42,
+            TargetInfo::Instanceof(_) => 0x43,
+            TargetInfo::New(_) => 0x44,
+            TargetInfo::ConstructorReference(_) => 0x45,
+            TargetInfo::MethodReference(_) => 0x46,
+            TargetInfo::Cast(_, _) => 0x47,
+            TargetInfo::ConstructorInvocationTypeArgument(_, _) => 0x48,
+            TargetInfo::MethodInvocationTypeArgument(_, _) => 0x49,
+            TargetInfo::ConstructorReferenceTypeArgument(_, _) => 0x4a,
+            TargetInfo::MethodReferenceTypeArgument(_, _) => 0x4b,
+        }
+    }
+}
+
+// same wording as javap
+impl fmt::Display for TargetInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TargetInfo::ClassTypeParameter(i) => write!(f, "CLASS_TYPE_PARAMETER, param_index={}", i),
+            TargetInfo::MethodTypeParameter(i) => write!(f, "METHOD_TYPE_PARAMETER, param_index={}", i),
+            TargetInfo::Supertype(i) => write!(f, "CLASS_EXTENDS, type_index={}", i),
+            TargetInfo::ClassTypeParameterBound(i, j) =>
+                write!(f, "CLASS_TYPE_PARAMETER_BOUND, param_index={}, bound_index={}", i, j),
+            TargetInfo::MethodTypeParameterBound(i, j) =>
+                write!(f, "METHOD_TYPE_PARAMETER_BOUND, param_index={}, bound_index={}", i, j),
+            TargetInfo::Field => write!(f, "FIELD"),
+            TargetInfo::Return => write!(f, "METHOD_RETURN"),
+            TargetInfo::Receiver => write!(f, "METHOD_RECEIVER"),
+            TargetInfo::FormalParameter(i) => write!(f, "METHOD_FORMAL_PARAMETER, param_index={}", i),
+            TargetInfo::Throws(i) => write!(f, "THROWS, type_index={}", i),
+            TargetInfo::LocalVariable(t) | TargetInfo::ResourceVariable(t) => {
+                match self {
+                    TargetInfo::LocalVariable(_) => write!(f, "LOCAL_VARIABLE, {{")?,
+                    _ => write!(f, "RESOURCE_VARIABLE, {{")?,
+                }
+                for (n, l) in t.iter().enumerate() {
+                    if n > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "start_pc={}, length={}, index={}", l.start_pc, l.length, l.index)?;
+                }
+                write!(f, "}}")
+            }
+            TargetInfo::ExceptionParameter(i) => write!(f, "EXCEPTION_PARAMETER, exception_index={}", i),
+            TargetInfo::Instanceof(o) => write!(f, "INSTANCEOF, offset={}", o),
+            TargetInfo::New(o) => write!(f, "NEW, offset={}", o),
+            TargetInfo::ConstructorReference(o) => write!(f, "CONSTRUCTOR_REFERENCE, offset={}", o),
+            TargetInfo::MethodReference(o) => write!(f, "METHOD_REFERENCE, offset={}", o),
+            TargetInfo::Cast(o, i) => write!(f, "CAST, offset={}, type_index={}", o, i),
+            TargetInfo::ConstructorInvocationTypeArgument(o, i) =>
+                write!(f, "CONSTRUCTOR_INVOCATION_TYPE_ARGUMENT, offset={}, type_index={}", o, i),
+            TargetInfo::MethodInvocationTypeArgument(o, i) =>
+                write!(f, "METHOD_INVOCATION_TYPE_ARGUMENT, offset={}, type_index={}", o, i),
+            TargetInfo::ConstructorReferenceTypeArgument(o, i) =>
+                write!(f, "CONSTRUCTOR_REFERENCE_TYPE_ARGUMENT, offset={}, type_index={}", o, i),
+            TargetInfo::MethodReferenceTypeArgument(o, i) =>
+                write!(f, "METHOD_REFERENCE_TYPE_ARGUMENT, offset={}, type_index={}", o, i),
+        }
+    }
+}
+
+// one step of the type_path
+#[derive(Debug)]
+pub enum TypePathKind {
+    Array,              // 0 deeper in an array type
+    Nested,             // 1 deeper in a nested type
+    WildcardBound,      // 2 on the bound of a wildcard type argument
+    TypeArgument(u8),   // 3 on a type argument of a parameterized type
+}
+
+impl fmt::Display for TypePathKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TypePathKind::Array => write!(f, "ARRAY"),
+            TypePathKind::Nested => write!(f, "INNER_TYPE"),
+            TypePathKind::WildcardBound => write!(f, "WILDCARD"),
+            TypePathKind::TypeArgument(i) => write!(f, "TYPE_ARGUMENT({})", i),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TypeAnnotation {
+    target: TargetInfo,
+    type_path: Vec<TypePathKind>,
+    annotation: Annotation,
+}
+
+impl TypeAnnotation {
+    fn new(reader: &mut AttributeReader, class_file: &ClassFile) -> Result<Self, DecodeError> {
+        let target = TargetInfo::new(reader)?;
+        let path_length = reader.try_u8()?;
+        let mut type_path = Vec::<TypePathKind>::with_capacity(path_length as usize);
+        for _ in 0..path_length {
+            let kind = reader.try_u8()?;
+            let type_argument_index = reader.try_u8()?;
+            type_path.push(match kind {
+                0 => TypePathKind::Array,
+                1 => TypePathKind::Nested,
+                2 => TypePathKind::WildcardBound,
+                3 => TypePathKind::TypeArgument(type_argument_index),
+                _ => return Err(DecodeError::Tag("type_path kind", kind)),
+            });
+        }
+
+        Ok(Self {
+            target,
+            type_path,
+            annotation: Annotation::new(reader, class_file)?,
+        })
+    }
+
+    pub fn get_target(&self) -> &TargetInfo {
+        &self.target
+    }
+
+    pub fn get_type_path(&self) -> &Vec<TypePathKind> {
+        &self.type_path
+    }
+
+    pub fn get_annotation(&self) -> &Annotation {
+        &self.annotation
+    }
+}
+
+impl fmt::Display for TypeAnnotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.annotation.get_type(), self.target)?;
+        if !self.type_path.is_empty() {
+            let path: Vec<String> = self.type_path.iter().map(|p| p.to_string()).collect();
+            write!(f, ", location=[{}]", path.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn get_type_annotations(class_file: &ClassFile, info: &[u8]) -> Result<Vec<TypeAnnotation>, DecodeError> {
+    let mut reader = AttributeReader::new(info);
+    let num = reader.try_u16()?;
+    let mut type_annotations = Vec::<TypeAnnotation>::with_capacity(num as usize);
+    for _ in 0..num {
+        type_annotations.push(TypeAnnotation::new(&mut reader, class_file)?);
+    }
+    Ok(type_annotations)
+}
+
+fn u16_at(info: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*info.get(at)?, *info.get(at + 1)?]))
+}
+
+// where the annotation starting at this index ends
+fn skip_annotation(info: &[u8], at: usize) -> Option<usize> {
+    let mut at = at + 4;
+    for _ in 0..u16_at(info, at - 2)? {
+        at = skip_element_value(info, at + 2)?;
+    }
+    Some(at)
+}
+
+fn skip_element_value(info: &[u8], at: usize) -> Option<usize> {
+    match *info.get(at)? {
+        b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' | b's' | b'c' => Some(at + 3),
+        b'e' => Some(at + 5),
+        b'@' => skip_annotation(info, at + 1),
+        b'[' => {
+            let mut at = at + 3;
+            for _ in 0..u16_at(info, at - 2)? {
+                at = skip_element_value(info, at)?;
+            }
+            Some(at)
+        }
+        _ => None,
+    }
+}
+
+// where the code offsets are in the type annotations of a Code attribute, so they can move with the code, each with
+// whether it is the start_pc of a local variable range with the length after it. None when the bytes are not
+// type annotations on code
+pub(crate) fn code_offsets(info: &[u8]) -> Option<Vec<(usize, bool)>> {
+    let mut offsets = Vec::<(usize, bool)>::new();
+    let mut at = 2;
+    for _ in 0..u16_at(info, 0)? {
+        match *info.get(at)? {
+            0x40 | 0x41 => {
+                let count = u16_at(info, at + 1)? as usize;
+                offsets.extend((0..count).map(|k| (at + 3 + 6 * k, true)));
+                at += 3 + 6 * count;
+            }
+            0x42 => at += 3,
+            0x43..=0x46 => {
+                offsets.push((at + 1, false));
+                at += 3;
+            }
+            0x47..=0x4b => {
+                offsets.push((at + 1, false));
+                at += 4;
+            }
+            _ => return None,
+        }
+        at += 1 + 2 * *info.get(at)? as usize;
+        at = skip_annotation(info, at)?;
+    }
+    (at == info.len()).then_some(offsets)
+}
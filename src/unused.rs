@@ -0,0 +1,298 @@
+// module unused
+// dead code across a set of classes: private methods and fields no used code refers to, classes the roots do not
+// reach, and Class constants nothing in their class uses, each with the number of bytes removing it saves
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use crate::access_flags::*;
+use crate::attribute::{self, AttributeKind, BootstrapMethod};
+use crate::class_file_reader::{AttributeInfo, ConstantInfo, Index, Tag};
+use crate::code::Code;
+use crate::code::opcode::Opcode;
+use crate::code::stack_map::{StackMapFrame, VerificationType};
+use crate::deps;
+use crate::ClassFile;
+
+// private members serialization and lambda deserialization call by reflection
+const SERIALIZATION_METHODS: [&str; 6] = ["writeObject", "readObject", "readObjectNoData", "writeReplace", "readResolve", "$deserializeLambda$"];
+const SERIALIZATION_FIELDS: [&str; 2] = ["serialVersionUID", "serialPersistentFields"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    Class,
+    Method,
+    Field,
+    Constant,   // a Class entry of the constant pool
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Kind::Class => write!(f, "class"),
+            Kind::Method => write!(f, "method"),
+            Kind::Field => write!(f, "field"),
+            Kind::Constant => write!(f, "constant"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Unused {
+    pub kind: Kind,
+    pub name: String,   // class, class.name:descriptor, or class -> constant
+    pub size: usize,
+}
+
+impl fmt::Display for Unused {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {}", self.size, self.kind, self.name)
+    }
+}
+
+// class, name and descriptor of a method or field
+type Key = (String, String, String);
+
+// what the code of a method refers to
+#[derive(Default)]
+struct References {
+    methods: Vec<Key>,
+    fields: Vec<Key>,
+    classes: BTreeSet<String>,
+}
+
+// method_info and field_info with their attributes
+fn member_size(attributes: &[AttributeInfo]) -> usize {
+    8 + attributes.iter().map(|a| 6 + a.info.len()).sum::<usize>()
+}
+
+fn array_element(name: &str) -> &str {
+    let element = name.trim_start_matches('[');
+    match element.strip_prefix('L') {
+        Some(class) => class.trim_end_matches(';'),
+        None => element,
+    }
+}
+
+fn class_name(class_file: &ClassFile, index: u16) -> Option<String> {
+    let cp = &class_file.constant_pool;
+    match cp.find_constant(index)? {
+        ConstantInfo(Tag::Class, Index::Single(n)) => Some(array_element(&cp.find_utf8(*n)?).to_string()),
+        _ => None,
+    }
+}
+
+// a loadable constant, ldc or a bootstrap argument, a class, a method handle's member or a dynamic constant's bootstrap
+fn add_constant(class_file: &ClassFile, index: u16, bootstrap_methods: &[BootstrapMethod], references: &mut References, depth: usize) {
+    let cp = &class_file.constant_pool;
+    match cp.find_constant(index) {
+        Some(ConstantInfo(Tag::Class, _)) => references.classes.extend(class_name(class_file, index)),
+        Some(ConstantInfo(Tag::MethodHandle, Index::MethodHandle(kind, member))) => {
+            if let Some((class, name, descriptor)) = cp.get_member(*member) {
+                references.classes.insert(array_element(&class).to_string());
+                match kind {
+                    1..=4 => references.fields.push((class, name, descriptor)),
+                    _ => references.methods.push((class, name, descriptor)),
+                }
+            }
+        }
+        Some(ConstantInfo(Tag::Dynamic | Tag::InvokeDynamic, Index::Dynamic(bootstrap, _))) if depth < 8 => {
+            if let Some(bootstrap) = bootstrap_methods.get(*bootstrap as usize) {
+                add_constant(class_file, bootstrap.method_ref, bootstrap_methods, references, depth + 1);
+                for argument in &bootstrap.arguments {
+                    add_constant(class_file, *argument, bootstrap_methods, references, depth + 1);
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+fn code_references(class_file: &ClassFile, code: &Code, bootstrap_methods: &[BootstrapMethod]) -> References {
+    let cp = &class_file.constant_pool;
+    let mut references = References::default();
+    for (_, opcode) in code.get_instructions() {
+        match opcode {
+            Opcode::Invokestatic(i) | Opcode::Invokespecial(i) | Opcode::Invokevirtual(i) | Opcode::Invokeinterface(i, _)
+                | Opcode::Getfield(i) | Opcode::Putfield(i) | Opcode::Getstatic(i) | Opcode::Putstatic(i) => {
+                if let Some((class, name, descriptor)) = cp.get_member(*i) {
+                    references.classes.insert(array_element(&class).to_string());
+                    match opcode {
+                        Opcode::Getfield(_) | Opcode::Putfield(_) | Opcode::Getstatic(_) | Opcode::Putstatic(_) =>
+                            references.fields.push((class, name, descriptor)),
+                        _ => references.methods.push((class, name, descriptor)),
+                    }
+                }
+            }
+            Opcode::New(i) | Opcode::Checkcast(i) | Opcode::Instanceof(i) | Opcode::Anewarray(i) | Opcode::Multianewarray(i, _) =>
+                references.classes.extend(class_name(class_file, *i)),
+            Opcode::Ldc(i) => add_constant(class_file, *i as u16, bootstrap_methods, &mut references, 0),
+            Opcode::LdcW(i) | Opcode::Invokedynamic(i) => add_constant(class_file, *i, bootstrap_methods, &mut references, 0),
+            _ => (),
+        }
+    }
+    for exception in code.get_exception_table() {
+        references.classes.extend(class_name(class_file, exception.catch_type));
+    }
+    for a in class_file.get_attributes_vec(code.get_attributes()) {
+        if let AttributeKind::StackMapTable(frames) = a.decode(class_file) {
+            for frame in &frames {
+                let types: Vec<&VerificationType> = match frame {
+                    StackMapFrame::SameLocals1StackItem(_, t) => vec![t],
+                    StackMapFrame::Append(_, locals) => locals.iter().collect(),
+                    StackMapFrame::Full(_, locals, stack) => locals.iter().chain(stack).collect(),
+                    _ => Vec::new(),
+                };
+                for t in types {
+                    if let VerificationType::Object(name) = t {
+                        references.classes.insert(array_element(name).to_string());
+                    }
+                }
+            }
+        }
+    }
+    references
+}
+
+// the classes a class uses, from its code, supertypes, member references, descriptors, signatures, annotations and
+// the attributes that name classes, and the nested classes it declares in InnerClasses and NestMembers
+fn class_references(class_file: &ClassFile, code_classes: BTreeSet<String>) -> (BTreeSet<String>, BTreeSet<String>) {
+    let this = class_file.get_class_name();
+    let mut used = deps::get_type_references(class_file);
+    used.extend(class_file.get_super_class_name());
+    used.extend(class_file.get_interface_names());
+    used.extend(code_classes);
+    let mut declared = BTreeSet::new();
+    let mut foreign = Vec::new();
+    let member_attributes = class_file.fields.iter().map(|f| &f.attributes).chain(class_file.methods.iter().map(|m| &m.attributes));
+    for attributes in member_attributes.chain([&class_file.attributes]) {
+        for a in class_file.get_attributes_vec(attributes) {
+            match a.decode(class_file) {
+                AttributeKind::Exceptions(classes) | AttributeKind::PermittedSubclasses(classes) => used.extend(classes),
+                AttributeKind::EnclosingMethod(class, _) | AttributeKind::NestHost(class) => {
+                    used.insert(class);
+                }
+                AttributeKind::NestMembers(classes) => declared.extend(classes),
+                AttributeKind::InnerClasses(inner_classes) => {
+                    for i in inner_classes {
+                        if i.inner_class == this {
+                            used.extend(i.outer_class);
+                        } else if i.outer_class.as_ref() == Some(&this) {
+                            declared.insert(i.inner_class);
+                        } else {
+                            foreign.push(i);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+    // the row of a nested class of another class that is used names its outer class
+    for i in foreign {
+        if used.contains(&i.inner_class) {
+            declared.extend(i.outer_class);
+        }
+    }
+    used.remove(&this);
+    (used, declared)
+}
+
+fn bootstrap_methods(class_file: &ClassFile) -> Vec<BootstrapMethod> {
+    match attribute::find(class_file, &class_file.get_attributes_vec(&class_file.attributes), "BootstrapMethods") {
+        Some(AttributeKind::BootstrapMethods(b)) => b,
+        _ => Vec::new(),
+    }
+}
+
+// ranked by size, largest first. The roots are internal names of classes, or packages ending in /, and classes with a
+// main method are roots too; without any, no class is reported
+pub fn find_unused(classes: &[ClassFile], roots: &[String]) -> Vec<Unused> {
+    let mut unused = Vec::new();
+    let mut bodies = HashMap::<Key, References>::new();
+    let mut pending = Vec::<Key>::new();
+    let mut private_methods = Vec::<(Key, usize)>::new();
+    let mut private_fields = Vec::<(Key, usize)>::new();
+    let mut uses = HashMap::<String, BTreeSet<String>>::new();
+    let mut main_classes = Vec::<String>::new();
+    for class_file in classes {
+        let cp = &class_file.constant_pool;
+        let this = class_file.get_class_name();
+        let bootstrap_methods = bootstrap_methods(class_file);
+        let mut code_classes = BTreeSet::new();
+        for method in &class_file.methods {
+            let key = (this.clone(), cp.get_item(&method.name_index), cp.get_item(&method.descriptor_index));
+            let attributes = class_file.get_attributes_vec(&method.attributes);
+            let references = match attributes.iter().find(|a| a.name == "Code") {
+                Some(code) => code_references(class_file, &Code::new(&code.info), &bootstrap_methods),
+                None => References::default(),
+            };
+            if key.1 == "main" && key.2 == "([Ljava/lang/String;)V" && method.access_flags & ACC_STATIC != 0 {
+                main_classes.push(this.clone());
+            }
+            let private = method.access_flags & ACC_PRIVATE != 0;
+            if private && !key.1.starts_with('<') && !SERIALIZATION_METHODS.contains(&key.1.as_str()) {
+                private_methods.push((key.clone(), member_size(&method.attributes)));
+            } else {
+                pending.push(key.clone());
+            }
+            code_classes.extend(references.classes.iter().cloned());
+            bodies.insert(key, references);
+        }
+        for field in &class_file.fields {
+            let name = cp.get_item(&field.name_index);
+            if field.access_flags & ACC_PRIVATE != 0 && !SERIALIZATION_FIELDS.contains(&name.as_str()) {
+                private_fields.push(((this.clone(), name, cp.get_item(&field.descriptor_index)), member_size(&field.attributes)));
+            }
+        }
+
+        // Class entries nothing uses, unused imports
+        let (used, declared) = class_references(class_file, code_classes);
+        for (i, constant) in cp.constant_info.iter().enumerate() {
+            if let ConstantInfo(Tag::Class, _) = constant {
+                let Some(name) = class_name(class_file, i as u16 + 1) else { continue };
+                if name != this && !used.contains(&name) && !declared.contains(&name) {
+                    unused.push(Unused { kind: Kind::Constant, name: format!("{} -> {}", this, name), size: 3 });
+                }
+            }
+        }
+        uses.insert(this, used);
+    }
+
+    // private members are used when a used method refers to them
+    let mut used_methods = HashSet::<Key>::new();
+    let mut used_fields = HashSet::<Key>::new();
+    while let Some(method) = pending.pop() {
+        if !used_methods.insert(method.clone()) {
+            continue;
+        }
+        let Some(references) = bodies.get(&method) else { continue };
+        pending.extend(references.methods.iter().filter(|m| !used_methods.contains(*m)).cloned());
+        used_fields.extend(references.fields.iter().cloned());
+    }
+    for (key, size) in private_methods.into_iter().filter(|(key, _)| !used_methods.contains(key)) {
+        unused.push(Unused { kind: Kind::Method, name: format!("{}.{}:{}", key.0, key.1, key.2), size });
+    }
+    for (key, size) in private_fields.into_iter().filter(|(key, _)| !used_fields.contains(key)) {
+        unused.push(Unused { kind: Kind::Field, name: format!("{}.{}:{}", key.0, key.1, key.2), size });
+    }
+
+    // classes the roots do not reach through what they use
+    let is_root = |class: &String| main_classes.contains(class)
+        || roots.iter().any(|r| r == class || (r.ends_with('/') && class.starts_with(r.as_str()) && !class[r.len()..].contains('/')));
+    let mut reached = HashSet::<&String>::new();
+    let mut pending: Vec<&String> = uses.keys().filter(|c| is_root(c)).collect();
+    while let Some(class) = pending.pop() {
+        if reached.insert(class) {
+            pending.extend(uses.get(class).into_iter().flatten().filter(|c| uses.contains_key(*c)));
+        }
+    }
+    if !reached.is_empty() {
+        for class_file in classes {
+            let name = class_file.get_class_name();
+            if !reached.contains(&name) && name != "module-info" {
+                unused.push(Unused { kind: Kind::Class, size: class_file.to_bytes().len(), name });
+            }
+        }
+    }
+    unused.sort_by(|a, b| b.size.cmp(&a.size).then(a.kind.cmp(&b.kind)).then(a.name.cmp(&b.name)));
+    unused
+}
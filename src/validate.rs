@@ -0,0 +1,860 @@
+// module validate
+// static format checks (JVMS 4.1 - 4.9.1), every problem found is reported instead of stopping at the first
+use std::collections::HashSet;
+use std::fmt;
+use crate::access_flags::*;
+use crate::class_file_reader::{AttributeInfo, ConstantInfo, ConstantPool, Index, Tag};
+use crate::code::limits;
+use crate::code::opcode::Opcode;
+use crate::code::Code;
+use crate::{Attribute, ClassFile};
+
+pub struct Diagnostic {
+    pub location: String,   // class, constant #3, field name descriptor, method name descriptor
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+// attributes a structure may have only one of
+const UNIQUE_ATTRIBUTES: [&str; 22] = [
+    "ConstantValue", "Code", "StackMapTable", "Exceptions", "InnerClasses", "EnclosingMethod", "Synthetic",
+    "Signature", "SourceFile", "SourceDebugExtension", "Deprecated", "RuntimeVisibleAnnotations",
+    "RuntimeInvisibleAnnotations", "AnnotationDefault", "BootstrapMethods", "MethodParameters", "Module",
+    "ModulePackages", "ModuleMainClass", "NestHost", "NestMembers", "PermittedSubclasses",
+];
+
+// attributes decoded with checks, a bad tag, constant index or length in one is reported
+const DECODED_ATTRIBUTES: [&str; 8] = [
+    "RuntimeVisibleAnnotations", "RuntimeInvisibleAnnotations", "RuntimeVisibleParameterAnnotations",
+    "RuntimeInvisibleParameterAnnotations", "RuntimeVisibleTypeAnnotations", "RuntimeInvisibleTypeAnnotations",
+    "AnnotationDefault", "StackMapTable",
+];
+
+fn u16_at(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*bytes.get(at)?, *bytes.get(at + 1)?]))
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes([*bytes.get(at)?, *bytes.get(at + 1)?, *bytes.get(at + 2)?, *bytes.get(at + 3)?]))
+}
+
+// JVMS 4.2.2, a method name may also be <init> or <clinit>
+fn is_unqualified_name(name: &str, method: bool) -> bool {
+    if method && (name == "<init>" || name == "<clinit>") {
+        return true;
+    }
+    !(name.is_empty() || name.contains(['.', ';', '[', '/']) || method && name.contains(['<', '>']))
+}
+
+// internal form java/lang/Object, or an array descriptor
+fn is_class_name(name: &str) -> bool {
+    match name.starts_with('[') {
+        true => is_field_descriptor(name),
+        false => name.split('/').all(|part| is_unqualified_name(part, false)),
+    }
+}
+
+// where the field type starting at i ends
+fn field_type_end(d: &str, i: usize) -> Option<usize> {
+    let dimensions = d[i..].chars().take_while(|c| *c == '[').count();
+    if dimensions > 255 {
+        return None;
+    }
+    let i = i + dimensions;
+    match d[i..].chars().next()? {
+        'B' | 'C' | 'D' | 'F' | 'I' | 'J' | 'S' | 'Z' => Some(i + 1),
+        'L' => {
+            let end = i + d[i..].find(';')?;
+            match d[i + 1..end].split('/').all(|part| is_unqualified_name(part, false)) {
+                true => Some(end + 1),
+                false => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn is_field_descriptor(d: &str) -> bool {
+    field_type_end(d, 0) == Some(d.len())
+}
+
+// parameter slots of a well formed method descriptor
+fn method_descriptor_slots(d: &str) -> Option<usize> {
+    let mut i = 1;
+    let mut slots = 0;
+    if !d.starts_with('(') {
+        return None;
+    }
+    while !d[i..].starts_with(')') {
+        let end = field_type_end(d, i)?;
+        slots += if &d[i..end] == "J" || &d[i..end] == "D" { 2 } else { 1 };
+        i = end;
+    }
+    match &d[i + 1..] {
+        "V" => Some(slots),
+        r if is_field_descriptor(r) => Some(slots),
+        _ => None,
+    }
+}
+
+// bytes taken by the instruction at pc, None for an opcode that cannot appear in a class file or one cut short
+fn instruction_length(code: &[u8], pc: usize) -> Option<usize> {
+    let padding = (4 - (pc + 1) % 4) % 4;
+    let length = match code[pc] {
+        16 | 18 | 21..=25 | 54..=58 | 169 | 188 => 2,
+        17 | 19 | 20 | 132 | 153..=168 | 178..=184 | 187 | 189 | 192 | 193 | 198 | 199 => 3,
+        197 => 4,
+        185 | 186 | 200 | 201 => 5,
+        196 => match *code.get(pc + 1)? {
+            132 => 6,
+            21..=25 | 54..=58 | 169 => 4,
+            _ => return None,
+        },
+        170 => {
+            let low = u32_at(code, pc + 1 + padding + 4)? as i32;
+            let high = u32_at(code, pc + 1 + padding + 8)? as i32;
+            if high < low {
+                return None;
+            }
+            1 + padding + 12 + 4 * (high as i64 - low as i64 + 1) as usize
+        }
+        171 => {
+            let pairs = u32_at(code, pc + 1 + padding + 4)? as i32;
+            if pairs < 0 {
+                return None;
+            }
+            1 + padding + 8 + 8 * pairs as usize
+        }
+        202..=255 => return None,
+        _ => 1,
+    };
+    match pc + length <= code.len() {
+        true => Some(length),
+        false => None,
+    }
+}
+
+fn tag_names(tags: &[Tag]) -> String {
+    tags.iter().map(|t| t.to_string().trim().to_string()).collect::<Vec<_>>().join(" or ")
+}
+
+struct Validator<'a> {
+    class_file: &'a ClassFile,
+    cp: &'a ConstantPool,
+    major: u16,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Validator<'_> {
+    fn report(&mut self, location: &str, message: String) {
+        self.diagnostics.push(Diagnostic { location: location.to_string(), message });
+    }
+
+    fn tag(&self, index: u16) -> Option<Tag> {
+        match self.cp.find_constant(index) {
+            Some(ConstantInfo(Tag::Unusable, _)) | None => None,
+            Some(ConstantInfo(tag, _)) => Some(*tag),
+        }
+    }
+
+    fn utf8(&self, index: u16) -> Option<String> {
+        self.cp.find_utf8(index)
+    }
+
+    // an entry of one of these tags, reported when it is not
+    fn expect(&mut self, location: &str, what: &str, index: u16, tags: &[Tag]) -> bool {
+        match self.tag(index) {
+            Some(tag) if tags.contains(&tag) => true,
+            Some(tag) => {
+                self.report(location, format!("{} #{} is {}, not {}", what, index, tag.to_string().trim(), tag_names(tags)));
+                false
+            }
+            None => {
+                self.report(location, format!("{} #{} is not a constant pool entry", what, index));
+                false
+            }
+        }
+    }
+
+    fn expect_utf8(&mut self, location: &str, what: &str, index: u16) -> Option<String> {
+        match self.expect(location, what, index, &[Tag::Utf8]) {
+            true => self.utf8(index),
+            false => None,
+        }
+    }
+
+    // name of the Class entry at index
+    fn expect_class(&mut self, location: &str, what: &str, index: u16) -> Option<String> {
+        if !self.expect(location, what, index, &[Tag::Class]) {
+            return None;
+        }
+        match &self.cp.get_constant(index).1 {
+            Index::Single(n) => self.utf8(*n),
+            _ => None,
+        }
+    }
+
+    fn optional_class(&mut self, location: &str, what: &str, index: u16) {
+        if index != 0 {
+            self.expect_class(location, what, index);
+        }
+    }
+
+    fn name_and_type(&self, index: u16) -> Option<(String, String)> {
+        match self.cp.find_constant(index)? {
+            ConstantInfo(Tag::NameAndType, Index::Pair(n, d)) => Some((self.utf8(*n)?, self.utf8(*d)?)),
+            _ => None,
+        }
+    }
+
+    fn since(&mut self, location: &str, what: &str, major: u16) {
+        if self.major < major {
+            self.report(location, format!("{} needs class file version {} or later", what, major));
+        }
+    }
+
+    fn constant_pool(&mut self) {
+        let module = self.class_file.access_flags & ACC_MODULE != 0;
+        for i in 1..=self.cp.constant_info.len() as u16 {
+            let location = format!("constant #{}", i);
+            let ConstantInfo(tag, index) = self.cp.get_constant(i).clone();
+            match (tag, index) {
+                (Tag::Class, Index::Single(n)) => {
+                    if let Some(name) = self.expect_utf8(&location, "name", n) {
+                        if !(is_class_name(&name) || module && name == "module-info") {
+                            self.report(&location, format!("bad class name {}", name));
+                        }
+                    }
+                }
+                (Tag::String, Index::Single(n)) => {
+                    self.expect(&location, "string", n, &[Tag::Utf8]);
+                }
+                (Tag::MethodType, Index::Single(n)) => {
+                    self.since(&location, "MethodType", 51);
+                    if let Some(d) = self.expect_utf8(&location, "descriptor", n) {
+                        if method_descriptor_slots(&d).is_none() {
+                            self.report(&location, format!("bad method descriptor {}", d));
+                        }
+                    }
+                }
+                (Tag::Module | Tag::Package, Index::Single(n)) => {
+                    self.since(&location, "Module and Package", 53);
+                    if !module {
+                        self.report(&location, "Module and Package entries belong only to module-info".to_string());
+                    }
+                    self.expect(&location, "name", n, &[Tag::Utf8]);
+                }
+                (Tag::NameAndType, Index::Pair(n, d)) => {
+                    let name = self.expect_utf8(&location, "name", n);
+                    let descriptor = self.expect_utf8(&location, "descriptor", d);
+                    if let (Some(name), Some(descriptor)) = (name, descriptor) {
+                        let method = descriptor.starts_with('(');
+                        if !is_unqualified_name(&name, method) {
+                            self.report(&location, format!("bad name {}", name));
+                        }
+                        if method && method_descriptor_slots(&descriptor).is_none() || !method && !is_field_descriptor(&descriptor) {
+                            self.report(&location, format!("bad descriptor {}", descriptor));
+                        }
+                    }
+                }
+                (Tag::FieldRef | Tag::MethodRef | Tag::InterfaceMethodRef, Index::Ref(c, nt)) => {
+                    self.expect_class(&location, "class", c);
+                    if self.expect(&location, "name and type", nt, &[Tag::NameAndType]) {
+                        if let Some((name, descriptor)) = self.name_and_type(nt) {
+                            let method = tag != Tag::FieldRef;
+                            if method != descriptor.starts_with('(') {
+                                self.report(&location, format!("{} has descriptor {}", tag.to_string().trim(), descriptor));
+                            }
+                            if name.starts_with('<') && (tag != Tag::MethodRef || name != "<init>" || !descriptor.ends_with(")V")) {
+                                self.report(&location, format!("{} cannot refer to {}{}", tag.to_string().trim(), name, descriptor));
+                            }
+                        }
+                    }
+                }
+                (Tag::MethodHandle, Index::MethodHandle(kind, reference)) => {
+                    self.since(&location, "MethodHandle", 51);
+                    let tags: &[Tag] = match kind {
+                        1..=4 => &[Tag::FieldRef],
+                        5 | 8 => &[Tag::MethodRef],
+                        6 | 7 if self.major >= 52 => &[Tag::MethodRef, Tag::InterfaceMethodRef],
+                        6 | 7 => &[Tag::MethodRef],
+                        9 => &[Tag::InterfaceMethodRef],
+                        _ => {
+                            self.report(&location, format!("bad reference kind {}", kind));
+                            continue;
+                        }
+                    };
+                    if self.expect(&location, "reference", reference, tags) {
+                        let name = self.cp.get_name_and_type(reference).map(|(n, _)| n).unwrap_or_default();
+                        if (kind == 8) != (name == "<init>") || name == "<clinit>" {
+                            self.report(&location, format!("reference kind {} cannot refer to {}", kind, name));
+                        }
+                    }
+                }
+                (Tag::Dynamic | Tag::InvokeDynamic, Index::Dynamic(bootstrap, nt)) => {
+                    self.since(&location, if tag == Tag::Dynamic { "Dynamic" } else { "InvokeDynamic" }, if tag == Tag::Dynamic { 55 } else { 51 });
+                    if self.expect(&location, "name and type", nt, &[Tag::NameAndType]) {
+                        if let Some((_, descriptor)) = self.name_and_type(nt) {
+                            if (tag == Tag::InvokeDynamic) != descriptor.starts_with('(') {
+                                self.report(&location, format!("{} has descriptor {}", tag.to_string().trim(), descriptor));
+                            }
+                        }
+                    }
+                    let methods = self.bootstrap_method_count();
+                    if bootstrap >= methods {
+                        self.report(&location, format!("bootstrap method {} of {}", bootstrap, methods));
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn bootstrap_method_count(&self) -> u16 {
+        self.class_file.attributes.iter()
+            .find(|a| self.attribute_name(a).as_deref() == Some("BootstrapMethods"))
+            .and_then(|a| u16_at(&a.info, 0))
+            .unwrap_or(0)
+    }
+
+    fn attribute_name(&self, a: &AttributeInfo) -> Option<String> {
+        match a.attribute_name_index {
+            Index::Single(i) => self.utf8(i),
+            _ => None,
+        }
+    }
+
+    fn class(&mut self) {
+        let cf = self.class_file;
+        let flags = cf.access_flags;
+        let location = "class";
+        let this = match cf.this_class {
+            Index::Single(i) => self.expect_class(location, "this_class", i),
+            _ => None,
+        };
+        let module = flags & ACC_MODULE != 0;
+        let interface = flags & ACC_INTERFACE != 0;
+        if module {
+            if flags != ACC_MODULE {
+                self.report(location, "a module-info has no access flags but ACC_MODULE".to_string());
+            }
+        } else if interface {
+            if flags & ACC_ABSTRACT == 0 {
+                self.report(location, "an interface must be ACC_ABSTRACT".to_string());
+            }
+            if flags & (ACC_FINAL | ACC_SUPER | ACC_ENUM) != 0 {
+                self.report(location, "an interface cannot be ACC_FINAL, ACC_SUPER or ACC_ENUM".to_string());
+            }
+        } else {
+            if flags & ACC_ANNOTATION != 0 {
+                self.report(location, "ACC_ANNOTATION needs ACC_INTERFACE".to_string());
+            }
+            if flags & ACC_FINAL != 0 && flags & ACC_ABSTRACT != 0 {
+                self.report(location, "a class cannot be both ACC_FINAL and ACC_ABSTRACT".to_string());
+            }
+        }
+        let super_class = match cf.super_class {
+            Index::Single(0) => None,
+            Index::Single(i) => self.expect_class(location, "super_class", i),
+            _ => None,
+        };
+        match (&super_class, cf.super_class == Index::Single(0)) {
+            (None, true) if !module && this.as_deref() != Some("java/lang/Object") =>
+                self.report(location, "only java/lang/Object has no superclass".to_string()),
+            (Some(s), _) if interface && s != "java/lang/Object" =>
+                self.report(location, format!("an interface must extend java/lang/Object, not {}", s)),
+            _ => (),
+        }
+        let mut seen = HashSet::<String>::new();
+        for i in &cf.interfaces {
+            if let Index::Single(i) = i {
+                if let Some(name) = self.expect_class(location, "interface", *i) {
+                    if !seen.insert(name.clone()) {
+                        self.report(location, format!("interface {} is listed twice", name));
+                    }
+                }
+            }
+        }
+    }
+
+    fn fields(&mut self) {
+        let cf = self.class_file;
+        let interface = cf.access_flags & ACC_INTERFACE != 0;
+        let mut seen = HashSet::<(String, String)>::new();
+        for (i, f) in cf.fields.iter().enumerate() {
+            let (name, descriptor) = self.member_name(&f.name_index, &f.descriptor_index, false);
+            let location = format!("field {} {}", name.as_deref().unwrap_or("?"), descriptor.as_deref().unwrap_or("?"));
+            if let (Some(n), Some(d)) = (&name, &descriptor) {
+                if !is_field_descriptor(d) {
+                    self.report(&location, format!("bad field descriptor {}", d));
+                }
+                if !seen.insert((n.clone(), d.clone())) {
+                    self.report(&location, "duplicate field".to_string());
+                }
+            }
+            let flags = f.access_flags;
+            if (flags & (ACC_PUBLIC | ACC_PRIVATE | ACC_PROTECTED)).count_ones() > 1 {
+                self.report(&location, "more than one of ACC_PUBLIC, ACC_PRIVATE and ACC_PROTECTED".to_string());
+            }
+            if flags & ACC_FINAL != 0 && flags & ACC_VOLATILE != 0 {
+                self.report(&location, "a field cannot be both ACC_FINAL and ACC_VOLATILE".to_string());
+            }
+            if interface && flags & !ACC_SYNTHETIC != ACC_PUBLIC | ACC_STATIC | ACC_FINAL {
+                self.report(&location, "an interface field must be ACC_PUBLIC, ACC_STATIC and ACC_FINAL only".to_string());
+            }
+            self.attributes(&location, &cf.fields[i].attributes, descriptor.as_deref());
+        }
+    }
+
+    fn member_name(&mut self, name_index: &Index, descriptor_index: &Index, method: bool) -> (Option<String>, Option<String>) {
+        let location = if method { "method" } else { "field" };
+        let name = match name_index {
+            Index::Single(i) => self.expect_utf8(location, "name", *i),
+            _ => None,
+        };
+        let descriptor = match descriptor_index {
+            Index::Single(i) => self.expect_utf8(location, "descriptor", *i),
+            _ => None,
+        };
+        if let Some(n) = &name {
+            if !is_unqualified_name(n, method) {
+                self.report(location, format!("bad name {}", n));
+            }
+        }
+        (name, descriptor)
+    }
+
+    fn methods(&mut self) {
+        let cf = self.class_file;
+        let interface = cf.access_flags & ACC_INTERFACE != 0;
+        let mut seen = HashSet::<(String, String)>::new();
+        for m in &cf.methods {
+            let (name, descriptor) = self.member_name(&m.name_index, &m.descriptor_index, true);
+            let location = format!("method {} {}", name.as_deref().unwrap_or("?"), descriptor.as_deref().unwrap_or("?"));
+            let name = name.unwrap_or_default();
+            let slots = descriptor.as_deref().and_then(method_descriptor_slots);
+            if let Some(d) = &descriptor {
+                if slots.is_none() {
+                    self.report(&location, format!("bad method descriptor {}", d));
+                }
+                if !seen.insert((name.clone(), d.clone())) {
+                    self.report(&location, "duplicate method".to_string());
+                }
+            }
+            let flags = m.access_flags;
+            if (flags & (ACC_PUBLIC | ACC_PRIVATE | ACC_PROTECTED)).count_ones() > 1 {
+                self.report(&location, "more than one of ACC_PUBLIC, ACC_PRIVATE and ACC_PROTECTED".to_string());
+            }
+            if name == "<clinit>" {
+                if self.major >= 51 && flags & ACC_STATIC == 0 {
+                    self.report(&location, "<clinit> must be ACC_STATIC".to_string());
+                }
+                if descriptor.as_deref().is_some_and(|d| d != "()V") {
+                    self.report(&location, "<clinit> must be ()V".to_string());
+                }
+            } else {
+                if interface && self.major >= 52 {
+                    if flags & (ACC_PROTECTED | ACC_FINAL | ACC_SYNCHRONIZED | ACC_NATIVE) != 0 {
+                        self.report(&location, "an interface method cannot be ACC_PROTECTED, ACC_FINAL, ACC_SYNCHRONIZED or ACC_NATIVE".to_string());
+                    }
+                    if (flags & (ACC_PUBLIC | ACC_PRIVATE)).count_ones() != 1 {
+                        self.report(&location, "an interface method must be one of ACC_PUBLIC and ACC_PRIVATE".to_string());
+                    }
+                } else if interface && flags & (ACC_PUBLIC | ACC_ABSTRACT) != ACC_PUBLIC | ACC_ABSTRACT {
+                    self.report(&location, "an interface method must be ACC_PUBLIC and ACC_ABSTRACT before version 52".to_string());
+                }
+                if flags & ACC_ABSTRACT != 0 {
+                    if flags & (ACC_PRIVATE | ACC_STATIC | ACC_FINAL | ACC_SYNCHRONIZED | ACC_NATIVE) != 0 {
+                        self.report(&location, "an ACC_ABSTRACT method cannot be ACC_PRIVATE, ACC_STATIC, ACC_FINAL, ACC_SYNCHRONIZED or ACC_NATIVE".to_string());
+                    }
+                    if flags & ACC_STRICT != 0 && (46..61).contains(&self.major) {
+                        self.report(&location, "an ACC_ABSTRACT method cannot be ACC_STRICT".to_string());
+                    }
+                }
+                if name == "<init>" {
+                    if interface {
+                        self.report(&location, "an interface has no <init>".to_string());
+                    }
+                    if flags & (ACC_STATIC | ACC_FINAL | ACC_SYNCHRONIZED | ACC_BRIDGE | ACC_NATIVE | ACC_ABSTRACT) != 0 {
+                        self.report(&location, "<init> cannot be ACC_STATIC, ACC_FINAL, ACC_SYNCHRONIZED, ACC_BRIDGE, ACC_NATIVE or ACC_ABSTRACT".to_string());
+                    }
+                    if descriptor.as_deref().is_some_and(|d| !d.ends_with(")V")) {
+                        self.report(&location, "<init> must return void".to_string());
+                    }
+                }
+            }
+
+            let codes = m.attributes.iter().filter(|a| self.attribute_name(a).as_deref() == Some("Code")).count();
+            let has_code = flags & (ACC_NATIVE | ACC_ABSTRACT) == 0;
+            if has_code && codes == 0 {
+                self.report(&location, "no Code attribute".to_string());
+            }
+            if !has_code && codes > 0 {
+                self.report(&location, "an ACC_NATIVE or ACC_ABSTRACT method has no Code".to_string());
+            }
+            let this_slot = if flags & ACC_STATIC == 0 { 1 } else { 0 };
+            if let Some(s) = slots {
+                if s + this_slot > 255 {
+                    self.report(&location, format!("{} parameter slots, at most 255", s + this_slot));
+                }
+            }
+            for a in &m.attributes {
+                if self.attribute_name(a).as_deref() == Some("Code") {
+                    self.code(&location, &a.info, slots.map(|s| s + this_slot));
+                }
+            }
+            self.attributes(&location, &m.attributes, None);
+        }
+    }
+
+    // the layout of each attribute JVMS describes and the constants it refers to, field_descriptor for a field's
+    fn attributes(&mut self, location: &str, attributes: &[AttributeInfo], field_descriptor: Option<&str>) {
+        let mut seen = HashSet::<String>::new();
+        for a in attributes {
+            let name = match a.attribute_name_index {
+                Index::Single(i) => self.expect_utf8(location, "attribute name", i),
+                _ => None,
+            };
+            let Some(name) = name else { continue };
+            if UNIQUE_ATTRIBUTES.contains(&name.as_str()) && !seen.insert(name.clone()) {
+                self.report(location, format!("more than one {} attribute", name));
+            }
+            let info = &a.info;
+            let location = format!("{}, {}", location, name);
+            let count = u16_at(info, 0).unwrap_or(0) as usize;
+            let expected = match name.as_str() {
+                "ConstantValue" | "Signature" | "SourceFile" | "NestHost" | "ModuleMainClass" => Some(2),
+                "EnclosingMethod" => Some(4),
+                "Synthetic" | "Deprecated" => Some(0),
+                "Exceptions" | "NestMembers" | "PermittedSubclasses" | "ModulePackages" => Some(2 + 2 * count),
+                "InnerClasses" => Some(2 + 8 * count),
+                "MethodParameters" => Some(1 + 4 * info.first().copied().unwrap_or(0) as usize),
+                "BootstrapMethods" => {
+                    let mut at = 2;
+                    for _ in 0..count {
+                        at += 4 + 2 * u16_at(info, at + 2).unwrap_or(0) as usize;
+                    }
+                    Some(at)
+                }
+                _ => None,
+            };
+            if let Some(expected) = expected {
+                if info.len() != expected {
+                    self.report(&location, format!("length {}, the entries need {}", info.len(), expected));
+                    continue;
+                }
+            }
+            let index = |k: usize| u16_at(info, k).unwrap_or(0);
+            match name.as_str() {
+                "ConstantValue" => {
+                    let tags: &[Tag] = match field_descriptor {
+                        Some("J") => &[Tag::Long],
+                        Some("F") => &[Tag::Float],
+                        Some("D") => &[Tag::Double],
+                        Some("I" | "S" | "C" | "B" | "Z") => &[Tag::Integer],
+                        Some("Ljava/lang/String;") => &[Tag::String],
+                        _ => continue,
+                    };
+                    self.expect(&location, "value", index(0), tags);
+                }
+                "Signature" | "SourceFile" => {
+                    self.expect(&location, "value", index(0), &[Tag::Utf8]);
+                }
+                "NestHost" | "ModuleMainClass" => {
+                    self.expect_class(&location, "class", index(0));
+                }
+                "Exceptions" | "NestMembers" | "PermittedSubclasses" => {
+                    for k in 0..count {
+                        self.expect_class(&location, "class", index(2 + 2 * k));
+                    }
+                }
+                "ModulePackages" => {
+                    for k in 0..count {
+                        self.expect(&location, "package", index(2 + 2 * k), &[Tag::Package]);
+                    }
+                }
+                "EnclosingMethod" => {
+                    self.expect_class(&location, "class", index(0));
+                    if index(2) != 0 {
+                        self.expect(&location, "method", index(2), &[Tag::NameAndType]);
+                    }
+                }
+                "InnerClasses" => {
+                    for k in 0..count {
+                        let at = 2 + 8 * k;
+                        self.expect_class(&location, "inner class", index(at));
+                        self.optional_class(&location, "outer class", index(at + 2));
+                        if index(at + 4) != 0 {
+                            self.expect(&location, "inner name", index(at + 4), &[Tag::Utf8]);
+                        }
+                    }
+                }
+                "MethodParameters" => {
+                    for k in 0..info[0] as usize {
+                        if index(1 + 4 * k) != 0 {
+                            self.expect(&location, "name", index(1 + 4 * k), &[Tag::Utf8]);
+                        }
+                    }
+                }
+                "BootstrapMethods" => {
+                    let mut at = 2;
+                    for _ in 0..count {
+                        self.expect(&location, "bootstrap method", index(at), &[Tag::MethodHandle]);
+                        for k in 0..index(at + 2) as usize {
+                            self.expect(&location, "argument", index(at + 4 + 2 * k), &[Tag::Integer, Tag::Float, Tag::Long,
+                                Tag::Double, Tag::Class, Tag::String, Tag::MethodHandle, Tag::MethodType, Tag::Dynamic]);
+                        }
+                        at += 4 + 2 * index(at + 2) as usize;
+                    }
+                }
+                name if DECODED_ATTRIBUTES.contains(&name) => self.decoded(&location, name, info),
+                _ => (),
+            }
+        }
+    }
+
+    fn decoded(&mut self, location: &str, name: &str, info: &[u8]) {
+        let attribute = Attribute { name: name.to_string(), info: info.to_vec() };
+        if let Err(e) = attribute.try_decode(self.class_file) {
+            self.report(location, e.to_string());
+        }
+    }
+
+    // a Code attribute's layout, instructions, exception table and debug tables, parameters in slots with this
+    fn code(&mut self, location: &str, info: &[u8], parameters: Option<usize>) {
+        let location = format!("{}, Code", location);
+        let (Some(max_locals), Some(code_length)) = (u16_at(info, 2), u32_at(info, 4)) else {
+            self.report(&location, "shorter than its header".to_string());
+            return;
+        };
+        let code_length = code_length as usize;
+        if code_length == 0 || code_length > 65535 {
+            self.report(&location, format!("code_length {} is not between 1 and 65535", code_length));
+            return;
+        }
+        // walk the layout before anything is decoded from it
+        let exceptions = 8 + code_length;
+        let Some(exception_count) = u16_at(info, exceptions) else {
+            self.report(&location, "attribute length is less than code_length".to_string());
+            return;
+        };
+        let mut at = exceptions + 2 + 8 * exception_count as usize;
+        let attribute_count = u16_at(info, at).unwrap_or(0);
+        at += 2;
+        for _ in 0..attribute_count {
+            at += 6 + u32_at(info, at + 2).unwrap_or(0) as usize;
+        }
+        if at != info.len() {
+            self.report(&location, format!("attribute length {}, its contents need {}", info.len(), at));
+            return;
+        }
+        if let Some(p) = parameters {
+            if p > max_locals as usize {
+                self.report(&location, format!("max_locals {} is less than the {} parameter slots", max_locals, p));
+            }
+        }
+        let bytes = &info[8..exceptions];
+        let mut boundaries = HashSet::<usize>::new();
+        let mut pc = 0;
+        while pc < code_length {
+            boundaries.insert(pc);
+            match instruction_length(bytes, pc) {
+                Some(length) => pc += length,
+                None => {
+                    self.report(&location, format!("pc {}: opcode {} is not allowed here or is cut short", pc, bytes[pc]));
+                    return;
+                }
+            }
+        }
+
+        let code = Code::new(info);
+        for (pc, o) in code.get_instructions() {
+            let at = format!("{} pc {} {}", location, pc, o);
+            self.instruction(&at, pc, o, bytes, max_locals, &boundaries);
+        }
+        let is_instruction = |pc: u16| boundaries.contains(&(pc as usize));
+        for e in code.get_exception_table() {
+            if !(e.start_pc < e.end_pc && is_instruction(e.start_pc)
+                    && (is_instruction(e.end_pc) || e.end_pc as usize == code_length) && is_instruction(e.handler_pc)) {
+                self.report(&location, format!("bad exception range {} {} handler {}", e.start_pc, e.end_pc, e.handler_pc));
+            }
+            if e.catch_type != 0 {
+                self.expect_class(&location, "catch type", e.catch_type);
+            }
+        }
+        for a in code.get_attributes() {
+            let name = match a.attribute_name_index {
+                Index::Single(i) => self.expect_utf8(&location, "attribute name", i),
+                _ => None,
+            };
+            let info = &a.info;
+            let count = u16_at(info, 0).unwrap_or(0) as usize;
+            let entry = match name.as_deref() {
+                Some(name) if DECODED_ATTRIBUTES.contains(&name) => {
+                    self.decoded(&format!("{}, {}", location, name), name, info);
+                    continue;
+                }
+                Some("LineNumberTable") => 4,
+                Some("LocalVariableTable" | "LocalVariableTypeTable") => 10,
+                _ => continue,
+            };
+            let name = name.unwrap();
+            let location = format!("{}, {}", location, name);
+            if info.len() != 2 + entry * count {
+                self.report(&location, format!("length {}, the entries need {}", info.len(), 2 + entry * count));
+                continue;
+            }
+            for k in 0..count {
+                let field = |n: usize| u16_at(info, 2 + entry * k + 2 * n).unwrap_or(0);
+                if entry == 4 {
+                    if !is_instruction(field(0)) {
+                        self.report(&location, format!("start_pc {} is not an instruction", field(0)));
+                    }
+                    continue;
+                }
+                let (start, length, slot) = (field(0), field(1), field(4));
+                let end = start as usize + length as usize;
+                if !is_instruction(start) || !(boundaries.contains(&end) || end == code_length) {
+                    self.report(&location, format!("range {} {} is not on instructions", start, end));
+                }
+                let variable = self.expect_utf8(&location, "name", field(2));
+                let descriptor = self.expect_utf8(&location, "descriptor", field(3));
+                if variable.as_deref().is_some_and(|v| !is_unqualified_name(v, false)) {
+                    self.report(&location, format!("bad name {}", variable.unwrap()));
+                }
+                let wide = match (name.as_str(), descriptor.as_deref()) {
+                    ("LocalVariableTable", Some(d)) if !is_field_descriptor(d) => {
+                        self.report(&location, format!("bad descriptor {}", d));
+                        false
+                    }
+                    (_, Some(d)) => d == "J" || d == "D",
+                    _ => false,
+                };
+                if slot as usize + if wide { 2 } else { 1 } > max_locals as usize {
+                    self.report(&location, format!("local {} is past max_locals {}", slot, max_locals));
+                }
+            }
+        }
+    }
+
+    fn instruction(&mut self, location: &str, pc: u16, o: &Opcode, bytes: &[u8], max_locals: u16, boundaries: &HashSet<usize>) {
+        let (jumps, _) = limits::targets(pc, o);
+        for t in jumps {
+            if t < 0 || !boundaries.contains(&(t as usize)) {
+                self.report(location, format!("branch target {} is not an instruction", t));
+            }
+        }
+        let local = match o {
+            Opcode::Iload(i) | Opcode::Fload(i) | Opcode::Aload(i) | Opcode::Istore(i) | Opcode::Fstore(i)
+                | Opcode::Astore(i) | Opcode::Ret(i) | Opcode::Iinc(i, _) => Some((*i as u16, 1)),
+            Opcode::Lload(i) | Opcode::Dload(i) | Opcode::Lstore(i) | Opcode::Dstore(i) => Some((*i as u16, 2)),
+            Opcode::WideIinc(i, _) => Some((*i, 1)),
+            Opcode::Wide(22 | 24 | 55 | 57, i) => Some((*i, 2)),
+            Opcode::Wide(_, i) => Some((*i, 1)),
+            o => match o.opcode() {
+                26..=29 | 34..=37 | 42..=45 => Some(((o.opcode() - 26) as u16 % 4, 1)),
+                59..=62 | 67..=70 | 75..=78 => Some(((o.opcode() - 59) as u16 % 4, 1)),
+                30..=33 | 38..=41 => Some(((o.opcode() - 30) as u16 % 4, 2)),
+                63..=66 | 71..=74 => Some(((o.opcode() - 63) as u16 % 4, 2)),
+                _ => None,
+            },
+        };
+        if let Some((i, size)) = local {
+            if i as usize + size > max_locals as usize {
+                self.report(location, format!("local {} is past max_locals {}", i, max_locals));
+            }
+        }
+        let loadable = |major: u16| {
+            let mut tags = vec![Tag::Integer, Tag::Float, Tag::String];
+            if major >= 49 {
+                tags.push(Tag::Class);
+            }
+            if major >= 51 {
+                tags.extend([Tag::MethodType, Tag::MethodHandle]);
+            }
+            if major >= 55 {
+                tags.push(Tag::Dynamic);
+            }
+            tags
+        };
+        match o {
+            Opcode::Ldc(i) => self.loadable(location, *i as u16, &loadable(self.major), false),
+            Opcode::LdcW(i) => self.loadable(location, *i, &loadable(self.major), false),
+            Opcode::Ldc2W(i) => self.loadable(location, *i, &[Tag::Long, Tag::Double, Tag::Dynamic], true),
+            Opcode::Getstatic(i) | Opcode::Putstatic(i) | Opcode::Getfield(i) | Opcode::Putfield(i) => {
+                self.expect(location, "field", *i, &[Tag::FieldRef]);
+            }
+            Opcode::Invokevirtual(i) | Opcode::Invokespecial(i) | Opcode::Invokestatic(i) | Opcode::Invokeinterface(i, _) => {
+                let tags: &[Tag] = match o {
+                    Opcode::Invokevirtual(_) => &[Tag::MethodRef],
+                    Opcode::Invokeinterface(..) => &[Tag::InterfaceMethodRef],
+                    _ if self.major >= 52 => &[Tag::MethodRef, Tag::InterfaceMethodRef],
+                    _ => &[Tag::MethodRef],
+                };
+                if !self.expect(location, "method", *i, tags) {
+                    return;
+                }
+                let Some((name, descriptor)) = self.cp.get_name_and_type(*i) else { return };
+                if name.starts_with('<') && !(name == "<init>" && matches!(o, Opcode::Invokespecial(_))) {
+                    self.report(location, format!("cannot invoke {}", name));
+                }
+                if let Opcode::Invokeinterface(_, count) = o {
+                    let slots = method_descriptor_slots(&descriptor).unwrap_or(0) + 1;
+                    if *count as usize != slots || bytes[pc as usize + 4] != 0 {
+                        self.report(location, format!("count {} for {} argument slots", count, slots));
+                    }
+                }
+            }
+            Opcode::Invokedynamic(i) => {
+                self.expect(location, "call site", *i, &[Tag::InvokeDynamic]);
+                if bytes[pc as usize + 3] != 0 || bytes[pc as usize + 4] != 0 {
+                    self.report(location, "the bytes after the index must be zero".to_string());
+                }
+            }
+            Opcode::New(i) | Opcode::Checkcast(i) | Opcode::Instanceof(i) | Opcode::Anewarray(i) | Opcode::Multianewarray(i, _) => {
+                let Some(class) = self.expect_class(location, "class", *i) else { return };
+                let dimensions = class.chars().take_while(|c| *c == '[').count();
+                match o {
+                    Opcode::New(_) if dimensions > 0 => self.report(location, format!("new of array {}", class)),
+                    Opcode::Anewarray(_) if dimensions >= 255 => self.report(location, format!("{} has 255 dimensions already", class)),
+                    Opcode::Multianewarray(_, d) if *d == 0 || *d as usize > dimensions =>
+                        self.report(location, format!("{} dimensions of {}", d, class)),
+                    _ => (),
+                }
+            }
+            Opcode::Newarray(t) if !(4..=11).contains(t) => self.report(location, format!("bad array type {}", t)),
+            _ => (),
+        }
+    }
+
+    fn loadable(&mut self, location: &str, index: u16, tags: &[Tag], wide: bool) {
+        if !self.expect(location, "constant", index, tags) {
+            return;
+        }
+        let is_wide = match self.tag(index) {
+            Some(Tag::Long | Tag::Double) => true,
+            Some(Tag::Dynamic) => self.cp.get_name_and_type(index).is_some_and(|(_, d)| d == "J" || d == "D"),
+            _ => false,
+        };
+        if is_wide != wide {
+            self.report(location, format!("constant #{} needs {}", index, if is_wide { "ldc2_w" } else { "ldc or ldc_w" }));
+        }
+    }
+}
+
+impl ClassFile {
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut validator = Validator { class_file: self, cp: &self.constant_pool, major: self.version.1, diagnostics: Vec::new() };
+        validator.constant_pool();
+        validator.class();
+        validator.fields();
+        validator.methods();
+        validator.attributes("class", &self.attributes, None);
+        validator.diagnostics
+    }
+}
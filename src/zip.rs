@@ -0,0 +1,284 @@
+// module zip
+// just enough of the zip format to read jar and jmod files, stored and deflated entries without zip64
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x06054b50;
+const CENTRAL_DIRECTORY_HEADER: u32 = 0x02014b50;
+const LOCAL_FILE_HEADER: u32 = 0x04034b50;
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+#[derive(Debug)]
+pub enum ZipError {
+    Io(std::io::Error),
+    NotZip,                     // no end of central directory record
+    BadDirectory,               // central directory outside the file or damaged
+    BadEntry(String),           // local header or compressed data of the entry damaged
+    Unsupported(String, u16),   // entry, compression method
+}
+
+impl fmt::Display for ZipError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ZipError::Io(e) => write!(f, "{}", e),
+            ZipError::NotZip => write!(f, "not a zip file"),
+            ZipError::BadDirectory => write!(f, "damaged central directory"),
+            ZipError::BadEntry(name) => write!(f, "damaged entry {}", name),
+            ZipError::Unsupported(name, method) => write!(f, "entry {} uses compression method {}", name, method),
+        }
+    }
+}
+
+impl std::error::Error for ZipError {}
+
+pub struct ZipEntry {
+    pub name: String,
+    pub size: u32,
+    method: u16,
+    compressed_size: u32,
+    offset: usize,      // of the local header, from the start of the bytes
+}
+
+pub struct ZipFile {
+    bytes: Vec<u8>,
+    entries: Vec<ZipEntry>,
+    index: HashMap<String, usize>,
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes([*bytes.get(at)?, *bytes.get(at + 1)?]))
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes([*bytes.get(at)?, *bytes.get(at + 1)?, *bytes.get(at + 2)?, *bytes.get(at + 3)?]))
+}
+
+impl ZipFile {
+    pub fn open(path: &Path) -> Result<Self, ZipError> {
+        ZipFile::from_bytes(std::fs::read(path).map_err(ZipError::Io)?)
+    }
+
+    // offsets are taken relative to where the central directory is found, so a prefix such as a jmod's header is skipped
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, ZipError> {
+        let lowest = bytes.len().saturating_sub(22 + 65535);
+        let end = (lowest..=bytes.len().saturating_sub(22)).rev()
+            .find(|at| u32_at(&bytes, *at) == Some(END_OF_CENTRAL_DIRECTORY))
+            .ok_or(ZipError::NotZip)?;
+        let count = u16_at(&bytes, end + 10).unwrap() as usize;
+        let directory_size = u32_at(&bytes, end + 12).unwrap() as usize;
+        let directory_offset = u32_at(&bytes, end + 16).unwrap() as usize;
+        let directory = end.checked_sub(directory_size).ok_or(ZipError::BadDirectory)?;
+        let prefix = directory.checked_sub(directory_offset).ok_or(ZipError::BadDirectory)?;
+
+        let mut entries = Vec::with_capacity(count);
+        let mut at = directory;
+        for _ in 0..count {
+            if u32_at(&bytes, at) != Some(CENTRAL_DIRECTORY_HEADER) {
+                return Err(ZipError::BadDirectory);
+            }
+            let header = |offset: usize| u32_at(&bytes, at + offset).ok_or(ZipError::BadDirectory);
+            let name_length = u16_at(&bytes, at + 28).ok_or(ZipError::BadDirectory)? as usize;
+            let extra_length = u16_at(&bytes, at + 30).ok_or(ZipError::BadDirectory)? as usize;
+            let comment_length = u16_at(&bytes, at + 32).ok_or(ZipError::BadDirectory)? as usize;
+            let name = bytes.get(at + 46..at + 46 + name_length).ok_or(ZipError::BadDirectory)?;
+            entries.push(ZipEntry {
+                name: String::from_utf8_lossy(name).to_string(),
+                size: header(24)?,
+                method: u16_at(&bytes, at + 10).ok_or(ZipError::BadDirectory)?,
+                compressed_size: header(20)?,
+                offset: prefix + header(42)? as usize,
+            });
+            at += 46 + name_length + extra_length + comment_length;
+        }
+        let index = entries.iter().enumerate().map(|(i, e)| (e.name.clone(), i)).collect();
+        Ok(ZipFile { bytes, entries, index })
+    }
+
+    pub fn get_entries(&self) -> &Vec<ZipEntry> {
+        &self.entries
+    }
+
+    pub fn get_entry(&self, name: &str) -> Option<&ZipEntry> {
+        self.index.get(name).map(|i| &self.entries[*i])
+    }
+
+    pub fn read(&self, entry: &ZipEntry) -> Result<Vec<u8>, ZipError> {
+        let damaged = || ZipError::BadEntry(entry.name.clone());
+        let at = entry.offset;
+        if u32_at(&self.bytes, at) != Some(LOCAL_FILE_HEADER) {
+            return Err(damaged());
+        }
+        let name_length = u16_at(&self.bytes, at + 26).ok_or_else(damaged)? as usize;
+        let extra_length = u16_at(&self.bytes, at + 28).ok_or_else(damaged)? as usize;
+        let start = at + 30 + name_length + extra_length;
+        let data = self.bytes.get(start..start + entry.compressed_size as usize).ok_or_else(damaged)?;
+        let bytes = match entry.method {
+            STORED => data.to_vec(),
+            DEFLATED => inflate(data, entry.size as usize).ok_or_else(damaged)?,
+            method => return Err(ZipError::Unsupported(entry.name.clone(), method)),
+        };
+        match bytes.len() == entry.size as usize {
+            true => Ok(bytes),
+            false => Err(damaged()),
+        }
+    }
+
+    // the entry by name, None when there is no such entry
+    pub fn read_entry(&self, name: &str) -> Option<Result<Vec<u8>, ZipError>> {
+        self.get_entry(name).map(|e| self.read(e))
+    }
+}
+
+// RFC 1951, raw deflate data
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073,
+    4097, 6145, 8193, 12289, 16385, 24577];
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+struct Bits<'a> {
+    data: &'a [u8],
+    at: usize,
+    bit: u32,
+}
+
+impl Bits<'_> {
+    fn get(&mut self, n: u32) -> Option<u32> {
+        let mut value = 0;
+        for i in 0..n {
+            let byte = *self.data.get(self.at)?;
+            value |= ((byte as u32 >> self.bit) & 1) << i;
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.at += 1;
+            }
+        }
+        Some(value)
+    }
+
+    fn align(&mut self) {
+        if self.bit != 0 {
+            self.bit = 0;
+            self.at += 1;
+        }
+    }
+}
+
+// canonical code, how many codes of each length and the symbols in code order
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for l in lengths {
+            counts[*l as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut symbols = Vec::with_capacity(lengths.len());
+        for length in 1..16 {
+            symbols.extend((0..lengths.len()).filter(|s| lengths[*s] == length).map(|s| s as u16));
+        }
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, bits: &mut Bits) -> Option<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for length in 1..16 {
+            code |= bits.get(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return self.symbols.get((index + code - first) as usize).copied();
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        None
+    }
+}
+
+pub(crate) fn inflate(data: &[u8], size: usize) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(size);
+    let mut bits = Bits { data, at: 0, bit: 0 };
+    loop {
+        let last = bits.get(1)? == 1;
+        match bits.get(2)? {
+            0 => {
+                bits.align();
+                let length = u16_at(data, bits.at)?;
+                if u16_at(data, bits.at + 2)? != !length {
+                    return None;
+                }
+                let start = bits.at + 4;
+                out.extend_from_slice(data.get(start..start + length as usize)?);
+                bits.at = start + length as usize;
+            }
+            1 => {
+                let mut lengths = [8u8; 288];
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                inflate_block(&mut bits, &mut out, &Huffman::new(&lengths), &Huffman::new(&[5; 30]))?;
+            }
+            2 => {
+                let literals = bits.get(5)? as usize + 257;
+                let distances = bits.get(5)? as usize + 1;
+                let code_lengths = bits.get(4)? as usize + 4;
+                let mut lengths = [0u8; 19];
+                for i in CODE_LENGTH_ORDER.iter().take(code_lengths) {
+                    lengths[*i] = bits.get(3)? as u8;
+                }
+                let code_length_code = Huffman::new(&lengths);
+                let mut lengths = Vec::with_capacity(literals + distances);
+                while lengths.len() < literals + distances {
+                    let (value, repeat) = match code_length_code.decode(&mut bits)? {
+                        s @ 0..=15 => (s as u8, 1),
+                        16 => (*lengths.last()?, 3 + bits.get(2)?),
+                        17 => (0, 3 + bits.get(3)?),
+                        _ => (0, 11 + bits.get(7)?),
+                    };
+                    lengths.extend(std::iter::repeat_n(value, repeat as usize));
+                }
+                if lengths.len() != literals + distances {
+                    return None;
+                }
+                let literal_code = Huffman::new(&lengths[..literals]);
+                let distance_code = Huffman::new(&lengths[literals..]);
+                inflate_block(&mut bits, &mut out, &literal_code, &distance_code)?;
+            }
+            _ => return None,
+        }
+        if last {
+            return Some(out);
+        }
+    }
+}
+
+fn inflate_block(bits: &mut Bits, out: &mut Vec<u8>, literal_code: &Huffman, distance_code: &Huffman) -> Option<()> {
+    loop {
+        let symbol = literal_code.decode(bits)? as usize;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Some(()),
+            _ => {
+                let i = symbol - 257;
+                let length = *LENGTH_BASE.get(i)? as usize + bits.get(LENGTH_EXTRA[i] as u32)? as usize;
+                let d = distance_code.decode(bits)? as usize;
+                let distance = *DISTANCE_BASE.get(d)? as usize + bits.get(DISTANCE_EXTRA[d] as u32)? as usize;
+                if distance > out.len() {
+                    return None;
+                }
+                let from = out.len() - distance;
+                for k in 0..length {
+                    out.push(out[from + k]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,39 @@
+// api-diff over tests/files/api/old and new, javac's classes for the sources beside them
+use std::process::Command;
+
+fn api_diff(old: &str, new: &str) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_jcfreader")).arg("api-diff")
+        .arg(format!("tests/files/api/old/{}", old)).arg(format!("tests/files/api/new/{}", new)).output().unwrap();
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+    (output.status.code(), String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[test]
+fn changes_are_classified() {
+    let (status, text) = api_diff("", "");
+    assert_eq!(text, "\
+breaking p/Widget: supertype p/Base removed
+breaking p/Widget.LIMIT:I: constant value changed from 10 to 20
+breaking p/Widget.count:I: static added
+breaking p/Widget.helper:()V: visibility reduced from public to package private
+breaking p/Widget.run:()V: final added
+source-only p/Service.stop:()V: abstract method added
+source-only p/Widget.load:()V: throws java/io/IOException removed
+5 breaking, 2 source-only, 0 compatible
+");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn source_only_changes_exit_with_success() {
+    let (status, text) = api_diff("p/Service.class", "p/Service.class");
+    assert_eq!(text, "source-only p/Service.stop:()V: abstract method added\n0 breaking, 1 source-only, 0 compatible\n");
+    assert_eq!(status, Some(0));
+}
+
+#[test]
+fn unchanged_api_has_no_changes() {
+    let (status, text) = api_diff("p/Base.class", "p/Base.class");
+    assert_eq!(text, "0 breaking, 0 source-only, 0 compatible\n");
+    assert_eq!(status, Some(0));
+}
@@ -0,0 +1,111 @@
+// methods taken apart with Assembler::from_code and put back, as they are and with code inserted
+use std::collections::HashMap;
+use jcfreader::attribute::{AttributeKind, HasAttributes};
+use jcfreader::class_file_reader::AttributeInfo;
+use jcfreader::code::assembler::{replace_code, Assembler, Instruction};
+use jcfreader::code::opcode::Opcode;
+use jcfreader::code::verifier;
+use jcfreader::code::{self, Code};
+use jcfreader::{ClassFile, Dump};
+
+const FIXTURES: [&str; 4] = ["test1.class", "test2.class", "Annotated.class", "Annotated$Info.class"];
+
+fn fixture(name: &str) -> ClassFile {
+    ClassFile::new(&format!("tests/files/{}", name), Dump::None)
+}
+
+// index in get_methods and Code info of the method with this name
+fn method_code(class_file: &ClassFile, name: &str) -> (usize, Vec<u8>) {
+    let (i, method) = class_file.get_methods().into_iter().enumerate().find(|(_, m)| m.get_name() == name).unwrap();
+    (i, method.get_attributes().iter().find(|a| a.name == "Code").unwrap().info.clone())
+}
+
+// the line numbers of every LineNumberTable of a Code attribute
+fn lines(class_file: &ClassFile, info: &[u8]) -> Vec<code::LineNumber> {
+    Code::new(info).attributes(class_file).iter().filter(|a| a.name == "LineNumberTable")
+        .flat_map(|a| code::get_line_number_table(&a.info).unwrap())
+        .collect()
+}
+
+fn names(class_file: &ClassFile, info: &[u8]) -> Vec<String> {
+    Code::new(info).attributes(class_file).iter().map(|a| a.name.clone()).collect()
+}
+
+#[test]
+fn from_code_assembles_the_same_code() {
+    for name in FIXTURES {
+        let class_file = fixture(name);
+        for method in class_file.get_methods() {
+            let Some(attribute) = method.get_attributes().iter().find(|a| a.name == "Code") else { continue };
+            let code = Code::new(&attribute.info);
+            let assembled = Assembler::from_code(&class_file, &code).assemble().unwrap();
+            let context = format!("{} {}", name, method.get_name());
+            assert_eq!(assembled.code, Code::new(&attribute.info).get_instructions().fold(Vec::new(), |mut bytes, (pc, op)| {
+                op.write(pc as u32, &mut bytes);
+                bytes
+            }), "{}", context);
+            assert_eq!(assembled.exception_table.len(), code.get_exception_table().len(), "{}", context);
+            assert_eq!(assembled.line_numbers.len(), lines(&class_file, &attribute.info).len(), "{}", context);
+        }
+    }
+}
+
+#[test]
+fn inserted_code_moves_type_annotations_and_drops_the_stack_map() {
+    let mut class_file = fixture("Annotated.class");
+    let (method, info) = method_code(&class_file, "count");
+    assert!(names(&class_file, &info).contains(&"StackMapTable".to_string()));
+    let mut assembler = Assembler::from_code(&class_file, &Code::new(&info));
+    assembler.insert(0, Instruction::Op(Opcode::Nop));
+    let replaced = replace_code(&class_file, &info, &assembler.assemble().unwrap());
+    assert!(!names(&class_file, &replaced).contains(&"StackMapTable".to_string()));
+
+    let code = Code::new(&replaced);
+    let annotations: Vec<String> = class_file.get_type_annotations(&code.attributes(&class_file)).runtime_visible.iter()
+        .map(|a| a.to_string())
+        .collect();
+    assert_eq!(annotations.len(), 3);
+    assert!(annotations[0].ends_with("INSTANCEOF, offset=4"), "{:?}", annotations);
+    assert!(annotations[1].ends_with("CAST, offset=11, type_index=0"), "{:?}", annotations);
+    // the local variable is in scope from after the astore_2, now at 3, to the end, so the length is the same
+    assert!(annotations[2].contains("start_pc=3, length=19, index=2"), "{:?}", annotations);
+
+    class_file.set_code(method, replaced);
+    let hierarchy: HashMap<String, ClassFile> = HashMap::new();
+    class_file.compute_stack_map(method, &hierarchy).unwrap();
+    let problems: Vec<String> = verifier::verify(&class_file, &hierarchy).iter().map(|p| p.to_string()).collect();
+    assert!(problems.is_empty(), "{:?}", problems);
+}
+
+#[test]
+fn split_line_number_table_is_written_once() {
+    let class_file = fixture("test1.class");
+    let (_, info) = method_code(&class_file, "method1");
+    let attributes = Code::read_attributes(&info);
+    let at = attributes.iter().position(|a| class_file.get_constant_pool().get_item(&a.attribute_name_index) == "LineNumberTable").unwrap();
+    let lines = lines(&class_file, &info);
+    assert!(lines.len() >= 2);
+
+    // the same entries as two attributes, split after the first
+    let table = |entries: &[code::LineNumber]| {
+        let mut bytes = (entries.len() as u16).to_be_bytes().to_vec();
+        for l in entries {
+            bytes.extend(l.start_pc.to_be_bytes());
+            bytes.extend(l.line_number.to_be_bytes());
+        }
+        AttributeInfo { attribute_name_index: attributes[at].attribute_name_index.clone(), info: bytes }
+    };
+    let mut split = attributes.clone();
+    split[at] = table(&lines[..1]);
+    split.insert(at + 1, table(&lines[1..]));
+    let info = code::set_attributes(&info, &split);
+
+    let assembled = Assembler::from_code(&class_file, &Code::new(&info)).assemble().unwrap();
+    let replaced = replace_code(&class_file, &info, &assembled);
+    assert_eq!(names(&class_file, &replaced).iter().filter(|n| *n == "LineNumberTable").count(), 1);
+    let code = Code::new(&replaced);
+    match code.find_attribute(&class_file, "LineNumberTable") {
+        Some(AttributeKind::LineNumberTable(table)) => assert_eq!(table.len(), lines.len()),
+        _ => panic!("LineNumberTable"),
+    }
+}
@@ -0,0 +1,25 @@
+// the call graph as JSON, with method names JSON has to escape
+use jcfreader::call_graph::{Algorithm, CallGraph};
+use jcfreader::class_path::ClassPath;
+use jcfreader::hierarchy::Hierarchy;
+
+#[test]
+fn json_escapes_control_characters() {
+    // the Utf8 method1 is the name of the method and of its Methodref, renamed keeping the length
+    let mut bytes = std::fs::read("tests/files/test1.class").unwrap();
+    let at = bytes.windows(10).position(|w| w == b"\x01\x00\x07method1").unwrap();
+    bytes[at + 3..at + 10].copy_from_slice(b"meth\x01\"1");
+    let dir = std::env::temp_dir().join(format!("jcfreader-call-graph-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("com/strl/test")).unwrap();
+    std::fs::write(dir.join("com/strl/test/test1.class"), bytes).unwrap();
+
+    let mut class_path = ClassPath::new();
+    class_path.add(&dir).unwrap();
+    let hierarchy = Hierarchy::new(class_path);
+    let graph = CallGraph::new(&hierarchy, &["com/strl/test/test1".to_string()], &[], Algorithm::Cha);
+    let json: serde_json::Value = serde_json::from_str(&graph.to_json()).unwrap();
+    let methods: Vec<&str> = json["methods"].as_array().unwrap().iter().map(|m| m.as_str().unwrap()).collect();
+    assert!(methods.contains(&"com/strl/test/test1.meth\u{1}\"1:(JJ)I"), "{:?}", methods);
+    assert!(!json["calls"].as_array().unwrap().is_empty());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,59 @@
+// basic blocks, dominators and loops of javac's methods in Flow.class
+use jcfreader::code::cfg::{ControlFlowGraph, EdgeKind};
+use jcfreader::code::Code;
+use jcfreader::ClassFile;
+
+fn code(name: &str) -> Code {
+    let class_file = ClassFile::try_from_bytes(std::fs::read("tests/files/Flow.class").unwrap()).unwrap();
+    let method = class_file.get_methods().into_iter().find(|m| m.get_name() == name).unwrap();
+    Code::new(&method.get_attributes().iter().find(|a| a.name == "Code").unwrap().info)
+}
+
+fn start_pcs(cfg: &ControlFlowGraph) -> Vec<u16> {
+    cfg.blocks.iter().map(|b| b.start_pc).collect()
+}
+
+fn immediate_dominators(cfg: &ControlFlowGraph) -> Vec<Option<usize>> {
+    let dominators = cfg.dominators();
+    (0..cfg.blocks.len()).map(|b| dominators.get_immediate(b)).collect()
+}
+
+#[test]
+fn nested_loops() {
+    let cfg = ControlFlowGraph::new(&code("nested")).unwrap();
+    assert_eq!(start_pcs(&cfg), [0, 4, 9, 12, 18, 29, 35]);
+    assert_eq!(immediate_dominators(&cfg), [None, Some(0), Some(1), Some(2), Some(3), Some(3), Some(1)]);
+    let dominators = cfg.dominators();
+    assert!(dominators.dominates(1, 4));
+    assert!(!dominators.dominates(4, 5));
+    assert_eq!(dominators.get_children(3), [4, 5]);
+
+    // the outer loop from pc 4 holds the inner one from pc 12
+    let loops: Vec<(usize, Vec<usize>, Vec<usize>)> = cfg.loops().into_iter().map(|l| (l.header, l.latches, l.blocks)).collect();
+    assert_eq!(loops, [(1, vec![5], vec![1, 2, 3, 4, 5]), (3, vec![4], vec![3, 4])]);
+}
+
+#[test]
+fn switch() {
+    let cfg = ControlFlowGraph::new(&code("choose")).unwrap();
+    assert_eq!(start_pcs(&cfg), [0, 28, 33, 39, 45, 47]);
+    let mut cases = cfg.blocks[0].successors.clone();
+    cases.sort_by_key(|(b, _)| *b);
+    assert_eq!(cases, [(1, EdgeKind::Switch), (2, EdgeKind::Switch), (3, EdgeKind::Switch), (4, EdgeKind::Switch)]);
+    // every case joins at the return, which only the switch dominates
+    assert_eq!(immediate_dominators(&cfg), [None, Some(0), Some(0), Some(0), Some(0), Some(0)]);
+    assert_eq!(cfg.post_dominators().get_immediate(0), Some(5));
+    assert!(cfg.loops().is_empty());
+}
+
+#[test]
+fn try_finally() {
+    let cfg = ControlFlowGraph::new(&code("guarded")).unwrap();
+    assert_eq!(start_pcs(&cfg), [0, 2, 6, 12, 18]);
+    // the finally block is inlined after the try and in the handler for anything thrown from it
+    assert_eq!(cfg.blocks[1].successors, [(2, EdgeKind::FallThrough), (3, EdgeKind::Exception(0))]);
+    assert!(cfg.blocks[3].successors.is_empty());
+    assert_eq!(cfg.get_block(13), Some(3));
+    assert_eq!(immediate_dominators(&cfg), [None, Some(0), Some(1), Some(1), Some(2)]);
+    assert!(cfg.loops().is_empty());
+}
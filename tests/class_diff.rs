@@ -0,0 +1,53 @@
+// classes compared by value, a renumbered constant pool is no change
+use std::collections::HashMap;
+use jcfreader::{class_diff, jasm, ClassFile};
+
+// Flow without its StackMapTables, jasm writes them as raw bytes that would keep the old pool indices
+fn flow() -> ClassFile {
+    let mut class_file = ClassFile::try_from_bytes(std::fs::read("tests/files/Flow.class").unwrap()).unwrap();
+    class_file.remove_attributes("StackMapTable");
+    class_file
+}
+
+// the frames computed again, against whichever pool the class has
+fn with_stack_maps(mut class_file: ClassFile) -> ClassFile {
+    for method in 0..class_file.get_methods().len() {
+        class_file.compute_stack_map(method, &HashMap::<String, ClassFile>::new()).unwrap();
+    }
+    class_file
+}
+
+// the class with its constant pool in reverse order, every #n in the .const lines renumbered to match
+fn reversed_pool(class_file: &ClassFile) -> ClassFile {
+    let text = jasm::disassemble(class_file);
+    let count = text.lines().filter(|l| l.starts_with(".const ")).count();
+    let renumber = |line: &str| line.split(' ').map(|word| match word.strip_prefix('#').and_then(|n| n.parse::<usize>().ok()) {
+        Some(n) => format!("#{}", count + 1 - n),
+        None => word.to_string(),
+    }).collect::<Vec<String>>().join(" ");
+    let mut constants: Vec<String> = text.lines().filter(|l| l.starts_with(".const ")).map(renumber).collect();
+    constants.reverse();
+    let mut lines: Vec<String> = text.lines().filter(|l| !l.starts_with(".const ")).map(|l| l.to_string()).collect();
+    let at = lines.iter().position(|l| l.starts_with(".super ")).unwrap() + 1;
+    lines.splice(at..at, constants);
+    jasm::assemble(&(lines.join("\n") + "\n")).unwrap()
+}
+
+#[test]
+fn renumbered_constant_pool_is_no_change() {
+    let old = flow();
+    let new = with_stack_maps(reversed_pool(&old));
+    let old = with_stack_maps(old);
+    assert_ne!(old.to_bytes(), new.to_bytes());
+    assert_eq!(new.get_constant_pool().get_utf8_bytes(1), old.get_constant_pool().get_utf8_bytes(22));
+    assert_eq!(class_diff::diff(&old, &new), "");
+}
+
+#[test]
+fn changed_constant_value_is_a_change() {
+    let old = flow();
+    let text = jasm::disassemble(&old).replace("\"Flow.java\"", "\"Other.java\"");
+    let new = reversed_pool(&jasm::assemble(&text).unwrap());
+    let diff = class_diff::diff(&old, &new);
+    assert!(diff.contains("Flow.java") && diff.contains("Other.java"), "{}", diff);
+}
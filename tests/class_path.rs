@@ -0,0 +1,89 @@
+// classes that cannot be read give errors rather than ending the process
+use std::path::PathBuf;
+use jcfreader::class_file_reader::ClassFileError;
+use jcfreader::class_path::{ClassPath, ClassPathError};
+use jcfreader::ClassFile;
+
+fn fixture(name: &str) -> Vec<u8> {
+    std::fs::read(format!("tests/files/{}", name)).unwrap()
+}
+
+// a directory of its own under the temp directory, p/Good.class, p/Junk.class and p/Short.class
+fn class_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("jcfreader-{}-{}", test, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("p")).unwrap();
+    let good = fixture("test1.class");
+    std::fs::write(dir.join("p/Good.class"), &good).unwrap();
+    std::fs::write(dir.join("p/Junk.class"), b"junk").unwrap();
+    std::fs::write(dir.join("p/Short.class"), &good[..100]).unwrap();
+    dir
+}
+
+#[test]
+fn try_from_bytes_reports_bad_magic() {
+    assert!(matches!(ClassFile::try_from_bytes(b"junk".to_vec()), Err(ClassFileError::NotClass)));
+    assert!(matches!(ClassFile::try_from_bytes(Vec::new()), Err(ClassFileError::Truncated(_))));
+}
+
+#[test]
+fn try_from_bytes_reports_truncation() {
+    let good = fixture("test1.class");
+    for len in [4, 9, 100, good.len() - 1] {
+        assert!(matches!(ClassFile::try_from_bytes(good[..len].to_vec()), Err(ClassFileError::Truncated(_))), "{}", len);
+    }
+}
+
+#[test]
+fn try_from_bytes_reports_bad_constant_tag() {
+    let mut bytes = fixture("test1.class");
+    bytes[10] = 2;      // tag of the first constant
+    assert!(matches!(ClassFile::try_from_bytes(bytes), Err(ClassFileError::BadTag(2))));
+}
+
+#[test]
+fn try_from_bytes_reports_a_method_name_that_is_not_utf8() {
+    let mut bytes = fixture("test1.class");
+    let class_file = ClassFile::try_from_bytes(bytes.clone()).unwrap();
+    let mut builder = class_file.constant_pool_builder();
+    let [name, descriptor, class] = [builder.utf8("method1"), builder.utf8("(JJ)I"), builder.class("com/strl/test/test1")].map(|i| i.unwrap());
+    // public static method1 (JJ)I, named by the class's Class entry instead
+    let method = [[0, 9], name.to_be_bytes(), descriptor.to_be_bytes()].concat();
+    let at = bytes.windows(6).position(|w| w == method).unwrap();
+    bytes[at + 2..at + 4].copy_from_slice(&class.to_be_bytes());
+    assert!(matches!(ClassFile::try_from_bytes(bytes.clone()), Err(ClassFileError::BadName)));
+
+    let dir = std::env::temp_dir().join(format!("jcfreader-bad-name-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("test1.class");
+    std::fs::write(&path, bytes).unwrap();
+    for args in [vec![], vec!["--json"], vec!["unused"]] {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_jcfreader")).args(args).arg(&path).output().unwrap();
+        assert_ne!(output.status.code(), Some(0));
+        assert!(!String::from_utf8_lossy(&output.stderr).contains("panicked"), "{}", String::from_utf8_lossy(&output.stderr));
+    }
+}
+
+#[test]
+fn try_from_bytes_reads_a_class() {
+    let class_file = ClassFile::try_from_bytes(fixture("test1.class")).unwrap();
+    assert_eq!(class_file.get_class_name(), "com/strl/test/test1");
+}
+
+#[test]
+fn class_path_skips_unreadable_classes() {
+    let dir = class_dir("skip");
+    let mut class_path = ClassPath::new();
+    class_path.add(&dir).unwrap();
+    assert!(class_path.get_class("p/Good").is_some());
+    assert!(class_path.get_class("p/Junk").is_none());
+    assert!(class_path.get_class("p/Short").is_none());
+    match class_path.read_class("p/Junk") {
+        Err(ClassPathError::Class(path, name, ClassFileError::NotClass)) => {
+            assert_eq!(path, dir);
+            assert_eq!(name, "p/Junk.class");
+        }
+        _ => panic!("p/Junk read"),
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
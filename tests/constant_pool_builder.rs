@@ -0,0 +1,86 @@
+// entries already in a pool keep their index, new ones go at the end once and survive writing the class
+use jcfreader::constant_pool_builder::{ConstantPoolBuilder, ConstantPoolError};
+use jcfreader::ClassFile;
+
+fn constants() -> ClassFile {
+    ClassFile::try_from_bytes(std::fs::read("tests/files/Constants.class").unwrap()).unwrap()
+}
+
+#[test]
+fn existing_entries_keep_their_index() {
+    let class_file = constants();
+    let mut builder = class_file.constant_pool_builder();
+    let count = builder.count();
+    let existing = [
+        builder.method_ref("java/lang/Object", "<init>", "()V").unwrap(),
+        builder.long(0x123456789abcdef).unwrap(),
+        builder.double(f64::NAN).unwrap(),
+        builder.float(-0.0).unwrap(),
+        builder.utf8("nul \0 and \u{1F600} outside the BMP").unwrap(),
+        builder.class("com/strl/test/Constants").unwrap(),
+    ];
+    assert!(existing.iter().all(|i| *i < count), "{:?}", existing);
+    assert_eq!(builder.count(), count);
+}
+
+#[test]
+fn new_entries_are_added_once() {
+    let mut class_file = constants();
+    let mut builder = class_file.constant_pool_builder();
+    let count = builder.count();
+    let method = builder.method_ref("p/New", "run", "(J)V").unwrap();
+    assert_eq!(builder.method_ref("p/New", "run", "(J)V").unwrap(), method);
+    // Utf8 p/New, Class, Utf8 run, Utf8 (J)V, NameAndType, Methodref
+    assert_eq!(builder.count(), count + 6);
+    // a long takes two slots, and 0.0 is not -0.0
+    let long = builder.long(7).unwrap();
+    assert_eq!(builder.count(), long + 2);
+    assert_ne!(builder.float(0.0).unwrap(), builder.float(-0.0).unwrap());
+
+    class_file.set_constant_pool(builder.build());
+    let again = ClassFile::try_from_bytes(class_file.to_bytes()).unwrap();
+    let cp = again.get_constant_pool();
+    assert_eq!(cp.get_member(method), Some(("p/New".to_string(), "run".to_string(), "(J)V".to_string())));
+    assert_eq!(cp.get_constant_value(long).to_string(), "7");
+}
+
+#[test]
+fn full_pool_is_an_error() {
+    let mut builder = ConstantPoolBuilder::new();
+    for i in 0..65534 {
+        builder.integer(i).unwrap();
+    }
+    assert!(matches!(builder.integer(-1), Err(ConstantPoolError::Full)));
+    assert_eq!(builder.integer(5).unwrap(), 6);
+}
+
+#[test]
+fn utf8_too_long_is_an_error() {
+    let mut builder = ConstantPoolBuilder::new();
+    assert_eq!(builder.utf8(&"a".repeat(65535)).unwrap(), 1);
+    // two bytes each in modified UTF-8
+    assert!(matches!(builder.utf8(&"\u{e9}".repeat(32768)), Err(ConstantPoolError::Utf8Length(65536))));
+    assert_eq!(builder.count(), 2);
+}
+
+#[test]
+fn failed_entries_add_nothing() {
+    let class_file = constants();
+    let mut builder = class_file.constant_pool_builder();
+    let count = builder.count();
+    let long = "a".repeat(65536);
+    assert!(builder.class(&long).is_err());
+    // the class and the name are new, the descriptor is too long after they are added
+    assert!(matches!(builder.method_ref("p/New", "run", &long), Err(ConstantPoolError::Utf8Length(65536))));
+    assert_eq!(builder.count(), count);
+    assert_eq!(builder.class("p/New").unwrap(), count + 1);
+
+    // a pool with room for two entries more, a Methodref needs six
+    let mut builder = ConstantPoolBuilder::new();
+    for i in 0..65532 {
+        builder.integer(i).unwrap();
+    }
+    assert!(matches!(builder.method_ref("p/New", "run", "()V"), Err(ConstantPoolError::Full)));
+    assert_eq!(builder.count(), 65533);
+    assert_eq!(builder.class("p/New").unwrap(), 65534);
+}
@@ -0,0 +1,262 @@
+// attributes with tags JVMS does not define decode as Unknown, validate reports them and nothing panics
+use std::path::PathBuf;
+use std::process::Command;
+use jcfreader::attribute::{AttributeKind, DecodeError};
+use jcfreader::{Attribute, ClassFile, Dump};
+
+fn annotated() -> ClassFile {
+    ClassFile::new(&"tests/files/Annotated.class".to_string(), Dump::None)
+}
+
+fn attribute(name: &str, info: Vec<u8>) -> Attribute {
+    Attribute { name: name.to_string(), info }
+}
+
+// the class written to a directory of its own under the temp directory
+fn write_class(test: &str, class_file: &ClassFile) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("jcfreader-{}-{}", test, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("Annotated.class");
+    std::fs::write(&path, class_file.to_bytes()).unwrap();
+    path
+}
+
+// exit code and stdout of the command line tool
+fn run(args: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_jcfreader")).args(args).output().unwrap();
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("panicked"), "{}", String::from_utf8_lossy(&output.stderr));
+    (output.status.code(), String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// Annotated's RuntimeVisibleAnnotations with the first element_value tag, name's 's', changed
+fn bad_element_value(class_file: &mut ClassFile) {
+    let names: Vec<String> = class_file.get_attributes_vec(&class_file.attributes).into_iter().map(|a| a.name).collect();
+    let at = names.iter().position(|n| n == "RuntimeVisibleAnnotations").unwrap();
+    assert_eq!(class_file.attributes[at].info[8], b's');
+    class_file.attributes[at].info[8] = b'x';
+}
+
+#[test]
+fn annotations_decode() {
+    let class_file = annotated();
+    let attributes = class_file.get_attributes_vec(&class_file.attributes);
+    let annotations = attributes.iter().find(|a| a.name == "RuntimeVisibleAnnotations").unwrap();
+    match annotations.try_decode(&class_file) {
+        Ok(AttributeKind::RuntimeVisibleAnnotations(v)) => assert_eq!(v[0].get_value_pairs().len(), 5),
+        _ => panic!("RuntimeVisibleAnnotations"),
+    }
+    let method = class_file.get_methods().into_iter().find(|m| m.get_name() == "count").unwrap();
+    let type_annotations = class_file.get_type_annotations(method.get_attributes());
+    assert_eq!(type_annotations.runtime_visible.len(), 1);
+    assert_eq!(type_annotations.runtime_visible[0].to_string(), "Lcom/strl/test/Annotated$NonNull;: METHOD_FORMAL_PARAMETER, param_index=0");
+}
+
+#[test]
+fn unknown_target_type() {
+    let class_file = annotated();
+    let bad = attribute("RuntimeVisibleTypeAnnotations", vec![0, 1, 0x50, 0, 0, 20, 0, 0]);
+    assert!(matches!(bad.try_decode(&class_file), Err(DecodeError::Tag("target_type", 0x50))));
+    assert!(matches!(bad.decode(&class_file), AttributeKind::Unknown(_, _)));
+}
+
+#[test]
+fn unknown_type_path_kind() {
+    let class_file = annotated();
+    // FIELD with a one step type_path of kind 4
+    let bad = attribute("RuntimeInvisibleTypeAnnotations", vec![0, 1, 0x13, 1, 4, 0, 0, 20, 0, 0]);
+    assert!(matches!(bad.try_decode(&class_file), Err(DecodeError::Tag("type_path kind", 4))));
+    let good = attribute("RuntimeInvisibleTypeAnnotations", vec![0, 1, 0x13, 1, 3, 0, 0, 20, 0, 0]);
+    assert!(matches!(good.try_decode(&class_file), Ok(AttributeKind::RuntimeInvisibleTypeAnnotations(_))));
+}
+
+#[test]
+fn unknown_element_value_tag() {
+    let class_file = annotated();
+    let bad = attribute("AnnotationDefault", vec![b'x', 0, 1]);
+    assert!(matches!(bad.try_decode(&class_file), Err(DecodeError::Tag("element_value tag", b'x'))));
+    let truncated = attribute("AnnotationDefault", vec![b'I', 0]);
+    assert!(matches!(truncated.try_decode(&class_file), Err(DecodeError::Truncated)));
+    let not_utf8 = attribute("AnnotationDefault", vec![b'c', 0, 1]);
+    assert!(matches!(not_utf8.try_decode(&class_file), Err(DecodeError::Constant(1))));
+}
+
+#[test]
+fn validate_reports_an_unknown_tag() {
+    let mut class_file = annotated();
+    assert!(class_file.validate().is_empty());
+    bad_element_value(&mut class_file);
+    let diagnostics: Vec<String> = class_file.validate().iter().map(|d| d.to_string()).collect();
+    assert_eq!(diagnostics, ["class, RuntimeVisibleAnnotations: unknown element_value tag 120"]);
+}
+
+#[test]
+fn commands_keep_an_attribute_that_does_not_decode() {
+    let mut class_file = annotated();
+    bad_element_value(&mut class_file);
+    let path = write_class("element-value", &class_file);
+    let path = path.to_str().unwrap();
+    assert_eq!(run(&["validate", path]).0, Some(1));
+    assert_eq!(run(&[path]).0, Some(0));
+    assert!(run(&["disasm", path]).1.contains(".attribute RuntimeVisibleAnnotations"));
+    let (code, json) = run(&["--json", path]);
+    assert_eq!(code, Some(0));
+    let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let attributes = json["attributes"].as_array().unwrap();
+    assert!(attributes.iter().any(|a| a["name"] == "RuntimeVisibleAnnotations" && a["info"].is_string()));
+}
+
+// Annotated.class with the int of count's second stack map frame, same_locals_1_stack_item, given tag 9
+fn bad_verification_type() -> Vec<u8> {
+    let mut bytes = std::fs::read("tests/files/Annotated.class").unwrap();
+    // name #25 StackMapTable, length 10, two frames, append then same_locals_1_stack_item
+    let at = bytes.windows(6).position(|w| w == [0, 25, 0, 0, 0, 10]).unwrap();
+    assert_eq!(bytes[at + 14..at + 16], [64, 1]);
+    bytes[at + 15] = 9;
+    bytes
+}
+
+#[test]
+fn stack_map_decodes() {
+    let class_file = annotated();
+    let table = attribute("StackMapTable", vec![0, 2, 252, 0, 19, 7, 0, 2, 64, 1]);
+    match table.try_decode(&class_file) {
+        Ok(AttributeKind::StackMapTable(frames)) => assert_eq!(frames.len(), 2),
+        _ => panic!("StackMapTable"),
+    }
+}
+
+#[test]
+fn unknown_stack_map_tags() {
+    let class_file = annotated();
+    let frame = attribute("StackMapTable", vec![0, 1, 200]);
+    assert!(matches!(frame.try_decode(&class_file), Err(DecodeError::Tag("stack map frame type", 200))));
+    assert!(matches!(frame.decode(&class_file), AttributeKind::Unknown(_, _)));
+    let verification_type = attribute("StackMapTable", vec![0, 1, 64, 9]);
+    assert!(matches!(verification_type.try_decode(&class_file), Err(DecodeError::Tag("verification type", 9))));
+    let not_class = attribute("StackMapTable", vec![0, 1, 64, 7, 0, 1]);
+    assert!(matches!(not_class.try_decode(&class_file), Err(DecodeError::Constant(1))));
+    let truncated = attribute("StackMapTable", vec![0, 2, 64, 1]);
+    assert!(matches!(truncated.try_decode(&class_file), Err(DecodeError::Truncated)));
+}
+
+#[test]
+fn commands_keep_a_stack_map_that_does_not_decode() {
+    let class_file = ClassFile::try_from_bytes(bad_verification_type()).unwrap();
+    let diagnostics: Vec<String> = class_file.validate().iter().map(|d| d.to_string()).collect();
+    assert_eq!(diagnostics, ["method count (Ljava/lang/String;)I, Code, StackMapTable: unknown verification type 9"]);
+    let path = write_class("stack-map", &class_file);
+    let path = path.to_str().unwrap();
+    let (code, text) = run(&["verify", path]);
+    assert_eq!(code, Some(1));
+    assert!(text.contains("bad stack map frame: unknown verification type 9"), "{}", text);
+    assert_eq!(run(&["validate", path]).0, Some(1));
+    assert_eq!(run(&["disasm", path]).0, Some(0));
+    assert_eq!(run(&["--json", path]).0, Some(0));
+    assert_eq!(run(&["diff", "tests/files/Annotated.class", path]).0, Some(1));
+    assert_eq!(run(&["unused", path]).0, Some(0));
+}
+
+// indexes of the constants the attributes below need, added to Annotated's pool
+struct Constants {
+    utf8: u16,
+    class: u16,
+    name_and_type: u16,
+    module: u16,
+    package: u16,
+    method_handle: u16,
+    integer: u16,
+}
+
+fn with_constants() -> (ClassFile, Constants) {
+    let mut class_file = annotated();
+    let mut builder = class_file.constant_pool_builder();
+    let method_ref = builder.method_ref("java/lang/Object", "toString", "()Ljava/lang/String;").unwrap();
+    let constants = Constants {
+        utf8: builder.utf8("name").unwrap(),
+        class: builder.class("p/C").unwrap(),
+        name_and_type: builder.name_and_type("run", "()V").unwrap(),
+        module: builder.module("m").unwrap(),
+        package: builder.package("p").unwrap(),
+        method_handle: builder.method_handle(5, method_ref).unwrap(),
+        integer: builder.integer(7).unwrap(),
+    };
+    class_file.set_constant_pool(builder.build());
+    (ClassFile::try_from_bytes(class_file.to_bytes()).unwrap(), constants)
+}
+
+// each attribute kind that reads the pool or has a length of its own, good bytes and the offset of an index to spoil
+fn attribute_bytes(c: &Constants) -> Vec<(&'static str, Vec<u8>, Option<usize>)> {
+    let u16s = |v: &[u16]| -> Vec<u8> { v.iter().flat_map(|i| i.to_be_bytes()).collect() };
+    vec![
+        ("ConstantValue", u16s(&[c.integer]), Some(0)),
+        ("Code", [u16s(&[1, 1, 0, 1]), vec![0xb1], u16s(&[0, 0])].concat(), None),
+        ("Exceptions", u16s(&[1, c.class]), Some(2)),
+        ("InnerClasses", u16s(&[1, c.class, c.class, c.utf8, 0x0008]), Some(6)),
+        ("EnclosingMethod", u16s(&[c.class, c.name_and_type]), Some(2)),
+        ("Signature", u16s(&[c.utf8]), Some(0)),
+        ("SourceFile", u16s(&[c.utf8]), Some(0)),
+        ("LineNumberTable", u16s(&[1, 0, 7]), None),
+        ("LocalVariableTable", u16s(&[1, 0, 1, c.utf8, c.utf8, 0]), Some(8)),
+        ("LocalVariableTypeTable", u16s(&[1, 0, 1, c.utf8, c.utf8, 0]), Some(6)),
+        ("BootstrapMethods", u16s(&[1, c.method_handle, 1, c.integer]), Some(2)),
+        ("MethodParameters", [vec![1], u16s(&[c.utf8, 0])].concat(), Some(1)),
+        ("Module", u16s(&[c.module, 0, 0, 1, c.module, 0, 0, 1, c.package, 0, 1, c.module, 0, 1, c.class, 1, c.class, 1, c.class]), Some(16)),
+        ("ModulePackages", u16s(&[1, c.package]), Some(2)),
+        ("ModuleMainClass", u16s(&[c.class]), Some(0)),
+        ("NestHost", u16s(&[c.class]), Some(0)),
+        ("NestMembers", u16s(&[1, c.class]), Some(2)),
+        ("Record", [u16s(&[1, c.utf8, c.utf8, 1, c.utf8]), vec![0, 0, 0, 2], u16s(&[c.utf8])].concat(), Some(8)),
+        ("PermittedSubclasses", u16s(&[1, c.class]), Some(2)),
+    ]
+}
+
+#[test]
+fn every_kind_reports_truncated_and_mis_indexed_bytes() {
+    let (class_file, constants) = with_constants();
+    for (name, info, index_at) in attribute_bytes(&constants) {
+        let good = attribute(name, info.clone());
+        assert!(good.try_decode(&class_file).is_ok(), "{}", name);
+        assert!(!matches!(good.decode(&class_file), AttributeKind::Unknown(_, _)), "{}", name);
+        for len in 0..info.len() {
+            let truncated = attribute(name, info[..len].to_vec());
+            assert!(matches!(truncated.try_decode(&class_file), Err(DecodeError::Truncated)), "{} of {} bytes", name, len);
+        }
+        let Some(at) = index_at else { continue };
+        // an Integer where anything else is wanted, a Class where the constant is
+        let wrong = match name {
+            "ConstantValue" => constants.class,
+            _ => constants.integer,
+        };
+        let mut mis_indexed = info.clone();
+        mis_indexed[at..at + 2].copy_from_slice(&wrong.to_be_bytes());
+        let decoded = attribute(name, mis_indexed).try_decode(&class_file);
+        assert!(matches!(decoded, Err(DecodeError::Constant(i)) if i == wrong), "{}", name);
+        let mut out_of_range = info.clone();
+        out_of_range[at..at + 2].copy_from_slice(&u16::MAX.to_be_bytes());
+        assert!(matches!(attribute(name, out_of_range).try_decode(&class_file), Err(DecodeError::Constant(u16::MAX))), "{}", name);
+    }
+}
+
+#[test]
+fn undefined_opcode_is_an_error() {
+    let class_file = annotated();
+    let code = attribute("Code", vec![0, 1, 0, 1, 0, 0, 0, 1, 0xcb, 0, 0, 0, 0]);
+    assert!(matches!(code.try_decode(&class_file), Err(DecodeError::Tag("opcode", 0xcb))));
+    // a tableswitch whose range is far longer than its bytes
+    let code = attribute("Code", vec![0, 1, 0, 1, 0, 0, 0, 16, 0xaa, 0, 0, 0, 0, 0, 0, 0, 0x80, 0, 0, 0, 0x7f, 0xff, 0xff, 0xff, 0, 0, 0, 0]);
+    assert!(matches!(code.try_decode(&class_file), Err(DecodeError::Truncated)));
+}
+
+#[test]
+fn commands_keep_truncated_inner_classes() {
+    let mut class_file = annotated();
+    let names: Vec<String> = class_file.get_attributes_vec(&class_file.attributes).into_iter().map(|a| a.name).collect();
+    let at = names.iter().position(|n| n == "InnerClasses").unwrap();
+    class_file.attributes[at].info.truncate(2);
+    let path = write_class("inner-classes", &class_file);
+    let path = path.to_str().unwrap();
+    assert_eq!(run(&[path]).0, Some(0));
+    assert_eq!(run(&["--json", path]).0, Some(0));
+    assert_eq!(run(&["unused", path]).0, Some(0));
+    assert_eq!(run(&["validate", path]).0, Some(1));
+}
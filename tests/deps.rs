@@ -0,0 +1,25 @@
+// the dependency report as JSON, with names JSON has to escape
+use std::collections::BTreeMap;
+use jcfreader::deps::{DependencyGraph, DependencyReport, InternalUse};
+
+#[test]
+fn json_report_parses() {
+    let mut graph = DependencyGraph::new();
+    graph.nodes.entry("p/A\"\n\u{1}".to_string()).or_default().insert("q/B\\".to_string());
+    let mut origins = BTreeMap::new();
+    origins.insert("q/B\\".to_string(), "lib\t.jar".to_string());
+    let report = DependencyReport {
+        by: "class".to_string(),
+        graph,
+        origins,
+        cycles: vec![vec!["p".to_string(), "q".to_string()]],
+        internal: vec![InternalUse { from: "p/A".to_string(), to: "sun/misc/Unsafe".to_string(), module: None }],
+    };
+    let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+    assert_eq!(json["dependencies"][0]["from"], "p.A\"\n\u{1}");
+    assert_eq!(json["dependencies"][0]["to"], "q.B\\");
+    assert_eq!(json["dependencies"][0]["origin"], "lib\t.jar");
+    assert_eq!(json["cycles"][0][1], "q");
+    assert_eq!(json["internal"][0]["to"], "sun.misc.Unsafe");
+    assert!(json["internal"][0]["module"].is_null());
+}
@@ -0,0 +1,31 @@
+package com.strl.test;
+
+import java.lang.annotation.ElementType;
+import java.lang.annotation.Retention;
+import java.lang.annotation.RetentionPolicy;
+import java.lang.annotation.Target;
+import java.util.List;
+
+@Annotated.Info(name = "class", level = 2, kind = ElementType.TYPE, type = String.class, tags = {"a", "b"})
+public class Annotated {
+    @Retention(RetentionPolicy.RUNTIME)
+    @interface Info {
+        String name();
+        int level() default 1;
+        ElementType kind() default ElementType.FIELD;
+        Class<?> type() default Object.class;
+        String[] tags() default {};
+    }
+
+    @Retention(RetentionPolicy.RUNTIME)
+    @Target(ElementType.TYPE_USE)
+    @interface NonNull {
+    }
+
+    @NonNull List<@NonNull String> names;
+
+    int count(@Info(name = "p") @NonNull String s) {
+        @NonNull Object o = s;
+        return o instanceof @NonNull String ? ((@NonNull String) o).length() : 0;
+    }
+}
@@ -0,0 +1,48 @@
+package com.strl.test;
+
+import java.util.List;
+import java.util.function.Supplier;
+
+// constants of every kind, invokedynamic, switches, handlers and loops, for the round trip tests
+class Constants {
+    static final long BIG = 0x123456789abcdefL;
+    static final double NOT_A_NUMBER = Double.NaN;
+    static final float NEGATIVE_ZERO = -0.0f;
+    static final String TEXT = "nul \0 and \uD83D\uDE00 outside the BMP";
+
+    int pick(int k) {
+        switch (k) {
+            case 1: return 10;
+            case 2: return 20;
+            case 3: return 30;
+            default: return 0;
+        }
+    }
+
+    int name(String s) {
+        switch (s) {
+            case "a": return 1;
+            case "bb": return 2;
+            default: return -1;
+        }
+    }
+
+    long sum(List<Long> values) {
+        long total = BIG;
+        for (long v : values) {
+            try {
+                total = Math.addExact(total, v);
+            } catch (ArithmeticException e) {
+                return Long.MAX_VALUE;
+            } finally {
+                total++;
+            }
+        }
+        return total;
+    }
+
+    Supplier<String> supplier(Object o) {
+        String text = o instanceof String s ? s : TEXT + o;
+        return () -> text + NOT_A_NUMBER + NEGATIVE_ZERO;
+    }
+}
@@ -0,0 +1,35 @@
+package com.strl.test;
+
+// nested loops, a switch and try/finally, for the control flow graph tests
+class Flow {
+    int nested(int n) {
+        int total = 0;
+        for (int i = 0; i < n; i++) {
+            for (int j = 0; j < i; j++) {
+                total += j;
+            }
+        }
+        return total;
+    }
+
+    int choose(int k) {
+        int r;
+        switch (k) {
+            case 0: r = 5; break;
+            case 1: r = 7; break;
+            case 2: r = 11; break;
+            default: r = 0;
+        }
+        return r;
+    }
+
+    int guarded(int[] a) {
+        int r = 0;
+        try {
+            r = a[0];
+        } finally {
+            r++;
+        }
+        return r;
+    }
+}
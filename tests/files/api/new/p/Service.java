@@ -0,0 +1,8 @@
+package p;
+
+// stop has no default, implementations compiled before still link
+public interface Service {
+    void start();
+
+    void stop();
+}
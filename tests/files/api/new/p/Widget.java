@@ -0,0 +1,16 @@
+package p;
+
+// no longer a Base, LIMIT changed, count static, run final, helper package private and load throws nothing
+public class Widget {
+    public static final int LIMIT = 20;
+    public static int count;
+
+    public final void run() {
+    }
+
+    void helper() {
+    }
+
+    public void load() {
+    }
+}
@@ -0,0 +1,16 @@
+package p;
+
+// the version before, api-diff compares it with ../new
+public class Widget extends Base {
+    public static final int LIMIT = 10;
+    public int count;
+
+    public void run() {
+    }
+
+    public void helper() {
+    }
+
+    public void load() throws java.io.IOException {
+    }
+}
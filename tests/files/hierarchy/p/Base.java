@@ -0,0 +1,4 @@
+package p;
+
+public abstract class Base implements Shape {
+}
@@ -0,0 +1,11 @@
+package p;
+
+public class Circle extends Base {
+    public double area() {
+        return 3.14;
+    }
+
+    public String name() {
+        return "circle";
+    }
+}
@@ -0,0 +1,7 @@
+package p;
+
+public interface Named extends Shape {
+    default String name() {
+        return "named";
+    }
+}
@@ -0,0 +1,10 @@
+package p;
+
+// default methods for the hierarchy tests, Named's name hides Shape's for a Square
+public interface Shape {
+    default String name() {
+        return "shape";
+    }
+
+    double area();
+}
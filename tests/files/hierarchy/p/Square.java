@@ -0,0 +1,7 @@
+package p;
+
+public class Square extends Base implements Named {
+    public double area() {
+        return 1;
+    }
+}
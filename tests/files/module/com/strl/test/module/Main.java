@@ -0,0 +1,6 @@
+package com.strl.test.module;
+
+public class Main {
+    public static void main(String[] args) {
+    }
+}
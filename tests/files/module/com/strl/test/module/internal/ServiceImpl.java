@@ -0,0 +1,6 @@
+package com.strl.test.module.internal;
+
+import com.strl.test.module.spi.Service;
+
+public class ServiceImpl implements Service {
+}
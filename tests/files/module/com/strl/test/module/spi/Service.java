@@ -0,0 +1,4 @@
+package com.strl.test.module.spi;
+
+public interface Service {
+}
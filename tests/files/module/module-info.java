@@ -0,0 +1,9 @@
+// compiled with javac --release 17 --module-version 1.2, then jar --main-class com.strl.test.module.Main
+open module com.strl.test.module {
+    requires transitive java.logging;
+    requires static java.sql;
+    exports com.strl.test.module;
+    exports com.strl.test.module.spi to java.base, java.logging;
+    uses com.strl.test.module.spi.Service;
+    provides com.strl.test.module.spi.Service with com.strl.test.module.internal.ServiceImpl;
+}
@@ -0,0 +1,32 @@
+package p;
+
+// private members nothing calls or reads: big and small, and unread, which only big uses
+public class Dead {
+    private int used = 1;
+    private long unread;
+    private String never;
+
+    public int live() {
+        return helper() + used;
+    }
+
+    private int helper() {
+        return 2;
+    }
+
+    private long big(long x) {
+        long total = unread;
+        for (int i = 0; i < 10; i++) {
+            total += x * i;
+            total ^= total >>> 3;
+        }
+        return total;
+    }
+
+    private void small() {
+    }
+
+    public static void main(String[] args) {
+        System.out.println(new Dead().live());
+    }
+}
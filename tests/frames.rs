@@ -0,0 +1,75 @@
+// StackMapTables computed for javac's methods verify and have frames at the pcs javac has them. The frames themselves
+// can differ, javac gives a local its declared type and drops it at the end of its scope, where the computation has
+// the type assigned to it and keeps it while it is still set
+use std::collections::HashMap;
+use jcfreader::attribute::{AttributeKind, HasAttributes};
+use jcfreader::code::Code;
+use jcfreader::code::verifier;
+use jcfreader::ClassFile;
+
+const FIXTURES: [&str; 5] = ["test1.class", "test2.class", "Annotated.class", "Annotated$Info.class", "Constants.class"];
+
+fn fixture(name: &str) -> ClassFile {
+    ClassFile::try_from_bytes(std::fs::read(format!("tests/files/{}", name)).unwrap()).unwrap()
+}
+
+// pcs of the frames of each method's StackMapTable, none without one
+fn frame_pcs(class_file: &ClassFile) -> Vec<Vec<u32>> {
+    class_file.get_methods().iter().map(|m| {
+        let Some(code) = m.get_attributes().iter().find(|a| a.name == "Code") else { return Vec::new() };
+        let Some(AttributeKind::StackMapTable(frames)) = Code::new(&code.info).find_attribute(class_file, "StackMapTable") else {
+            return Vec::new();
+        };
+        // the first frame is at its offset_delta, each after it at offset_delta + 1 past the one before
+        let mut pc: Option<u32> = None;
+        frames.iter().map(|f| {
+            let at = pc.map_or(f.offset_delta() as u32, |p| p + f.offset_delta() as u32 + 1);
+            pc = Some(at);
+            at
+        }).collect()
+    }).collect()
+}
+
+#[test]
+fn computed_frames_are_where_javac_has_them() {
+    let classes: HashMap<String, ClassFile> = FIXTURES.iter().map(|f| fixture(f)).map(|c| (c.get_class_name(), c)).collect();
+    for name in FIXTURES {
+        let mut class_file = fixture(name);
+        let javac = frame_pcs(&class_file);
+        for method in 0..class_file.get_methods().len() {
+            if class_file.get_methods()[method].get_attributes().iter().any(|a| a.name == "Code") {
+                class_file.compute_stack_map(method, &classes).unwrap();
+            }
+        }
+        assert_eq!(frame_pcs(&class_file), javac, "{}", name);
+        let problems: Vec<String> = verifier::verify(&class_file, &classes).iter().map(|p| p.to_string()).collect();
+        assert!(problems.is_empty(), "{}: {:?}", name, problems);
+    }
+}
+
+#[test]
+fn computed_frames_replace_removed_ones() {
+    let mut class_file = fixture("Constants.class");
+    class_file.remove_attributes("StackMapTable");
+    let classes: HashMap<String, ClassFile> = HashMap::new();
+    assert!(!verifier::verify(&class_file, &classes).is_empty());
+    for method in 0..class_file.get_methods().len() {
+        if class_file.get_methods()[method].get_attributes().iter().any(|a| a.name == "Code") {
+            class_file.compute_stack_map(method, &classes).unwrap();
+        }
+    }
+    let problems: Vec<String> = verifier::verify(&class_file, &classes).iter().map(|p| p.to_string()).collect();
+    assert!(problems.is_empty(), "{:?}", problems);
+}
+
+#[test]
+fn truncated_code_is_an_error() {
+    let mut class_file = fixture("test1.class");
+    let (method, info) = class_file.get_methods().iter().enumerate()
+        .find(|(_, m)| m.get_name() == "method1")
+        .map(|(i, m)| (i, m.get_attributes().iter().find(|a| a.name == "Code").unwrap().info.clone()))
+        .unwrap();
+    class_file.set_code(method, info[..10].to_vec());
+    let error = class_file.compute_stack_map(method, &HashMap::<String, ClassFile>::new()).unwrap_err();
+    assert_eq!(error.to_string(), "bad Code attribute: the entries run past the end of the attribute");
+}
@@ -0,0 +1,52 @@
+// resolution, selection and assignability over the default methods of tests/files/hierarchy
+use std::path::Path;
+use jcfreader::access_flags::ACC_ABSTRACT;
+use jcfreader::class_path::ClassPath;
+use jcfreader::hierarchy::Hierarchy;
+
+const NAME: &str = "()Ljava/lang/String;";
+
+fn hierarchy() -> Hierarchy {
+    let mut class_path = ClassPath::new();
+    class_path.add(Path::new("tests/files/hierarchy")).unwrap();
+    Hierarchy::new(class_path)
+}
+
+#[test]
+fn find_method_resolves_the_most_specific_default() {
+    let hierarchy = hierarchy();
+    // Square inherits both defaults, Named's extends Shape so hides it
+    assert_eq!(hierarchy.find_method("p/Square", "name", NAME).unwrap().to_string(), "p/Named.name:()Ljava/lang/String;");
+    assert_eq!(hierarchy.find_method("p/Base", "name", NAME).unwrap().class, "p/Shape");
+    let area = hierarchy.find_method("p/Base", "area", "()D").unwrap();
+    assert_eq!(area.class, "p/Shape");
+    assert_ne!(area.access_flags & ACC_ABSTRACT, 0);
+    assert!(hierarchy.find_method("p/Square", "perimeter", "()D").is_none());
+}
+
+#[test]
+fn select_method_runs_the_default_or_the_override() {
+    let hierarchy = hierarchy();
+    let resolved = hierarchy.find_method("p/Shape", "name", NAME).unwrap();
+    assert_eq!(resolved.class, "p/Shape");
+    assert_eq!(hierarchy.select_method("p/Square", &resolved).unwrap().class, "p/Named");
+    assert_eq!(hierarchy.select_method("p/Circle", &resolved).unwrap().class, "p/Circle");
+    let area = hierarchy.find_method("p/Shape", "area", "()D").unwrap();
+    assert_eq!(hierarchy.select_method("p/Square", &area).unwrap().class, "p/Square");
+    // an abstract class with nothing concrete selects nothing
+    assert!(hierarchy.select_method("p/Base", &area).is_none());
+}
+
+#[test]
+fn is_assignable_over_interfaces() {
+    let hierarchy = hierarchy();
+    assert!(hierarchy.is_assignable("p/Square", "p/Shape"));
+    assert!(hierarchy.is_assignable("p/Square", "p/Named"));
+    assert!(hierarchy.is_assignable("p/Named", "p/Shape"));
+    assert!(hierarchy.is_assignable("p/Circle", "p/Shape"));
+    assert!(!hierarchy.is_assignable("p/Circle", "p/Named"));
+    assert!(!hierarchy.is_assignable("p/Shape", "p/Named"));
+    assert!(hierarchy.is_assignable("[Lp/Square;", "[Lp/Shape;"));
+    assert!(!hierarchy.is_assignable("[Lp/Circle;", "[Lp/Named;"));
+    assert!(hierarchy.is_assignable("[Lp/Circle;", "java/lang/Cloneable"));
+}
@@ -0,0 +1,31 @@
+// classes disassembled to text and assembled again come back byte for byte, and bad text is an error with its line
+use jcfreader::{jasm, ClassFile};
+
+const FIXTURES: [&str; 5] = ["test1.class", "test2.class", "Annotated.class", "Annotated$Info.class", "Constants.class"];
+
+#[test]
+fn fixtures_assemble_back_the_same() {
+    for name in FIXTURES {
+        let bytes = std::fs::read(format!("tests/files/{}", name)).unwrap();
+        let text = jasm::disassemble(&ClassFile::try_from_bytes(bytes.clone()).unwrap());
+        let class_file = jasm::assemble(&text).unwrap_or_else(|e| panic!("{}: {}", name, e));
+        assert!(class_file.to_bytes() == bytes, "{}", name);
+    }
+}
+
+#[test]
+fn bad_instruction_is_reported_with_its_line() {
+    let text = jasm::disassemble(&ClassFile::try_from_bytes(std::fs::read("tests/files/test1.class").unwrap()).unwrap());
+    let at = text.find("    iconst_0\n").unwrap();
+    let line = text[..at].lines().count() + 1;
+    let error = jasm::assemble(&text.replacen("    iconst_0\n", "    iconst_9\n", 1)).err().unwrap();
+    assert_eq!(error.line, line);
+    assert!(error.to_string().contains("iconst_9"), "{}", error);
+}
+
+#[test]
+fn stray_token_is_reported_with_its_line() {
+    let text = ".version 61 0\n.class super p/A\n.super java/lang/Object\nstray\n.end class\n";
+    let error = jasm::assemble(text).err().unwrap();
+    assert_eq!((error.line, error.message.as_str()), (4, "expected a directive but found stray"));
+}
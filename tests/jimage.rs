@@ -0,0 +1,65 @@
+// the running JDK's lib/modules, and one jlink writes with --compress=2, skipped when there is no JDK to find through
+// JAVA_HOME or the javac on the PATH
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use jcfreader::jimage::{Image, ImageError};
+use jcfreader::ClassFile;
+
+const OBJECT: &str = "/java.base/java/lang/Object.class";
+
+fn java_home() -> Option<PathBuf> {
+    let home = match std::env::var_os("JAVA_HOME") {
+        Some(home) => PathBuf::from(home),
+        None => {
+            let path = std::env::var_os("PATH")?;
+            let javac = std::env::split_paths(&path).map(|d| d.join("javac")).find(|j| j.is_file())?;
+            javac.canonicalize().ok()?.parent()?.parent()?.to_path_buf()
+        }
+    };
+    match home.join("lib/modules").is_file() {
+        true => Some(home),
+        false => {
+            eprintln!("no JDK found, skipped");
+            None
+        }
+    }
+}
+
+#[test]
+fn runtime_image_reads() {
+    let Some(home) = java_home() else { return };
+    let image = Image::open(&home.join("lib/modules")).unwrap();
+    let names = image.get_names();
+    assert!(names.iter().any(|n| n == OBJECT));
+    assert!(names.iter().any(|n| n == "/java.base/module-info.class"));
+    assert!(image.contains(OBJECT));
+    let object = ClassFile::try_from_bytes(image.read_entry(OBJECT).unwrap().unwrap()).unwrap();
+    assert_eq!(object.get_class_name(), "java/lang/Object");
+    assert_eq!(object.get_super_class_name(), None);
+    assert!(!image.contains("/java.base/java/lang/Missing.class"));
+    assert!(image.read_entry("/java.base/java/lang/Missing.class").is_none());
+}
+
+#[test]
+fn compressed_image_reads_the_same() {
+    let Some(home) = java_home() else { return };
+    let dir = std::env::temp_dir().join(format!("jcfreader-{}-{}", "compressed-image", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let status = Command::new(home.join("bin/jlink"))
+        .args(["--add-modules", "java.base", "--compress=2", "--output"]).arg(&dir).status().unwrap();
+    assert!(status.success());
+    let compressed = Image::open(&dir.join("lib/modules")).unwrap();
+    let image = Image::open(&home.join("lib/modules")).unwrap();
+    let names: Vec<String> = compressed.get_names().into_iter().filter(|n| n.starts_with("/java.base/java/lang/")).collect();
+    assert!(names.len() > 100, "{}", names.len());
+    for name in &names {
+        let bytes = compressed.read_entry(name).unwrap().unwrap();
+        assert!(bytes == image.read_entry(name).unwrap().unwrap(), "{}", name);
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn not_an_image() {
+    assert!(matches!(Image::open(Path::new("tests/files/test1.class")), Err(ImageError::NotImage)));
+}
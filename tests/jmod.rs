@@ -0,0 +1,59 @@
+// tests/files/module/module.jmod, made by jmod create from the classes of tests/files/module with --module-version 1.2,
+// --main-class com.strl.test.module.Main and a legal/NOTICE
+use std::path::Path;
+use jcfreader::class_path::ClassPath;
+use jcfreader::jmod::{Jmod, JmodError, Section};
+use jcfreader::ClassFile;
+
+const JMOD: &str = "tests/files/module/module.jmod";
+
+fn jmod() -> Jmod {
+    Jmod::open(Path::new(JMOD)).unwrap()
+}
+
+#[test]
+fn entries_by_section() {
+    let jmod = jmod();
+    assert_eq!(jmod.get_entries(), [
+        (Section::Classes, "module-info.class"),
+        (Section::Classes, "com/strl/test/module/Main.class"),
+        (Section::Classes, "com/strl/test/module/internal/ServiceImpl.class"),
+        (Section::Classes, "com/strl/test/module/spi/Service.class"),
+        (Section::LegalNotices, "NOTICE"),
+    ]);
+    assert_eq!(jmod.get_class_names(), ["com/strl/test/module/Main", "com/strl/test/module/internal/ServiceImpl", "com/strl/test/module/spi/Service"]);
+    assert!(jmod.contains(Section::LegalNotices, "NOTICE"));
+    assert!(!jmod.contains(Section::Classes, "NOTICE"));
+    assert_eq!(jmod.read_entry(Section::LegalNotices, "NOTICE").unwrap().unwrap(), b"test notice\n");
+}
+
+#[test]
+fn classes_and_module_info_read() {
+    let jmod = jmod();
+    let main = ClassFile::try_from_bytes(jmod.read_class("com/strl/test/module/Main").unwrap().unwrap()).unwrap();
+    assert_eq!(main.get_class_name(), "com/strl/test/module/Main");
+    assert!(jmod.read_class("com/strl/test/module/Missing").is_none());
+    // jmod create adds the version, ModulePackages and ModuleMainClass
+    let module = jmod.get_module_info().unwrap().unwrap().get_class_attributes().module.unwrap();
+    assert_eq!(module.name, "com.strl.test.module");
+    assert_eq!(module.version.as_deref(), Some("1.2"));
+    assert_eq!(module.packages, ["com.strl.test.module", "com.strl.test.module.internal", "com.strl.test.module.spi"]);
+    assert_eq!(module.main_class.as_deref(), Some("com.strl.test.module.Main"));
+}
+
+#[test]
+fn found_through_the_module_path() {
+    let mut class_path = ClassPath::new();
+    class_path.add_module_path(JMOD).unwrap();
+    assert_eq!(class_path.get_class("com/strl/test/module/spi/Service").unwrap().get_class_name(), "com/strl/test/module/spi/Service");
+}
+
+#[test]
+fn a_jar_is_not_a_jmod() {
+    let mut bytes = std::fs::read(JMOD).unwrap();
+    // without the JM header the rest is a plain zip
+    assert!(matches!(Jmod::from_bytes(bytes[4..].to_vec()), Err(JmodError::NotJmod)));
+    // nor is a header with no end of central directory after it
+    bytes.truncate(100);
+    assert!(matches!(Jmod::from_bytes(bytes), Err(JmodError::NotJmod)));
+}
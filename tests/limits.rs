@@ -0,0 +1,39 @@
+// max_stack and max_locals computed for javac's methods are the ones javac declares
+use jcfreader::access_flags::ACC_STATIC;
+use jcfreader::code::limits::{self, Limits};
+use jcfreader::code::Code;
+use jcfreader::ClassFile;
+
+const FIXTURES: [&str; 6] = ["test1.class", "test2.class", "Annotated.class", "Annotated$Info.class", "Constants.class", "Flow.class"];
+
+fn fixture(name: &str) -> ClassFile {
+    ClassFile::try_from_bytes(std::fs::read(format!("tests/files/{}", name)).unwrap()).unwrap()
+}
+
+#[test]
+fn computed_limits_are_javac_limits() {
+    for name in FIXTURES {
+        let class_file = fixture(name);
+        for method in class_file.get_methods() {
+            let Some(attribute) = method.get_attributes().iter().find(|a| a.name == "Code") else { continue };
+            let code = Code::new(&attribute.info);
+            let computed = limits::compute(class_file.get_constant_pool(), &code, method.get_descriptor(), method.get_flags() & ACC_STATIC != 0).unwrap();
+            let declared = Limits { max_stack: code.get_max_stack(), max_locals: code.get_max_locals() };
+            assert_eq!(computed, declared, "{} {}{}", name, method.get_name(), method.get_descriptor());
+        }
+        assert!(limits::check(&class_file).is_empty(), "{}", name);
+    }
+}
+
+#[test]
+fn check_reports_a_max_stack_too_small() {
+    let mut class_file = fixture("Flow.class");
+    let (nested, method) = class_file.get_methods().into_iter().enumerate().find(|(_, m)| m.get_name() == "nested").unwrap();
+    // max_stack is the first u16 of the Code attribute, javac's 2 for the adds
+    let mut info = method.get_attributes().iter().find(|a| a.name == "Code").unwrap().info.clone();
+    assert_eq!(info[..2], [0, 2]);
+    info[1] = 1;
+    class_file.set_code(nested, info);
+    let problems: Vec<String> = limits::check(&class_file).iter().map(|p| p.to_string()).collect();
+    assert_eq!(problems, ["nested(I)I: declares max_stack 1 max_locals 5, needs 2 5"]);
+}
@@ -0,0 +1,58 @@
+// module-info.class from javac and jar, its Module, ModulePackages and ModuleMainClass attributes
+use jcfreader::access_flags::{ACC_MANDATED, ACC_MODULE};
+use jcfreader::module::{self, ACC_OPEN, ACC_STATIC_PHASE, ACC_TRANSITIVE};
+use jcfreader::ClassFile;
+
+fn module_info() -> ClassFile {
+    ClassFile::try_from_bytes(std::fs::read("tests/files/module/module-info.class").unwrap()).unwrap()
+}
+
+#[test]
+fn module_attributes_decode() {
+    let class_file = module_info();
+    assert_eq!(class_file.get_access_flags(), ACC_MODULE);
+    assert_eq!(class_file.get_super_class_name(), None);
+    let module = class_file.get_class_attributes().module.unwrap();
+    assert_eq!(module.name, "com.strl.test.module");
+    assert_eq!(module.version.as_deref(), Some("1.2"));
+    assert_eq!(module.flags, ACC_OPEN);
+
+    let requires: Vec<(&str, u16)> = module.requires.iter().map(|r| (r.name.as_str(), r.flags)).collect();
+    assert_eq!(requires, [("java.base", ACC_MANDATED), ("java.logging", ACC_TRANSITIVE), ("java.sql", ACC_STATIC_PHASE)]);
+    let exports: Vec<(&str, Vec<String>)> = module.exports.iter().map(|e| (e.package.as_str(), e.to.clone())).collect();
+    assert_eq!(exports, [
+        ("com.strl.test.module", vec![]),
+        ("com.strl.test.module.spi", vec!["java.base".to_string(), "java.logging".to_string()]),
+    ]);
+    // an open module opens every package, so has no opens of its own
+    assert!(module.opens.is_empty());
+    assert_eq!(module.uses, ["com.strl.test.module.spi.Service"]);
+    assert_eq!(module.provides.len(), 1);
+    assert_eq!(module.provides[0].with, ["com.strl.test.module.internal.ServiceImpl"]);
+
+    // from jar, ModulePackages and ModuleMainClass
+    assert_eq!(module.packages, ["com.strl.test.module", "com.strl.test.module.internal", "com.strl.test.module.spi"]);
+    assert_eq!(module.main_class.as_deref(), Some("com.strl.test.module.Main"));
+}
+
+#[test]
+fn module_flag_names() {
+    let module = module_info().get_class_attributes().module.unwrap();
+    assert_eq!(module::module_flag_names(module.flags), ["ACC_OPEN"]);
+    let names: Vec<Vec<&str>> = module.requires.iter().map(|r| module::requires_flag_names(r.flags)).collect();
+    assert_eq!(names, [vec!["ACC_MANDATED"], vec!["ACC_TRANSITIVE"], vec!["ACC_STATIC_PHASE"]]);
+}
+
+#[test]
+fn module_declaration() {
+    let module = module_info().get_class_attributes().module.unwrap();
+    assert_eq!(module.to_string(), "open module com.strl.test.module@1.2 {
+    requires java.base; // mandated
+    requires transitive java.logging;
+    requires static java.sql;
+    exports com.strl.test.module;
+    exports com.strl.test.module.spi to java.base, java.logging;
+    uses com.strl.test.module.spi.Service;
+    provides com.strl.test.module.spi.Service with com.strl.test.module.internal.ServiceImpl;
+}");
+}
@@ -0,0 +1,28 @@
+// parameter names and flags from MethodParameters, used only when it has an entry for each parameter
+use jcfreader::{jasm, ClassFile, Dump};
+
+// test1 with a MethodParameters attribute on method1 (JJ)I, names from the Utf8 entries #23 field1 and #25 field2
+fn with_method_parameters(hex: &str) -> ClassFile {
+    let text = jasm::disassemble(&ClassFile::new(&"tests/files/test1.class".to_string(), Dump::None));
+    let method = ".method public static method1 (JJ)I\n";
+    let end = text[text.find(method).unwrap()..].find(".end method").unwrap() + text.find(method).unwrap();
+    let text = format!("{}.attribute MethodParameters \"{}\"\n{}", &text[..end], hex, &text[end..]);
+    jasm::assemble(&text).unwrap()
+}
+
+fn parameters(class_file: &ClassFile) -> Vec<(Option<String>, u16)> {
+    let method = class_file.get_methods().into_iter().find(|m| m.get_name() == "method1").unwrap();
+    method.parameters().into_iter().map(|p| (p.name, p.flags)).collect()
+}
+
+#[test]
+fn names_from_method_parameters() {
+    let class_file = with_method_parameters("020017000000190010");
+    assert_eq!(parameters(&class_file), [(Some("field1".to_string()), 0), (Some("field2".to_string()), 0x10)]);
+}
+
+#[test]
+fn method_parameters_with_the_wrong_count_are_not_used() {
+    let class_file = with_method_parameters("0100190010");
+    assert_eq!(parameters(&class_file), [(None, 0), (None, 0)]);
+}
@@ -0,0 +1,39 @@
+// source lines through the SourceDebugExtension, for lines the SMAP maps and lines it leaves to the source file
+use jcfreader::smap::Smap;
+use jcfreader::{jasm, ClassFile, Dump};
+
+// line 29 of Annotated.java, the second line of count, comes from line 7 of Other.kt
+const SMAP: &str = "SMAP\nAnnotated.java\nKotlin\n*S Kotlin\n*F\n+ 1 Other.kt\np/Other.kt\n*L\n7#1:29\n*E\n";
+
+fn with_smap() -> ClassFile {
+    let text = jasm::disassemble(&ClassFile::new(&"tests/files/Annotated.class".to_string(), Dump::None));
+    let hex: String = SMAP.bytes().map(|b| format!("{:02x}", b)).collect();
+    jasm::assemble(&text.replace(".end class", &format!(".attribute SourceDebugExtension \"{}\"\n.end class", hex))).unwrap()
+}
+
+#[test]
+fn remap_leaves_uncovered_lines() {
+    let smap = Smap::parse(SMAP).unwrap();
+    assert_eq!(smap.remap(29), Some(("Other.kt".to_string(), 7)));
+    assert_eq!(smap.remap(28), None);
+}
+
+#[test]
+fn source_line_falls_back_to_the_source_file() {
+    let class_file = with_smap();
+    let method = class_file.get_methods().into_iter().find(|m| m.get_name() == "count").unwrap();
+    assert_eq!(class_file.get_source_line(&method, 0), Some(("Annotated.java".to_string(), 28)));
+    assert_eq!(class_file.get_source_line(&method, 2), Some(("Other.kt".to_string(), 7)));
+}
+
+#[test]
+fn large_counts_do_not_overflow() {
+    // 4294967295 input lines from 1 of 4294967295 output lines each, and one starting at the last input line
+    let smap = "SMAP\nA.java\nKotlin\n*S Kotlin\n*F\n1 A.kt\n*L\n1#1,4294967295:10,4294967295\n4294967295#1,2:5,1\n*E\n";
+    let smap = Smap::parse(smap).unwrap();
+    assert_eq!(smap.remap(10), Some(("A.kt".to_string(), 1)));
+    assert_eq!(smap.remap(u32::MAX), Some(("A.kt".to_string(), 1)));
+    assert_eq!(smap.remap(5), Some(("A.kt".to_string(), u32::MAX)));
+    // the input line after the last is past a u32
+    assert_eq!(smap.remap(6), None);
+}
@@ -0,0 +1,33 @@
+// private members of tests/files/unused/p/Dead.class nothing reaches, largest first
+use std::process::Command;
+use jcfreader::unused::{self, Kind};
+use jcfreader::ClassFile;
+
+fn dead() -> ClassFile {
+    ClassFile::try_from_bytes(std::fs::read("tests/files/unused/p/Dead.class").unwrap()).unwrap()
+}
+
+#[test]
+fn unreferenced_private_members_ranked_by_size() {
+    let found: Vec<(Kind, String, usize)> = unused::find_unused(&[dead()], &[]).into_iter().map(|u| (u.kind, u.name, u.size)).collect();
+    // unread is read only from big, a field_info with no attributes is 8 bytes
+    assert_eq!(found, [
+        (Kind::Method, "p/Dead.big:(J)J".to_string(), 111),
+        (Kind::Method, "p/Dead.small:()V".to_string(), 39),
+        (Kind::Field, "p/Dead.never:Ljava/lang/String;".to_string(), 8),
+        (Kind::Field, "p/Dead.unread:J".to_string(), 8),
+    ]);
+}
+
+#[test]
+fn unused_reports_the_bytes_removable() {
+    let output = Command::new(env!("CARGO_BIN_EXE_jcfreader")).args(["unused", "tests/files/unused"]).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "\
+111 method p/Dead.big:(J)J
+39 method p/Dead.small:()V
+8 field p/Dead.never:Ljava/lang/String;
+8 field p/Dead.unread:J
+166 bytes removable
+");
+    assert_eq!(output.status.code(), Some(0));
+}
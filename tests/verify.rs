@@ -0,0 +1,92 @@
+// the verifier on javac output and on classes with a type error, through the library and the verify command
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Command;
+use jcfreader::code::frames::ObjectHierarchy;
+use jcfreader::code::verifier;
+use jcfreader::{jasm, ClassFile, Dump};
+
+const FIXTURES: [&str; 4] = ["test1.class", "test2.class", "Annotated.class", "Annotated$Info.class"];
+
+fn fixture(name: &str) -> ClassFile {
+    ClassFile::new(&format!("tests/files/{}", name), Dump::None)
+}
+
+// test1 with method1 returning null rather than 0
+fn returns_null() -> ClassFile {
+    let text = jasm::disassemble(&fixture("test1.class"));
+    assert_eq!(text.matches("    iconst_0\n    ireturn\n").count(), 1);
+    jasm::assemble(&text.replace("    iconst_0\n    ireturn\n", "    aconst_null\n    ireturn\n")).unwrap()
+}
+
+// exit code and stdout of jcfreader verify
+fn verify(files: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_jcfreader")).arg("verify").args(files).output().unwrap();
+    (output.status.code(), String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// a directory of its own under the temp directory
+fn temp_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("jcfreader-{}-{}", test, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn javac_output_verifies() {
+    let classes: HashMap<String, ClassFile> = FIXTURES.iter().map(|f| fixture(f)).map(|c| (c.get_class_name(), c)).collect();
+    for class_file in classes.values() {
+        let problems: Vec<String> = verifier::verify(class_file, &classes).iter().map(|p| p.to_string()).collect();
+        assert!(problems.is_empty(), "{}: {:?}", class_file.get_class_name(), problems);
+    }
+}
+
+#[test]
+fn type_error_is_found() {
+    let problems = verifier::verify(&returns_null(), &ObjectHierarchy);
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0].method, "method1(JJ)I");
+    assert_eq!(problems[0].error.to_string(), "expected int, found null");
+}
+
+#[test]
+fn every_class_given_is_verified() {
+    let dir = temp_dir("verify");
+    let bad = dir.join("test1.class");
+    std::fs::write(&bad, returns_null().to_bytes()).unwrap();
+    let (bad, good) = (bad.to_str().unwrap(), "tests/files/test1.class");
+    assert_eq!(verify(&[good]), (Some(0), String::new()));
+    for files in [[bad, good], [good, bad]] {
+        let (code, text) = verify(&files);
+        assert_eq!(code, Some(1), "{:?}", files);
+        assert_eq!(text.lines().count(), 1, "{}", text);
+        assert!(text.starts_with(&format!("{}: ", bad)) && text.contains("expected int, found null"), "{}", text);
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn truncated_code_is_a_problem() {
+    let mut class_file = fixture("test1.class");
+    let (method, info) = class_file.get_methods().iter().enumerate()
+        .find(|(_, m)| m.get_name() == "method1")
+        .map(|(i, m)| (i, m.get_attributes().iter().find(|a| a.name == "Code").unwrap().info.clone()))
+        .unwrap();
+    // max_stack, max_locals, code_length and the first two bytes of the code
+    class_file.set_code(method, info[..10].to_vec());
+    let problems: Vec<String> = verifier::verify(&class_file, &ObjectHierarchy).iter().map(|p| p.to_string()).collect();
+    assert_eq!(problems, ["method1(JJ)I: pc 0 : bad Code attribute: the entries run past the end of the attribute"]);
+    let limits: Vec<String> = jcfreader::code::limits::check(&class_file).iter().map(|p| p.to_string()).collect();
+    assert_eq!(limits, ["method1(JJ)I: bad Code attribute: the entries run past the end of the attribute"]);
+
+    let path = temp_dir("truncated-code").join("test1.class");
+    std::fs::write(&path, class_file.to_bytes()).unwrap();
+    let path = path.to_str().unwrap();
+    let (code, text) = verify(&[path]);
+    assert_eq!(code, Some(1));
+    assert!(text.contains("bad Code attribute"), "{}", text);
+    for args in [vec![path], vec!["cfg", path, "method1"], vec!["disasm", path], vec!["--json", path]] {
+        let output = Command::new(env!("CARGO_BIN_EXE_jcfreader")).args(&args).output().unwrap();
+        assert!(!String::from_utf8_lossy(&output.stderr).contains("panicked"), "{:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    }
+}
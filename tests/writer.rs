@@ -0,0 +1,39 @@
+// classes read and written back are byte for byte the same
+use jcfreader::constant_pool_builder::ConstantPoolError;
+use jcfreader::ClassFile;
+
+const FIXTURES: [&str; 5] = ["test1.class", "test2.class", "Annotated.class", "Annotated$Info.class", "Constants.class"];
+
+#[test]
+fn fixtures_write_back_the_same() {
+    for name in FIXTURES {
+        let bytes = std::fs::read(format!("tests/files/{}", name)).unwrap();
+        let class_file = ClassFile::try_from_bytes(bytes.clone()).unwrap();
+        assert!(class_file.to_bytes() == bytes, "{}", name);
+    }
+}
+
+#[test]
+fn written_class_reads_the_same() {
+    let class_file = ClassFile::try_from_bytes(std::fs::read("tests/files/Constants.class").unwrap()).unwrap();
+    let again = ClassFile::try_from_bytes(class_file.to_bytes()).unwrap();
+    assert_eq!(again.to_json(), class_file.to_json());
+    let json: serde_json::Value = serde_json::from_str(&again.to_json()).unwrap();
+    let constants = json["constant_pool"].as_array().unwrap();
+    assert!(constants.iter().any(|c| c["tag"] == "Long" && c["value"] == 0x123456789abcdef_i64));
+    assert!(constants.iter().any(|c| c["tag"] == "Double" && c["value"].is_null()));
+    assert!(constants.iter().any(|c| c["tag"] == "Float" && c["bits"] == 0x8000_0000_u32));
+    assert!(constants.iter().any(|c| c["tag"] == "Utf8" && c["value"] == "nul \0 and \u{1F600} outside the BMP"));
+}
+
+#[test]
+fn longest_utf8_writes_back_and_a_longer_one_is_refused() {
+    let mut class_file = ClassFile::try_from_bytes(std::fs::read("tests/files/test1.class").unwrap()).unwrap();
+    let mut builder = class_file.constant_pool_builder();
+    let longest = builder.utf8(&"a".repeat(65535)).unwrap();
+    // the builder is where one too long is refused, try_to_bytes checks the pools it is given all the same
+    assert!(matches!(builder.utf8(&"\u{e9}".repeat(32768)), Err(ConstantPoolError::Utf8Length(65536))));
+    class_file.set_constant_pool(builder.build());
+    let again = ClassFile::try_from_bytes(class_file.try_to_bytes().unwrap()).unwrap();
+    assert_eq!(again.get_constant_pool().get_utf8_bytes(longest).len(), 65535);
+}